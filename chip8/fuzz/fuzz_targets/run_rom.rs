@@ -0,0 +1,55 @@
+//! Runs arbitrary bytes as a ROM for thousands of instructions, under every
+//! quirk and bounds policy combination the first bytes pick. The core must
+//! return errors, never panic.
+#![no_main]
+
+use chip8::{BoundsPolicy, Chip8Builder, Quirks, Timing, NUM_KEYS};
+use libfuzzer_sys::fuzz_target;
+
+const FRAMES: usize = 300;
+
+fuzz_target!(|data: &[u8]| {
+    let [config, keys, rom @ ..] = data else {
+        return;
+    };
+    let quirks = match config & 0x3 {
+        0 => Quirks::default(),
+        1 => Quirks::VIP,
+        _ => Quirks::SUPER_CHIP,
+    };
+    let bounds_policy = match config >> 2 & 0x3 {
+        0 => BoundsPolicy::Error,
+        1 => BoundsPolicy::Wrap,
+        _ => BoundsPolicy::Saturate,
+    };
+    let timing = if config & 0x10 != 0 {
+        Timing::CosmacVip
+    } else {
+        Timing::Fixed
+    };
+    let Ok(mut cpu) = Chip8Builder::new()
+        .rom(rom)
+        .quirks(quirks)
+        .bounds_policy(bounds_policy)
+        .timing(timing)
+        .seed(0)
+        .build()
+    else {
+        return;
+    };
+    if config & 0x20 != 0 {
+        cpu.set_rewind_depth(4);
+    }
+    for frame in 0..FRAMES {
+        // holds a different key every few frames so FX0A and EX9E move on
+        let key = (*keys as usize + frame / 8) % (NUM_KEYS + 1);
+        cpu.keypress(key, frame % 8 < 4);
+        if cpu.run_frame(cpu.clock_hz()).is_err() {
+            break;
+        }
+        cpu.tick_timers();
+    }
+    let state = cpu.save_state();
+    cpu.load_state(&state).expect("a save state loads back");
+    let _ = cpu.to_string();
+});
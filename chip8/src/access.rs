@@ -0,0 +1,58 @@
+//! How often the program reads and writes every address, to spot its
+//! variables, sprite data and the code it rewrites. See
+//! [`CPU::set_access_tracking`].
+
+use crate::CPU;
+
+/// Reads and writes of every address since tracking started: the data
+/// FX33, FX55 and FX65, sprites and the MegaChip instructions access, not
+/// the instructions fetched.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryAccess {
+    // by address, grown as far as the program reached
+    reads: Vec<u32>,
+    writes: Vec<u32>,
+}
+
+impl MemoryAccess {
+    /// Reads by address from 0, shorter than the memory when its end was
+    /// never read. Counts stop at `u32::MAX`.
+    pub fn reads(&self) -> &[u32] {
+        &self.reads
+    }
+
+    /// Writes by address from 0, like [`MemoryAccess::reads`].
+    pub fn writes(&self) -> &[u32] {
+        &self.writes
+    }
+
+    pub(crate) fn record_read(&mut self, address: usize) {
+        count(&mut self.reads, address);
+    }
+
+    pub(crate) fn record_write(&mut self, address: usize) {
+        count(&mut self.writes, address);
+    }
+}
+
+fn count(counts: &mut Vec<u32>, address: usize) {
+    if counts.len() <= address {
+        counts.resize(address + 1, 0);
+    }
+    counts[address] = counts[address].saturating_add(1);
+}
+
+impl CPU {
+    /// Starts counting the reads and writes of every address, or stops and
+    /// forgets the counts.
+    pub fn set_access_tracking(&mut self, enabled: bool) {
+        if enabled != self.ram.access.is_some() {
+            self.ram.access = enabled.then(Box::default);
+        }
+    }
+
+    /// The reads and writes since tracking started, None when it's off.
+    pub fn memory_access(&self) -> Option<&MemoryAccess> {
+        self.ram.access.as_deref()
+    }
+}
@@ -0,0 +1,270 @@
+//! Assembles the mnemonic syntax produced by [`crate::disasm`] into a ROM.
+//!
+//! One instruction per line, `;` starts a comment and `name:` defines a
+//! label usable wherever an address or number is expected. Numbers are
+//! decimal, `0x` hex or `0b` binary. `DB` and `DW` emit raw bytes and words.
+//!
+//! ```text
+//! start:
+//!     LD V0, 0x0A     ; counter
+//! loop:
+//!     ADD V0, 0xFF
+//!     SE V0, 0
+//!     JP loop
+//!     JP start
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::memory::START_ADDR;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    /// 1-based line number of the offending source line.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand<'a> {
+    V(u16),
+    I,
+    IndirectI,
+    Dt,
+    St,
+    K,
+    F,
+    Hf,
+    B,
+    R,
+    Value(&'a str),
+}
+
+struct Statement<'a> {
+    line: usize,
+    mnemonic: String,
+    operands: Vec<Operand<'a>>,
+}
+
+/// Assembles `source` into ROM bytes meant to be loaded at 0x200.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    // first pass: collect labels and statements, sizing every statement
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    let mut address = START_ADDR as usize;
+    for (idx, raw) in source.lines().enumerate() {
+        let line = idx + 1;
+        let mut text = raw.split(';').next().unwrap_or("").trim();
+
+        while let Some((label, rest)) = split_label(text) {
+            if labels.insert(label, address as u16).is_some() {
+                return Err(error(line, format!("duplicate label `{label}`")));
+            }
+            text = rest;
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let operands = rest
+            .split(',')
+            .map(str::trim)
+            .filter(|op| !op.is_empty())
+            .map(parse_operand)
+            .collect::<Vec<_>>();
+        let statement = Statement {
+            line,
+            mnemonic: mnemonic.to_ascii_uppercase(),
+            operands,
+        };
+        address += match statement.mnemonic.as_str() {
+            "DB" => statement.operands.len(),
+            "DW" => 2 * statement.operands.len(),
+            _ => 2,
+        };
+        statements.push(statement);
+    }
+
+    // second pass: encode with every label known
+    let mut rom = Vec::with_capacity(address - START_ADDR as usize);
+    for statement in &statements {
+        let resolve = |operand: &Operand, max: u16| -> Result<u16, AsmError> {
+            let Operand::Value(text) = operand else {
+                return Err(error(statement.line, "expected a number or label"));
+            };
+            let value = match labels.get(text) {
+                Some(&addr) => addr,
+                None => parse_number(text)
+                    .ok_or_else(|| error(statement.line, format!("unknown label `{text}`")))?,
+            };
+            if value > max {
+                return Err(error(
+                    statement.line,
+                    format!("{text} doesn't fit in {max:#X}"),
+                ));
+            }
+            Ok(value)
+        };
+
+        match statement.mnemonic.as_str() {
+            "DB" => {
+                for operand in &statement.operands {
+                    rom.push(resolve(operand, 0xFF)? as u8);
+                }
+            }
+            "DW" => {
+                for operand in &statement.operands {
+                    rom.extend_from_slice(&resolve(operand, 0xFFFF)?.to_be_bytes());
+                }
+            }
+            _ => {
+                let op = encode(statement, resolve)?;
+                rom.extend_from_slice(&op.to_be_bytes());
+            }
+        }
+    }
+    Ok(rom)
+}
+
+fn encode(
+    statement: &Statement,
+    resolve: impl Fn(&Operand, u16) -> Result<u16, AsmError>,
+) -> Result<u16, AsmError> {
+    use Operand::*;
+
+    let addr = |op: &Operand| resolve(op, 0xFFF);
+    let byte = |op: &Operand| resolve(op, 0xFF);
+    let nibble = |op: &Operand| resolve(op, 0xF);
+
+    let op = match (statement.mnemonic.as_str(), statement.operands.as_slice()) {
+        ("CLS", []) => 0x00E0,
+        ("RET", []) => 0x00EE,
+        ("SCR", []) => 0x00FB,
+        ("SCL", []) => 0x00FC,
+        ("EXIT", []) => 0x00FD,
+        ("LOW", []) => 0x00FE,
+        ("HIGH", []) => 0x00FF,
+        ("SCD", [n]) => 0x00C0 | nibble(n)?,
+        ("SCU", [n]) => 0x00B0 | nibble(n)?,
+        ("MEGAOFF", []) => 0x0010,
+        ("MEGAON", []) => 0x0011,
+        ("LDHI", [nn]) => 0x0100 | byte(nn)?,
+        ("LDPAL", [nn]) => 0x0200 | byte(nn)?,
+        ("SPRW", [nn]) => 0x0300 | byte(nn)?,
+        ("SPRH", [nn]) => 0x0400 | byte(nn)?,
+        ("ALPHA", [nn]) => 0x0500 | byte(nn)?,
+        ("DIGISND", [n]) => 0x0600 | nibble(n)?,
+        ("STOPSND", []) => 0x0700,
+        ("BMODE", [n]) => 0x0800 | nibble(n)?,
+        ("CCOL", [nn]) => 0x0900 | byte(nn)?,
+        ("PLANE", [n]) => 0xF001 | nibble(n)? << 8,
+        ("SYS", [nnn]) => addr(nnn)?,
+        ("JP", [V(0), nnn]) => 0xB000 | addr(nnn)?,
+        ("JP", [nnn]) => 0x1000 | addr(nnn)?,
+        ("CALL", [nnn]) => 0x2000 | addr(nnn)?,
+        ("SE", [V(x), V(y)]) => 0x5000 | x << 8 | y << 4,
+        ("SE", [V(x), nn]) => 0x3000 | x << 8 | byte(nn)?,
+        ("SNE", [V(x), V(y)]) => 0x9000 | x << 8 | y << 4,
+        ("SNE", [V(x), nn]) => 0x4000 | x << 8 | byte(nn)?,
+        ("LD", [V(x), V(y)]) => 0x8000 | x << 8 | y << 4,
+        ("LD", [V(x), Dt]) => 0xF007 | x << 8,
+        ("LD", [V(x), K]) => 0xF00A | x << 8,
+        ("LD", [V(x), IndirectI]) => 0xF065 | x << 8,
+        ("LD", [V(x), R]) => 0xF085 | x << 8,
+        ("LD", [V(x), nn]) => 0x6000 | x << 8 | byte(nn)?,
+        ("LD", [I, nnn]) => 0xA000 | addr(nnn)?,
+        ("LD", [Dt, V(x)]) => 0xF015 | x << 8,
+        ("LD", [St, V(x)]) => 0xF018 | x << 8,
+        ("LD", [F, V(x)]) => 0xF029 | x << 8,
+        ("LD", [Hf, V(x)]) => 0xF030 | x << 8,
+        ("LD", [B, V(x)]) => 0xF033 | x << 8,
+        ("LD", [IndirectI, V(x)]) => 0xF055 | x << 8,
+        ("LD", [R, V(x)]) => 0xF075 | x << 8,
+        ("ADD", [V(x), V(y)]) => 0x8004 | x << 8 | y << 4,
+        ("ADD", [V(x), nn]) => 0x7000 | x << 8 | byte(nn)?,
+        ("ADD", [I, V(x)]) => 0xF01E | x << 8,
+        ("OR", [V(x), V(y)]) => 0x8001 | x << 8 | y << 4,
+        ("AND", [V(x), V(y)]) => 0x8002 | x << 8 | y << 4,
+        ("XOR", [V(x), V(y)]) => 0x8003 | x << 8 | y << 4,
+        ("SUB", [V(x), V(y)]) => 0x8005 | x << 8 | y << 4,
+        ("SHR", [V(x)]) => 0x8006 | x << 8,
+        ("SHR", [V(x), V(y)]) => 0x8006 | x << 8 | y << 4,
+        ("SUBN", [V(x), V(y)]) => 0x8007 | x << 8 | y << 4,
+        ("SHL", [V(x)]) => 0x800E | x << 8,
+        ("SHL", [V(x), V(y)]) => 0x800E | x << 8 | y << 4,
+        ("RND", [V(x), nn]) => 0xC000 | x << 8 | byte(nn)?,
+        ("DRW", [V(x), V(y), n]) => 0xD000 | x << 8 | y << 4 | nibble(n)?,
+        ("SKP", [V(x)]) => 0xE09E | x << 8,
+        ("SKNP", [V(x)]) => 0xE0A1 | x << 8,
+        ("SKP2", [V(x)]) => 0xE0F2 | x << 8,
+        ("SKNP2", [V(x)]) => 0xE0F5 | x << 8,
+        ("ADDN", [V(x), V(y)]) => 0x5001 | x << 8 | y << 4,
+        ("BGCOL", []) => 0x02A0,
+        ("COL", [V(x), V(y)]) => 0xB000 | x << 8 | y << 4,
+        ("COL", [V(x), V(y), n]) => 0xB000 | x << 8 | y << 4 | nibble(n)?,
+        (mnemonic, _) => {
+            return Err(error(
+                statement.line,
+                format!("invalid instruction `{mnemonic}` with these operands"),
+            ))
+        }
+    };
+    Ok(op)
+}
+
+// splits a leading `label:` off a line
+fn split_label(text: &str) -> Option<(&str, &str)> {
+    let (label, rest) = text.split_once(':')?;
+    let label = label.trim();
+    let valid = !label.is_empty()
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !label.starts_with(|c: char| c.is_ascii_digit());
+    valid.then(|| (label, rest.trim()))
+}
+
+fn parse_operand(text: &str) -> Operand<'_> {
+    match text.to_ascii_uppercase().as_str() {
+        "I" => Operand::I,
+        "[I]" => Operand::IndirectI,
+        "DT" => Operand::Dt,
+        "ST" => Operand::St,
+        "K" => Operand::K,
+        "F" => Operand::F,
+        "HF" => Operand::Hf,
+        "B" => Operand::B,
+        "R" => Operand::R,
+        reg if reg.len() == 2 && reg.starts_with('V') => match u16::from_str_radix(&reg[1..], 16) {
+            Ok(x) => Operand::V(x),
+            Err(_) => Operand::Value(text),
+        },
+        _ => Operand::Value(text),
+    }
+}
+
+pub(crate) fn parse_number(text: &str) -> Option<u16> {
+    let lower = text.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        u16::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        u16::from_str_radix(bin, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+fn error(line: usize, message: impl Into<String>) -> AsmError {
+    AsmError {
+        line,
+        message: message.into(),
+    }
+}
@@ -0,0 +1,122 @@
+//! PCM samples of the buzzer, for frontends streaming audio instead of
+//! turning a tone on and off, see [`AudioSource`].
+
+use crate::{DigitizedSound, CPU};
+
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+pub const DEFAULT_PITCH_HZ: f32 = 440.0;
+// a full scale square wave is unpleasantly loud
+pub const DEFAULT_VOLUME: f32 = 0.1;
+
+/// A square wave while the sound timer counts down and silence otherwise,
+/// mixed with the digitized sound of a MegaChip program. The wave carries on
+/// between calls so buffers join without clicks.
+#[derive(Debug, Clone)]
+pub struct AudioSource {
+    sample_rate: u32,
+    pitch_hz: f32,
+    volume: f32,
+    // fraction of the current period played
+    phase: f32,
+    // the digitized sound playing and the sample reached in it
+    sound_id: Option<u32>,
+    sound_position: f64,
+}
+
+impl Default for AudioSource {
+    fn default() -> Self {
+        Self::new(DEFAULT_SAMPLE_RATE)
+    }
+}
+
+impl AudioSource {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate: sample_rate.max(1),
+            pitch_hz: DEFAULT_PITCH_HZ,
+            volume: DEFAULT_VOLUME,
+            phase: 0.0,
+            sound_id: None,
+            sound_position: 0.0,
+        }
+    }
+
+    /// Sets the frequency of the tone.
+    pub fn with_pitch(mut self, hz: f32) -> Self {
+        self.pitch_hz = hz.max(0.0);
+        self
+    }
+
+    /// Sets the amplitude of the wave, from 0 to 1.
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Samples played during a 60 Hz frame, rounded down.
+    pub fn samples_per_frame(&self) -> usize {
+        self.sample_rate as usize / 60
+    }
+
+    /// The next sample, from -1 to 1, of the tone or of silence.
+    pub fn next_sample(&mut self, playing: bool) -> f32 {
+        if !playing {
+            return 0.0;
+        }
+        let sample = if self.phase < 0.5 {
+            self.volume
+        } else {
+            -self.volume
+        };
+        self.phase = (self.phase + self.pitch_hz / self.sample_rate as f32) % 1.0;
+        sample
+    }
+
+    /// The next sample of the digitized sound, silence once a sound not
+    /// looping is over.
+    fn next_digitized(&mut self, sound: Option<&DigitizedSound>) -> f32 {
+        let Some(sound) = sound else {
+            self.sound_id = None;
+            return 0.0;
+        };
+        if self.sound_id != Some(sound.id) {
+            self.sound_id = Some(sound.id);
+            self.sound_position = 0.0;
+        }
+        let len = sound.samples.len() as f64;
+        if sound.looping && len > 0.0 {
+            self.sound_position %= len;
+        }
+        let Some(&byte) = sound.samples.get(self.sound_position as usize) else {
+            return 0.0;
+        };
+        self.sound_position += sound.sample_rate as f64 / self.sample_rate as f64;
+        (byte as f32 - 128.0) / 128.0 * self.volume
+    }
+
+    /// Fills `out` with the tone if the sound timer of `cpu` is counting
+    /// down, with silence otherwise, plus any digitized sound.
+    pub fn fill(&mut self, cpu: &CPU, out: &mut [f32]) {
+        let playing = cpu.is_sound_playing();
+        let sound = cpu.digitized_sound();
+        for sample in out {
+            *sample =
+                (self.next_sample(playing) + self.next_digitized(sound.as_ref())).clamp(-1.0, 1.0);
+        }
+    }
+
+    /// Like [`AudioSource::fill`], in signed 16 bit samples.
+    pub fn fill_i16(&mut self, cpu: &CPU, out: &mut [i16]) {
+        let playing = cpu.is_sound_playing();
+        let sound = cpu.digitized_sound();
+        for sample in out {
+            let mixed =
+                (self.next_sample(playing) + self.next_digitized(sound.as_ref())).clamp(-1.0, 1.0);
+            *sample = (mixed * i16::MAX as f32) as i16;
+        }
+    }
+}
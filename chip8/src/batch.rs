@@ -0,0 +1,63 @@
+//! Running instructions in batches, and what they did, see [`CPU::run`].
+
+use crate::{Chip8Error, CPU};
+
+/// What a batch of instructions did, for frontends to react to without
+/// stepping the CPU themselves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunSummary {
+    /// Instructions executed, fewer than asked for once the program halts
+    /// or waits for the display.
+    pub instructions: usize,
+    pub display_changed: bool,
+    /// See [`CPU::is_waiting_for_key`].
+    pub waiting_for_key: bool,
+}
+
+impl CPU {
+    /// Executes up to `instructions` instructions, stopping early when the
+    /// program halts or waits for the next frame with the display wait
+    /// quirk. Doesn't tick the timers, see [`CPU::run_frame`] to run a frame
+    /// at the CPU's own speed.
+    pub fn run(&mut self, instructions: usize) -> Result<RunSummary, Chip8Error> {
+        self.summarize(|cpu| {
+            let mut left = instructions;
+            while left > 0 && !cpu.halted && !cpu.waiting_vblank {
+                left -= cpu.step(left)?;
+            }
+            Ok(())
+        })
+    }
+
+    // executes the translated block at the program counter when it fits in
+    // `budget`, see `CPU::set_jit`, or else one instruction, returning how
+    // many of the budget it used
+    #[cfg_attr(not(feature = "jit"), allow(unused_variables))]
+    pub(crate) fn step(&mut self, budget: usize) -> Result<usize, Chip8Error> {
+        #[cfg(feature = "jit")]
+        if let Some(instructions) = self.run_block(budget) {
+            return Ok(instructions);
+        }
+        self.tick()?;
+        Ok(1)
+    }
+
+    /// Whether the program is blocked in FX0A until a key is pressed and
+    /// released, see [`CPU::waiting_for_key`] for which.
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.waiting_for_key().is_some()
+    }
+
+    pub(crate) fn summarize(
+        &mut self,
+        run: impl FnOnce(&mut CPU) -> Result<(), Chip8Error>,
+    ) -> Result<RunSummary, Chip8Error> {
+        let (executed, changes) = (self.executed, self.screen.changes());
+        run(self)?;
+        Ok(RunSummary {
+            instructions: (self.executed - executed) as usize,
+            display_changed: self.screen.changes() != changes,
+            waiting_for_key: self.is_waiting_for_key(),
+        })
+    }
+}
@@ -0,0 +1,19 @@
+use std::{env, fs, process};
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    if args.len() != 3 {
+        println!("Usage: chip8-asm source.asm output.ch8");
+        process::exit(1);
+    }
+
+    let source = fs::read_to_string(&args[1]).expect("Unable to read source file");
+    let rom = match chip8::asm::assemble(&source) {
+        Ok(rom) => rom,
+        Err(err) => {
+            eprintln!("{}:{err}", args[1]);
+            process::exit(1);
+        }
+    };
+    fs::write(&args[2], rom).expect("Unable to write ROM file");
+}
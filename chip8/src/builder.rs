@@ -0,0 +1,157 @@
+//! One place to configure a [`CPU`] before it runs, see [`Chip8Builder`].
+
+use crate::font::{BIG_FONTSET_SIZE, FONTSET_SIZE};
+use crate::quirks::Quirks;
+use crate::romdb::rom_hash;
+use crate::{is_two_page_rom, BoundsPolicy, RomError, Timing, CHIP8X_START_ADDR, CPU, START_ADDR};
+
+/// Builds a ready to run [`CPU`].
+///
+/// ```ignore
+/// let cpu = Chip8Builder::new()
+///     .rom(&rom)
+///     .clock_hz(1000)
+///     .seed(42)
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Chip8Builder<'a> {
+    rom: Option<&'a [u8]>,
+    start_addr: Option<u16>,
+    chip8x: bool,
+    megachip: bool,
+    quirks: Quirks,
+    bounds_policy: BoundsPolicy,
+    clock_hz: Option<u32>,
+    timing: Timing,
+    seed: Option<u64>,
+    font: Option<&'a [u8; FONTSET_SIZE]>,
+    big_font: Option<&'a [u8; BIG_FONTSET_SIZE]>,
+    latched_input: bool,
+}
+
+impl<'a> Chip8Builder<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The program loaded at 0x200, see [`Chip8Builder::start_addr`].
+    pub fn rom(mut self, rom: &'a [u8]) -> Self {
+        self.rom = Some(rom);
+        self
+    }
+
+    /// Loads the ROM and starts running at `addr` instead of 0x200, e.g.
+    /// [`ETI660_START_ADDR`].
+    ///
+    /// [`ETI660_START_ADDR`]: crate::ETI660_START_ADDR
+    pub fn start_addr(mut self, addr: u16) -> Self {
+        self.start_addr = Some(addr);
+        self
+    }
+
+    /// Runs a CHIP-8X program, loaded at [`CHIP8X_START_ADDR`] unless
+    /// [`Chip8Builder::start_addr`] says otherwise, see [`CPU::set_chip8x`].
+    /// Its interpreter ran on the VIP, see [`Quirks::VIP`].
+    pub fn chip8x(mut self) -> Self {
+        self.chip8x = true;
+        self
+    }
+
+    /// Loads a MegaChip ROM larger than the RAM, see [`CPU::load_megachip`].
+    pub fn megachip(mut self) -> Self {
+        self.megachip = true;
+        self
+    }
+
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    /// What reads and writes past the end of the RAM do.
+    pub fn bounds_policy(mut self, policy: BoundsPolicy) -> Self {
+        self.bounds_policy = policy;
+        self
+    }
+
+    /// Instructions executed per second, see [`CPU::set_clock_hz`].
+    pub fn clock_hz(mut self, hz: u32) -> Self {
+        self.clock_hz = Some(hz);
+        self
+    }
+
+    /// How instructions are fit into frames, see [`CPU::run_frame`].
+    pub fn timing(mut self, timing: Timing) -> Self {
+        self.timing = timing;
+        self
+    }
+
+    /// Seeds the CXNN random source, see [`CPU::seed_rng`]. Without a seed
+    /// it's seeded from the OS.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Replaces the 4x5 hex digit sprites, 5 bytes per digit from 0 to F.
+    pub fn font(mut self, font: &'a [u8; FONTSET_SIZE]) -> Self {
+        self.font = Some(font);
+        self
+    }
+
+    /// Replaces the SUPER-CHIP 8x10 digit sprites, 10 bytes per digit from
+    /// 0 to F.
+    pub fn big_font(mut self, font: &'a [u8; BIG_FONTSET_SIZE]) -> Self {
+        self.big_font = Some(font);
+        self
+    }
+
+    /// Applies key changes at frame boundaries only, see
+    /// [`CPU::set_latched_input`].
+    pub fn latched_input(mut self) -> Self {
+        self.latched_input = true;
+        self
+    }
+
+    /// Fails if the ROM can't be loaded, see [`CPU::load`].
+    pub fn build(self) -> Result<CPU, RomError> {
+        let mut cpu = CPU::default();
+        if let Some(font) = self.font {
+            cpu.set_font(font);
+        }
+        if let Some(font) = self.big_font {
+            cpu.set_big_font(font);
+        }
+        let default_start = if self.chip8x {
+            CHIP8X_START_ADDR
+        } else {
+            START_ADDR
+        };
+        let start = self.start_addr.unwrap_or(default_start);
+        if let Some(rom) = self.rom {
+            if self.megachip {
+                cpu.ram.load_megachip(start, rom)?;
+            } else {
+                cpu.ram.load(start, rom)?;
+            }
+            cpu.rom_hash = Some(rom_hash(rom));
+            if start == START_ADDR && is_two_page_rom(rom) {
+                cpu.set_two_page(true);
+            }
+        }
+        cpu.program_counter = start;
+        cpu.set_chip8x(self.chip8x);
+        if let Some(seed) = self.seed {
+            cpu.seed_rng(seed);
+        }
+        cpu.quirks = self.quirks;
+        cpu.set_bounds_policy(self.bounds_policy);
+        if let Some(hz) = self.clock_hz {
+            cpu.set_clock_hz(hz);
+        }
+        cpu.timing = self.timing;
+        cpu.set_latched_input(self.latched_input);
+        Ok(cpu)
+    }
+}
@@ -0,0 +1,99 @@
+//! The CHIP-8X extension of the COSMAC VIP interpreter: the colors of the
+//! VP-590 color board and a second keypad, see [`CPU::set_chip8x`].
+
+use crate::screen::{CHIP8X_BACKGROUNDS, CHIP8X_COLORS, CHIP8X_ZONE_COLUMNS, SCREEN_HEIGHT};
+use crate::{Chip8Error, CPU};
+
+// BXY0 colors zones of 8 pixels by 4 rows
+const ZONE_ROWS: usize = 4;
+
+impl CPU {
+    /// Runs CHIP-8X programs: 02A0 cycles the background color, BXYN
+    /// colors the display instead of jumping, and the colors show in
+    /// [`FrameBuffer::rgb`]. Their ROMs start at [`CHIP8X_START_ADDR`], see
+    /// [`Chip8Builder::chip8x`].
+    ///
+    /// [`FrameBuffer::rgb`]: crate::screen::FrameBuffer::rgb
+    /// [`CHIP8X_START_ADDR`]: crate::CHIP8X_START_ADDR
+    /// [`Chip8Builder::chip8x`]: crate::Chip8Builder::chip8x
+    pub fn set_chip8x(&mut self, on: bool) {
+        self.screen.chip8x = on.then(Box::default);
+        self.screen.mark_all_dirty();
+    }
+
+    pub fn is_chip8x(&self) -> bool {
+        self.screen.chip8x.is_some()
+    }
+
+    /// Presses or releases key 0x0 to 0xF of the second keypad of CHIP-8X,
+    /// other keys are ignored.
+    pub fn keypress2(&mut self, idx: usize, pressed: bool) {
+        if let Some(key) = self.keys2.get_mut(idx) {
+            *key = pressed;
+        }
+    }
+
+    /// Whether key `vx` of the second keypad is held, for EXF2 and EXF5.
+    pub(crate) fn key2(&self, vx: u8) -> Result<bool, Chip8Error> {
+        self.keys2
+            .get(vx as usize)
+            .copied()
+            .ok_or(Chip8Error::InvalidKey(vx))
+    }
+
+    /// 02A0: the next background color, blue, black, green then red.
+    pub(crate) fn next_background(&mut self) {
+        if let Some(colors) = &mut self.screen.chip8x {
+            colors.background = (colors.background + 1) % CHIP8X_BACKGROUNDS.len() as u8;
+        }
+        self.screen.mark_all_dirty();
+    }
+
+    /// BXY0: colors zones of 8 pixels by 4 rows with Vy. The low nibble of
+    /// Vx is the first column of zones and its high nibble how many more
+    /// follow, Vx+1 the same for the rows.
+    pub(crate) fn color_zones(&mut self, x: u8, y: u8) {
+        let vx = self.v_registers[x as usize];
+        let vx1 = self.v_registers[(x as usize + 1) % self.v_registers.len()];
+        let color = self.v_registers[y as usize] % CHIP8X_COLORS.len() as u8;
+        let columns = (vx & 0xF) as usize..=((vx & 0xF) + (vx >> 4)) as usize;
+        let rows =
+            (vx1 & 0xF) as usize * ZONE_ROWS..((vx1 & 0xF) + (vx1 >> 4) + 1) as usize * ZONE_ROWS;
+        self.fill_zones(columns, rows, color);
+    }
+
+    /// BXYN: colors the zones of the N rows from Vx+1 in the column of
+    /// pixel Vx with Vy.
+    pub(crate) fn color_rows(&mut self, x: u8, y: u8, n: u8) {
+        let vx = self.v_registers[x as usize] as usize;
+        let vx1 = self.v_registers[(x as usize + 1) % self.v_registers.len()] as usize;
+        let color = self.v_registers[y as usize] % CHIP8X_COLORS.len() as u8;
+        let column = vx / 8 % CHIP8X_ZONE_COLUMNS;
+        self.fill_zones(column..=column, vx1..vx1 + n as usize, color);
+    }
+
+    // zones out of the display are left alone
+    fn fill_zones(
+        &mut self,
+        columns: impl Iterator<Item = usize> + Clone,
+        rows: impl Iterator<Item = usize>,
+        color: u8,
+    ) {
+        let Some(colors) = &mut self.screen.chip8x else {
+            return;
+        };
+        for row in rows.filter(|&row| row < SCREEN_HEIGHT) {
+            for column in columns.clone().filter(|&col| col < CHIP8X_ZONE_COLUMNS) {
+                colors.zones[row * CHIP8X_ZONE_COLUMNS + column] = color;
+            }
+        }
+        self.screen.mark_all_dirty();
+    }
+}
+
+/// 5XY1: adds the nibbles of `a` and `b` separately, each modulo 8.
+pub(crate) fn add_nibbles(a: u8, b: u8) -> u8 {
+    let high = ((a >> 4) + (b >> 4)) % 8;
+    let low = ((a & 0xF) + (b & 0xF)) % 8;
+    high << 4 | low
+}
@@ -0,0 +1,80 @@
+//! Which bytes of memory were executed, to find the code a test never
+//! reached or the content of a ROM nothing jumps to. See
+//! [`CPU::set_coverage`].
+
+use std::ops::Range;
+
+use crate::{Instruction, CPU};
+
+/// The bytes executed since coverage started, the two of every opcode and
+/// the address word after 01NN.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    // by address, grown as far as the program ran
+    executed: Vec<bool>,
+}
+
+impl Coverage {
+    pub fn is_executed(&self, address: usize) -> bool {
+        self.executed.get(address).copied().unwrap_or(false)
+    }
+
+    /// A flag per byte of memory from address 0, set for the ones executed.
+    /// Shorter than the memory when its end never ran.
+    pub fn bitmap(&self) -> &[bool] {
+        &self.executed
+    }
+
+    /// How many bytes of `range` were executed.
+    pub fn executed_in(&self, range: Range<usize>) -> usize {
+        range.filter(|&address| self.is_executed(address)).count()
+    }
+
+    /// Share of `range` executed, from 0 to 1, e.g. of the ROM: sprites and
+    /// other data among its code are never executed.
+    pub fn share(&self, range: Range<usize>) -> f64 {
+        if range.is_empty() {
+            return 0.0;
+        }
+        self.executed_in(range.clone()) as f64 / range.len() as f64
+    }
+
+    /// The runs of bytes of `range` never executed, in address order.
+    pub fn gaps(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        let mut gaps: Vec<Range<usize>> = Vec::new();
+        for address in range.filter(|&address| !self.is_executed(address)) {
+            match gaps.last_mut() {
+                Some(gap) if gap.end == address => gap.end += 1,
+                _ => gaps.push(address..address + 1),
+            }
+        }
+        gaps
+    }
+
+    pub(crate) fn record(&mut self, address: u16, instruction: Instruction) {
+        let len = match instruction {
+            Instruction::LoadLongI(_) => 4,
+            _ => 2,
+        };
+        let range = address as usize..address as usize + len;
+        if self.executed.len() < range.end {
+            self.executed.resize(range.end, false);
+        }
+        self.executed[range].fill(true);
+    }
+}
+
+impl CPU {
+    /// Starts recording which bytes are executed, or stops and forgets
+    /// them.
+    pub fn set_coverage(&mut self, enabled: bool) {
+        if enabled != self.coverage.is_some() {
+            self.coverage = enabled.then(Coverage::default);
+        }
+    }
+
+    /// The bytes executed since coverage started, None when it's off.
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+}
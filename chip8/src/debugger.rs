@@ -0,0 +1,208 @@
+//! Breakpoints, single stepping and state inspection on top of a [`CPU`].
+
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use crate::symbols::Symbols;
+use crate::{Chip8Error, Instruction, CPU};
+
+/// What happened on a call to [`Debugger::tick`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickResult {
+    /// An instruction was executed.
+    Executed,
+    /// The debugger is paused, nothing was executed.
+    Paused,
+    /// The program counter reached a breakpoint, the debugger is now paused
+    /// before executing the instruction at that address.
+    Breakpoint(u16),
+}
+
+/// Owns a CPU and drives it, stopping on breakpoints.
+///
+/// Frontends call [`Debugger::tick`] in place of [`CPU::tick`] and check
+/// [`Debugger::is_paused`] to know whether to keep going.
+pub struct Debugger {
+    cpu: CPU,
+    breakpoints: BTreeSet<u16>,
+    paused: bool,
+    // set when resuming or stepping from a breakpoint, so the instruction
+    // under it runs instead of stopping there again
+    skip_breakpoint: bool,
+    symbols: Symbols,
+}
+
+impl Debugger {
+    pub fn new(cpu: CPU) -> Self {
+        Self {
+            cpu,
+            breakpoints: BTreeSet::new(),
+            paused: false,
+            skip_breakpoint: false,
+            symbols: Symbols::default(),
+        }
+    }
+
+    pub fn cpu(&self) -> &CPU {
+        &self.cpu
+    }
+
+    pub fn cpu_mut(&mut self) -> &mut CPU {
+        &mut self.cpu
+    }
+
+    pub fn into_cpu(self) -> CPU {
+        self.cpu
+    }
+
+    /// Names addresses after the source of the program, e.g. read from the
+    /// symbol file of an Octo program.
+    pub fn set_symbols(&mut self, symbols: Symbols) {
+        self.symbols = symbols;
+    }
+
+    pub fn symbols(&self) -> &Symbols {
+        &self.symbols
+    }
+
+    /// The label the program counter is in, and how far past it, see
+    /// [`Symbols::locate`].
+    pub fn location(&self) -> Option<(&str, u16)> {
+        self.symbols.locate(self.cpu.program_counter)
+    }
+
+    /// The source line the next instruction was built from.
+    pub fn source_line(&self) -> Option<usize> {
+        self.symbols.source_line(self.cpu.program_counter)
+    }
+
+    /// The alias of register Vx in the source, or `VX` without one.
+    pub fn register_name(&self, x: u8) -> String {
+        match self.symbols.alias(x) {
+            Some(alias) => alias.to_string(),
+            None => format!("V{x:X}"),
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    /// Adds a breakpoint at the label called `name` in the symbols, see
+    /// [`Debugger::set_symbols`], and returns its address.
+    pub fn add_breakpoint_at(&mut self, name: &str) -> Option<u16> {
+        let addr = self.symbols.labels().find(|&(_, label)| label == name)?.0;
+        self.add_breakpoint(addr);
+        Some(addr)
+    }
+
+    /// Adds a breakpoint at the first instruction built from source line
+    /// `line`, and returns its address.
+    pub fn add_breakpoint_at_line(&mut self, line: usize) -> Option<u16> {
+        let addr = self.symbols.line_address(line)?;
+        self.add_breakpoint(addr);
+        Some(addr)
+    }
+
+    /// Returns whether there was a breakpoint at `addr`.
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// The breakpoint addresses in ascending order.
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.skip_breakpoint = true;
+    }
+
+    /// Executes one instruction unless paused or sitting on a breakpoint.
+    /// The debugger pauses if the instruction fails, so the faulting state
+    /// can be inspected.
+    pub fn tick(&mut self) -> Result<TickResult, Chip8Error> {
+        if self.paused {
+            return Ok(TickResult::Paused);
+        }
+        let pc = self.cpu.program_counter;
+        if !std::mem::take(&mut self.skip_breakpoint) && self.breakpoints.contains(&pc) {
+            self.paused = true;
+            return Ok(TickResult::Breakpoint(pc));
+        }
+        self.cpu.tick().inspect_err(|_| self.paused = true)?;
+        Ok(TickResult::Executed)
+    }
+
+    /// Executes exactly one instruction, ignoring breakpoints, and pauses.
+    pub fn step(&mut self) -> Result<(), Chip8Error> {
+        self.paused = true;
+        self.skip_breakpoint = false;
+        self.cpu.tick()
+    }
+
+    pub fn program_counter(&self) -> u16 {
+        self.cpu.program_counter
+    }
+
+    /// The instruction at the program counter, the next to execute, `None`
+    /// past the end of the RAM.
+    pub fn instruction(&self) -> Option<Instruction> {
+        let pc = self.cpu.program_counter as usize;
+        match *self.cpu.read_memory(pc..pc + 2) {
+            [hi, lo] => Some(Instruction::decode(u16::from_be_bytes([hi, lo]))),
+            _ => None,
+        }
+    }
+
+    pub fn i_register(&self) -> u16 {
+        self.cpu.i_register
+    }
+
+    /// V0 to VF.
+    pub fn v_registers(&self) -> &[u8] {
+        &self.cpu.v_registers
+    }
+
+    /// The return addresses of the active subroutine calls, oldest first.
+    pub fn stack(&self) -> &[u16] {
+        &self.cpu.stack.stack[..self.cpu.stack.stack_point as usize]
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.cpu.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.cpu.sound_timer
+    }
+
+    /// The RAM in `range`, clamped to the RAM size.
+    pub fn memory(&self, range: Range<usize>) -> &[u8] {
+        self.cpu.read_memory(range)
+    }
+
+    /// Pokes `data` into the RAM at `addr`, see [`CPU::write_memory`].
+    pub fn write_memory(&mut self, addr: usize, data: &[u8]) -> Result<(), Chip8Error> {
+        self.cpu.write_memory(addr, data)
+    }
+}
+
+impl From<CPU> for Debugger {
+    fn from(cpu: CPU) -> Self {
+        Self::new(cpu)
+    }
+}
@@ -0,0 +1,228 @@
+//! What changed between two save states, see [`SaveState::diff`], to find
+//! where two runs of a program diverge.
+
+use std::fmt;
+
+use crate::screen::{
+    HIRES_SCREEN_HEIGHT, HIRES_SCREEN_WIDTH, MEGACHIP_SCREEN_HEIGHT, MEGACHIP_SCREEN_WIDTH,
+    SCREEN_HEIGHT, SCREEN_WIDTH, TWO_PAGE_SCREEN_HEIGHT,
+};
+use crate::{MegaChipDisplay, SaveState};
+
+// bytes listed per line of the memory changes
+const MEMORY_LINE: usize = 16;
+
+/// A register, timer, stack slot or flag that differs, booleans are 0 or 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    pub name: String,
+    pub old: u16,
+    pub new: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryChange {
+    pub address: u32,
+    pub old: u8,
+    pub new: u8,
+}
+
+/// A pixel of a different color, see [`FrameBuffer::color`]. The colors
+/// of MegaChip programs are 0 for black and 1 for any other.
+///
+/// [`FrameBuffer::color`]: crate::screen::FrameBuffer::color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelChange {
+    pub x: usize,
+    pub y: usize,
+    pub old: u8,
+    pub new: u8,
+}
+
+/// The differences between two save states, printed as a report of the
+/// fields, the memory bytes and a map of the display pixels that changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateDiff {
+    pub fields: Vec<FieldChange>,
+    pub memory: Vec<MemoryChange>,
+    /// Compared at the resolution of the newer state.
+    pub pixels: Vec<PixelChange>,
+    width: usize,
+    height: usize,
+}
+
+impl StateDiff {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty() && self.memory.is_empty() && self.pixels.is_empty()
+    }
+}
+
+impl SaveState {
+    /// What changed from this state to `other`.
+    pub fn diff(&self, other: &SaveState) -> StateDiff {
+        let mut fields = Vec::new();
+        let mut field = |name: String, old: u16, new: u16| {
+            if old != new {
+                fields.push(FieldChange { name, old, new });
+            }
+        };
+        field("PC".into(), self.program_counter, other.program_counter);
+        field("I".into(), self.i_register, other.i_register);
+        for (x, (&old, &new)) in self.v_registers.iter().zip(&other.v_registers).enumerate() {
+            field(format!("V{x:X}"), old as u16, new as u16);
+        }
+        field("SP".into(), self.stack_pointer, other.stack_pointer);
+        for (idx, (&old, &new)) in self.stack.iter().zip(&other.stack).enumerate() {
+            field(format!("stack[{idx}]"), old, new);
+        }
+        field(
+            "DT".into(),
+            self.delay_timer as u16,
+            other.delay_timer as u16,
+        );
+        field(
+            "ST".into(),
+            self.sound_timer as u16,
+            other.sound_timer as u16,
+        );
+        for (key, (&old, &new)) in self.keys.iter().zip(&other.keys).enumerate() {
+            field(format!("key {key:X}"), old as u16, new as u16);
+        }
+        // no key is 0xFF, like in the binary form
+        let key_wait = |state: &SaveState| state.key_wait.map_or(0xFF, u16::from);
+        field("key wait".into(), key_wait(self), key_wait(other));
+        field(
+            "planes".into(),
+            self.selected_planes as u16,
+            other.selected_planes as u16,
+        );
+        field("hires".into(), self.hires as u16, other.hires as u16);
+        field(
+            "two_page".into(),
+            self.two_page as u16,
+            other.two_page as u16,
+        );
+        field("halted".into(), self.halted as u16, other.halted as u16);
+        field(
+            "waiting_vblank".into(),
+            self.waiting_vblank as u16,
+            other.waiting_vblank as u16,
+        );
+        let flags = |state: &SaveState| state.flags.unwrap_or_default();
+        for (idx, (old, new)) in flags(self).into_iter().zip(flags(other)).enumerate() {
+            field(format!("flag {idx:X}"), old as u16, new as u16);
+        }
+        field(
+            "megachip".into(),
+            self.megachip_display().is_some() as u16,
+            other.megachip_display().is_some() as u16,
+        );
+
+        let memory = (0..self.ram.len().min(other.ram.len()))
+            .filter(|&addr| self.ram[addr] != other.ram[addr])
+            .map(|addr| MemoryChange {
+                address: addr as u32,
+                old: self.ram[addr],
+                new: other.ram[addr],
+            })
+            .collect();
+
+        let (width, height) = if other.megachip_display().is_some() {
+            (MEGACHIP_SCREEN_WIDTH, MEGACHIP_SCREEN_HEIGHT)
+        } else if other.hires {
+            (HIRES_SCREEN_WIDTH, HIRES_SCREEN_HEIGHT)
+        } else if other.two_page {
+            (SCREEN_WIDTH, TWO_PAGE_SCREEN_HEIGHT)
+        } else {
+            (SCREEN_WIDTH, SCREEN_HEIGHT)
+        };
+        let mut pixels = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let idx = y * width + x;
+                let change = match (self.megachip_display(), other.megachip_display()) {
+                    (Some(old), Some(new)) => {
+                        let (old, new) = (old.front[idx], new.front[idx]);
+                        let lit = |rgb: [u8; 3]| (rgb != [0; 3]) as u8;
+                        (old != new).then(|| (lit(old), lit(new)))
+                    }
+                    _ => {
+                        let (old, new) = (self.color(idx), other.color(idx));
+                        (old != new).then_some((old, new))
+                    }
+                };
+                if let Some((old, new)) = change {
+                    pixels.push(PixelChange { x, y, old, new });
+                }
+            }
+        }
+
+        StateDiff {
+            fields,
+            memory,
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    fn megachip_display(&self) -> Option<&MegaChipDisplay> {
+        self.megachip.as_ref()?.display.as_ref()
+    }
+
+    // the color index of a pixel, bit n set when lit in plane n
+    fn color(&self, idx: usize) -> u8 {
+        let lit = |plane: &[bool]| plane.get(idx).copied().unwrap_or(false) as u8;
+        lit(&self.display) | lit(&self.display2) << 1
+    }
+}
+
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no differences");
+        }
+        for change in &self.fields {
+            writeln!(f, "{}: {:#X} -> {:#X}", change.name, change.old, change.new)?;
+        }
+
+        if !self.memory.is_empty() {
+            writeln!(f, "{} memory bytes differ:", self.memory.len())?;
+        }
+        // consecutive bytes share a line
+        let runs = self.memory.chunk_by(|a, b| b.address == a.address + 1);
+        for run in runs.flat_map(|run| run.chunks(MEMORY_LINE)) {
+            let hex = |byte: fn(&MemoryChange) -> u8| {
+                run.iter()
+                    .map(|change| format!("{:02X}", byte(change)))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            writeln!(
+                f,
+                "  {:#05X}: {} -> {}",
+                run[0].address,
+                hex(|change| change.old),
+                hex(|change| change.new)
+            )?;
+        }
+
+        if self.pixels.is_empty() {
+            return Ok(());
+        }
+        // `+` lit, `-` cleared, `*` of another color, `.` unchanged
+        writeln!(f, "{} pixels differ:", self.pixels.len())?;
+        let mut map = vec![b'.'; self.width * self.height];
+        for pixel in &self.pixels {
+            map[pixel.y * self.width + pixel.x] = match (pixel.old, pixel.new) {
+                (0, _) => b'+',
+                (_, 0) => b'-',
+                _ => b'*',
+            };
+        }
+        for row in map.chunks(self.width) {
+            writeln!(f, "{}", String::from_utf8_lossy(row))?;
+        }
+        Ok(())
+    }
+}
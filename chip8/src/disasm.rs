@@ -0,0 +1,222 @@
+//! Turns raw ROM bytes into human readable mnemonics, line by line or as
+//! an annotated listing following the program, see [`annotate_rom`].
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::lint::follow;
+use crate::symbols::Symbols;
+use crate::Instruction;
+
+// data bytes per `DB` line of a listing
+const DATA_LINE_BYTES: usize = 8;
+
+/// One disassembled instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub address: u16,
+    pub opcode: u16,
+    pub mnemonic: String,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:03X}: {}", self.address, self.mnemonic)
+    }
+}
+
+/// Disassembles `rom` as if it was loaded at address `start`, two bytes per
+/// instruction. A trailing odd byte is emitted as a `DB` data byte.
+pub fn disassemble_rom(rom: &[u8], start: u16) -> Vec<Line> {
+    rom.chunks(2)
+        .enumerate()
+        .map(|(idx, bytes)| {
+            let address = start.wrapping_add(2 * idx as u16);
+            match *bytes {
+                [hi, lo] => {
+                    let opcode = (hi as u16) << 8 | lo as u16;
+                    Line {
+                        address,
+                        opcode,
+                        mnemonic: disassemble(opcode),
+                    }
+                }
+                [byte] => Line {
+                    address,
+                    opcode: byte as u16,
+                    mnemonic: format!("DB 0x{byte:02X}"),
+                },
+                _ => unreachable!(),
+            }
+        })
+        .collect()
+}
+
+/// Returns the mnemonic for a single opcode, or a `DW` data word if the
+/// opcode doesn't decode to any instruction.
+pub fn disassemble(op: u16) -> String {
+    Instruction::decode(op).to_string()
+}
+
+/// A disassembly following the program, see [`annotate_rom`]. Prints as
+/// source [`crate::asm`] assembles back into the ROM when it starts at
+/// 0x200 and the labels have names the assembler takes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listing {
+    pub entries: Vec<Entry>,
+}
+
+/// A line of a [`Listing`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Entry {
+    /// `L_022A:`, an address jumped to, called or pointed I at, or named
+    /// by the symbols.
+    Label { address: u16, name: String },
+    /// An instruction the program reaches, jumping and calling labels.
+    Code(Line),
+    /// Bytes no path runs, sprites and other data.
+    Data { address: u16, bytes: Vec<u8> },
+}
+
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            match entry {
+                Entry::Label { name, .. } => writeln!(f, "{name}:")?,
+                Entry::Code(line) => writeln!(
+                    f,
+                    "    {:<23} ; 0x{:03X}: {:04X}",
+                    line.mnemonic, line.address, line.opcode
+                )?,
+                Entry::Data { address, bytes } => {
+                    let bytes: Vec<_> = bytes.iter().map(|byte| format!("0x{byte:02X}")).collect();
+                    let data = format!("DB {}", bytes.join(", "));
+                    writeln!(f, "    {data:<23} ; 0x{address:03X}")?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Disassembles `rom` loaded at `start` by following every path through
+/// it like [`crate::lint::lint`]: the instructions reached are code, the
+/// rest data, and the addresses jumped to, called or loaded into I get
+/// labels.
+pub fn annotate_rom(rom: &[u8], start: u16) -> Listing {
+    annotate_rom_with(rom, start, &Symbols::default())
+}
+
+/// Like [`annotate_rom`], naming the labels after `symbols`, e.g. read from
+/// the symbol file of an Octo program.
+pub fn annotate_rom_with(rom: &[u8], start: u16, symbols: &Symbols) -> Listing {
+    let flow = follow(rom, start);
+    let at = |address: usize| start.wrapping_add(address as u16);
+    let opcode = |offset: usize| (rom[offset] as u16) << 8 | rom[offset + 1] as u16;
+
+    // the instructions the listing shows, an instruction reached in the
+    // middle of another one shows as part of it
+    let mut code = Vec::new();
+    let mut covered = HashSet::new();
+    let mut offset = 0;
+    while offset < rom.len() {
+        if !flow.code.contains(&at(offset)) {
+            offset += 1;
+            continue;
+        }
+        let instruction = Instruction::decode(opcode(offset));
+        // LDHI takes the word after it too
+        let len = match instruction {
+            Instruction::LoadLongI(_) if offset + 4 <= rom.len() => 4,
+            _ => 2,
+        };
+        code.push(offset);
+        covered.extend(offset + 1..offset + len);
+        offset += len;
+    }
+
+    // labels only go where a line of the listing starts
+    let pointed = code
+        .iter()
+        .filter_map(|&offset| match Instruction::decode(opcode(offset)) {
+            Instruction::LoadI(addr) => Some(addr),
+            _ => None,
+        });
+    let labels: BTreeMap<u16, String> = flow
+        .targets
+        .iter()
+        .copied()
+        .chain(pointed)
+        .chain(symbols.labels().map(|(addr, _)| addr))
+        .filter(|&addr| {
+            let offset = addr.wrapping_sub(start) as usize;
+            addr >= start && offset < rom.len() && !covered.contains(&offset)
+        })
+        .map(|addr| {
+            let name = symbols
+                .label(addr)
+                .map_or_else(|| label(addr), String::from);
+            (addr, name)
+        })
+        .collect();
+    let operand = |addr: u16| match labels.get(&addr) {
+        Some(name) => name.clone(),
+        None => format!("0x{addr:03X}"),
+    };
+
+    let mut entries = Vec::new();
+    let mut code = code.into_iter().peekable();
+    let mut offset = 0;
+    while offset < rom.len() {
+        let address = at(offset);
+        if let Some(name) = labels.get(&address) {
+            entries.push(Entry::Label {
+                address,
+                name: name.clone(),
+            });
+        }
+        if code.next_if_eq(&offset).is_some() {
+            let op = opcode(offset);
+            let instruction = Instruction::decode(op);
+            let mnemonic = match instruction {
+                Instruction::Jump(addr) => format!("JP {}", operand(addr)),
+                Instruction::Call(addr) => format!("CALL {}", operand(addr)),
+                Instruction::LoadI(addr) => format!("LD I, {}", operand(addr)),
+                _ => instruction.to_string(),
+            };
+            entries.push(Entry::Code(Line {
+                address,
+                opcode: op,
+                mnemonic,
+            }));
+            offset += 2;
+            if matches!(instruction, Instruction::LoadLongI(_)) && covered.contains(&offset) {
+                let low = opcode(offset);
+                entries.push(Entry::Code(Line {
+                    address: at(offset),
+                    opcode: low,
+                    mnemonic: format!("DW 0x{low:04X}"),
+                }));
+                offset += 2;
+            }
+            continue;
+        }
+        // data up to the next line of code or label
+        let len = (offset + 1..rom.len())
+            .take(DATA_LINE_BYTES - 1)
+            .take_while(|&next| code.peek() != Some(&next) && !labels.contains_key(&at(next)))
+            .count()
+            + 1;
+        entries.push(Entry::Data {
+            address,
+            bytes: rom[offset..offset + len].to_vec(),
+        });
+        offset += len;
+    }
+    Listing { entries }
+}
+
+// the name of the label at `address`
+fn label(address: u16) -> String {
+    format!("L_{address:04X}")
+}
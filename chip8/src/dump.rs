@@ -0,0 +1,56 @@
+//! Human readable dumps of the machine, for tests and crash logs.
+
+use std::fmt;
+
+use crate::CPU;
+
+/// The registers, timers and flags, without the memory and the display.
+impl fmt::Debug for CPU {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state();
+        f.debug_struct("CPU")
+            .field(
+                "program_counter",
+                &format_args!("{:#05X}", state.program_counter),
+            )
+            .field("i_register", &format_args!("{:#05X}", state.i_register))
+            .field("v_registers", &format_args!("{:02X?}", state.v_registers))
+            .field("stack", &format_args!("{:03X?}", state.call_stack()))
+            .field("delay_timer", &state.delay_timer)
+            .field("sound_timer", &state.sound_timer)
+            .field("hires", &self.screen.hires)
+            .field("two_page", &self.screen.two_page)
+            .field("halted", &self.halted)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Registers, stack and timers followed by the display drawn in ASCII, see
+/// the `Display` of [`FrameBuffer`].
+///
+/// [`FrameBuffer`]: crate::screen::FrameBuffer
+impl fmt::Display for CPU {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state();
+        writeln!(
+            f,
+            "PC {:03X}  I {:03X}  DT {:02X}  ST {:02X}{}",
+            state.program_counter,
+            state.i_register,
+            state.delay_timer,
+            state.sound_timer,
+            if self.halted { "  halted" } else { "" }
+        )?;
+        for (x, value) in state.v_registers.iter().enumerate() {
+            let sep = if x == 0 { "" } else { " " };
+            write!(f, "{sep}V{x:X} {value:02X}")?;
+        }
+        writeln!(f)?;
+        write!(f, "stack")?;
+        for addr in state.call_stack() {
+            write!(f, " {addr:03X}")?;
+        }
+        writeln!(f)?;
+        write!(f, "{}", self.frame_buffer())
+    }
+}
@@ -0,0 +1,60 @@
+use std::fmt;
+
+/// Errors raised while executing a program, instead of aborting the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Chip8Error {
+    /// The opcode doesn't decode to any known instruction.
+    UnknownOpcode { opcode: u16, address: u16 },
+    /// A subroutine call was made with the stack already full.
+    StackOverflow,
+    /// A return was made outside of any subroutine.
+    StackUnderflow,
+    /// A read or write went past the end of the RAM.
+    AddressOutOfBounds(usize),
+    /// A key instruction referenced a key outside of the 16 key keypad.
+    InvalidKey(u8),
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Error::UnknownOpcode { opcode, address } => {
+                write!(f, "unknown opcode {opcode:#06X} at {address:#05X}")
+            }
+            Chip8Error::StackOverflow => write!(f, "stack overflow"),
+            Chip8Error::StackUnderflow => write!(f, "return with an empty stack"),
+            Chip8Error::AddressOutOfBounds(addr) => {
+                write!(f, "memory access out of bounds at {addr:#X}")
+            }
+            Chip8Error::InvalidKey(key) => write!(f, "invalid key {key:#X}"),
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {}
+
+/// Errors raised when loading a ROM that can't be a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RomError {
+    /// The ROM has no bytes at all.
+    Empty,
+    /// The ROM doesn't fit in the RAM after the address it's loaded at.
+    TooLarge { len: usize, max: usize },
+    /// Every byte of the ROM is 0x00 or every byte is 0xFF, like an erased
+    /// or zeroed image.
+    Blank,
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomError::Empty => write!(f, "ROM is empty"),
+            RomError::TooLarge { len, max } => {
+                write!(f, "ROM is {len} bytes, at most {max} fit in memory")
+            }
+            RomError::Blank => write!(f, "ROM is blank, every byte is the same filler"),
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
@@ -0,0 +1,49 @@
+//! The user flags FX75 and FX85 store and load, the RPL flags of the HP48
+//! calculators SUPER-CHIP ran on. Games keep high scores and progress in
+//! them, frontends persist them per ROM like battery saves, see
+//! [`CPU::set_flags_sink`].
+
+use crate::CPU;
+
+/// Number of user flags, 8 on the HP48 and 16 with XO-CHIP.
+pub const NUM_FLAGS: usize = 16;
+
+/// Receives the user flags whenever the program stores them. Implemented
+/// for closures that are `Send` and `Sync`, like the machine.
+pub trait FlagsSink: Send + Sync {
+    fn store(&mut self, flags: &[u8; NUM_FLAGS]);
+}
+
+impl<F: FnMut(&[u8; NUM_FLAGS]) + Send + Sync> FlagsSink for F {
+    fn store(&mut self, flags: &[u8; NUM_FLAGS]) {
+        self(flags)
+    }
+}
+
+impl CPU {
+    /// The user flags. Save states carry them, so a state loaded after
+    /// FX75 reads back what was stored.
+    pub fn flags(&self) -> &[u8; NUM_FLAGS] {
+        &self.flags
+    }
+
+    /// Sets the user flags, e.g. to the ones persisted by the last session.
+    /// Extra bytes are ignored, missing ones left as they were.
+    pub fn set_flags(&mut self, flags: &[u8]) {
+        let len = flags.len().min(NUM_FLAGS);
+        self.flags[..len].copy_from_slice(&flags[..len]);
+    }
+
+    /// Sends the user flags to `sink` every time FX75 stores them, or stops
+    /// with `None`.
+    pub fn set_flags_sink(&mut self, sink: Option<Box<dyn FlagsSink>>) {
+        self.flags_sink = sink;
+    }
+
+    pub(crate) fn store_flags(&mut self, count: usize) {
+        self.flags[..count].copy_from_slice(&self.v_registers[..count]);
+        if let Some(sink) = &mut self.flags_sink {
+            sink.store(&self.flags);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Many independent machines running one ROM in parallel, e.g. to search
+//! quirk settings or sweep a ROM collection for compatibility, see
+//! [`Fleet`].
+
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::thread;
+
+use crate::{Chip8Builder, Chip8Error, RomError, CPU};
+
+/// Machines built from one ROM, run frame by frame on a pool of threads.
+/// The fleet keeps the ROM to build them from, every machine holds its own
+/// copy in its RAM next to its own display and random source, there is no
+/// state shared between them.
+///
+/// ```ignore
+/// let mut fleet = Fleet::new(rom);
+/// for quirks in [Quirks::VIP, Quirks::SUPER_CHIP] {
+///     fleet.spawn(|builder| builder.quirks(quirks).seed(1))?;
+/// }
+/// let results = fleet.run_frames(600);
+/// ```
+pub struct Fleet {
+    rom: Arc<[u8]>,
+    machines: Vec<CPU>,
+    threads: usize,
+}
+
+impl Fleet {
+    /// No machines yet, see [`Fleet::spawn`]. Runs on as many threads as
+    /// the system has cores.
+    pub fn new(rom: impl Into<Arc<[u8]>>) -> Self {
+        Self {
+            rom: rom.into(),
+            machines: Vec::new(),
+            threads: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        }
+    }
+
+    /// Caps the threads [`Fleet::run_frames`] uses, at least one.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// The ROM every machine was built from.
+    pub fn rom(&self) -> &Arc<[u8]> {
+        &self.rom
+    }
+
+    /// Adds a machine running the ROM, copied into its RAM, configured by
+    /// `configure`, and returns its index.
+    pub fn spawn(
+        &mut self,
+        configure: impl FnOnce(Chip8Builder<'_>) -> Chip8Builder<'_>,
+    ) -> Result<usize, RomError> {
+        let cpu = configure(Chip8Builder::new().rom(&self.rom)).build()?;
+        self.machines.push(cpu);
+        Ok(self.machines.len() - 1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.machines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.machines.is_empty()
+    }
+
+    pub fn cpus(&self) -> &[CPU] {
+        &self.machines
+    }
+
+    pub fn cpus_mut(&mut self) -> &mut [CPU] {
+        &mut self.machines
+    }
+
+    pub fn into_cpus(self) -> Vec<CPU> {
+        self.machines
+    }
+
+    /// Runs `frames` frames on every machine, ticking their timers after
+    /// each, see [`CPU::run_frame`]. A machine stops at its first error,
+    /// which is returned in its slot.
+    pub fn run_frames(&mut self, frames: usize) -> Vec<Result<(), Chip8Error>> {
+        self.for_each(|_, cpu| {
+            for _ in 0..frames {
+                cpu.run_frame(cpu.clock_hz())?;
+                cpu.tick_timers();
+            }
+            Ok(())
+        })
+    }
+
+    /// Calls `f` with the index of every machine and the machine, spread
+    /// over the threads, and collects what it returns in machine order.
+    pub fn for_each<T: Send>(&mut self, f: impl Fn(usize, &mut CPU) -> T + Sync) -> Vec<T> {
+        let chunk = self.machines.len().div_ceil(self.threads).max(1);
+        let f = &f;
+        thread::scope(|scope| {
+            let workers: Vec<_> = self
+                .machines
+                .chunks_mut(chunk)
+                .enumerate()
+                .map(|(n, machines)| {
+                    scope.spawn(move || {
+                        machines
+                            .iter_mut()
+                            .enumerate()
+                            .map(|(idx, cpu)| f(n * chunk + idx, cpu))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("a fleet worker panicked"))
+                .collect()
+        })
+    }
+}
@@ -0,0 +1,200 @@
+//! Traits for plugging the CPU into a frontend, and a [`Driver`] pumping a
+//! CPU against them so new frontends don't have to rewrite the main loop.
+
+use std::future::Future;
+use std::ops::ControlFlow;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{Chip8Error, CPU, NUM_KEYS};
+
+/// Default number of instructions executed per 60 Hz frame.
+pub const DEFAULT_TICKS_PER_FRAME: usize = 10;
+
+const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+// most frames `Driver::run_for` runs at once to catch up
+const MAX_CATCH_UP_FRAMES: u32 = 4;
+
+/// Something that shows the CHIP-8 display.
+pub trait Display {
+    /// Called once per frame with the pixels of the current resolution,
+    /// row by row.
+    fn draw(&mut self, pixels: &[bool], width: usize, height: usize);
+}
+
+/// Something that reads the 16 key keypad.
+pub trait Input {
+    /// Called once per frame to update `keys` with the keys currently held.
+    /// Breaking stops the driver, e.g. when the user closes the window.
+    fn poll(&mut self, keys: &mut [bool; NUM_KEYS]) -> ControlFlow<()>;
+}
+
+/// Something that reads the keypad without blocking an async runtime, e.g.
+/// a channel fed by a chat bot, see [`Driver::run_async`].
+pub trait AsyncInput {
+    /// Called once per frame to update `keys` with the keys currently held,
+    /// like [`Input::poll`]. Resolving late slows the emulation down.
+    fn poll(&mut self, keys: &mut [bool; NUM_KEYS])
+        -> impl Future<Output = ControlFlow<()>> + Send;
+}
+
+/// Something that plays the buzzer.
+pub trait AudioSink {
+    /// Called when the buzzer starts or stops.
+    fn set_playing(&mut self, playing: bool);
+}
+
+/// A silent frontend.
+impl AudioSink for () {
+    fn set_playing(&mut self, _playing: bool) {}
+}
+
+/// Runs a CPU against a display, an input and an audio sink.
+pub struct Driver<D, I, A> {
+    cpu: CPU,
+    display: D,
+    input: I,
+    audio: A,
+    keys: [bool; NUM_KEYS],
+    playing: bool,
+    // time passed to `run_for` not run yet
+    pending: Duration,
+}
+
+impl<D, I, A> Driver<D, I, A> {
+    /// Runs `cpu` at its own speed, see [`CPU::run_frame`].
+    pub fn new(cpu: CPU, display: D, input: I, audio: A) -> Self {
+        Self {
+            cpu,
+            display,
+            input,
+            audio,
+            keys: [false; NUM_KEYS],
+            playing: false,
+            pending: Duration::ZERO,
+        }
+    }
+
+    /// Sets how many instructions run per frame, the emulation speed.
+    pub fn with_ticks_per_frame(mut self, ticks_per_frame: usize) -> Self {
+        self.cpu.set_ticks_per_frame(ticks_per_frame);
+        self
+    }
+
+    pub fn cpu(&self) -> &CPU {
+        &self.cpu
+    }
+
+    pub fn cpu_mut(&mut self) -> &mut CPU {
+        &mut self.cpu
+    }
+
+    pub fn display_mut(&mut self) -> &mut D {
+        &mut self.display
+    }
+
+    pub fn input_mut(&mut self) -> &mut I {
+        &mut self.input
+    }
+
+    pub fn audio_mut(&mut self) -> &mut A {
+        &mut self.audio
+    }
+
+    pub fn into_parts(self) -> (CPU, D, I, A) {
+        (self.cpu, self.display, self.input, self.audio)
+    }
+}
+
+impl<D: Display, I, A: AudioSink> Driver<D, I, A> {
+    // presses the keys polled, runs one frame worth of instructions, ticks
+    // the timers, then updates the audio and the display
+    fn step(&mut self) -> Result<(), Chip8Error> {
+        for (idx, &pressed) in self.keys.iter().enumerate() {
+            self.cpu.keypress(idx, pressed);
+        }
+
+        self.cpu.run_frame(self.cpu.clock_hz())?;
+        self.cpu.tick_timers();
+
+        let playing = self.cpu.is_sound_playing();
+        if playing != self.playing {
+            self.playing = playing;
+            self.audio.set_playing(playing);
+        }
+
+        let (width, height) = self.cpu.display_size();
+        self.display.draw(self.cpu.get_display(), width, height);
+        Ok(())
+    }
+
+    fn stop_audio(&mut self) {
+        if self.playing {
+            self.audio.set_playing(false);
+        }
+    }
+}
+
+impl<D: Display, I: Input, A: AudioSink> Driver<D, I, A> {
+    /// Polls input, runs one frame worth of instructions, ticks the timers,
+    /// then updates the audio and the display. Doesn't wait, see
+    /// [`Driver::run`] for a paced loop.
+    pub fn run_frame(&mut self) -> Result<ControlFlow<()>, Chip8Error> {
+        if self.input.poll(&mut self.keys).is_break() {
+            return Ok(ControlFlow::Break(()));
+        }
+        self.step()?;
+        Ok(ControlFlow::Continue(()))
+    }
+
+    /// Runs as many frames as fit in `elapsed`, for loops paced by someone
+    /// else at any rate, e.g. the animation frames of a browser. The
+    /// leftover time carries over to the next call.
+    pub fn run_for(&mut self, elapsed: Duration) -> Result<ControlFlow<()>, Chip8Error> {
+        self.pending = self.pending.saturating_add(elapsed);
+        // after a long stall, e.g. a hidden browser tab, skip ahead instead
+        // of running every missed frame at once
+        if self.pending > FRAME_DURATION * MAX_CATCH_UP_FRAMES {
+            self.pending = FRAME_DURATION;
+        }
+        while self.pending >= FRAME_DURATION {
+            self.pending -= FRAME_DURATION;
+            if self.run_frame()?.is_break() {
+                return Ok(ControlFlow::Break(()));
+            }
+        }
+        Ok(ControlFlow::Continue(()))
+    }
+
+    /// Runs frames at 60 Hz until the input breaks or the program fails.
+    pub fn run(&mut self) -> Result<(), Chip8Error> {
+        let mut next_frame = Instant::now();
+        while self.run_frame()?.is_continue() {
+            next_frame += FRAME_DURATION;
+            thread::sleep(next_frame.saturating_duration_since(Instant::now()));
+        }
+        self.stop_audio();
+        Ok(())
+    }
+}
+
+impl<D: Display, I: AsyncInput, A: AudioSink> Driver<D, I, A> {
+    /// Runs frames at 60 Hz inside an async runtime until the input breaks
+    /// or the program fails, awaiting the input every frame and `sleep`
+    /// until the next one, e.g. `tokio::time::sleep`, so other tasks run in
+    /// between.
+    pub async fn run_async<F: Future<Output = ()>>(
+        &mut self,
+        mut sleep: impl FnMut(Duration) -> F,
+    ) -> Result<(), Chip8Error> {
+        let mut next_frame = Instant::now();
+        while self.input.poll(&mut self.keys).await.is_continue() {
+            self.step()?;
+            next_frame += FRAME_DURATION;
+            sleep(next_frame.saturating_duration_since(Instant::now())).await;
+        }
+        self.stop_audio();
+        Ok(())
+    }
+}
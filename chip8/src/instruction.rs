@@ -0,0 +1,466 @@
+//! The instructions opcodes decode to, shared by the interpreter, the
+//! disassembler, the tracer and the debugger.
+
+use std::fmt;
+
+/// A decoded opcode, `x` and `y` are register numbers. Prints as the
+/// mnemonic [`crate::asm`] assembles back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Instruction {
+    /// 0010 - MEGAOFF: Leave the MegaChip mode for the 64x32 display
+    /// (MegaChip).
+    MegaOff,
+    /// 0011 - MEGAON: Switch to the 256x192 MegaChip mode (MegaChip).
+    MegaOn,
+    /// 00BN - SCU nibble: Scroll the display up N lines (MegaChip).
+    ScrollUp(u8),
+    /// 00CN - SCD nibble: Scroll the display down N lines (SUPER-CHIP).
+    ScrollDown(u8),
+    /// 00E0 - CLS: Clear the display.
+    Clear,
+    /// 00EE - RET: Return from a subroutine.
+    Return,
+    /// 00FB - SCR: Scroll the display right 4 pixels (SUPER-CHIP).
+    ScrollRight,
+    /// 00FC - SCL: Scroll the display left 4 pixels (SUPER-CHIP).
+    ScrollLeft,
+    /// 00FD - EXIT: Stop the interpreter (SUPER-CHIP).
+    Exit,
+    /// 00FE - LOW: Switch to the 64x32 low resolution mode (SUPER-CHIP).
+    LowRes,
+    /// 00FF - HIGH: Switch to the 128x64 high resolution mode (SUPER-CHIP).
+    HighRes,
+    /// 01NN - LDHI byte: Set I = NN followed by the next word, a 24 bit
+    /// address (MegaChip).
+    LoadLongI(u8),
+    /// 02NN - LDPAL byte: Load colors 1 to NN of the palette from I, 4 bytes
+    /// of ARGB each (MegaChip).
+    LoadPalette(u8),
+    /// 03NN - SPRW byte: Set the width of sprites to NN, 256 when 0
+    /// (MegaChip).
+    SpriteWidth(u8),
+    /// 04NN - SPRH byte: Set the height of sprites to NN, 256 when 0
+    /// (MegaChip).
+    SpriteHeight(u8),
+    /// 05NN - ALPHA byte: Set the brightness of the screen shown by the next
+    /// CLS (MegaChip).
+    Alpha(u8),
+    /// 060N - DIGISND nibble: Play the digitized sound at I, looping when N
+    /// is 0 (MegaChip).
+    PlaySound(u8),
+    /// 0700 - STOPSND: Stop the digitized sound (MegaChip).
+    StopSound,
+    /// 080N - BMODE nibble: Set how sprites blend with the colors under
+    /// them: normal, 25%, 50% or 75% opaque, additive or multiplied
+    /// (MegaChip).
+    BlendMode(u8),
+    /// 09NN - CCOL byte: Set the color a sprite has to be drawn over for
+    /// DXYN to set VF (MegaChip).
+    CollisionColor(u8),
+    /// 0NNN - SYS addr: Call machine code at NNN, only 0000 runs, as a
+    /// no-op.
+    Sys(u16),
+    /// 1NNN - JP addr: Jump to address NNN.
+    Jump(u16),
+    /// 2NNN - CALL addr: Call subroutine at NNN.
+    Call(u16),
+    /// 3XNN - SE Vx, byte: Skip next instruction if Vx == NN.
+    SkipEqImm { x: u8, nn: u8 },
+    /// 4XNN - SNE Vx, byte: Skip next instruction if Vx != NN.
+    SkipNeImm { x: u8, nn: u8 },
+    /// 5XY0 - SE Vx, Vy: Skip next instruction if Vx == Vy.
+    SkipEq { x: u8, y: u8 },
+    /// 5XY1 - ADDN Vx, Vy: Add each nibble of Vy to the one of Vx, modulo 8
+    /// (CHIP-8X).
+    AddNibbles { x: u8, y: u8 },
+    /// 6XNN - LD Vx, byte: Set Vx = NN.
+    LoadImm { x: u8, nn: u8 },
+    /// 7XNN - ADD Vx, byte: Set Vx = Vx + NN.
+    AddImm { x: u8, nn: u8 },
+    /// 8XY0 - LD Vx, Vy: Set Vx = Vy.
+    Load { x: u8, y: u8 },
+    /// 8XY1 - OR Vx, Vy: Set Vx = Vx OR Vy.
+    Or { x: u8, y: u8 },
+    /// 8XY2 - AND Vx, Vy: Set Vx = Vx AND Vy.
+    And { x: u8, y: u8 },
+    /// 8XY3 - XOR Vx, Vy: Set Vx = Vx XOR Vy.
+    Xor { x: u8, y: u8 },
+    /// 8XY4 - ADD Vx, Vy: Set Vx = Vx + Vy, set VF = carry.
+    Add { x: u8, y: u8 },
+    /// 8XY5 - SUB Vx, Vy: Set Vx = Vx - Vy, set VF = NOT borrow.
+    Sub { x: u8, y: u8 },
+    /// 8XY6 - SHR Vx {, Vy}: Set Vx = Vx SHR 1, or Vy SHR 1 with the shift
+    /// quirk.
+    ShiftRight { x: u8, y: u8 },
+    /// 8XY7 - SUBN Vx, Vy: Set Vx = Vy - Vx, set VF = NOT borrow.
+    SubN { x: u8, y: u8 },
+    /// 8XYE - SHL Vx {, Vy}: Set Vx = Vx SHL 1, or Vy SHL 1 with the shift
+    /// quirk.
+    ShiftLeft { x: u8, y: u8 },
+    /// 9XY0 - SNE Vx, Vy: Skip next instruction if Vx != Vy.
+    SkipNe { x: u8, y: u8 },
+    /// ANNN - LD I, addr: Set I = NNN.
+    LoadI(u16),
+    /// BNNN - JP V0, addr: Jump to address NNN + V0, or XNN + VX with the
+    /// jump quirk. CHIP-8X programs color the display with it instead.
+    JumpOffset { x: u8, nnn: u16 },
+    /// CXNN - RND Vx, byte: Set Vx = random byte AND NN.
+    Random { x: u8, nn: u8 },
+    /// DXYN - DRW Vx, Vy, nibble: Display n-byte sprite at memory location
+    /// I at (Vx, Vy), set VF = collision. A 16x16 sprite when N is 0
+    /// (SUPER-CHIP). In MegaChip mode a sprite of palette indices.
+    Draw { x: u8, y: u8, n: u8 },
+    /// EX9E - SKP Vx: Skip next instruction if key with the value of Vx is
+    /// pressed.
+    SkipKey { x: u8 },
+    /// EXA1 - SKNP Vx: Skip next instruction if key with the value of Vx is
+    /// not pressed.
+    SkipNotKey { x: u8 },
+    /// EXF2 - SKP2 Vx: Skip next instruction if key Vx of the second keypad
+    /// is pressed (CHIP-8X).
+    SkipKey2 { x: u8 },
+    /// EXF5 - SKNP2 Vx: Skip next instruction if key Vx of the second
+    /// keypad is not pressed (CHIP-8X).
+    SkipNotKey2 { x: u8 },
+    /// FN01 - PLANE n: Select the bit-planes drawn, cleared and scrolled, a
+    /// bit per plane (XO-CHIP).
+    Plane(u8),
+    /// FX07 - LD Vx, DT: Set Vx = delay timer value.
+    ReadDelay { x: u8 },
+    /// FX0A - LD Vx, K: Wait for a key press and release, store the value
+    /// of the key in Vx.
+    WaitKey { x: u8 },
+    /// FX15 - LD DT, Vx: Set delay timer = Vx.
+    SetDelay { x: u8 },
+    /// FX18 - LD ST, Vx: Set sound timer = Vx.
+    SetSound { x: u8 },
+    /// FX1E - ADD I, Vx: Set I = I + Vx.
+    AddI { x: u8 },
+    /// FX29 - LD F, Vx: Set I = location of sprite for digit Vx.
+    Font { x: u8 },
+    /// FX30 - LD HF, Vx: Set I = location of the 8x10 sprite for digit Vx
+    /// (SUPER-CHIP).
+    BigFont { x: u8 },
+    /// FX33 - LD B, Vx: Store BCD representation of Vx in memory locations
+    /// I, I+1, and I+2.
+    Bcd { x: u8 },
+    /// FX55 - LD [I], Vx: Store registers V0 through Vx in memory starting
+    /// at location I.
+    Store { x: u8 },
+    /// FX65 - LD Vx, [I]: Read registers V0 through Vx from memory starting
+    /// at location I.
+    Restore { x: u8 },
+    /// FX75 - LD R, Vx: Store registers V0 through Vx in the user flags
+    /// (SUPER-CHIP).
+    SaveFlags { x: u8 },
+    /// FX85 - LD Vx, R: Read registers V0 through Vx from the user flags
+    /// (SUPER-CHIP).
+    LoadFlags { x: u8 },
+    /// An opcode no interpreter runs, printed as a `DW` data word.
+    Unknown(u16),
+}
+
+impl Instruction {
+    pub fn decode(op: u16) -> Instruction {
+        DECODERS[(op >> 12) as usize](op)
+    }
+
+    // the MegaChip instructions but 0011 turning it on, machine code calls
+    // outside its mode
+    pub(crate) fn is_megachip_only(self) -> bool {
+        matches!(
+            self,
+            Instruction::MegaOff
+                | Instruction::ScrollUp(_)
+                | Instruction::LoadLongI(_)
+                | Instruction::LoadPalette(_)
+                | Instruction::SpriteWidth(_)
+                | Instruction::SpriteHeight(_)
+                | Instruction::Alpha(_)
+                | Instruction::PlaySound(_)
+                | Instruction::StopSound
+                | Instruction::BlendMode(_)
+                | Instruction::CollisionColor(_)
+        )
+    }
+
+    /// The opcodes of this instruction, e.g. "8XY4", the family the
+    /// execution statistics count by. "????" for unknown opcodes.
+    pub fn pattern(self) -> &'static str {
+        match self {
+            Instruction::MegaOff => "0010",
+            Instruction::MegaOn => "0011",
+            Instruction::ScrollUp(_) => "00BN",
+            Instruction::ScrollDown(_) => "00CN",
+            Instruction::Clear => "00E0",
+            Instruction::Return => "00EE",
+            Instruction::ScrollRight => "00FB",
+            Instruction::ScrollLeft => "00FC",
+            Instruction::Exit => "00FD",
+            Instruction::LowRes => "00FE",
+            Instruction::HighRes => "00FF",
+            Instruction::LoadLongI(_) => "01NN",
+            Instruction::LoadPalette(_) => "02NN",
+            Instruction::SpriteWidth(_) => "03NN",
+            Instruction::SpriteHeight(_) => "04NN",
+            Instruction::Alpha(_) => "05NN",
+            Instruction::PlaySound(_) => "060N",
+            Instruction::StopSound => "0700",
+            Instruction::BlendMode(_) => "080N",
+            Instruction::CollisionColor(_) => "09NN",
+            Instruction::Sys(_) => "0NNN",
+            Instruction::Jump(_) => "1NNN",
+            Instruction::Call(_) => "2NNN",
+            Instruction::SkipEqImm { .. } => "3XNN",
+            Instruction::SkipNeImm { .. } => "4XNN",
+            Instruction::SkipEq { .. } => "5XY0",
+            Instruction::AddNibbles { .. } => "5XY1",
+            Instruction::LoadImm { .. } => "6XNN",
+            Instruction::AddImm { .. } => "7XNN",
+            Instruction::Load { .. } => "8XY0",
+            Instruction::Or { .. } => "8XY1",
+            Instruction::And { .. } => "8XY2",
+            Instruction::Xor { .. } => "8XY3",
+            Instruction::Add { .. } => "8XY4",
+            Instruction::Sub { .. } => "8XY5",
+            Instruction::ShiftRight { .. } => "8XY6",
+            Instruction::SubN { .. } => "8XY7",
+            Instruction::ShiftLeft { .. } => "8XYE",
+            Instruction::SkipNe { .. } => "9XY0",
+            Instruction::LoadI(_) => "ANNN",
+            Instruction::JumpOffset { .. } => "BNNN",
+            Instruction::Random { .. } => "CXNN",
+            Instruction::Draw { .. } => "DXYN",
+            Instruction::SkipKey { .. } => "EX9E",
+            Instruction::SkipNotKey { .. } => "EXA1",
+            Instruction::SkipKey2 { .. } => "EXF2",
+            Instruction::SkipNotKey2 { .. } => "EXF5",
+            Instruction::Plane(_) => "FN01",
+            Instruction::ReadDelay { .. } => "FX07",
+            Instruction::WaitKey { .. } => "FX0A",
+            Instruction::SetDelay { .. } => "FX15",
+            Instruction::SetSound { .. } => "FX18",
+            Instruction::AddI { .. } => "FX1E",
+            Instruction::Font { .. } => "FX29",
+            Instruction::BigFont { .. } => "FX30",
+            Instruction::Bcd { .. } => "FX33",
+            Instruction::Store { .. } => "FX55",
+            Instruction::Restore { .. } => "FX65",
+            Instruction::SaveFlags { .. } => "FX75",
+            Instruction::LoadFlags { .. } => "FX85",
+            Instruction::Unknown(_) => "????",
+        }
+    }
+}
+
+// decodes the instructions of a group of opcodes
+type Decoder = fn(u16) -> Instruction;
+
+// by the first digit of the opcode, a table lookup instead of matching all
+// four digits, the hot path of the interpreter
+const DECODERS: [Decoder; 16] = [
+    decode_system,
+    |op| Instruction::Jump(nnn(op)),
+    |op| Instruction::Call(nnn(op)),
+    |op| Instruction::SkipEqImm {
+        x: x(op),
+        nn: nn(op),
+    },
+    |op| Instruction::SkipNeImm {
+        x: x(op),
+        nn: nn(op),
+    },
+    |op| match op & 0xF {
+        0 => Instruction::SkipEq { x: x(op), y: y(op) },
+        1 => Instruction::AddNibbles { x: x(op), y: y(op) },
+        _ => Instruction::Unknown(op),
+    },
+    |op| Instruction::LoadImm {
+        x: x(op),
+        nn: nn(op),
+    },
+    |op| Instruction::AddImm {
+        x: x(op),
+        nn: nn(op),
+    },
+    |op| ARITHMETIC[(op & 0xF) as usize](op),
+    |op| match op & 0xF {
+        0 => Instruction::SkipNe { x: x(op), y: y(op) },
+        _ => Instruction::Unknown(op),
+    },
+    |op| Instruction::LoadI(nnn(op)),
+    |op| Instruction::JumpOffset {
+        x: x(op),
+        nnn: nnn(op),
+    },
+    |op| Instruction::Random {
+        x: x(op),
+        nn: nn(op),
+    },
+    |op| Instruction::Draw {
+        x: x(op),
+        y: y(op),
+        n: (op & 0xF) as u8,
+    },
+    |op| KEYS[(op & 0xFF) as usize](op),
+    |op| MISC[(op & 0xFF) as usize](op),
+];
+
+// 8XYN by N
+const ARITHMETIC: [Decoder; 16] = {
+    let mut table: [Decoder; 16] = [Instruction::Unknown; 16];
+    table[0x0] = |op| Instruction::Load { x: x(op), y: y(op) };
+    table[0x1] = |op| Instruction::Or { x: x(op), y: y(op) };
+    table[0x2] = |op| Instruction::And { x: x(op), y: y(op) };
+    table[0x3] = |op| Instruction::Xor { x: x(op), y: y(op) };
+    table[0x4] = |op| Instruction::Add { x: x(op), y: y(op) };
+    table[0x5] = |op| Instruction::Sub { x: x(op), y: y(op) };
+    table[0x6] = |op| Instruction::ShiftRight { x: x(op), y: y(op) };
+    table[0x7] = |op| Instruction::SubN { x: x(op), y: y(op) };
+    table[0xE] = |op| Instruction::ShiftLeft { x: x(op), y: y(op) };
+    table
+};
+
+// EXNN by NN
+const KEYS: [Decoder; 256] = {
+    let mut table: [Decoder; 256] = [Instruction::Unknown; 256];
+    table[0x9E] = |op| Instruction::SkipKey { x: x(op) };
+    table[0xA1] = |op| Instruction::SkipNotKey { x: x(op) };
+    table[0xF2] = |op| Instruction::SkipKey2 { x: x(op) };
+    table[0xF5] = |op| Instruction::SkipNotKey2 { x: x(op) };
+    table
+};
+
+// FXNN by NN
+const MISC: [Decoder; 256] = {
+    let mut table: [Decoder; 256] = [Instruction::Unknown; 256];
+    table[0x01] = |op| Instruction::Plane(x(op));
+    table[0x07] = |op| Instruction::ReadDelay { x: x(op) };
+    table[0x0A] = |op| Instruction::WaitKey { x: x(op) };
+    table[0x15] = |op| Instruction::SetDelay { x: x(op) };
+    table[0x18] = |op| Instruction::SetSound { x: x(op) };
+    table[0x1E] = |op| Instruction::AddI { x: x(op) };
+    table[0x29] = |op| Instruction::Font { x: x(op) };
+    table[0x30] = |op| Instruction::BigFont { x: x(op) };
+    table[0x33] = |op| Instruction::Bcd { x: x(op) };
+    table[0x55] = |op| Instruction::Store { x: x(op) };
+    table[0x65] = |op| Instruction::Restore { x: x(op) };
+    table[0x75] = |op| Instruction::SaveFlags { x: x(op) };
+    table[0x85] = |op| Instruction::LoadFlags { x: x(op) };
+    table
+};
+
+// 0NNN, the few SUPER-CHIP and MegaChip instructions among the machine
+// code calls
+fn decode_system(op: u16) -> Instruction {
+    match op {
+        0x0010 => Instruction::MegaOff,
+        0x0011 => Instruction::MegaOn,
+        0x00B0..=0x00BF => Instruction::ScrollUp((op & 0xF) as u8),
+        0x0100..=0x01FF => Instruction::LoadLongI(nn(op)),
+        0x0200..=0x02FF => Instruction::LoadPalette(nn(op)),
+        0x0300..=0x03FF => Instruction::SpriteWidth(nn(op)),
+        0x0400..=0x04FF => Instruction::SpriteHeight(nn(op)),
+        0x0500..=0x05FF => Instruction::Alpha(nn(op)),
+        0x0600..=0x060F => Instruction::PlaySound((op & 0xF) as u8),
+        0x0700 => Instruction::StopSound,
+        0x0800..=0x080F => Instruction::BlendMode((op & 0xF) as u8),
+        0x0900..=0x09FF => Instruction::CollisionColor(nn(op)),
+        0x00C0..=0x00CF => Instruction::ScrollDown((op & 0xF) as u8),
+        0x00E0 => Instruction::Clear,
+        0x00EE => Instruction::Return,
+        0x00FB => Instruction::ScrollRight,
+        0x00FC => Instruction::ScrollLeft,
+        0x00FD => Instruction::Exit,
+        0x00FE => Instruction::LowRes,
+        0x00FF => Instruction::HighRes,
+        _ => Instruction::Sys(nnn(op)),
+    }
+}
+
+fn x(op: u16) -> u8 {
+    ((op & 0x0F00) >> 8) as u8
+}
+
+fn y(op: u16) -> u8 {
+    ((op & 0x00F0) >> 4) as u8
+}
+
+fn nn(op: u16) -> u8 {
+    (op & 0xFF) as u8
+}
+
+fn nnn(op: u16) -> u16 {
+    op & 0xFFF
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Instruction::MegaOff => write!(f, "MEGAOFF"),
+            Instruction::MegaOn => write!(f, "MEGAON"),
+            Instruction::ScrollUp(n) => write!(f, "SCU {n}"),
+            Instruction::ScrollDown(n) => write!(f, "SCD {n}"),
+            Instruction::Clear => write!(f, "CLS"),
+            Instruction::Return => write!(f, "RET"),
+            Instruction::ScrollRight => write!(f, "SCR"),
+            Instruction::ScrollLeft => write!(f, "SCL"),
+            Instruction::Exit => write!(f, "EXIT"),
+            Instruction::LowRes => write!(f, "LOW"),
+            Instruction::HighRes => write!(f, "HIGH"),
+            Instruction::LoadLongI(nn) => write!(f, "LDHI 0x{nn:02X}"),
+            Instruction::LoadPalette(nn) => write!(f, "LDPAL {nn}"),
+            Instruction::SpriteWidth(nn) => write!(f, "SPRW {nn}"),
+            Instruction::SpriteHeight(nn) => write!(f, "SPRH {nn}"),
+            Instruction::Alpha(nn) => write!(f, "ALPHA 0x{nn:02X}"),
+            Instruction::PlaySound(n) => write!(f, "DIGISND {n}"),
+            Instruction::StopSound => write!(f, "STOPSND"),
+            Instruction::BlendMode(n) => write!(f, "BMODE {n}"),
+            Instruction::CollisionColor(nn) => write!(f, "CCOL {nn}"),
+            Instruction::Sys(nnn) => write!(f, "SYS 0x{nnn:03X}"),
+            Instruction::Jump(nnn) => write!(f, "JP 0x{nnn:03X}"),
+            Instruction::Call(nnn) => write!(f, "CALL 0x{nnn:03X}"),
+            Instruction::SkipEqImm { x, nn } => write!(f, "SE V{x:X}, 0x{nn:02X}"),
+            Instruction::SkipNeImm { x, nn } => write!(f, "SNE V{x:X}, 0x{nn:02X}"),
+            Instruction::SkipEq { x, y } => write!(f, "SE V{x:X}, V{y:X}"),
+            Instruction::AddNibbles { x, y } => write!(f, "ADDN V{x:X}, V{y:X}"),
+            Instruction::LoadImm { x, nn } => write!(f, "LD V{x:X}, 0x{nn:02X}"),
+            Instruction::AddImm { x, nn } => write!(f, "ADD V{x:X}, 0x{nn:02X}"),
+            Instruction::Load { x, y } => write!(f, "LD V{x:X}, V{y:X}"),
+            Instruction::Or { x, y } => write!(f, "OR V{x:X}, V{y:X}"),
+            Instruction::And { x, y } => write!(f, "AND V{x:X}, V{y:X}"),
+            Instruction::Xor { x, y } => write!(f, "XOR V{x:X}, V{y:X}"),
+            Instruction::Add { x, y } => write!(f, "ADD V{x:X}, V{y:X}"),
+            Instruction::Sub { x, y } => write!(f, "SUB V{x:X}, V{y:X}"),
+            Instruction::ShiftRight { x, y: 0 } => write!(f, "SHR V{x:X}"),
+            Instruction::ShiftRight { x, y } => write!(f, "SHR V{x:X}, V{y:X}"),
+            Instruction::SubN { x, y } => write!(f, "SUBN V{x:X}, V{y:X}"),
+            Instruction::ShiftLeft { x, y: 0 } => write!(f, "SHL V{x:X}"),
+            Instruction::ShiftLeft { x, y } => write!(f, "SHL V{x:X}, V{y:X}"),
+            Instruction::SkipNe { x, y } => write!(f, "SNE V{x:X}, V{y:X}"),
+            Instruction::LoadI(nnn) => write!(f, "LD I, 0x{nnn:03X}"),
+            // with the jump quirk X is also the first digit of the address
+            Instruction::JumpOffset { nnn, .. } => write!(f, "JP V0, 0x{nnn:03X}"),
+            Instruction::Random { x, nn } => write!(f, "RND V{x:X}, 0x{nn:02X}"),
+            Instruction::Draw { x, y, n } => write!(f, "DRW V{x:X}, V{y:X}, {n}"),
+            Instruction::SkipKey { x } => write!(f, "SKP V{x:X}"),
+            Instruction::SkipNotKey { x } => write!(f, "SKNP V{x:X}"),
+            Instruction::SkipKey2 { x } => write!(f, "SKP2 V{x:X}"),
+            Instruction::SkipNotKey2 { x } => write!(f, "SKNP2 V{x:X}"),
+            Instruction::Plane(n) => write!(f, "PLANE {n}"),
+            Instruction::ReadDelay { x } => write!(f, "LD V{x:X}, DT"),
+            Instruction::WaitKey { x } => write!(f, "LD V{x:X}, K"),
+            Instruction::SetDelay { x } => write!(f, "LD DT, V{x:X}"),
+            Instruction::SetSound { x } => write!(f, "LD ST, V{x:X}"),
+            Instruction::AddI { x } => write!(f, "ADD I, V{x:X}"),
+            Instruction::Font { x } => write!(f, "LD F, V{x:X}"),
+            Instruction::BigFont { x } => write!(f, "LD HF, V{x:X}"),
+            Instruction::Bcd { x } => write!(f, "LD B, V{x:X}"),
+            Instruction::Store { x } => write!(f, "LD [I], V{x:X}"),
+            Instruction::Restore { x } => write!(f, "LD V{x:X}, [I]"),
+            Instruction::SaveFlags { x } => write!(f, "LD R, V{x:X}"),
+            Instruction::LoadFlags { x } => write!(f, "LD V{x:X}, R"),
+            Instruction::Unknown(op) => write!(f, "DW 0x{op:04X}"),
+        }
+    }
+}
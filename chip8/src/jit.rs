@@ -0,0 +1,491 @@
+//! Translation of basic blocks of CHIP-8 code to native code with
+//! Cranelift, for batches of millions of instructions. See
+//! [`CPU::set_jit`].
+
+use std::collections::HashMap;
+use std::mem::{offset_of, ManuallyDrop};
+
+use cranelift_codegen::ir::condcodes::IntCC;
+use cranelift_codegen::ir::types::{I16, I8};
+use cranelift_codegen::ir::{AbiParam, InstBuilder, MemFlags, Value};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, Module};
+
+use crate::{Instruction, Quirks, Timing, CPU, NUM_REGS};
+
+// instructions in a block at most, a jump or skip ends it earlier
+const MAX_BLOCK_LEN: usize = 64;
+
+// translations before the code memory is freed and the blocks translated
+// again, so self-modifying programs don't grow it forever
+const MAX_TRANSLATIONS: usize = 4096;
+
+// the registers a block reads and writes, copied in and out of the CPU
+#[repr(C)]
+struct Registers {
+    v: [u8; NUM_REGS],
+    i: u16,
+    pc: u16,
+}
+
+type BlockFn = extern "C" fn(*mut Registers);
+
+// the code at an address, with the bytes it was translated from to notice
+// when the program wrote over them
+struct Block {
+    bytes: Vec<u8>,
+    // None when the first instruction can't be translated
+    code: Option<BlockFn>,
+    instructions: usize,
+    cycles: u64,
+    // whether an ANNN sets I, clearing the MegaChip high byte
+    loads_i: bool,
+}
+
+pub(crate) struct Jit {
+    module: ManuallyDrop<JITModule>,
+    blocks: HashMap<u16, Block>,
+    translations: usize,
+    // what the blocks were translated for
+    quirks: Quirks,
+    timing: Timing,
+}
+
+// SAFETY: the module is only used through `&mut Jit`, and its symbol lookup
+// functions are `Send`. The code it holds is plain memory, freed with it.
+unsafe impl Send for Jit {}
+unsafe impl Sync for Jit {}
+
+impl Drop for Jit {
+    fn drop(&mut self) {
+        // SAFETY: the functions of the blocks go away with them
+        unsafe { ManuallyDrop::take(&mut self.module).free_memory() };
+    }
+}
+
+impl Jit {
+    // None on hosts Cranelift doesn't generate code for
+    fn new(quirks: Quirks, timing: Timing) -> Option<Self> {
+        Some(Self {
+            module: ManuallyDrop::new(new_module()?),
+            blocks: HashMap::new(),
+            translations: 0,
+            quirks,
+            timing,
+        })
+    }
+
+    // the block at `pc`, translated again when the bytes under it changed
+    fn block(&mut self, pc: u16, ram: &[u8], quirks: Quirks, timing: Timing) -> Option<&Block> {
+        if quirks != self.quirks || timing != self.timing {
+            self.blocks.clear();
+            (self.quirks, self.timing) = (quirks, timing);
+        }
+        let start = pc as usize;
+        let current = self
+            .blocks
+            .get(&pc)
+            .is_some_and(|block| ram.get(start..start + block.bytes.len()) == Some(&block.bytes));
+        if !current {
+            if self.translations == MAX_TRANSLATIONS {
+                let module = std::mem::replace(&mut *self.module, new_module()?);
+                self.blocks.clear();
+                // SAFETY: the blocks using its code are gone
+                unsafe { module.free_memory() };
+                self.translations = 0;
+            }
+            let block = self.translate(pc, ram);
+            self.translations += 1;
+            self.blocks.insert(pc, block);
+        }
+        self.blocks.get(&pc)
+    }
+
+    fn translate(&mut self, pc: u16, ram: &[u8]) -> Block {
+        let instructions = scan(pc, ram);
+        let end = pc as usize + 2 * instructions.len().max(1);
+        let code = if instructions.is_empty() {
+            None
+        } else {
+            self.compile(pc, &instructions)
+        };
+        Block {
+            bytes: ram[pc as usize..end.min(ram.len())].to_vec(),
+            code,
+            instructions: instructions.len(),
+            cycles: instructions
+                .iter()
+                .map(|&(opcode, _)| self.timing.cycles(opcode))
+                .sum(),
+            loads_i: instructions
+                .iter()
+                .any(|(_, instruction)| matches!(instruction, Instruction::LoadI(_))),
+        }
+    }
+
+    // None when Cranelift fails, the block is then interpreted
+    fn compile(&mut self, pc: u16, instructions: &[(u16, Instruction)]) -> Option<BlockFn> {
+        let module = &mut *self.module;
+        let mut ctx = module.make_context();
+        let pointer = module.target_config().pointer_type();
+        ctx.func.signature.params.push(AbiParam::new(pointer));
+        let mut builder_ctx = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut ctx.func, &mut builder_ctx);
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        builder.seal_block(entry);
+        let registers = builder.block_params(entry)[0];
+
+        let mut emitter = Emitter::new(builder, registers, self.quirks);
+        let mut next = pc;
+        for &(_, instruction) in instructions {
+            next = next.wrapping_add(2);
+            emitter.emit(instruction, next);
+        }
+        emitter.finish(next);
+
+        let id = module
+            .declare_anonymous_function(&ctx.func.signature)
+            .ok()?;
+        module.define_function(id, &mut ctx).ok()?;
+        module.clear_context(&mut ctx);
+        module.finalize_definitions().ok()?;
+        let code = module.get_finalized_function(id);
+        // SAFETY: the function was built with the signature of `BlockFn`
+        Some(unsafe { std::mem::transmute::<*const u8, BlockFn>(code) })
+    }
+}
+
+fn new_module() -> Option<JITModule> {
+    let mut flags = settings::builder();
+    flags.set("opt_level", "speed").ok()?;
+    let isa = cranelift_native::builder()
+        .ok()?
+        .finish(settings::Flags::new(flags))
+        .ok()?;
+    Some(JITModule::new(JITBuilder::with_isa(
+        isa,
+        default_libcall_names(),
+    )))
+}
+
+// the instructions of the block at `pc`: the ones only touching the
+// registers, up to and with a jump or skip
+fn scan(pc: u16, ram: &[u8]) -> Vec<(u16, Instruction)> {
+    let mut instructions = Vec::new();
+    let mut addr = pc;
+    while instructions.len() < MAX_BLOCK_LEN && addr < 0xFFFE {
+        let Some(&[high, low]) = ram.get(addr as usize..addr as usize + 2) else {
+            break;
+        };
+        let opcode = u16::from_be_bytes([high, low]);
+        let instruction = Instruction::decode(opcode);
+        match instruction {
+            Instruction::Jump(_)
+            | Instruction::SkipEqImm { .. }
+            | Instruction::SkipNeImm { .. }
+            | Instruction::SkipEq { .. }
+            | Instruction::SkipNe { .. } => {
+                instructions.push((opcode, instruction));
+                break;
+            }
+            Instruction::LoadImm { .. }
+            | Instruction::AddImm { .. }
+            | Instruction::Load { .. }
+            | Instruction::Or { .. }
+            | Instruction::And { .. }
+            | Instruction::Xor { .. }
+            | Instruction::Add { .. }
+            | Instruction::Sub { .. }
+            | Instruction::SubN { .. }
+            | Instruction::ShiftRight { .. }
+            | Instruction::ShiftLeft { .. }
+            | Instruction::LoadI(_)
+            | Instruction::AddI { .. } => instructions.push((opcode, instruction)),
+            _ => break,
+        }
+        addr += 2;
+    }
+    instructions
+}
+
+// builds the function of a block, keeping the registers in variables
+struct Emitter<'a> {
+    builder: FunctionBuilder<'a>,
+    registers: Value,
+    quirks: Quirks,
+    v: [Variable; NUM_REGS],
+    i: Variable,
+    // set by the jump or skip ending the block
+    pc: Option<Value>,
+}
+
+impl<'a> Emitter<'a> {
+    fn new(mut builder: FunctionBuilder<'a>, registers: Value, quirks: Quirks) -> Self {
+        let v: [Variable; NUM_REGS] = std::array::from_fn(|n| Variable::from_u32(n as u32));
+        for (n, &var) in v.iter().enumerate() {
+            builder.declare_var(var, I8);
+            let value = builder.ins().load(
+                I8,
+                MemFlags::trusted(),
+                registers,
+                (offset_of!(Registers, v) + n) as i32,
+            );
+            builder.def_var(var, value);
+        }
+        let i = Variable::from_u32(NUM_REGS as u32);
+        builder.declare_var(i, I16);
+        let value = builder.ins().load(
+            I16,
+            MemFlags::trusted(),
+            registers,
+            offset_of!(Registers, i) as i32,
+        );
+        builder.def_var(i, value);
+        Self {
+            builder,
+            registers,
+            quirks,
+            v,
+            i,
+            pc: None,
+        }
+    }
+
+    fn get(&mut self, x: u8) -> Value {
+        self.builder.use_var(self.v[x as usize])
+    }
+
+    fn set(&mut self, x: u8, value: Value) {
+        self.builder.def_var(self.v[x as usize], value);
+    }
+
+    fn constant(&mut self, ty: cranelift_codegen::ir::Type, value: u16) -> Value {
+        self.builder.ins().iconst(ty, i64::from(value))
+    }
+
+    // a skip to `next + 2` when `cond` holds, `next` being the address
+    // after the skip
+    fn skip(&mut self, cond: IntCC, a: Value, b: Value, next: u16) {
+        let taken = self.builder.ins().icmp(cond, a, b);
+        let skipped = self.constant(I16, next.wrapping_add(2));
+        let not_skipped = self.constant(I16, next);
+        self.pc = Some(self.builder.ins().select(taken, skipped, not_skipped));
+    }
+
+    // see `CPU::execute`, which these follow down to the order VF is set in
+    fn emit(&mut self, instruction: Instruction, next: u16) {
+        match instruction {
+            Instruction::Jump(nnn) => {
+                self.pc = Some(self.constant(I16, nnn));
+            }
+            Instruction::SkipEqImm { x, nn } => {
+                let (vx, nn) = (self.get(x), self.constant(I8, nn as u16));
+                self.skip(IntCC::Equal, vx, nn, next);
+            }
+            Instruction::SkipNeImm { x, nn } => {
+                let (vx, nn) = (self.get(x), self.constant(I8, nn as u16));
+                self.skip(IntCC::NotEqual, vx, nn, next);
+            }
+            Instruction::SkipEq { x, y } => {
+                let (vx, vy) = (self.get(x), self.get(y));
+                self.skip(IntCC::Equal, vx, vy, next);
+            }
+            Instruction::SkipNe { x, y } => {
+                let (vx, vy) = (self.get(x), self.get(y));
+                self.skip(IntCC::NotEqual, vx, vy, next);
+            }
+            Instruction::LoadImm { x, nn } => {
+                let nn = self.constant(I8, nn as u16);
+                self.set(x, nn);
+            }
+            Instruction::AddImm { x, nn } => {
+                let vx = self.get(x);
+                let sum = self.builder.ins().iadd_imm(vx, i64::from(nn));
+                self.set(x, sum);
+            }
+            Instruction::Load { x, y } => {
+                let vy = self.get(y);
+                self.set(x, vy);
+            }
+            Instruction::Or { x, y } => {
+                let (vx, vy) = (self.get(x), self.get(y));
+                let res = self.builder.ins().bor(vx, vy);
+                self.set(x, res);
+            }
+            Instruction::And { x, y } => {
+                let (vx, vy) = (self.get(x), self.get(y));
+                let res = self.builder.ins().band(vx, vy);
+                self.set(x, res);
+            }
+            Instruction::Xor { x, y } => {
+                let (vx, vy) = (self.get(x), self.get(y));
+                let res = self.builder.ins().bxor(vx, vy);
+                self.set(x, res);
+            }
+            Instruction::Add { x, y } => {
+                let (vx, vy) = (self.get(x), self.get(y));
+                let res = self.builder.ins().iadd(vx, vy);
+                let carry = self.builder.ins().icmp(IntCC::UnsignedLessThan, res, vx);
+                self.set(x, res);
+                self.set(0xF, carry);
+            }
+            Instruction::Sub { x, y } => {
+                let (vx, vy) = (self.get(x), self.get(y));
+                let res = self.builder.ins().isub(vx, vy);
+                let no_borrow = self
+                    .builder
+                    .ins()
+                    .icmp(IntCC::UnsignedGreaterThanOrEqual, vx, vy);
+                self.set(x, res);
+                self.set(0xF, no_borrow);
+            }
+            Instruction::SubN { x, y } => {
+                let (vx, vy) = (self.get(x), self.get(y));
+                let res = self.builder.ins().isub(vy, vx);
+                let no_borrow = self
+                    .builder
+                    .ins()
+                    .icmp(IntCC::UnsignedGreaterThanOrEqual, vy, vx);
+                self.set(x, res);
+                self.set(0xF, no_borrow);
+            }
+            Instruction::ShiftRight { x, y } => {
+                let src = self.shift_source(x, y);
+                let lsb = self.builder.ins().band_imm(src, 1);
+                let res = self.builder.ins().ushr_imm(src, 1);
+                self.set(0xF, lsb);
+                self.set(x, res);
+            }
+            Instruction::ShiftLeft { x, y } => {
+                let src = self.shift_source(x, y);
+                let msb = self.builder.ins().ushr_imm(src, 7);
+                let res = self.builder.ins().ishl_imm(src, 1);
+                self.set(0xF, msb);
+                self.set(x, res);
+            }
+            Instruction::LoadI(nnn) => {
+                let nnn = self.constant(I16, nnn);
+                self.builder.def_var(self.i, nnn);
+            }
+            Instruction::AddI { x } => {
+                let vx = self.get(x);
+                let vx = self.builder.ins().uextend(I16, vx);
+                let i = self.builder.use_var(self.i);
+                let sum = self.builder.ins().iadd(i, vx);
+                self.builder.def_var(self.i, sum);
+            }
+            _ => unreachable!("{instruction:?} isn't translated"),
+        }
+    }
+
+    fn shift_source(&mut self, x: u8, y: u8) -> Value {
+        self.get(if self.quirks.shift_vy { y } else { x })
+    }
+
+    // stores the registers back and returns, at `next` unless a jump or
+    // skip set the program counter
+    fn finish(mut self, next: u16) {
+        for n in 0..NUM_REGS {
+            let value = self.builder.use_var(self.v[n]);
+            self.builder.ins().store(
+                MemFlags::trusted(),
+                value,
+                self.registers,
+                (offset_of!(Registers, v) + n) as i32,
+            );
+        }
+        let i = self.builder.use_var(self.i);
+        self.builder.ins().store(
+            MemFlags::trusted(),
+            i,
+            self.registers,
+            offset_of!(Registers, i) as i32,
+        );
+        let pc = match self.pc {
+            Some(pc) => pc,
+            None => self.constant(I16, next),
+        };
+        self.builder.ins().store(
+            MemFlags::trusted(),
+            pc,
+            self.registers,
+            offset_of!(Registers, pc) as i32,
+        );
+        self.builder.ins().return_(&[]);
+        self.builder.finalize();
+    }
+}
+
+impl CPU {
+    /// Translates the code run by [`CPU::run`] and [`CPU::run_frame`] to
+    /// native code with Cranelift, a basic block at a time, or goes back to
+    /// interpreting it. Blocks are straight runs of the instructions only
+    /// touching V0-VF and I, ending with a jump or skip, and are translated
+    /// again once the program writes over them. Their results are the
+    /// interpreter's, the [`Timing`] and quirks included.
+    ///
+    /// Tracing, profiling, coverage, memory access tracking, queued key
+    /// events without frame-latched input and the MegaChip and two-page
+    /// modes run on the interpreter, as do hosts Cranelift has no code
+    /// generator for: the JIT then stays off, see [`CPU::is_jit`].
+    pub fn set_jit(&mut self, on: bool) {
+        if on != self.jit.is_some() {
+            self.jit = on
+                .then(|| Jit::new(self.quirks, self.timing))
+                .flatten()
+                .map(Box::new);
+        }
+    }
+
+    pub fn is_jit(&self) -> bool {
+        self.jit.is_some()
+    }
+
+    /// Runs the translated block at the program counter if it is no more
+    /// than `budget` instructions, returning how many ran.
+    pub(crate) fn run_block(&mut self, budget: usize) -> Option<usize> {
+        if !self.can_run_blocks() {
+            return None;
+        }
+        let jit = self.jit.as_deref_mut()?;
+        let block = jit.block(
+            self.program_counter,
+            &self.ram.data,
+            self.quirks,
+            self.timing,
+        )?;
+        let code = block.code.filter(|_| block.instructions <= budget)?;
+        let mut registers = Registers {
+            v: self.v_registers,
+            i: self.i_register,
+            pc: self.program_counter,
+        };
+        code(&mut registers);
+        self.v_registers = registers.v;
+        self.i_register = registers.i;
+        self.program_counter = registers.pc;
+        if block.loads_i {
+            self.megachip.i_high = 0;
+        }
+        self.cycles += block.cycles;
+        self.executed += block.instructions as u64;
+        Some(block.instructions)
+    }
+
+    // what `tick` does besides executing, which blocks skip
+    fn can_run_blocks(&self) -> bool {
+        !self.halted
+            && !self.waiting_vblank
+            && (self.latched_keys.is_some() || self.key_events.is_empty())
+            && self.tracer.is_none()
+            && self.profile.is_none()
+            && self.coverage.is_none()
+            && self.ram.access.is_none()
+            && !self.is_megachip()
+            && !self.is_two_page()
+    }
+}
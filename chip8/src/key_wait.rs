@@ -0,0 +1,73 @@
+//! FX0A blocks until a key is pressed and released by running again and
+//! again. Frontends can see the wait with [`CPU::waiting_for_key`], e.g. to
+//! show a prompt, or answer it themselves with a [`KeyWaitHook`].
+
+use crate::{Chip8Error, CPU, NUM_KEYS};
+
+/// What a program blocked in FX0A waits for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyWait {
+    /// Any key to be pressed.
+    Press,
+    /// The key pressed to be released, FX0A then stores it.
+    Release(u8),
+}
+
+/// Asked every time FX0A runs without finishing, returns the key to finish
+/// it with right away, or None to go on waiting. Implemented for closures
+/// that are `Send` and `Sync`, like the machine.
+pub trait KeyWaitHook: Send + Sync {
+    fn wait(&mut self, wait: KeyWait) -> Option<u8>;
+}
+
+impl<F: FnMut(KeyWait) -> Option<u8> + Send + Sync> KeyWaitHook for F {
+    fn wait(&mut self, wait: KeyWait) -> Option<u8> {
+        self(wait)
+    }
+}
+
+impl CPU {
+    /// What the program waits for, None unless the last FX0A run is still
+    /// waiting.
+    pub fn waiting_for_key(&self) -> Option<KeyWait> {
+        self.waiting_key
+            .then(|| self.key_wait.map_or(KeyWait::Press, KeyWait::Release))
+    }
+
+    /// Asks `hook` for a key whenever FX0A would wait, or stops with `None`.
+    pub fn set_key_wait_hook(&mut self, hook: Option<Box<dyn KeyWaitHook>>) {
+        self.key_wait_hook = hook;
+    }
+
+    /// FX0A: stores the key pressed and released in Vx, or runs again.
+    pub(crate) fn wait_key(&mut self, x: u8) -> Result<(), Chip8Error> {
+        match self.key_wait {
+            Some(key) if !self.keys[key as usize] => {
+                self.end_key_wait(x, key);
+                return Ok(());
+            }
+            Some(_) => {}
+            None => self.key_wait = self.keys.iter().position(|&k| k).map(|k| k as u8),
+        }
+        if let Some(hook) = &mut self.key_wait_hook {
+            let wait = self.key_wait.map_or(KeyWait::Press, KeyWait::Release);
+            if let Some(key) = hook.wait(wait) {
+                if key as usize >= NUM_KEYS {
+                    return Err(Chip8Error::InvalidKey(key));
+                }
+                self.end_key_wait(x, key);
+                return Ok(());
+            }
+        }
+        self.waiting_key = true;
+        // run again until the key is released
+        self.program_counter = self.program_counter.wrapping_sub(2);
+        Ok(())
+    }
+
+    fn end_key_wait(&mut self, x: u8, key: u8) {
+        self.v_registers[x as usize] = key;
+        self.key_wait = None;
+        self.waiting_key = false;
+    }
+}
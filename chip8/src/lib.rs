@@ -1,16 +1,94 @@
-use rand::random;
-
+mod access;
+pub mod asm;
+pub mod audio;
+mod batch;
+mod builder;
+mod chip8x;
+mod coverage;
+pub mod debugger;
+pub mod diff;
+pub mod disasm;
+mod dump;
+mod error;
+mod flags;
+pub mod fleet;
 mod font;
+pub mod frontend;
+mod instruction;
+#[cfg(feature = "jit")]
+mod jit;
+mod key_wait;
+pub mod lint;
+mod megachip;
 mod memory;
+pub mod movie;
+mod opcode_handler;
+mod peripheral;
+pub mod profile;
+mod quirks;
+mod rewind;
+mod rng;
+pub mod romdb;
+pub mod runner;
 pub mod screen;
-
+mod state;
+pub mod symbols;
+pub mod text;
+mod timing;
+pub mod trace;
+mod two_page;
+
+use std::collections::VecDeque;
+use std::ops::{Bound, RangeBounds};
+use std::time::Duration;
+
+pub use access::MemoryAccess;
+pub use batch::RunSummary;
+pub use builder::Chip8Builder;
+pub use coverage::Coverage;
+pub use error::{Chip8Error, RomError};
+pub use flags::{FlagsSink, NUM_FLAGS};
+pub use font::{Font, BIG_FONTSET_SIZE, FONTSET_SIZE};
+pub use instruction::Instruction;
+pub use key_wait::{KeyWait, KeyWaitHook};
+pub use megachip::DigitizedSound;
+use megachip::MegaChip;
+pub use memory::{
+    BoundsPolicy, CHIP8X_START_ADDR, ETI660_START_ADDR, MAX_ROM_SIZE, MEGACHIP_MEMORY_SIZE,
+    MEMORY_SIZE, START_ADDR,
+};
 use memory::{Ram, Stack};
-use screen::Screen;
+pub use opcode_handler::OpcodeHandler;
+pub use peripheral::Peripheral;
+pub use quirks::Quirks;
+use rewind::RewindBuffer;
+pub use rewind::{DEFAULT_REWIND_FRAMES, REWIND_MEMORY_LIMIT};
+pub use rng::{RandomSource, XorShiftRng};
+use screen::{DirtyRows, FrameBuffer, Palette, Screen};
+pub use state::{
+    Chip8XState, CpuState, MegaChipDisplay, MegaChipState, SaveState, StateError,
+    STATE_FORMAT_VERSION,
+};
+pub use timing::{Timing, VIP_CYCLES_PER_FRAME};
+pub use two_page::{is_two_page_rom, TWO_PAGE_START_ADDR};
 
 const NUM_REGS: usize = 16;
 
-const NUM_KEYS: usize = 16;
+pub const NUM_KEYS: usize = 16;
+
+// the delay and sound timers count down at 60 Hz
+const TIMER_PERIOD: Duration = Duration::from_nanos(1_000_000_000 / 60);
 
+// frontends run the machine on worker threads and share it with their UI,
+// a hook that isn't thread safe must not slip in
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<CPU>();
+    assert_send_sync::<SaveState>();
+};
+
+/// The machine. It is `Send` and `Sync`, the random source and the hooks
+/// plugged into it have to be too.
 pub struct CPU {
     // index of the current instruction, to know where the
     // program is currently executing in ram memory
@@ -27,386 +105,862 @@ pub struct CPU {
 
     // where the game program will be loaded, read/write
     ram: Ram,
+    // of the ROM loaded, to tell its save states from other ROMs'
+    rom_hash: Option<u64>,
 
     screen: Screen,
     // the keyboard keys
     keys: [bool; NUM_KEYS],
+    // key changes queued by `push_key_event`, applied one per instruction
+    key_events: VecDeque<(u8, bool)>,
+    // with frame-latched input, the keys to hold from the next frame on
+    latched_keys: Option<[bool; NUM_KEYS]>,
+    // the second keypad of CHIP-8X
+    keys2: [bool; NUM_KEYS],
+    // the key pressed during FX0A, stored once it's released
+    key_wait: Option<u8>,
+    // the last FX0A run is waiting, and who may answer it
+    waiting_key: bool,
+    key_wait_hook: Option<Box<dyn KeyWaitHook>>,
 
     // timer registers
     delay_timer: u8, // executes something uppon hitting 0
     sound_timer: u8, // emit a sound uppon hitting 0
+    // time passed to `advance_timers` not yet counted down
+    timer_elapsed: Duration,
+
+    // set by the SUPER-CHIP exit instruction, no more instructions run
+    halted: bool,
+
+    // set by DXYN with the display wait quirk, no more instructions run
+    // until the next frame
+    waiting_vblank: bool,
+
+    // history of the last frames, when rewinding is enabled
+    rewind: Option<RewindBuffer>,
+
+    // random bytes for the CXNN instruction
+    rng: Box<dyn RandomSource>,
+
+    quirks: Quirks,
+
+    // runs the opcodes the interpreter doesn't know
+    opcode_handler: Option<Box<dyn OpcodeHandler>>,
+
+    // execution counts, when profiling
+    profile: Option<profile::Profile>,
+    // the bytes executed, when recording coverage
+    coverage: Option<Coverage>,
+
+    // receives every executed instruction when tracing
+    tracer: Option<Box<dyn trace::TraceSink>>,
+
+    // registers of the MegaChip instructions
+    megachip: MegaChip,
+
+    // SUPER-CHIP user flags, and who persists them
+    flags: [u8; NUM_FLAGS],
+    flags_sink: Option<Box<dyn FlagsSink>>,
+
+    // emulation speed, for the frontends running the CPU
+    ticks_per_frame: usize,
+    timing: Timing,
+    // machine cycles executed, and where the current frame ends in them
+    cycles: u64,
+    frame_end: u64,
+    // instructions executed, for the summaries of `CPU::run`
+    executed: u64,
+
+    // the translated blocks, when running them
+    #[cfg(feature = "jit")]
+    jit: Option<Box<jit::Jit>>,
 }
 
 impl Default for CPU {
     fn default() -> Self {
         Self {
-            program_counter: memory::START_ADDR,
+            program_counter: START_ADDR,
             v_registers: [0; NUM_REGS],
             i_register: 0,
             stack: Stack::default(),
             ram: Ram::default(),
+            rom_hash: None,
             screen: Screen::default(),
             keys: [false; NUM_KEYS],
+            key_events: VecDeque::new(),
+            latched_keys: None,
+            keys2: [false; NUM_KEYS],
+            key_wait: None,
+            waiting_key: false,
+            key_wait_hook: None,
             delay_timer: 0,
             sound_timer: 0,
+            timer_elapsed: Duration::ZERO,
+            halted: false,
+            waiting_vblank: false,
+            rewind: None,
+            rng: rng::default_source(),
+            quirks: Quirks::default(),
+            opcode_handler: None,
+            profile: None,
+            coverage: None,
+            tracer: None,
+            megachip: MegaChip::default(),
+            flags: [0; NUM_FLAGS],
+            flags_sink: None,
+            ticks_per_frame: frontend::DEFAULT_TICKS_PER_FRAME,
+            timing: Timing::default(),
+            cycles: 0,
+            frame_end: 0,
+            executed: 0,
+            #[cfg(feature = "jit")]
+            jit: None,
         }
     }
 }
 
 impl CPU {
-    fn fetch(&mut self) -> u16 {
-        let instruction = self.ram.fetch_instruction(self.program_counter as usize);
-        self.program_counter += 2;
-        instruction
+    fn fetch(&mut self) -> Result<u16, Chip8Error> {
+        let instruction = self.ram.fetch_instruction(self.program_counter as usize)?;
+        // wraps with the program counter driven past 0xFFFF by the Wrap
+        // bounds policy
+        self.program_counter = self.program_counter.wrapping_add(2);
+        Ok(instruction)
     }
 
     pub fn tick_timers(&mut self) {
         if self.delay_timer > 0 {
             self.delay_timer -= 1;
         }
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+        }
+        self.waiting_vblank = false;
+        self.latch_keys();
+        if let Some(profile) = &mut self.profile {
+            profile.record_frame();
+        }
+        self.record_rewind_frame();
     }
 
-    pub fn tick(&mut self) {
-        let instruction = self.fetch();
-        self.execute(instruction);
+    /// Counts the timers down at exactly 60 Hz for `elapsed` time, keeping
+    /// the leftover for the next call, for frontends not running at 60 fps.
+    /// Returns how many times [`CPU::tick_timers`] ran.
+    pub fn advance_timers(&mut self, elapsed: Duration) -> u32 {
+        self.timer_elapsed = self.timer_elapsed.saturating_add(elapsed);
+        let mut ticks = 0;
+        while self.timer_elapsed >= TIMER_PERIOD {
+            self.timer_elapsed -= TIMER_PERIOD;
+            self.tick_timers();
+            ticks += 1;
+        }
+        ticks
+    }
+
+    /// Whether the buzzer should sound, which is as long as the sound timer
+    /// is counting down.
+    pub fn is_sound_playing(&self) -> bool {
+        self.sound_timer > 0
     }
 
+    /// Fetches and executes one instruction. On error the program counter
+    /// is left pointing at the faulting instruction.
+    pub fn tick(&mut self) -> Result<(), Chip8Error> {
+        if self.halted || self.waiting_vblank {
+            return Ok(());
+        }
+        if self.latched_keys.is_none() && !self.key_events.is_empty() {
+            self.apply_key_event();
+        }
+        let pc = self.program_counter;
+        let before = self
+            .tracer
+            .is_some()
+            .then(|| trace::Registers::capture(self));
+        let opcode = self.fetch()?;
+        let instruction = self.decode(opcode);
+        self.execute(instruction)
+            .inspect_err(|_| self.program_counter = pc)?;
+        self.cycles += self.timing.cycles(opcode);
+        self.executed += 1;
+        if let Some(profile) = &mut self.profile {
+            profile.record(pc, instruction);
+        }
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record(pc, instruction);
+        }
+        if let Some(before) = before {
+            self.trace(pc, opcode, instruction, before);
+        }
+        Ok(())
+    }
+
+    /// The pixels of the current resolution, row by row,
+    /// see [`CPU::display_size`]. Only holds the first plane, see
+    /// [`CPU::frame_buffer`] for the colors of XO-CHIP programs.
     pub fn get_display(&self) -> &[bool] {
-        &self.screen.display
+        self.screen.pixels()
+    }
+
+    /// The RAM in `range`, clamped to the end of the RAM, e.g.
+    /// `cpu.read_memory(0x200..0x210)` or `cpu.read_memory(..)` for all of it.
+    pub fn read_memory(&self, range: impl RangeBounds<usize>) -> &[u8] {
+        let data = &self.ram.data;
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.saturating_add(1),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => data.len(),
+        }
+        .min(data.len());
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        }
+        .min(end);
+        &data[start..end]
+    }
+
+    /// Pokes `data` into the RAM at `addr`, e.g. to try a change to the
+    /// game's state or a fix to the ROM while paused. Nothing is written
+    /// when it doesn't fit, the error holding the first address past the
+    /// end. Peripherals and access tracking don't see it.
+    pub fn write_memory(&mut self, addr: usize, data: &[u8]) -> Result<(), Chip8Error> {
+        let end = addr.saturating_add(data.len());
+        if end > self.ram.data.len() {
+            return Err(Chip8Error::AddressOutOfBounds(
+                addr.max(self.ram.data.len()),
+            ));
+        }
+        self.ram.data[addr..end].copy_from_slice(data);
+        Ok(())
     }
 
+    /// The display at its current resolution, with (x, y) and row access.
+    pub fn frame_buffer(&self) -> FrameBuffer<'_> {
+        self.screen.frame_buffer()
+    }
+
+    /// The display with `palette` applied to the color of every pixel, 3
+    /// bytes of RGB per pixel row by row.
+    pub fn display_rgb(&self, palette: &Palette) -> Vec<u8> {
+        self.frame_buffer()
+            .colors()
+            .flat_map(|color| palette.color(color))
+            .collect()
+    }
+
+    /// The display rows changed since the last call, see [`DirtyRows`].
+    pub fn take_dirty(&mut self) -> DirtyRows {
+        self.screen.take_dirty()
+    }
+
+    /// Width and height of the display, which changes when a SUPER-CHIP
+    /// program switches between low and high resolution.
+    pub fn display_size(&self) -> (usize, usize) {
+        (self.screen.width(), self.screen.height())
+    }
+
+    /// Whether the program ended with the SUPER-CHIP exit instruction.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Instructions to execute per 60 Hz frame, see [`CPU::set_clock_hz`].
+    pub fn ticks_per_frame(&self) -> usize {
+        self.ticks_per_frame
+    }
+
+    pub fn set_ticks_per_frame(&mut self, ticks_per_frame: usize) {
+        self.ticks_per_frame = ticks_per_frame;
+    }
+
+    /// Instructions executed per second with the fixed [`Timing`].
+    pub fn clock_hz(&self) -> u32 {
+        u32::try_from(self.ticks_per_frame)
+            .unwrap_or(u32::MAX)
+            .saturating_mul(60)
+    }
+
+    /// Sets the instructions executed per second, rounded down to a whole
+    /// number per 60 Hz frame but at least one.
+    pub fn set_clock_hz(&mut self, hz: u32) {
+        self.ticks_per_frame = (hz as usize / 60).max(1);
+    }
+
+    /// Sets what reads and writes past the end of the RAM do.
+    pub fn set_bounds_policy(&mut self, policy: BoundsPolicy) {
+        self.ram.policy = policy;
+    }
+
+    /// Presses or releases key 0x0 to 0xF, other keys are ignored. With
+    /// frame-latched input the program sees it from the next frame on.
     pub fn keypress(&mut self, idx: usize, pressed: bool) {
-        self.keys[idx] = pressed;
-    }
-
-    pub fn load(&mut self, data: &[u8]) {
-        self.ram.load(data);
-    }
-
-    // 00E0 - CLS: Clear the display.
-    // 00EE - RET: Return from a subroutine.
-    // 1NNN - JP addr: Jump to address NNN.
-    // 2NNN - CALL addr: Call subroutine at NNN.
-    // 3XNN - SE Vx, byte: Skip next instruction if Vx == NN.
-    // 4XNN - SNE Vx, byte: Skip next instruction if Vx != NN.
-    // 5XY0 - SE Vx, Vy: Skip next instruction if Vx == Vy.
-    // 6XNN - LD Vx, byte: Set Vx = NN.
-    // 7XNN - ADD Vx, byte: Set Vx = Vx + NN.
-    // 8XY0 - LD Vx, Vy: Set Vx = Vy.
-    // 8XY1 - OR Vx, Vy: Set Vx = Vx OR Vy.
-    // 8XY2 - AND Vx, Vy: Set Vx = Vx AND Vy.
-    // 8XY3 - XOR Vx, Vy: Set Vx = Vx XOR Vy.
-    // 8XY4 - ADD Vx, Vy: Set Vx = Vx + Vy, set VF = carry.
-    // 8XY5 - SUB Vx, Vy: Set Vx = Vx - Vy, set VF = NOT borrow.
-    // 8XY6 - SHR Vx: Set Vx = Vx SHR 1.
-    // 8XY7 - SUBN Vx, Vy: Set Vx = Vy - Vx, set VF = NOT borrow.
-    // 8XYE - SHL Vx: Set Vx = Vx SHL 1.
-    // 9XY0 - SNE Vx, Vy: Skip next instruction if Vx != Vy.
-    // ANNN - LD I, addr: Set I = NNN.
-    // BNNN - JP V0, addr: Jump to address NNN + V0.
-    // CXNN - RND Vx, byte: Set Vx = random byte AND NN.
-    // DXYN - DRW Vx, Vy, nibble: Display n-byte sprite at memory location I at (Vx, Vy), set VF = collision.
-    // EX9E - SKP Vx: Skip next instruction if key with the value of Vx is pressed.
-    // EXA1 - SKNP Vx: Skip next instruction if key with the value of Vx is not pressed.
-    // FX07 - LD Vx, DT: Set Vx = delay timer value.
-    // FX0A - LD Vx, K: Wait for a key press, store the value of the key in Vx.
-    // FX15 - LD DT, Vx: Set delay timer = Vx.
-    // FX18 - LD ST, Vx: Set sound timer = Vx.
-    // FX1E - ADD I, Vx: Set I = I + Vx.
-    // FX29 - LD F, Vx: Set I = location of sprite for digit Vx.
-    // FX33 - LD B, Vx: Store BCD representation of Vx in memory locations I, I+1, and I+2.
-    // FX55 - LD [I], Vx: Store registers V0 through Vx in memory starting at location I.
-    // FX65 - LD Vx, [I]: Read registers V0 through Vx from memory starting at location I.
-    fn execute(&mut self, op: u16) {
-        let digit1 = (op & 0xF000) >> 12;
-        let digit2 = (op & 0x0F00) >> 8;
-        let digit3 = (op & 0x00F0) >> 4;
-        let digit4 = op & 0x000F;
-
-        match (digit1, digit2, digit3, digit4) {
-            (0, 0, 0, 0) => (),
-            (0, 0, 0xE, 0) => {
+        let keys = self.latched_keys.as_mut().unwrap_or(&mut self.keys);
+        if let Some(key) = keys.get_mut(idx) {
+            *key = pressed;
+        }
+    }
+
+    /// Latches key changes at frame boundaries, when [`CPU::tick_timers`]
+    /// runs, instead of applying them at once, like the interpreters that
+    /// sampled the keypad once per frame. Events queued with
+    /// [`CPU::push_key_event`] then change a key per frame instead of per
+    /// instruction. Turning it off applies the changes latched.
+    pub fn set_latched_input(&mut self, on: bool) {
+        if on {
+            self.latched_keys.get_or_insert(self.keys);
+        } else if let Some(keys) = self.latched_keys.take() {
+            self.keys = keys;
+        }
+    }
+
+    pub fn is_latched_input(&self) -> bool {
+        self.latched_keys.is_some()
+    }
+
+    // applies the key changes latched during the frame
+    fn latch_keys(&mut self) {
+        let Some(latched) = self.latched_keys else {
+            return;
+        };
+        self.keys = latched;
+        self.apply_key_event();
+        self.latched_keys = Some(self.keys);
+    }
+
+    /// Whether key 0x0 to 0xF is held, false for other keys.
+    pub fn is_key_pressed(&self, idx: usize) -> bool {
+        self.keys.get(idx).copied().unwrap_or(false)
+    }
+
+    /// The keys held, by key 0x0 to 0xF. Changes queued with
+    /// [`CPU::push_key_event`] show once an instruction saw them.
+    pub fn keys(&self) -> &[bool; NUM_KEYS] {
+        &self.keys
+    }
+
+    /// Queues pressing or releasing key 0x0 to 0xF, other keys are ignored.
+    /// Unlike [`CPU::keypress`] every change is seen by an instruction in
+    /// the order pushed, one per instruction, so a press and a release
+    /// polled in the same frame still reach FX0A and EX9E.
+    pub fn push_key_event(&mut self, idx: usize, pressed: bool) {
+        if idx < NUM_KEYS {
+            self.key_events.push_back((idx as u8, pressed));
+        }
+    }
+
+    // applies the queued events up to the first changing a key
+    fn apply_key_event(&mut self) {
+        while let Some((key, pressed)) = self.key_events.pop_front() {
+            let key = &mut self.keys[key as usize];
+            if *key != pressed {
+                *key = pressed;
+                return;
+            }
+        }
+    }
+
+    /// Copies a ROM into memory at 0x200. Memory is left untouched if the
+    /// ROM is empty, too large or blank. ROMs of the two-page hi-res
+    /// variant switch to its 64x64 display, see [`CPU::set_two_page`].
+    pub fn load(&mut self, data: &[u8]) -> Result<(), RomError> {
+        self.ram.load(START_ADDR, data)?;
+        self.rom_hash = Some(romdb::rom_hash(data));
+        if is_two_page_rom(data) {
+            self.set_two_page(true);
+        }
+        Ok(())
+    }
+
+    /// Like [`CPU::load`] for MegaChip ROMs, which carry their graphics and
+    /// sounds: the RAM grows up to [`MEGACHIP_MEMORY_SIZE`] to fit them.
+    /// The program still turns the MegaChip mode on with `0011`.
+    pub fn load_megachip(&mut self, data: &[u8]) -> Result<(), RomError> {
+        self.ram.load_megachip(START_ADDR, data)?;
+        self.rom_hash = Some(romdb::rom_hash(data));
+        Ok(())
+    }
+
+    /// Copies `data` into memory at `addr`, e.g. the segments of a ROM or
+    /// a test fixture. Only fails if it runs past the end of the RAM.
+    pub fn load_at(&mut self, addr: u16, data: &[u8]) -> Result<(), RomError> {
+        self.ram.load_at(addr, data)
+    }
+
+    /// Jumps to `addr`, e.g. to start a program loaded somewhere else than
+    /// 0x200.
+    pub fn set_program_counter(&mut self, addr: u16) {
+        self.program_counter = addr;
+    }
+
+    /// Sets register V`x`, e.g. from an [`OpcodeHandler`]. Panics unless
+    /// `x` is 0x0 to 0xF.
+    pub fn set_v_register(&mut self, x: usize, value: u8) {
+        self.v_registers[x] = value;
+    }
+
+    /// Sets I, e.g. from an [`OpcodeHandler`].
+    pub fn set_i_register(&mut self, addr: u16) {
+        self.set_index(addr as usize);
+    }
+
+    // the register 8XY6 and 8XYE shift, see `Quirks::shift_vy`
+    fn shift_source(&self, x: usize, y: usize) -> u8 {
+        if self.quirks.shift_vy {
+            self.v_registers[y]
+        } else {
+            self.v_registers[x]
+        }
+    }
+
+    // the MegaChip opcodes are machine code calls outside its mode, for the
+    // opcode handler if any, but for 02A0 of CHIP-8X and 0230 of the
+    // two-page display
+    fn decode(&self, opcode: u16) -> Instruction {
+        let instruction = Instruction::decode(opcode);
+        if self.is_megachip() || !instruction.is_megachip_only() {
+            return instruction;
+        }
+        match instruction {
+            Instruction::LoadPalette(0xA0) if self.is_chip8x() => instruction,
+            Instruction::LoadPalette(0x30) if self.is_two_page() => instruction,
+            _ => Instruction::Sys(opcode & 0xFFF),
+        }
+    }
+
+    // see `Instruction` for what every instruction does
+    fn execute(&mut self, instruction: Instruction) -> Result<(), Chip8Error> {
+        match instruction {
+            Instruction::Sys(0) => (),
+            Instruction::MegaOff => {
+                // leave the MegaChip mode
+                self.set_megachip(false);
+            }
+            Instruction::MegaOn => {
+                // MegaChip mode
+                self.set_megachip(true);
+            }
+            Instruction::ScrollUp(n) => {
+                // scroll up n lines
+                self.screen.scroll_up(n as usize);
+            }
+            Instruction::LoadLongI(high) => {
+                // set i = the 24 bit address of nn and the next word
+                let low = self.fetch()?;
+                self.set_index((high as usize) << 16 | low as usize);
+            }
+            Instruction::LoadPalette(0xA0) if self.is_chip8x() => {
+                // 02A0, the next background color
+                self.next_background();
+            }
+            Instruction::LoadPalette(0x30) if self.is_two_page() => {
+                // 0230, clear the two-page display
+                self.screen.clear();
+            }
+            Instruction::LoadPalette(count) => {
+                self.load_palette(count)?;
+            }
+            Instruction::SpriteWidth(width) => {
+                self.megachip.sprite_width = width;
+            }
+            Instruction::SpriteHeight(height) => {
+                self.megachip.sprite_height = height;
+            }
+            Instruction::Alpha(alpha) => {
+                self.megachip.alpha = alpha;
+            }
+            Instruction::PlaySound(n) => {
+                self.play_sound(n == 0)?;
+            }
+            Instruction::StopSound => {
+                self.megachip.sound = None;
+            }
+            Instruction::BlendMode(mode) => {
+                self.megachip.blend_mode = mode;
+            }
+            Instruction::CollisionColor(color) => {
+                self.megachip.collision_color = color;
+            }
+            Instruction::ScrollDown(n) => {
+                // scroll down n lines
+                self.screen.scroll_down(n as usize);
+            }
+            Instruction::Clear if self.is_megachip() => {
+                // show the frame drawn and wait for the next one
+                self.screen.flip(self.megachip.alpha);
+                self.waiting_vblank = true;
+            }
+            Instruction::Clear => {
                 // clear screen
                 self.screen.clear();
             }
-            (0, 0, 0xE, 0xE) => {
+            Instruction::Return => {
                 // return
-                let ret_addr = self.stack.pop();
+                let ret_addr = self.stack.pop()?;
                 self.program_counter = ret_addr;
             }
-            (1, _, _, _) => {
+            Instruction::ScrollRight => {
+                // scroll right 4 pixels
+                self.screen.scroll_right(4);
+            }
+            Instruction::ScrollLeft => {
+                // scroll left 4 pixels
+                self.screen.scroll_left(4);
+            }
+            Instruction::Exit => {
+                // exit the interpreter
+                self.halted = true;
+            }
+            Instruction::LowRes => {
+                // low resolution
+                self.screen.set_hires(false);
+            }
+            Instruction::HighRes => {
+                // high resolution
+                self.screen.set_hires(true);
+            }
+            Instruction::Jump(nnn) if self.is_two_page_entry(nnn) => {
+                // skip the machine code setting up the two-page display
+                self.program_counter = TWO_PAGE_START_ADDR;
+            }
+            Instruction::Jump(nnn) => {
                 // jump nnn
-                let nnn = op & 0xFFF;
                 self.program_counter = nnn;
             }
-            (2, _, _, _) => {
+            Instruction::Call(nnn) => {
                 // call nnn
-                let nnn = op & 0xFFF;
-                self.stack.push(self.program_counter);
+                self.stack.push(self.program_counter)?;
                 self.program_counter = nnn;
             }
-            (3, _, _, _) => {
+            Instruction::SkipEqImm { x, nn } => {
                 // skip vx == nn
-                let x = digit2 as usize;
-                let nn = (op & 0xFF) as u8;
-                if self.v_registers[x] == nn {
-                    self.program_counter += 2;
+                if self.v_registers[x as usize] == nn {
+                    self.program_counter = self.program_counter.wrapping_add(2);
                 }
             }
-            (4, _, _, _) => {
+            Instruction::SkipNeImm { x, nn } => {
                 // skip vx != nn
-                let x = digit2 as usize;
-                let nn = (op & 0xFF) as u8;
-                if self.v_registers[x] != nn {
-                    self.program_counter += 2;
+                if self.v_registers[x as usize] != nn {
+                    self.program_counter = self.program_counter.wrapping_add(2);
                 }
             }
-            (5, _, _, 0) => {
+            Instruction::SkipEq { x, y } => {
                 // skip vx == vy
-                let x = digit2 as usize;
-                let y = digit3 as usize;
-                if self.v_registers[x] == self.v_registers[y] {
-                    self.program_counter += 2;
+                if self.v_registers[x as usize] == self.v_registers[y as usize] {
+                    self.program_counter = self.program_counter.wrapping_add(2);
                 }
             }
-            (6, _, _, _) => {
+            Instruction::AddNibbles { x, y } => {
+                // set vx += vy a nibble at a time
+                let vy = self.v_registers[y as usize];
+                let vx = &mut self.v_registers[x as usize];
+                *vx = chip8x::add_nibbles(*vx, vy);
+            }
+            Instruction::LoadImm { x, nn } => {
                 // set vx = nn
-                let x = digit2 as usize;
-                let nn = (op & 0xFF) as u8;
-                self.v_registers[x] = nn;
+                self.v_registers[x as usize] = nn;
             }
-            (7, _, _, _) => {
+            Instruction::AddImm { x, nn } => {
                 // set vx += nn
-                let x = digit2 as usize;
-                let nn = (op & 0xFF) as u8;
+                let x = x as usize;
                 self.v_registers[x] = self.v_registers[x].wrapping_add(nn);
             }
-            (8, _, _, 0) => {
+            Instruction::Load { x, y } => {
                 // set vx = vy
-                let x = digit2 as usize;
-                let y = digit3 as usize;
-                self.v_registers[x] = self.v_registers[y];
+                self.v_registers[x as usize] = self.v_registers[y as usize];
             }
-            (8, _, _, 1) => {
+            Instruction::Or { x, y } => {
                 // set vx |= vy
-                let x = digit2 as usize;
-                let y = digit3 as usize;
-                self.v_registers[x] |= self.v_registers[y];
+                self.v_registers[x as usize] |= self.v_registers[y as usize];
             }
-            (8, _, _, 2) => {
+            Instruction::And { x, y } => {
                 // set vx &= vy
-                let x = digit2 as usize;
-                let y = digit3 as usize;
-                self.v_registers[x] &= self.v_registers[y];
+                self.v_registers[x as usize] &= self.v_registers[y as usize];
             }
-            (8, _, _, 3) => {
+            Instruction::Xor { x, y } => {
                 // set vx ^= vy
-                let x = digit2 as usize;
-                let y = digit3 as usize;
-                self.v_registers[x] ^= self.v_registers[y];
+                self.v_registers[x as usize] ^= self.v_registers[y as usize];
             }
-            (8, _, _, 4) => {
+            Instruction::Add { x, y } => {
                 // set vx += vy, set vf = carry
-                let x = digit2 as usize;
-                let y = digit3 as usize;
+                let (x, y) = (x as usize, y as usize);
                 let (res, overflow) = self.v_registers[x].overflowing_add(self.v_registers[y]);
                 self.v_registers[x] = res;
                 self.v_registers[0xF] = if overflow { 1 } else { 0 };
             }
-            (8, _, _, 5) => {
+            Instruction::Sub { x, y } => {
                 // set vx -= vy, set vf = !borrow
-                let x = digit2 as usize;
-                let y = digit3 as usize;
+                let (x, y) = (x as usize, y as usize);
                 let (res, overflow) = self.v_registers[x].overflowing_sub(self.v_registers[y]);
                 self.v_registers[x] = res;
                 self.v_registers[0xF] = if overflow { 0 } else { 1 };
             }
-            (8, _, _, 6) => {
-                // set vx >>= 1, set vf = lsb
-                let x = digit2 as usize;
-                self.v_registers[0xF] = self.v_registers[x] & 0x1;
-                self.v_registers[x] >>= 1;
+            Instruction::ShiftRight { x, y } => {
+                // set vx = vx >> 1 (or vy >> 1), set vf = lsb
+                let x = x as usize;
+                let src = self.shift_source(x, y as usize);
+                self.v_registers[0xF] = src & 0x1;
+                self.v_registers[x] = src >> 1;
             }
-            (8, _, _, 7) => {
+            Instruction::SubN { x, y } => {
                 // set vx = vy - vx, set vf = !borrow
-                let x = digit2 as usize;
-                let y = digit3 as usize;
+                let (x, y) = (x as usize, y as usize);
                 let (res, overflow) = self.v_registers[y].overflowing_sub(self.v_registers[x]);
                 self.v_registers[x] = res;
                 self.v_registers[0xF] = if overflow { 0 } else { 1 };
             }
-            (8, _, _, 0xE) => {
-                // set vx <<= 1, set vf = msb
-                let x = digit2 as usize;
-                self.v_registers[0xF] = (self.v_registers[x] & 0x80) >> 7;
-                self.v_registers[x] <<= 1;
+            Instruction::ShiftLeft { x, y } => {
+                // set vx = vx << 1 (or vy << 1), set vf = msb
+                let x = x as usize;
+                let src = self.shift_source(x, y as usize);
+                self.v_registers[0xF] = (src & 0x80) >> 7;
+                self.v_registers[x] = src << 1;
             }
-            (9, _, _, 0) => {
+            Instruction::SkipNe { x, y } => {
                 // skip vx != vy
-                let x = digit2 as usize;
-                let y = digit3 as usize;
-                if self.v_registers[x] != self.v_registers[y] {
-                    self.program_counter += 2;
+                if self.v_registers[x as usize] != self.v_registers[y as usize] {
+                    self.program_counter = self.program_counter.wrapping_add(2);
                 }
             }
-            (0xA, _, _, _) => {
+            Instruction::LoadI(nnn) => {
                 // set i = nnn
-                let nnn = op & 0xFFF;
-                self.i_register = nnn;
+                self.set_index(nnn as usize);
+            }
+            Instruction::JumpOffset { x, nnn } if self.is_chip8x() => {
+                // BXY0 colors zones, BXYN rows
+                let (y, n) = (((nnn >> 4) & 0xF) as u8, (nnn & 0xF) as u8);
+                if n == 0 {
+                    self.color_zones(x, y);
+                } else {
+                    self.color_rows(x, y, n);
+                }
             }
-            (0xB, _, _, _) => {
-                // jump nnn + v0
-                let nnn = op & 0xFFF;
-                self.program_counter = nnn + self.v_registers[0] as u16;
+            Instruction::JumpOffset { x, nnn } => {
+                // jump nnn + v0, or xnn + vx with the jump quirk
+                let reg = if self.quirks.jump_vx { x as usize } else { 0 };
+                self.program_counter = nnn + self.v_registers[reg] as u16;
             }
-            (0xC, _, _, _) => {
+            Instruction::Random { x, nn } => {
                 // set vx = rand() & nn
-                let x = digit2 as usize;
-                let nn = (op & 0xFF) as u8;
-                let rand_byte = random::<u8>();
-                self.v_registers[x] = rand_byte & nn;
+                let rand_byte = self.rng.next_byte();
+                self.v_registers[x as usize] = rand_byte & nn;
+            }
+            Instruction::Draw { x, y, n } if self.is_megachip() => {
+                self.draw_megachip(x, y, n)?;
             }
-            (0xD, _, _, _) => {
+            Instruction::Draw { x, y, n } => {
                 // opcode Dxyn: Draw a sprite at coordinate (Vx, Vy) with a height of n pixels.
                 // The sprite is located in memory at the address stored in the I register.
+                let n = n as usize;
 
-                // Extract x, y, and n from the opcode
-                let x = digit2 as usize;
-                let y = digit3 as usize;
-                let n = digit4 as usize;
+                let width = self.screen.width();
+                let height = self.screen.height();
 
-                // Get the x and y coordinates from the V registers
-                let vx = self.v_registers[x] as usize;
-                let vy = self.v_registers[y] as usize;
+                // Get the x and y coordinates from the V registers, the
+                // start of the sprite always wraps around the screen
+                let vx = self.v_registers[x as usize] as usize % width;
+                let vy = self.v_registers[y as usize] as usize % height;
+                let clip = self.quirks.clip_sprites;
+
+                // A height of 0 draws a SUPER-CHIP 16x16 sprite, two bytes per row
+                let (rows, cols) = if n == 0 { (16, 16) } else { (n, 8) };
 
                 // Reset the collision flag
                 self.v_registers[0xF] = 0;
 
-                // Loop over each row of the sprite
-                for row in 0..n {
-                    // Fetch the sprite byte from memory
-                    let sprite = self.ram.fetch_byte((self.i_register + row as u16) as usize);
+                // Every selected plane gets its own sprite, one after the
+                // other in memory
+                let mut addr = self.index();
+                for plane in 0..screen::NUM_PLANES {
+                    if self.screen.selected & (1 << plane) == 0 {
+                        continue;
+                    }
+
+                    // Loop over each row of the sprite
+                    for row in 0..rows {
+                        // Rows below the screen are skipped when clipping,
+                        // their sprite data still has to be read past
+                        let clipped_row = clip && vy + row >= height;
+                        // Fetch the sprite row from memory, left aligned in 16 bits
+                        let sprite = if cols == 16 {
+                            let word = (self.ram.read_byte(addr)? as u16) << 8
+                                | self.ram.read_byte(addr + 1)? as u16;
+                            addr += 2;
+                            word
+                        } else {
+                            let byte = (self.ram.read_byte(addr)? as u16) << 8;
+                            addr += 1;
+                            byte
+                        };
+                        if clipped_row {
+                            continue;
+                        }
+                        if sprite != 0 {
+                            self.screen.mark_dirty((vy + row) % height);
+                        }
+                        let pixels = &mut self.screen.planes[plane];
+
+                        // Loop over each bit in the sprite row
+                        for col in 0..cols {
+                            if clip && vx + col >= width {
+                                break;
+                            }
 
-                    // Loop over each bit in the sprite byte
-                    for col in 0..8 {
-                        // Extract the bit value (0 or 1)
-                        let bit = (sprite >> (7 - col)) & 1;
+                            // Extract the bit value (0 or 1)
+                            let bit = (sprite >> (15 - col)) & 1;
 
-                        // Calculate the screen index, wrapping around screen dimensions
-                        let idx = (vx + col) % screen::SCREEN_WIDTH
-                            + ((vy + row) % screen::SCREEN_HEIGHT) * screen::SCREEN_WIDTH;
+                            // Calculate the screen index, wrapping around screen dimensions
+                            let idx = (vx + col) % width + ((vy + row) % height) * width;
 
-                        // Get the current bit on the screen
-                        let prev_bit = self.screen.display[idx];
+                            // Get the current bit on the screen
+                            let prev_bit = pixels[idx];
 
-                        // XOR the screen bit with the sprite bit (draw the sprite)
-                        self.screen.display[idx] ^= bit == 1;
+                            // XOR the screen bit with the sprite bit (draw the sprite)
+                            pixels[idx] ^= bit == 1;
 
-                        // Check for collision (if a bit was set and is now unset)
-                        if prev_bit && !self.screen.display[idx] {
-                            // Set the collision flag
-                            self.v_registers[0xF] = 1;
+                            // Check for collision (if a bit was set and is now unset)
+                            if prev_bit && !pixels[idx] {
+                                // Set the collision flag
+                                self.v_registers[0xF] = 1;
+                            }
                         }
                     }
                 }
+
+                // The rest of the frame is spent waiting for the display
+                self.waiting_vblank = self.quirks.display_wait;
             }
-            (0xE, _, 9, 0xE) => {
+            Instruction::SkipKey { x } => {
                 // skip key press
-                let x = digit2 as usize;
-                let vx = self.v_registers[x];
-                let key = self.keys[vx as usize];
+                let vx = self.v_registers[x as usize];
+                let key = *self
+                    .keys
+                    .get(vx as usize)
+                    .ok_or(Chip8Error::InvalidKey(vx))?;
                 if key {
-                    self.program_counter += 2;
+                    self.program_counter = self.program_counter.wrapping_add(2);
                 }
             }
-            (0xE, _, 0xA, 1) => {
+            Instruction::SkipNotKey { x } => {
                 // skip key release
-                let x = digit2 as usize;
-                let vx = self.v_registers[x];
-                let key = self.keys[vx as usize];
+                let vx = self.v_registers[x as usize];
+                let key = *self
+                    .keys
+                    .get(vx as usize)
+                    .ok_or(Chip8Error::InvalidKey(vx))?;
                 if !key {
-                    self.program_counter += 2;
+                    self.program_counter = self.program_counter.wrapping_add(2);
                 }
             }
-            (0xF, _, 0, 7) => {
-                // vx = delay timer
-                let x = digit2 as usize;
-                self.v_registers[x] = self.delay_timer;
-            }
-            (0xF, _, 0, 0xA) => {
-                // wait for key press and store the key value in Vx
-                let x = digit2 as usize;
-                let mut pressed = false;
-                for i in 0..self.keys.len() {
-                    if self.keys[i] {
-                        self.v_registers[x] = i as u8;
-                        pressed = true;
-                        break;
-                    }
+            Instruction::SkipKey2 { x } => {
+                // skip key press on the second keypad
+                if self.key2(self.v_registers[x as usize])? {
+                    self.program_counter = self.program_counter.wrapping_add(2);
                 }
-                if !pressed {
-                    // Repeat the current opcode if no key is pressed
-                    self.program_counter -= 2;
+            }
+            Instruction::SkipNotKey2 { x } => {
+                // skip key release on the second keypad
+                if !self.key2(self.v_registers[x as usize])? {
+                    self.program_counter = self.program_counter.wrapping_add(2);
                 }
             }
-            (0xF, _, 1, 5) => {
+            Instruction::Plane(planes) => {
+                // select the planes drawn on
+                self.screen.select(planes);
+            }
+            Instruction::ReadDelay { x } => {
+                // vx = delay timer
+                self.v_registers[x as usize] = self.delay_timer;
+            }
+            Instruction::WaitKey { x } => {
+                // wait for a key to be pressed then released, and store
+                // the key value in Vx
+                self.wait_key(x)?;
+            }
+            Instruction::SetDelay { x } => {
                 // delay_timer = vx
-                let x = digit2 as usize;
-                self.delay_timer = self.v_registers[x];
+                self.delay_timer = self.v_registers[x as usize];
             }
-            (0xF, _, 1, 8) => {
+            Instruction::SetSound { x } => {
                 // sound_timer = vx
-                let x = digit2 as usize;
-                self.sound_timer = self.v_registers[x];
+                self.sound_timer = self.v_registers[x as usize];
+            }
+            Instruction::AddI { x } if self.is_megachip() => {
+                // i register += vx, carrying into the 24 bit address
+                let vx = self.v_registers[x as usize] as usize;
+                self.set_index((self.index() + vx) & 0xFF_FFFF);
             }
-            (0xF, _, 1, 0xE) => {
+            Instruction::AddI { x } => {
                 // i register += vx
-                let x = digit2 as usize;
-                let vx = self.v_registers[x] as u16;
+                let vx = self.v_registers[x as usize] as u16;
                 self.i_register = self.i_register.wrapping_add(vx);
             }
-            (0xF, _, 2, 9) => {
-                let x = digit2 as usize;
-                let c = self.v_registers[x] as u16;
-                self.i_register = c * 5;
+            Instruction::Font { x } => {
+                let c = self.v_registers[x as usize] as usize;
+                self.set_index(c * 5);
                 // starting memory address of the sprite for that character.
                 // this is because the sprites are stored sequentially in memory,
                 // and each sprite occupies 5 bytes.
             }
-            (0xF, x, 3, 3) => {
+            Instruction::BigFont { x } => {
+                // the 8x10 font sprites are 10 bytes each
+                let c = self.v_registers[x as usize] as usize;
+                self.set_index(font::BIG_FONTSET_ADDR + c * 10);
+            }
+            Instruction::Bcd { x } => {
                 // retrieve the value from register vx
                 // we need the value in vx to convert it to its binary-coded decimal (bcd) representation
                 let value = self.v_registers[x as usize];
 
                 // store the hundreds digit of the value at memory address i
                 // the bcd representation requires splitting the value into hundreds, tens, and units
-                self.ram.write_byte(self.i_register as usize, value / 100);
+                let i = self.index();
+                self.ram.write_byte(i, value / 100)?;
 
                 // store the tens digit of the value at memory address i+1
                 // this ensures the correct bcd representation is stored in consecutive memory locations
-                self.ram
-                    .write_byte((self.i_register + 1) as usize, (value / 10) % 10);
+                self.ram.write_byte(i + 1, (value / 10) % 10)?;
 
                 // store the units digit of the value at memory address i+2
                 // storing the units completes the bcd representation in memory
-                self.ram
-                    .write_byte((self.i_register + 2) as usize, value % 10);
+                self.ram.write_byte(i + 2, value % 10)?;
             }
-            (0xF, x, 5, 5) => {
+            Instruction::Store { x } => {
                 // store the values of registers v0 to vx in memory starting at address i
-                let i = self.i_register as usize;
+                let i = self.index();
                 for idx in 0..=x as usize {
-                    self.ram.write_byte(i + idx, self.v_registers[idx]);
+                    self.ram.write_byte(i + idx, self.v_registers[idx])?;
+                }
+                if self.quirks.increment_i {
+                    self.i_register = self.i_register.wrapping_add(x as u16 + 1);
                 }
             }
-            (0xF, x, 6, 5) => {
+            Instruction::Restore { x } => {
                 // load v0 - vx
-                let i = self.i_register as usize;
+                let i = self.index();
                 for idx in 0..=x as usize {
-                    self.v_registers[idx] = self.ram.fetch_byte(i + idx);
+                    self.v_registers[idx] = self.ram.read_byte(i + idx)?;
                 }
+                if self.quirks.increment_i {
+                    self.i_register = self.i_register.wrapping_add(x as u16 + 1);
+                }
+            }
+            Instruction::SaveFlags { x } => {
+                // store v0 - vx in the user flags
+                self.store_flags(x as usize + 1);
+            }
+            Instruction::LoadFlags { x } => {
+                // load v0 - vx from the user flags
+                let count = x as usize + 1;
+                self.v_registers[..count].copy_from_slice(&self.flags[..count]);
+            }
+            // machine code routines of the COSMAC VIP can't run, unless
+            // the embedder runs them
+            Instruction::Sys(opcode) | Instruction::Unknown(opcode) => {
+                self.unknown_opcode(opcode)?;
             }
-            (_, _, _, _) => unimplemented!("Unimplemented opcode: {op}"),
         }
+        Ok(())
     }
 }
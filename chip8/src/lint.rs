@@ -0,0 +1,145 @@
+//! Checks a ROM for mistakes without running it, to tell a bad ROM file
+//! from an emulator bug, see [`lint`].
+
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+
+use crate::two_page::ENTRY_JUMP;
+use crate::{is_two_page_rom, Instruction, START_ADDR, TWO_PAGE_START_ADDR};
+
+/// Something wrong with a ROM found by [`lint`], at the address of the
+/// instruction it is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Warning {
+    /// The program reaches an opcode no interpreter runs, or machine code.
+    InvalidOpcode { address: u16, opcode: u16 },
+    /// A jump or call to an address outside of the ROM.
+    OutOfRom { address: u16, target: u16 },
+    /// An instruction starting in the middle of another one, paths through
+    /// the code out of step with each other.
+    Overlap { address: u16 },
+    /// The program runs past the last whole instruction of the ROM.
+    RunsOffEnd { address: u16 },
+}
+
+impl Warning {
+    pub fn address(&self) -> u16 {
+        match *self {
+            Warning::InvalidOpcode { address, .. }
+            | Warning::OutOfRom { address, .. }
+            | Warning::Overlap { address }
+            | Warning::RunsOffEnd { address } => address,
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:03X}: ", self.address())?;
+        match self {
+            Warning::InvalidOpcode { opcode, .. } => write!(f, "invalid opcode 0x{opcode:04X}"),
+            Warning::OutOfRom { target, .. } => {
+                write!(f, "jumps to 0x{target:03X}, outside of the ROM")
+            }
+            Warning::Overlap { .. } => write!(f, "starts in the middle of an instruction"),
+            Warning::RunsOffEnd { .. } => write!(f, "runs past the end of the ROM"),
+        }
+    }
+}
+
+/// Follows every path through `rom`, loaded at `start`, and reports what
+/// looks wrong on the way, sorted by address. Code reached only through
+/// BNNN isn't followed, its target is known only when it runs.
+pub fn lint(rom: &[u8], start: u16) -> Vec<Warning> {
+    follow(rom, start).warnings
+}
+
+/// The paths through a ROM, see [`follow`].
+pub(crate) struct Flow {
+    /// Addresses of the whole instructions the program reaches.
+    pub code: BTreeSet<u16>,
+    /// Addresses in the ROM jumped to or called.
+    pub targets: BTreeSet<u16>,
+    /// Sorted by address.
+    pub warnings: Vec<Warning>,
+}
+
+/// Follows every path through `rom` loaded at `start`, for [`lint`] and
+/// the listings of [`crate::disasm`].
+pub(crate) fn follow(rom: &[u8], start: u16) -> Flow {
+    let end = start as u32 + rom.len() as u32;
+    let two_page = start == START_ADDR && is_two_page_rom(rom);
+    let mut warnings = Vec::new();
+    let mut seen = HashSet::new();
+    let mut code = BTreeSet::new();
+    let mut targets = BTreeSet::new();
+    let mut pending = vec![start];
+
+    while let Some(address) = pending.pop() {
+        if !seen.insert(address) {
+            continue;
+        }
+        let offset = address.wrapping_sub(start) as usize;
+        let (Some(&hi), Some(&lo)) = (rom.get(offset), rom.get(offset + 1)) else {
+            warnings.push(Warning::RunsOffEnd { address });
+            continue;
+        };
+        code.insert(address);
+        let opcode = (hi as u16) << 8 | lo as u16;
+        let next = address.wrapping_add(2);
+
+        let mut branch = |target: u16| {
+            if target < start || target as u32 >= end {
+                warnings.push(Warning::OutOfRom { address, target });
+                return;
+            }
+            targets.insert(target);
+            pending.push(target);
+        };
+        match Instruction::decode(opcode) {
+            Instruction::Jump(ENTRY_JUMP) if two_page && address == start => {
+                branch(TWO_PAGE_START_ADDR)
+            }
+            Instruction::Jump(target) => branch(target),
+            Instruction::Call(target) => {
+                branch(target);
+                pending.push(next);
+            }
+            Instruction::Return | Instruction::Exit | Instruction::JumpOffset { .. } => {}
+            // followed by the low word of the address
+            Instruction::LoadLongI(_) => pending.push(next.wrapping_add(2)),
+            Instruction::SkipEqImm { .. }
+            | Instruction::SkipNeImm { .. }
+            | Instruction::SkipEq { .. }
+            | Instruction::SkipNe { .. }
+            | Instruction::SkipKey { .. }
+            | Instruction::SkipNotKey { .. }
+            | Instruction::SkipKey2 { .. }
+            | Instruction::SkipNotKey2 { .. } => {
+                pending.push(next);
+                pending.push(next.wrapping_add(2));
+            }
+            Instruction::Sys(0) => pending.push(next),
+            Instruction::Sys(_) | Instruction::Unknown(_) => {
+                warnings.push(Warning::InvalidOpcode { address, opcode })
+            }
+            _ => pending.push(next),
+        }
+    }
+
+    // odd addresses are fine as long as no path runs a byte earlier, and
+    // paths out of step are reported where they first meet
+    let ran = |address: u16, back: u16| seen.contains(&address.wrapping_sub(back));
+    warnings.extend(
+        seen.iter()
+            .filter(|&&address| ran(address, 1) && !ran(address, 2))
+            .map(|&address| Warning::Overlap { address }),
+    );
+    warnings.sort_by_key(Warning::address);
+    warnings.dedup();
+    Flow {
+        code,
+        targets,
+        warnings,
+    }
+}
@@ -0,0 +1,231 @@
+//! The MegaChip extension of the Mega8 interpreter: a 256x192 display in
+//! 256 colors, sprites of palette indices blended onto it, digitized sound
+//! and a 24 bit I reaching ROMs of up to 16 MiB. 0011 switches it on, see
+//! [`CPU::is_megachip`].
+
+use crate::screen::{Argb, Rgb, MEGACHIP_COLORS, MEGACHIP_SCREEN_HEIGHT, MEGACHIP_SCREEN_WIDTH};
+use crate::{Chip8Error, CPU, START_ADDR};
+
+// font sprites, stored below the programs, are drawn a bit per pixel in
+// this color
+const FONT_COLOR: u8 = 0xFF;
+
+// sample rate, 16 bits, and length, 24 bits, before the samples of a sound
+const SOUND_HEADER: usize = 5;
+
+/// The registers of the MegaChip instructions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MegaChip {
+    // bits 16 to 23 of I, set by LDHI
+    pub i_high: u8,
+    // color 0 is transparent
+    pub palette: [Argb; MEGACHIP_COLORS],
+    // 0 for 256
+    pub sprite_width: u8,
+    pub sprite_height: u8,
+    pub blend_mode: u8,
+    pub collision_color: u8,
+    // brightness the next 00E0 shows the screen at
+    pub alpha: u8,
+    pub sound: Option<Sound>,
+    // sounds started so far
+    pub sounds_started: u32,
+}
+
+impl Default for MegaChip {
+    /// Opaque white until the program loads its palette.
+    fn default() -> Self {
+        let mut palette = [[0xFF; 4]; MEGACHIP_COLORS];
+        palette[0] = [0; 4];
+        Self {
+            i_high: 0,
+            palette,
+            sprite_width: 0,
+            sprite_height: 0,
+            blend_mode: 0,
+            collision_color: 0,
+            alpha: 0xFF,
+            sound: None,
+            sounds_started: 0,
+        }
+    }
+}
+
+/// A digitized sound started by 060N, unsigned 8 bit samples in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Sound {
+    pub start: usize,
+    pub len: usize,
+    pub sample_rate: u16,
+    pub looping: bool,
+    // tells the sounds started apart, so replaying one restarts it
+    pub id: u32,
+}
+
+/// The digitized sound a MegaChip program plays, see
+/// [`CPU::digitized_sound`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DigitizedSound<'a> {
+    /// Unsigned 8 bit samples, 128 being silence.
+    pub samples: &'a [u8],
+    pub sample_rate: u16,
+    /// Whether it starts over once played, instead of stopping.
+    pub looping: bool,
+    /// Differs for every sound started, to tell a restart apart.
+    pub id: u32,
+}
+
+// how sprites mix with the colors under them, by 080N
+fn blend(mode: u8, under: Rgb, color: Argb) -> Rgb {
+    let [alpha, r, g, b] = color.map(u32::from);
+    let under = under.map(u32::from);
+    let over = [r, g, b];
+    let mix = |opacity: u32| {
+        std::array::from_fn(|c| ((over[c] * opacity + under[c] * (255 - opacity)) / 255) as u8)
+    };
+    match mode {
+        1 => mix(alpha / 4),
+        2 => mix(alpha / 2),
+        3 => mix(alpha * 3 / 4),
+        4 => std::array::from_fn(|c| (under[c] + over[c] * alpha / 255).min(255) as u8),
+        5 => std::array::from_fn(|c| (under[c] * over[c] / 255) as u8),
+        // unknown modes draw normally
+        _ => mix(alpha),
+    }
+}
+
+impl CPU {
+    /// Whether a MegaChip program switched to the 256x192 display. Its
+    /// colors are in [`FrameBuffer::rgb`].
+    ///
+    /// [`FrameBuffer::rgb`]: crate::screen::FrameBuffer::rgb
+    pub fn is_megachip(&self) -> bool {
+        self.screen.megachip.is_some()
+    }
+
+    /// The digitized sound a MegaChip program started, until it stops it.
+    /// [`AudioSource`] plays it.
+    ///
+    /// [`AudioSource`]: crate::audio::AudioSource
+    pub fn digitized_sound(&self) -> Option<DigitizedSound<'_>> {
+        let sound = self.megachip.sound?;
+        Some(DigitizedSound {
+            samples: self.read_memory(sound.start..sound.start + sound.len),
+            sample_rate: sound.sample_rate,
+            looping: sound.looping,
+            id: sound.id,
+        })
+    }
+
+    /// I, with the bits above the 16 of the register LDHI sets.
+    pub(crate) fn index(&self) -> usize {
+        (self.megachip.i_high as usize) << 16 | self.i_register as usize
+    }
+
+    pub(crate) fn set_index(&mut self, addr: usize) {
+        self.i_register = addr as u16;
+        self.megachip.i_high = (addr >> 16) as u8;
+    }
+
+    pub(crate) fn set_megachip(&mut self, on: bool) {
+        self.screen.set_megachip(on);
+        if !on {
+            self.megachip.sound = None;
+        }
+    }
+
+    /// Loads colors 1 to `count` of the palette from I.
+    pub(crate) fn load_palette(&mut self, count: u8) -> Result<(), Chip8Error> {
+        let addr = self.index();
+        for color in 0..count as usize {
+            let mut argb = [0; 4];
+            for (c, byte) in argb.iter_mut().enumerate() {
+                *byte = self.ram.read_byte(addr + 4 * color + c)?;
+            }
+            if let Some(entry) = self.megachip.palette.get_mut(color + 1) {
+                *entry = argb;
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts the sound at I, a header giving its sample rate and length
+    /// followed by its samples.
+    pub(crate) fn play_sound(&mut self, looping: bool) -> Result<(), Chip8Error> {
+        let addr = self.index();
+        let mut header = [0; SOUND_HEADER];
+        for (offset, byte) in header.iter_mut().enumerate() {
+            *byte = self.ram.read_byte(addr + offset)?;
+        }
+        let [rate_hi, rate_lo, len_hi, len_mid, len_lo] = header;
+        let id = self.megachip.sounds_started;
+        self.megachip.sounds_started = id.wrapping_add(1);
+        self.megachip.sound = Some(Sound {
+            start: addr + SOUND_HEADER,
+            len: u32::from_be_bytes([0, len_hi, len_mid, len_lo]) as usize,
+            sample_rate: u16::from_be_bytes([rate_hi, rate_lo]),
+            looping,
+            id,
+        });
+        Ok(())
+    }
+
+    /// Draws the sprite at I at (Vx, Vy) on the back buffer: a palette index
+    /// per pixel, 0 being transparent, or for the font N rows of 8 bits.
+    /// Sprites are clipped at the edges. VF is set when a pixel of the
+    /// collision color is drawn over, color 0 being the background never
+    /// collides.
+    pub(crate) fn draw_megachip(&mut self, x: u8, y: u8, n: u8) -> Result<(), Chip8Error> {
+        let vx = self.v_registers[x as usize] as usize;
+        let vy = self.v_registers[y as usize] as usize;
+        let addr = self.index();
+        let font = addr < START_ADDR as usize;
+        let size = |size: u8| if size == 0 { 256 } else { size as usize };
+        let (width, height) = if font {
+            (8, n as usize)
+        } else {
+            (
+                size(self.megachip.sprite_width),
+                size(self.megachip.sprite_height),
+            )
+        };
+        let Some(display) = self.screen.megachip.as_deref_mut() else {
+            return Ok(());
+        };
+
+        let mut collision = false;
+        for row in 0..height {
+            let bits = if font {
+                self.ram.read_byte(addr + row)?
+            } else {
+                0
+            };
+            for col in 0..width {
+                let index = if font {
+                    if bits & (0x80 >> col) != 0 {
+                        FONT_COLOR
+                    } else {
+                        0
+                    }
+                } else {
+                    self.ram.read_byte(addr + row * width + col)?
+                };
+                let (px, py) = (vx + col, vy + row);
+                if index == 0 || px >= MEGACHIP_SCREEN_WIDTH || py >= MEGACHIP_SCREEN_HEIGHT {
+                    continue;
+                }
+                let idx = py * MEGACHIP_SCREEN_WIDTH + px;
+                let under = display.indices[idx];
+                collision |= under != 0 && under == self.megachip.collision_color;
+                display.indices[idx] = index;
+                display.pixels[idx] = blend(
+                    self.megachip.blend_mode,
+                    display.pixels[idx],
+                    self.megachip.palette[index as usize],
+                );
+            }
+        }
+        self.v_registers[0xF] = collision as u8;
+        Ok(())
+    }
+}
@@ -1,25 +1,53 @@
-use crate::font::{FONTSET, FONTSET_SIZE};
+use crate::access::MemoryAccess;
+use crate::error::{Chip8Error, RomError};
+use crate::font::{BIG_FONTSET, BIG_FONTSET_ADDR, BIG_FONTSET_SIZE, FONTSET, FONTSET_SIZE};
+use crate::peripheral::Peripherals;
 
 pub(crate) const RAM_SIZE: usize = 4096;
-pub(crate) const START_ADDR: u16 = 0x200;
+/// Size of the RAM in bytes, unless a larger MegaChip ROM grew it, see
+/// [`CPU::load_megachip`].
+///
+/// [`CPU::load_megachip`]: crate::CPU::load_megachip
+pub const MEMORY_SIZE: usize = RAM_SIZE;
+/// Where programs are loaded and start running.
+pub const START_ADDR: u16 = 0x200;
+/// Where programs of the ETI-660 start, its interpreter being larger.
+pub const ETI660_START_ADDR: u16 = 0x600;
+/// Where CHIP-8X programs start, its interpreter taking more room.
+pub const CHIP8X_START_ADDR: u16 = 0x300;
+
+/// Largest ROM that fits in memory, from 0x200 to the end of the RAM.
+pub const MAX_ROM_SIZE: usize = RAM_SIZE - START_ADDR as usize;
+
+/// Size the RAM grows up to for larger MegaChip ROMs, the programs carrying
+/// their graphics and sounds, as far as its 24 bit I reaches.
+pub const MEGACHIP_MEMORY_SIZE: usize = 1 << 24;
 
 pub(crate) const STACK_SIZE: usize = 16;
 
 /// The stack for the subroutines
 pub(crate) struct Stack {
-    stack_point: u16, // index in the 'stack' as we are using raw arrays
-    stack: [u16; STACK_SIZE],
+    pub(crate) stack_point: u16, // index in the 'stack' as we are using raw arrays
+    pub(crate) stack: [u16; STACK_SIZE],
 }
 
 impl Stack {
-    pub(crate) fn push(&mut self, value: u16) {
-        self.stack[self.stack_point as usize] = value;
+    pub(crate) fn push(&mut self, value: u16) -> Result<(), Chip8Error> {
+        let slot = self
+            .stack
+            .get_mut(self.stack_point as usize)
+            .ok_or(Chip8Error::StackOverflow)?;
+        *slot = value;
         self.stack_point += 1;
+        Ok(())
     }
 
-    pub(crate) fn pop(&mut self) -> u16 {
+    pub(crate) fn pop(&mut self) -> Result<u16, Chip8Error> {
+        if self.stack_point == 0 {
+            return Err(Chip8Error::StackUnderflow);
+        }
         self.stack_point -= 1;
-        self.stack[self.stack_point as usize]
+        Ok(self.stack[self.stack_point as usize])
     }
 }
 
@@ -32,8 +60,28 @@ impl Default for Stack {
     }
 }
 
+/// What a read or write past the end of the RAM does, e.g. a program
+/// setting I near 0xFFF and loading registers from it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BoundsPolicy {
+    /// Stops the program with [`Chip8Error::AddressOutOfBounds`].
+    #[default]
+    Error,
+    /// Wraps around to the start of the RAM, like the 12 bit address bus
+    /// of the original hardware.
+    Wrap,
+    /// Clamps the address to the last byte of the RAM.
+    Saturate,
+}
+
 pub(crate) struct Ram {
-    data: [u8; RAM_SIZE],
+    // RAM_SIZE bytes, unless a larger MegaChip ROM grew it
+    pub(crate) data: Vec<u8>,
+    pub(crate) policy: BoundsPolicy,
+    pub(crate) peripherals: Peripherals,
+    // reads and writes by address, when tracking them
+    pub(crate) access: Option<Box<MemoryAccess>>,
 }
 
 impl Ram {
@@ -45,42 +93,136 @@ impl Ram {
     ///
     /// # Returns
     ///
-    /// A 2-byte instruction (u16) fetched from the RAM that is [u8; 4096].
+    /// A 2-byte instruction (u16) fetched from the RAM that is [u8; 4096],
+    /// or an error if the instruction lies past the end of the RAM and the
+    /// [`BoundsPolicy`] is [`BoundsPolicy::Error`].
     ///
     /// # Example
     ///
+    /// ```ignore
+    /// let instruction = ram.fetch_instruction(0x200)?;
     /// ```
-    /// let instruction = ram.fetch(0x200);
-    /// ```
-    pub(crate) fn fetch_instruction(&self, address: usize) -> u16 {
-        let higher_byte = self.data[address] as u16;
-        let lower_byte = self.data[address + 1] as u16;
+    pub(crate) fn fetch_instruction(&self, address: usize) -> Result<u16, Chip8Error> {
+        let higher_byte = self.fetch_byte(address)? as u16;
+        let lower_byte = self.fetch_byte(address + 1)? as u16;
         // big endian
-        let op = (higher_byte << 8) | lower_byte;
-        op
+        Ok((higher_byte << 8) | lower_byte)
     }
 
-    pub(crate) fn fetch_byte(&self, address: usize) -> u8 {
-        self.data[address]
+    pub(crate) fn fetch_byte(&self, address: usize) -> Result<u8, Chip8Error> {
+        Ok(self.data[self.checked_address(address)?])
     }
 
-    pub(crate) fn load(&mut self, data: &[u8]) {
-        let start = START_ADDR as usize;
-        let end = (START_ADDR as usize) + data.len();
-        self.data[start..end].copy_from_slice(data);
+    /// Reads a byte of data for the program, from a peripheral mapped at
+    /// `address` if there is one.
+    pub(crate) fn read_byte(&mut self, address: usize) -> Result<u8, Chip8Error> {
+        let (address, value) = match self.peripherals.read(address) {
+            Some(value) => (address, value),
+            None => {
+                let address = self.checked_address(address)?;
+                (address, self.data[address])
+            }
+        };
+        if let Some(access) = &mut self.access {
+            access.record_read(address);
+        }
+        Ok(value)
     }
 
-    pub(crate) fn write_byte(&mut self, address: usize, value: u8) {
-        self.data[address] = value;
+    /// Loads a ROM at `address`, rejecting empty, too large and blank ones.
+    pub(crate) fn load(&mut self, address: u16, data: &[u8]) -> Result<(), RomError> {
+        self.load_within(address, data, self.data.len())
+    }
+
+    /// Like [`Ram::load`], but the RAM grows up to [`MEGACHIP_MEMORY_SIZE`]
+    /// to fit a MegaChip ROM.
+    pub(crate) fn load_megachip(&mut self, address: u16, data: &[u8]) -> Result<(), RomError> {
+        self.load_within(address, data, MEGACHIP_MEMORY_SIZE)
+    }
+
+    // the RAM only grows once the ROM passed every check
+    fn load_within(&mut self, address: u16, data: &[u8], size: usize) -> Result<(), RomError> {
+        if data.is_empty() {
+            return Err(RomError::Empty);
+        }
+        let max = size.saturating_sub(address as usize);
+        if data.len() > max {
+            return Err(RomError::TooLarge {
+                len: data.len(),
+                max,
+            });
+        }
+        if data.iter().all(|&byte| byte == 0x00) || data.iter().all(|&byte| byte == 0xFF) {
+            return Err(RomError::Blank);
+        }
+        let end = address as usize + data.len();
+        if end > self.data.len() {
+            self.data.resize(end, 0);
+        }
+        self.load_at(address, data)
+    }
+
+    /// Copies any data at `address`.
+    pub(crate) fn load_at(&mut self, address: u16, data: &[u8]) -> Result<(), RomError> {
+        self.check_fits(address, data)?;
+        let start = address as usize;
+        self.data[start..start + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+
+    fn check_fits(&self, address: u16, data: &[u8]) -> Result<(), RomError> {
+        let max = self.data.len().saturating_sub(address as usize);
+        // even nothing can't be copied to past the end
+        if data.len() > max || address as usize > self.data.len() {
+            return Err(RomError::TooLarge {
+                len: data.len(),
+                max,
+            });
+        }
+        Ok(())
+    }
+
+    /// Writes a byte of data for the program, to a peripheral mapped at
+    /// `address` if there is one.
+    pub(crate) fn write_byte(&mut self, address: usize, value: u8) -> Result<(), Chip8Error> {
+        let address = if self.peripherals.write(address, value) {
+            address
+        } else {
+            let address = self.checked_address(address)?;
+            self.data[address] = value;
+            address
+        };
+        if let Some(access) = &mut self.access {
+            access.record_write(address);
+        }
+        Ok(())
+    }
+
+    // applies the bounds policy to an address
+    fn checked_address(&self, address: usize) -> Result<usize, Chip8Error> {
+        let size = self.data.len();
+        if address < size {
+            return Ok(address);
+        }
+        match self.policy {
+            BoundsPolicy::Error => Err(Chip8Error::AddressOutOfBounds(address)),
+            BoundsPolicy::Wrap => Ok(address % size),
+            BoundsPolicy::Saturate => Ok(size - 1),
+        }
     }
 }
 
 impl Default for Ram {
     fn default() -> Self {
         let mut ram = Self {
-            data: [0; RAM_SIZE],
+            data: vec![0; RAM_SIZE],
+            policy: BoundsPolicy::default(),
+            peripherals: Peripherals::default(),
+            access: None,
         };
         ram.data[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        ram.data[BIG_FONTSET_ADDR..BIG_FONTSET_ADDR + BIG_FONTSET_SIZE]
+            .copy_from_slice(&BIG_FONTSET);
         ram
     }
 }
@@ -0,0 +1,221 @@
+//! Input movies: the keypad presses of a session stamped with the frame and
+//! tick they happened on, replayed deterministically from the same starting
+//! state and random seed.
+
+use std::fmt;
+
+use crate::{StateError, CPU, NUM_KEYS};
+
+// identifies a chip8 input movie
+const MAGIC: &[u8; 4] = b"C8MV";
+
+const EVENT_SIZE: usize = 4 + 2 + 1 + 1;
+
+/// A keypad key pressed or released at a point of the movie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// Frames run since the movie started.
+    pub frame: u32,
+    /// Instructions run since the start of the frame.
+    pub tick: u16,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MovieError {
+    /// The data doesn't start with the movie header.
+    BadMagic,
+    /// The data ends in the middle of the movie.
+    Truncated,
+    /// The starting state of the movie is invalid.
+    BadState(StateError),
+    /// An event presses a key past 0xF.
+    BadKey(u8),
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MovieError::BadMagic => write!(f, "not a chip8 movie"),
+            MovieError::Truncated => write!(f, "movie is truncated"),
+            MovieError::BadState(err) => write!(f, "invalid movie start: {err}"),
+            MovieError::BadKey(key) => write!(f, "movie presses invalid key {key:#X}"),
+        }
+    }
+}
+
+impl std::error::Error for MovieError {}
+
+impl From<StateError> for MovieError {
+    fn from(err: StateError) -> Self {
+        MovieError::BadState(err)
+    }
+}
+
+/// A recorded session, see [`MovieRecorder`] and [`MoviePlayer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    /// Seed of the CXNN random source, see [`CPU::seed_rng`].
+    pub seed: u64,
+    /// Length of the movie in frames.
+    pub frames: u32,
+    /// The machine when the recording started, see [`CPU::save_state`].
+    pub start: Vec<u8>,
+    /// In the order they happened.
+    pub events: Vec<KeyEvent>,
+}
+
+impl Movie {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(
+            MAGIC.len() + 8 + 4 + 4 + self.start.len() + 4 + self.events.len() * EVENT_SIZE,
+        );
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&self.seed.to_be_bytes());
+        out.extend_from_slice(&self.frames.to_be_bytes());
+        out.extend_from_slice(&(self.start.len() as u32).to_be_bytes());
+        out.extend_from_slice(&self.start);
+        out.extend_from_slice(&(self.events.len() as u32).to_be_bytes());
+        for event in &self.events {
+            out.extend_from_slice(&event.frame.to_be_bytes());
+            out.extend_from_slice(&event.tick.to_be_bytes());
+            out.push(event.key);
+            out.push(event.pressed as u8);
+        }
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, MovieError> {
+        let mut data = data;
+        let mut take = |len: usize| {
+            if data.len() < len {
+                return Err(MovieError::Truncated);
+            }
+            let (head, tail) = data.split_at(len);
+            data = tail;
+            Ok(head)
+        };
+        let u32_at = |bytes: &[u8]| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+        if take(MAGIC.len())? != MAGIC {
+            return Err(MovieError::BadMagic);
+        }
+        let mut seed = [0; 8];
+        seed.copy_from_slice(take(8)?);
+        let frames = u32_at(take(4)?);
+        let start_len = u32_at(take(4)?) as usize;
+        let start = take(start_len)?.to_vec();
+        let count = u32_at(take(4)?) as usize;
+        let events = take(count.checked_mul(EVENT_SIZE).ok_or(MovieError::Truncated)?)?
+            .chunks(EVENT_SIZE)
+            .map(|event| {
+                let key = event[6];
+                if key as usize >= NUM_KEYS {
+                    return Err(MovieError::BadKey(key));
+                }
+                Ok(KeyEvent {
+                    frame: u32_at(&event[..4]),
+                    tick: u16::from_be_bytes([event[4], event[5]]),
+                    key,
+                    pressed: event[7] != 0,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            seed: u64::from_be_bytes(seed),
+            frames,
+            start,
+            events,
+        })
+    }
+}
+
+/// Records the keypad of a running CPU.
+///
+/// The frontend reports every key change with [`MovieRecorder::key`] and
+/// the end of every frame with [`MovieRecorder::end_frame`].
+pub struct MovieRecorder {
+    movie: Movie,
+}
+
+impl MovieRecorder {
+    /// Starts recording from the current state of `cpu`, reseeding its random
+    /// source with `seed` so the playback draws the same numbers.
+    pub fn start(cpu: &mut CPU, seed: u64) -> Self {
+        cpu.seed_rng(seed);
+        Self {
+            movie: Movie {
+                seed,
+                frames: 0,
+                start: cpu.save_state(),
+                events: Vec::new(),
+            },
+        }
+    }
+
+    /// Records a key change happening before instruction `tick` of the
+    /// current frame runs.
+    pub fn key(&mut self, tick: u16, key: u8, pressed: bool) {
+        self.movie.events.push(KeyEvent {
+            frame: self.movie.frames,
+            tick,
+            key,
+            pressed,
+        });
+    }
+
+    pub fn end_frame(&mut self) {
+        self.movie.frames += 1;
+    }
+
+    pub fn finish(self) -> Movie {
+        self.movie
+    }
+}
+
+/// Feeds a [`Movie`] back into a CPU.
+///
+/// The frontend calls [`MoviePlayer::apply`] before every instruction and
+/// [`MoviePlayer::end_frame`] at the end of every frame, the same way the
+/// recording was made.
+pub struct MoviePlayer {
+    movie: Movie,
+    frame: u32,
+    next_event: usize,
+}
+
+impl MoviePlayer {
+    /// Puts `cpu` back in the state the movie starts from.
+    pub fn start(movie: Movie, cpu: &mut CPU) -> Result<Self, MovieError> {
+        cpu.load_state(&movie.start)?;
+        cpu.seed_rng(movie.seed);
+        Ok(Self {
+            movie,
+            frame: 0,
+            next_event: 0,
+        })
+    }
+
+    /// Presses the keys recorded before instruction `tick` of the current
+    /// frame.
+    pub fn apply(&mut self, cpu: &mut CPU, tick: u16) {
+        while let Some(event) = self.movie.events.get(self.next_event) {
+            if (event.frame, event.tick) > (self.frame, tick) {
+                break;
+            }
+            cpu.keypress(event.key as usize, event.pressed);
+            self.next_event += 1;
+        }
+    }
+
+    pub fn end_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    /// Whether every frame of the movie was played.
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.movie.frames
+    }
+}
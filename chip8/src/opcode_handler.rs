@@ -0,0 +1,40 @@
+//! Opcodes the interpreter doesn't know, e.g. of an extension, run by the
+//! embedder instead of stopping the program, see [`CPU::set_opcode_handler`].
+
+use crate::{Chip8Error, CPU};
+
+/// Executes the opcodes no instruction decodes to, and the 0NNN machine
+/// code calls, the MegaChip opcodes among them outside its mode. Called with the program counter past the opcode; returning
+/// [`Chip8Error::UnknownOpcode`] for the ones it doesn't know either stops
+/// the program like without a handler. Implemented for closures that are
+/// `Send` and `Sync`, like the machine.
+pub trait OpcodeHandler: Send + Sync {
+    fn execute(&mut self, cpu: &mut CPU, opcode: u16) -> Result<(), Chip8Error>;
+}
+
+impl<F: FnMut(&mut CPU, u16) -> Result<(), Chip8Error> + Send + Sync> OpcodeHandler for F {
+    fn execute(&mut self, cpu: &mut CPU, opcode: u16) -> Result<(), Chip8Error> {
+        self(cpu, opcode)
+    }
+}
+
+impl CPU {
+    /// Runs the opcodes the interpreter doesn't know with `handler`, or
+    /// stops with `None` so they fail with [`Chip8Error::UnknownOpcode`].
+    pub fn set_opcode_handler(&mut self, handler: Option<Box<dyn OpcodeHandler>>) {
+        self.opcode_handler = handler;
+    }
+
+    pub(crate) fn unknown_opcode(&mut self, opcode: u16) -> Result<(), Chip8Error> {
+        let Some(mut handler) = self.opcode_handler.take() else {
+            return Err(Chip8Error::UnknownOpcode {
+                opcode,
+                address: self.program_counter.wrapping_sub(2),
+            });
+        };
+        let result = handler.execute(self, opcode);
+        // unless the handler replaced itself
+        self.opcode_handler.get_or_insert(handler);
+        result
+    }
+}
@@ -0,0 +1,73 @@
+//! Devices mapped into memory, e.g. a serial port homebrew talks to the
+//! host through, see [`CPU::map_peripheral`].
+
+use std::ops::Range;
+
+use crate::CPU;
+
+/// A device reached through memory. Reads and writes of programs in its
+/// range go to it instead of the RAM: FX33, FX55 and FX65, sprites and the
+/// MegaChip palettes and sounds. Instructions are always fetched from the
+/// RAM. Implementations have to be `Send` and `Sync`, like the machine.
+pub trait Peripheral: Send + Sync {
+    /// The byte read `offset` bytes into the device's range.
+    fn read(&mut self, offset: usize) -> u8;
+    /// Writes `value` `offset` bytes into the device's range.
+    fn write(&mut self, offset: usize, value: u8);
+}
+
+pub(crate) struct Mapping {
+    range: Range<usize>,
+    device: Box<dyn Peripheral>,
+}
+
+/// The devices mapped, the latest first where ranges overlap.
+#[derive(Default)]
+pub(crate) struct Peripherals {
+    mappings: Vec<Mapping>,
+}
+
+impl Peripherals {
+    // the device at `address` and the offset into it
+    fn at(&mut self, address: usize) -> Option<(&mut Box<dyn Peripheral>, usize)> {
+        self.mappings
+            .iter_mut()
+            .rev()
+            .find(|mapping| mapping.range.contains(&address))
+            .map(|mapping| (&mut mapping.device, address - mapping.range.start))
+    }
+
+    pub(crate) fn read(&mut self, address: usize) -> Option<u8> {
+        let (device, offset) = self.at(address)?;
+        Some(device.read(offset))
+    }
+
+    /// Whether a device took the write.
+    pub(crate) fn write(&mut self, address: usize, value: u8) -> bool {
+        let Some((device, offset)) = self.at(address) else {
+            return false;
+        };
+        device.write(offset, value);
+        true
+    }
+}
+
+impl CPU {
+    /// Routes the program's reads and writes of `range` to `device`, see
+    /// [`Peripheral`]. The range may lie past the end of the RAM, where the
+    /// [`BoundsPolicy`] doesn't apply to it. A later device takes over the
+    /// addresses it shares with earlier ones.
+    ///
+    /// [`BoundsPolicy`]: crate::BoundsPolicy
+    pub fn map_peripheral(&mut self, range: Range<usize>, device: Box<dyn Peripheral>) {
+        self.ram
+            .peripherals
+            .mappings
+            .push(Mapping { range, device });
+    }
+
+    /// Unmaps every device, reads and writes go to the RAM again.
+    pub fn unmap_peripherals(&mut self) {
+        self.ram.peripherals.mappings.clear();
+    }
+}
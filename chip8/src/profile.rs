@@ -0,0 +1,185 @@
+//! Counts how many times every address and every kind of instruction is
+//! executed, to find the hot loops of a program and the instructions it
+//! uses. See [`CPU::set_profiling`].
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::memory::RAM_SIZE;
+use crate::{Instruction, CPU};
+
+/// An address and how many times it was executed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HotSpot {
+    pub address: u16,
+    pub opcode: u16,
+    pub count: u64,
+    /// Share of all the instructions executed while profiling, from 0 to 1.
+    pub share: f64,
+}
+
+impl fmt::Display for HotSpot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "0x{:03X}: {:<16} {:>10} {:5.1}%",
+            self.address,
+            Instruction::decode(self.opcode).to_string(),
+            self.count,
+            self.share * 100.0
+        )
+    }
+}
+
+/// How many times the instructions of an opcode family were executed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpcodeStats {
+    /// The opcodes, see [`Instruction::pattern`].
+    pub pattern: &'static str,
+    pub count: u64,
+    /// Share of all the instructions executed while profiling, from 0 to 1.
+    pub share: f64,
+}
+
+impl fmt::Display for OpcodeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:>10} {:5.1}%",
+            self.pattern,
+            self.count,
+            self.share * 100.0
+        )
+    }
+}
+
+/// What the program executed since profiling started, see
+/// [`CPU::execution_stats`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionStats {
+    pub instructions: u64,
+    /// 60 Hz frames, timer ticks, run meanwhile.
+    pub frames: u64,
+    /// The opcode families executed, most executed first.
+    pub opcodes: Vec<OpcodeStats>,
+}
+
+impl ExecutionStats {
+    /// Instructions executed per second of emulated time, None before the
+    /// first frame.
+    pub fn instructions_per_second(&self) -> Option<f64> {
+        (self.frames > 0).then(|| self.instructions as f64 * 60.0 / self.frames as f64)
+    }
+}
+
+impl fmt::Display for ExecutionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} instructions in {} frames",
+            self.instructions, self.frames
+        )?;
+        if let Some(ips) = self.instructions_per_second() {
+            write!(f, ", {ips:.0} per second")?;
+        }
+        for opcode in &self.opcodes {
+            write!(f, "\n{opcode}")?;
+        }
+        Ok(())
+    }
+}
+
+// execution count of every address and opcode family
+pub(crate) struct Profile {
+    counts: Box<[u64; RAM_SIZE]>,
+    total: u64,
+    families: HashMap<&'static str, u64>,
+    frames: u64,
+}
+
+impl Profile {
+    fn new() -> Self {
+        Self {
+            counts: Box::new([0; RAM_SIZE]),
+            total: 0,
+            families: HashMap::new(),
+            frames: 0,
+        }
+    }
+
+    pub(crate) fn record(&mut self, address: u16, instruction: Instruction) {
+        self.counts[address as usize % RAM_SIZE] += 1;
+        self.total += 1;
+        *self.families.entry(instruction.pattern()).or_default() += 1;
+    }
+
+    pub(crate) fn record_frame(&mut self) {
+        self.frames += 1;
+    }
+}
+
+impl CPU {
+    /// Starts counting the executions of every address, or stops and
+    /// forgets the counts.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        if enabled != self.profile.is_some() {
+            self.profile = enabled.then(Profile::new);
+        }
+    }
+
+    /// How many times the instruction at `address` was executed since
+    /// profiling started.
+    pub fn execution_count(&self, address: u16) -> u64 {
+        self.profile
+            .as_ref()
+            .map_or(0, |profile| profile.counts[address as usize % RAM_SIZE])
+    }
+
+    /// The instructions executed by opcode family and how fast, since
+    /// profiling started. None when profiling is off.
+    pub fn execution_stats(&self) -> Option<ExecutionStats> {
+        let profile = self.profile.as_ref()?;
+        let mut opcodes: Vec<_> = profile
+            .families
+            .iter()
+            .map(|(&pattern, &count)| OpcodeStats {
+                pattern,
+                count,
+                share: count as f64 / profile.total as f64,
+            })
+            .collect();
+        // most executed first, ties in opcode order
+        opcodes.sort_by(|a, b| b.count.cmp(&a.count).then(a.pattern.cmp(b.pattern)));
+        Some(ExecutionStats {
+            instructions: profile.total,
+            frames: profile.frames,
+            opcodes,
+        })
+    }
+
+    /// The `limit` most executed addresses, most executed first. Empty when
+    /// profiling is off.
+    pub fn hot_spots(&self, limit: usize) -> Vec<HotSpot> {
+        let Some(profile) = &self.profile else {
+            return Vec::new();
+        };
+        let mut spots: Vec<_> = (0..RAM_SIZE)
+            .filter(|&addr| profile.counts[addr] > 0)
+            .collect();
+        // stable, so ties stay in address order
+        spots.sort_by_key(|&addr| std::cmp::Reverse(profile.counts[addr]));
+        spots
+            .into_iter()
+            .take(limit)
+            .map(|addr| HotSpot {
+                address: addr as u16,
+                opcode: u16::from_be_bytes([
+                    self.ram.data[addr],
+                    self.ram.data[(addr + 1) % RAM_SIZE],
+                ]),
+                count: profile.counts[addr],
+                share: profile.counts[addr] as f64 / profile.total as f64,
+            })
+            .collect()
+    }
+}
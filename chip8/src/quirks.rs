@@ -0,0 +1,69 @@
+//! Behaviours that differ between the CHIP-8 interpreters ROMs were written
+//! for, see [`Quirks`].
+
+/// Which interpreter's behaviour the ambiguous instructions follow.
+///
+/// The default matches what the CPU always did, so existing programs keep
+/// running the same.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Quirks {
+    /// DXYN clips sprites at the edges of the display instead of wrapping
+    /// them around to the other side. The position the sprite starts at
+    /// still wraps.
+    pub clip_sprites: bool,
+    /// FX55 and FX65 leave I pointing past the last register stored or
+    /// loaded, I + X + 1, instead of untouched.
+    pub increment_i: bool,
+    /// 8XY6 and 8XYE shift Vy and store the result in Vx, instead of
+    /// shifting Vx in place like CHIP-48 and SUPER-CHIP.
+    pub shift_vy: bool,
+    /// BNNN jumps to XNN + VX, X being the high nibble of the address, like
+    /// CHIP-48 and SUPER-CHIP, instead of NNN + V0.
+    pub jump_vx: bool,
+    /// DXYN waits for the next display refresh: no more instructions run
+    /// until [`CPU::tick_timers`] is called, so a program draws at most one
+    /// sprite per frame.
+    ///
+    /// [`CPU::tick_timers`]: crate::CPU::tick_timers
+    pub display_wait: bool,
+}
+
+impl Quirks {
+    /// The original COSMAC VIP interpreter.
+    pub const VIP: Quirks = Quirks {
+        clip_sprites: true,
+        increment_i: true,
+        shift_vy: true,
+        jump_vx: false,
+        display_wait: true,
+    };
+    /// SUPER-CHIP 1.1 on the HP 48 calculators.
+    pub const SUPER_CHIP: Quirks = Quirks {
+        clip_sprites: true,
+        increment_i: false,
+        shift_vy: false,
+        jump_vx: true,
+        display_wait: false,
+    };
+    /// Octo's XO-CHIP, which wraps sprites around the edges and otherwise
+    /// follows the VIP but for the display wait.
+    pub const XO_CHIP: Quirks = Quirks {
+        clip_sprites: false,
+        increment_i: true,
+        shift_vy: true,
+        jump_vx: false,
+        display_wait: false,
+    };
+}
+
+impl crate::CPU {
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+}
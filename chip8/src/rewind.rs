@@ -0,0 +1,201 @@
+use std::collections::VecDeque;
+
+use crate::megachip::MegaChip;
+use crate::memory::STACK_SIZE;
+use crate::screen::{ColorDisplay, ColorZones, NUM_PLANES};
+use crate::{CPU, NUM_REGS};
+
+/// Rewind depth for 10 seconds at 60 frames per second.
+pub const DEFAULT_REWIND_FRAMES: usize = 600;
+
+/// Memory the rewind history takes at most, whatever its depth. A frame
+/// holds a copy of the RAM, so large MegaChip ROMs keep fewer frames.
+pub const REWIND_MEMORY_LIMIT: usize = 64 << 20;
+
+// Machine state at the end of a frame. Every plane of the display is
+// packed 8 pixels per byte to keep a few seconds of history small. The
+// keypad isn't captured, keys follow the player's hands, not the rewound
+// game.
+struct Frame {
+    program_counter: u16,
+    v_registers: [u8; NUM_REGS],
+    i_register: u16,
+    stack_point: u16,
+    stack: [u16; STACK_SIZE],
+    ram: Box<[u8]>,
+    // the pixels of the current resolution
+    display: [Box<[u8]>; NUM_PLANES],
+    selected: u8,
+    hires: bool,
+    two_page: bool,
+    megachip: Box<MegaChip>,
+    color_display: Option<Box<ColorDisplay>>,
+    color_zones: Option<Box<ColorZones>>,
+    key_wait: Option<u8>,
+    delay_timer: u8,
+    sound_timer: u8,
+    halted: bool,
+}
+
+impl Frame {
+    fn capture(cpu: &CPU) -> Self {
+        let len = cpu.screen.width() * cpu.screen.height();
+        let display = std::array::from_fn(|plane| {
+            cpu.screen.planes[plane][..len]
+                .chunks(8)
+                .map(|pixels| pixels.iter().fold(0, |acc, &px| (acc << 1) | px as u8))
+                .collect()
+        });
+        Self {
+            program_counter: cpu.program_counter,
+            v_registers: cpu.v_registers,
+            i_register: cpu.i_register,
+            stack_point: cpu.stack.stack_point,
+            stack: cpu.stack.stack,
+            ram: cpu.ram.data.clone().into_boxed_slice(),
+            display,
+            selected: cpu.screen.selected,
+            hires: cpu.screen.hires,
+            two_page: cpu.screen.two_page,
+            megachip: Box::new(cpu.megachip.clone()),
+            color_display: cpu.screen.megachip.clone(),
+            color_zones: cpu.screen.chip8x.clone(),
+            key_wait: cpu.key_wait,
+            delay_timer: cpu.delay_timer,
+            sound_timer: cpu.sound_timer,
+            halted: cpu.halted,
+        }
+    }
+
+    // bytes held, roughly
+    fn size(&self) -> usize {
+        let color_display = self.color_display.as_ref().map_or(0, |display| {
+            display.indices.len() + 3 * (display.pixels.len() + display.front.len())
+        });
+        size_of::<Self>()
+            + size_of::<MegaChip>()
+            + self.ram.len()
+            + self.display.iter().map(|plane| plane.len()).sum::<usize>()
+            + color_display
+            + self
+                .color_zones
+                .as_ref()
+                .map_or(0, |_| size_of::<ColorZones>())
+    }
+
+    fn apply(&self, cpu: &mut CPU) {
+        cpu.program_counter = self.program_counter;
+        cpu.v_registers = self.v_registers;
+        cpu.i_register = self.i_register;
+        cpu.stack.stack_point = self.stack_point;
+        cpu.stack.stack = self.stack;
+        cpu.ram.data = self.ram.to_vec();
+        cpu.screen.megachip.clone_from(&self.color_display);
+        cpu.screen.fit_planes();
+        for (plane, packed) in cpu.screen.planes.iter_mut().zip(&self.display) {
+            for (pixels, byte) in plane.chunks_mut(8).zip(packed.iter()) {
+                for (bit, px) in pixels.iter_mut().enumerate() {
+                    *px = byte & (0x80 >> bit) != 0;
+                }
+            }
+        }
+        cpu.screen.select(self.selected);
+        cpu.screen.hires = self.hires;
+        cpu.screen.two_page = self.two_page;
+        cpu.screen.chip8x.clone_from(&self.color_zones);
+        cpu.megachip.clone_from(&self.megachip);
+        cpu.screen.mark_all_dirty();
+        cpu.key_wait = self.key_wait;
+        cpu.waiting_key = false;
+        cpu.delay_timer = self.delay_timer;
+        cpu.sound_timer = self.sound_timer;
+        cpu.halted = self.halted;
+    }
+}
+
+/// Ring buffer of the most recent frames.
+pub(crate) struct RewindBuffer {
+    frames: VecDeque<Frame>,
+    depth: usize,
+    // bytes held by the frames, kept under REWIND_MEMORY_LIMIT
+    size: usize,
+}
+
+impl RewindBuffer {
+    pub(crate) fn new(depth: usize) -> Self {
+        Self {
+            frames: VecDeque::new(),
+            depth,
+            size: 0,
+        }
+    }
+
+    pub(crate) fn record(&mut self, cpu: &CPU) {
+        let frame = Frame::capture(cpu);
+        self.size += frame.size();
+        self.frames.push_back(frame);
+        // forget the oldest frames once the buffer is full, keeping the
+        // newest one
+        while self.frames.len() > 1
+            && (self.frames.len() > self.depth || self.size > REWIND_MEMORY_LIMIT)
+        {
+            if let Some(oldest) = self.frames.pop_front() {
+                self.size -= oldest.size();
+            }
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Drops the `frames` most recent frames and restores the one before
+    /// them, never going past the oldest frame. Returns how many frames were
+    /// dropped.
+    pub(crate) fn rewind(&mut self, frames: usize, cpu: &mut CPU) -> usize {
+        let dropped = frames.min(self.frames.len().saturating_sub(1));
+        for frame in self.frames.drain(self.frames.len() - dropped..) {
+            self.size -= frame.size();
+        }
+        if let Some(frame) = self.frames.back() {
+            frame.apply(cpu);
+        }
+        dropped
+    }
+}
+
+impl CPU {
+    /// Keeps the state of the last `frames` frames, captured on every call to
+    /// [`CPU::tick_timers`], so they can be restored with [`CPU::rewind`].
+    /// Fewer are kept when they would take more than
+    /// [`REWIND_MEMORY_LIMIT`]. A depth of 0 disables rewinding and frees
+    /// the history.
+    pub fn set_rewind_depth(&mut self, frames: usize) {
+        self.rewind = (frames > 0).then(|| RewindBuffer::new(frames));
+    }
+
+    /// Number of frames that can currently be rewound.
+    pub fn rewind_frames_available(&self) -> usize {
+        self.rewind
+            .as_ref()
+            .map_or(0, |buffer| buffer.len().saturating_sub(1))
+    }
+
+    /// Goes back `frames` frames in the rewind history, or as far as the
+    /// history goes. Returns how many frames were actually rewound.
+    pub fn rewind(&mut self, frames: usize) -> usize {
+        let Some(mut buffer) = self.rewind.take() else {
+            return 0;
+        };
+        let rewound = buffer.rewind(frames, self);
+        self.rewind = Some(buffer);
+        rewound
+    }
+
+    pub(crate) fn record_rewind_frame(&mut self) {
+        if let Some(mut buffer) = self.rewind.take() {
+            buffer.record(self);
+            self.rewind = Some(buffer);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! The random bytes of CXNN. With the `rand` feature, on by default, the
+//! machine uses `rand`'s generators seeded from the OS, in browsers too.
+//! Without it a built-in [`XorShiftRng`] is used, so the same seed gives
+//! different bytes in the two builds.
+
+#[cfg(feature = "rand")]
+use rand::rngs::StdRng;
+#[cfg(feature = "rand")]
+use rand::{Rng, RngCore, SeedableRng};
+
+use crate::CPU;
+
+/// Source of the random bytes used by the CXNN instruction.
+///
+/// Implemented for every `rand` RNG that can be cloned, so any of them can
+/// be plugged in with [`CPU::set_rng`]. Like the rest of the machine it has
+/// to be `Send` and `Sync`, which rules out `rand::thread_rng`.
+pub trait RandomSource: Send + Sync {
+    fn next_byte(&mut self) -> u8;
+
+    /// Identifies the state of the generator without advancing it, for
+    /// [`CPU::state_hash`]. Sources that can't tell return 0, the default.
+    fn fingerprint(&self) -> u64 {
+        0
+    }
+}
+
+#[cfg(feature = "rand")]
+impl<R: RngCore + Clone + Send + Sync> RandomSource for R {
+    fn next_byte(&mut self) -> u8 {
+        self.gen()
+    }
+
+    // the next bytes it gives, from a copy
+    fn fingerprint(&self) -> u64 {
+        self.clone().next_u64()
+    }
+}
+
+/// A small xorshift64* generator, the random source without the `rand`
+/// feature. Not fit for anything but games.
+#[derive(Debug, Clone)]
+pub struct XorShiftRng {
+    state: u64,
+}
+
+impl XorShiftRng {
+    pub fn new(seed: u64) -> Self {
+        // the state must not be 0, and close seeds should not give close
+        // sequences, so the seed goes through splitmix64 first
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        Self { state: z.max(1) }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
+
+impl RandomSource for XorShiftRng {
+    fn next_byte(&mut self) -> u8 {
+        // the high bits are the best ones
+        (self.next_u64() >> 56) as u8
+    }
+
+    fn fingerprint(&self) -> u64 {
+        self.state
+    }
+}
+
+// seeded from the OS, different on every run
+#[cfg(feature = "rand")]
+pub(crate) fn default_source() -> Box<dyn RandomSource> {
+    Box::new(StdRng::from_entropy())
+}
+
+// seeded from the keys std seeds its hash maps with, which come from the OS
+// where there is one. On wasm32-unknown-unknown those are fixed, seed the
+// machine there, e.g. with the time.
+#[cfg(not(feature = "rand"))]
+pub(crate) fn default_source() -> Box<dyn RandomSource> {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_usize(&hasher as *const _ as usize);
+    Box::new(XorShiftRng::new(hasher.finish()))
+}
+
+impl CPU {
+    /// Replaces the random source of the CXNN instruction.
+    pub fn set_rng(&mut self, rng: Box<dyn RandomSource>) {
+        self.rng = rng;
+    }
+
+    /// Makes CXNN produce the same sequence of bytes on every run with the
+    /// same `seed`, for reproducible tests and replays.
+    pub fn seed_rng(&mut self, seed: u64) {
+        #[cfg(feature = "rand")]
+        {
+            self.rng = Box::new(StdRng::seed_from_u64(seed));
+        }
+        #[cfg(not(feature = "rand"))]
+        {
+            self.rng = Box::new(XorShiftRng::new(seed));
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! A small database of known ROMs and the settings they play best with,
+//! see [`CPU::configure_for_rom`].
+
+use crate::frontend::DEFAULT_TICKS_PER_FRAME;
+use crate::{Quirks, CPU};
+
+/// The machine a ROM was written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    /// The original interpreter of the COSMAC VIP.
+    Chip8,
+    /// SUPER-CHIP 1.1 on the HP 48 calculators.
+    SuperChip,
+    /// Octo's XO-CHIP extensions.
+    XoChip,
+}
+
+impl Platform {
+    /// The quirks of the interpreter the platform ran on.
+    pub fn quirks(&self) -> Quirks {
+        match self {
+            Platform::Chip8 => Quirks::VIP,
+            Platform::SuperChip => Quirks::SUPER_CHIP,
+            Platform::XoChip => Quirks::XO_CHIP,
+        }
+    }
+}
+
+/// What is known about a ROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RomInfo {
+    pub title: &'static str,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    /// Instructions per 60 Hz frame, see [`CPU::ticks_per_frame`].
+    pub ticks_per_frame: usize,
+}
+
+// the VIP's quirks for CHIP-8 games that draw several sprites a frame and
+// crawl when every one waits for the display
+const VIP_WITHOUT_DISPLAY_WAIT: Quirks = Quirks {
+    display_wait: false,
+    ..Quirks::VIP
+};
+
+// keyed by `rom_hash`
+const KNOWN_ROMS: &[(u64, RomInfo)] = &[
+    (
+        0x8e54_7ebb_12c0_26b4,
+        RomInfo {
+            title: "Space Invaders (David Winter)",
+            platform: Platform::Chip8,
+            quirks: VIP_WITHOUT_DISPLAY_WAIT,
+            ticks_per_frame: DEFAULT_TICKS_PER_FRAME,
+        },
+    ),
+    (
+        0x04eb_2109_dc29_b1ab,
+        RomInfo {
+            title: "Tetris (Fran Dachille)",
+            platform: Platform::Chip8,
+            quirks: VIP_WITHOUT_DISPLAY_WAIT,
+            ticks_per_frame: DEFAULT_TICKS_PER_FRAME,
+        },
+    ),
+    (
+        0x5604_9e83_866b_207d,
+        RomInfo {
+            title: "Tic-Tac-Toe (David Winter)",
+            platform: Platform::Chip8,
+            quirks: VIP_WITHOUT_DISPLAY_WAIT,
+            ticks_per_frame: DEFAULT_TICKS_PER_FRAME,
+        },
+    ),
+];
+
+/// 64-bit FNV-1a of the ROM, the key of the database.
+pub fn rom_hash(rom: &[u8]) -> u64 {
+    rom.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Looks the ROM up in the database.
+pub fn lookup(rom: &[u8]) -> Option<&'static RomInfo> {
+    let hash = rom_hash(rom);
+    KNOWN_ROMS
+        .iter()
+        .find(|(known, _)| *known == hash)
+        .map(|(_, info)| info)
+}
+
+impl CPU {
+    /// Applies the recommended quirks and speed of `rom` when it's in the
+    /// database, and returns what is known about it. Doesn't load it.
+    pub fn configure_for_rom(&mut self, rom: &[u8]) -> Option<&'static RomInfo> {
+        let info = lookup(rom)?;
+        self.quirks = info.quirks;
+        self.ticks_per_frame = info.ticks_per_frame;
+        Some(info)
+    }
+}
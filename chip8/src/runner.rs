@@ -0,0 +1,113 @@
+//! Running a CPU against the wall clock at a set number of instructions
+//! per second, see [`Runner`].
+
+use std::time::{Duration, Instant};
+
+use crate::{Chip8Error, RunSummary, CPU, TIMER_PERIOD};
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+// most time `Runner::advance` catches up on after a stall, e.g. the window
+// being dragged, instead of running every missed instruction at once
+const MAX_CATCH_UP: Duration = Duration::from_nanos(4 * 1_000_000_000 / 60);
+
+/// Owns a CPU and runs it at a steady number of instructions per second,
+/// ticking the timers at 60 Hz in between, however often it is advanced.
+///
+/// ```ignore
+/// let mut runner = Runner::new(cpu).with_ips(700);
+/// loop {
+///     runner.advance(Instant::now())?;
+///     draw(runner.cpu().get_display());
+/// }
+/// ```
+pub struct Runner {
+    cpu: CPU,
+    ips: u32,
+    // when `advance` was last called, none before the first call
+    last: Option<Instant>,
+    // time run since the timers last ticked
+    since_tick: Duration,
+    // instructions times nanoseconds owed but not run yet
+    owed: u128,
+}
+
+impl Runner {
+    /// Runs `cpu` at its own speed, see [`CPU::clock_hz`].
+    pub fn new(cpu: CPU) -> Self {
+        Self {
+            ips: cpu.clock_hz(),
+            cpu,
+            last: None,
+            since_tick: Duration::ZERO,
+            owed: 0,
+        }
+    }
+
+    /// Sets how many instructions run per second.
+    pub fn with_ips(mut self, ips: u32) -> Self {
+        self.ips = ips;
+        self
+    }
+
+    pub fn ips(&self) -> u32 {
+        self.ips
+    }
+
+    /// Changes the speed, from the next call to [`Runner::advance`] on.
+    pub fn set_ips(&mut self, ips: u32) {
+        self.ips = ips;
+    }
+
+    pub fn cpu(&self) -> &CPU {
+        &self.cpu
+    }
+
+    pub fn cpu_mut(&mut self) -> &mut CPU {
+        &mut self.cpu
+    }
+
+    pub fn into_cpu(self) -> CPU {
+        self.cpu
+    }
+
+    /// Forgets when the runner was last advanced, so the time until the
+    /// next [`Runner::advance`] isn't run, e.g. while the game is paused.
+    pub fn pause(&mut self) {
+        self.last = None;
+    }
+
+    /// Runs the instructions and timer ticks due between the last call and
+    /// `now`, in the order they fall in. The first call only starts the
+    /// clock. After a stall at most a few frames are caught up on.
+    ///
+    /// Instructions run at the set speed whatever the CPU's [`Timing`], and
+    /// none run while the program halts or waits for the display.
+    ///
+    /// [`Timing`]: crate::Timing
+    pub fn advance(&mut self, now: Instant) -> Result<RunSummary, Chip8Error> {
+        let Some(last) = self.last.replace(now) else {
+            return Ok(RunSummary::default());
+        };
+        let mut elapsed = now.saturating_duration_since(last).min(MAX_CATCH_UP);
+        let ips = u128::from(self.ips);
+        let (since_tick, owed) = (&mut self.since_tick, &mut self.owed);
+        self.cpu.summarize(|cpu| {
+            while !elapsed.is_zero() {
+                // run up to the next timer tick at most
+                let step = elapsed.min(TIMER_PERIOD - *since_tick);
+                elapsed -= step;
+                *since_tick += step;
+                *owed += step.as_nanos() * ips;
+                let due = *owed / NANOS_PER_SEC;
+                *owed %= NANOS_PER_SEC;
+                cpu.run(due as usize)?;
+                if *since_tick == TIMER_PERIOD {
+                    *since_tick = Duration::ZERO;
+                    cpu.tick_timers();
+                }
+            }
+            Ok(())
+        })
+    }
+}
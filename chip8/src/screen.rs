@@ -1,20 +1,575 @@
+use std::fmt;
+
 pub const SCREEN_WIDTH: usize = 64;
 pub const SCREEN_HEIGHT: usize = 32;
 
+// SUPER-CHIP high resolution mode
+pub const HIRES_SCREEN_WIDTH: usize = 128;
+pub const HIRES_SCREEN_HEIGHT: usize = 64;
+
+// the two-page mode of the hi-res CHIP-8 variant, as wide as the low
+// resolution
+pub const TWO_PAGE_SCREEN_HEIGHT: usize = 64;
+
+// MegaChip mode
+pub const MEGACHIP_SCREEN_WIDTH: usize = 256;
+pub const MEGACHIP_SCREEN_HEIGHT: usize = 192;
+/// Number of colors of the palette MegaChip programs load.
+pub const MEGACHIP_COLORS: usize = 256;
+
+/// Number of bit-planes, XO-CHIP programs draw in two for four colors.
+pub const NUM_PLANES: usize = 2;
+/// Number of colors a pixel can take, see [`FrameBuffer::color`].
+pub const NUM_COLORS: usize = 1 << NUM_PLANES;
+
+/// The foreground colors of CHIP-8X programs: black, red, blue, violet,
+/// green, yellow, aqua and white.
+pub const CHIP8X_COLORS: [Rgb; 8] = [
+    [0x00, 0x00, 0x00],
+    [0xFF, 0x00, 0x00],
+    [0x00, 0x00, 0xFF],
+    [0xFF, 0x00, 0xFF],
+    [0x00, 0xFF, 0x00],
+    [0xFF, 0xFF, 0x00],
+    [0x00, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0xFF],
+];
+/// The background colors 02A0 cycles CHIP-8X programs through: blue,
+/// black, green and red, dimmer than the foreground.
+pub const CHIP8X_BACKGROUNDS: [Rgb; 4] = [
+    [0x00, 0x00, 0x80],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x80, 0x00],
+    [0x80, 0x00, 0x00],
+];
+// the color map of CHIP-8X is 8 pixels wide and a row high
+pub(crate) const CHIP8X_ZONE_COLUMNS: usize = SCREEN_WIDTH / 8;
+const CHIP8X_ZONES: usize = CHIP8X_ZONE_COLUMNS * SCREEN_HEIGHT;
+// red, until the program colors the display
+const CHIP8X_DEFAULT_COLOR: u8 = 1;
+
+// the largest resolution
+pub(crate) const DISPLAY_SIZE: usize = MEGACHIP_SCREEN_WIDTH * MEGACHIP_SCREEN_HEIGHT;
+// the largest resolution without MegaChip
+const HIRES_DISPLAY_SIZE: usize = HIRES_SCREEN_WIDTH * HIRES_SCREEN_HEIGHT;
+
+/// A view of the display at its current resolution, see
+/// [`CPU::frame_buffer`].
+///
+/// [`CPU::frame_buffer`]: crate::CPU::frame_buffer
+#[derive(Debug, Clone, Copy)]
+pub struct FrameBuffer<'a> {
+    planes: [&'a [bool]; NUM_PLANES],
+    width: usize,
+    height: usize,
+    // the colors shown in MegaChip mode
+    megachip: Option<&'a [Rgb]>,
+    zones: Option<&'a ColorZones>,
+}
+
+impl<'a> FrameBuffer<'a> {
+    /// Size of the low resolution display.
+    pub const WIDTH: usize = SCREEN_WIDTH;
+    pub const HEIGHT: usize = SCREEN_HEIGHT;
+    /// Size of the SUPER-CHIP high resolution display.
+    pub const HIRES_WIDTH: usize = HIRES_SCREEN_WIDTH;
+    pub const HIRES_HEIGHT: usize = HIRES_SCREEN_HEIGHT;
+    /// Size of the MegaChip display.
+    pub const MEGACHIP_WIDTH: usize = MEGACHIP_SCREEN_WIDTH;
+    pub const MEGACHIP_HEIGHT: usize = MEGACHIP_SCREEN_HEIGHT;
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Whether the pixel at column `x` of row `y` is lit in the first plane,
+    /// the only one monochrome programs draw in.
+    ///
+    /// # Panics
+    ///
+    /// When `x` or `y` is outside of the display.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        assert!(x < self.width, "column {x} is outside of the display");
+        self.row(y)[x]
+    }
+
+    /// The pixels of row `y`, left to right.
+    ///
+    /// # Panics
+    ///
+    /// When `y` is outside of the display.
+    pub fn row(&self, y: usize) -> &'a [bool] {
+        assert!(y < self.height, "row {y} is outside of the display");
+        &self.planes[0][y * self.width..(y + 1) * self.width]
+    }
+
+    /// The rows of the first plane, top to bottom.
+    pub fn rows(&self) -> impl Iterator<Item = &'a [bool]> + 'a {
+        self.planes[0].chunks_exact(self.width)
+    }
+
+    /// All the pixels of the first plane row by row, like
+    /// [`CPU::get_display`].
+    ///
+    /// [`CPU::get_display`]: crate::CPU::get_display
+    pub fn as_slice(&self) -> &'a [bool] {
+        self.planes[0]
+    }
+
+    /// The color index of the pixel at column `x` of row `y`, bit n set
+    /// when the pixel is lit in plane n. Look it up with [`Palette::color`],
+    /// or use [`FrameBuffer::rgb`] to get the colors of MegaChip programs
+    /// too, their pixels being only lit or not here.
+    ///
+    /// # Panics
+    ///
+    /// When `x` or `y` is outside of the display.
+    pub fn color(&self, x: usize, y: usize) -> u8 {
+        assert!(x < self.width, "column {x} is outside of the display");
+        assert!(y < self.height, "row {y} is outside of the display");
+        self.color_at(y * self.width + x)
+    }
+
+    /// The color indices of all the pixels, row by row.
+    pub fn colors(&self) -> impl Iterator<Item = u8> + 'a {
+        let frame = *self;
+        (0..self.width * self.height).map(move |idx| frame.color_at(idx))
+    }
+
+    /// Whether the program draws in its own colors, see
+    /// [`FrameBuffer::rgb`].
+    pub fn is_megachip(&self) -> bool {
+        self.megachip.is_some()
+    }
+
+    /// The RGB color of the pixel at column `x` of row `y`: its color index
+    /// looked up in `palette`, the color a MegaChip program drew, or for
+    /// CHIP-8X programs the color of its zone when lit and the background
+    /// otherwise.
+    ///
+    /// # Panics
+    ///
+    /// When `x` or `y` is outside of the display.
+    pub fn rgb(&self, x: usize, y: usize, palette: &Palette) -> Rgb {
+        match self.megachip {
+            Some(pixels) => {
+                assert!(x < self.width, "column {x} is outside of the display");
+                assert!(y < self.height, "row {y} is outside of the display");
+                pixels[y * self.width + x]
+            }
+            None => match self.zones {
+                Some(zones) if self.color(x, y) != 0 => {
+                    let zone = zones.zone(
+                        x * SCREEN_WIDTH / self.width,
+                        y * SCREEN_HEIGHT / self.height,
+                    );
+                    CHIP8X_COLORS[zone as usize]
+                }
+                Some(zones) => CHIP8X_BACKGROUNDS[zones.background as usize],
+                None => palette.color(self.color(x, y)),
+            },
+        }
+    }
+
+    fn color_at(&self, idx: usize) -> u8 {
+        self.planes
+            .iter()
+            .enumerate()
+            .fold(0, |color, (plane, pixels)| {
+                color | (pixels[idx] as u8) << plane
+            })
+    }
+}
+
+/// Draws the display in ASCII, a line per row: `.` for unlit pixels, `#`
+/// for the first plane, `+` for the second and `@` for both.
+impl fmt::Display for FrameBuffer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let c = match self.color(x, y) {
+                    0 => '.',
+                    1 => '#',
+                    2 => '+',
+                    _ => '@',
+                };
+                write!(f, "{c}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// An RGB color.
+pub type Rgb = [u8; 3];
+
+/// Colors the display is drawn with, indexed by the color of a pixel, see
+/// [`FrameBuffer::color`]. Monochrome programs only use the first two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [Rgb; NUM_COLORS],
+}
+
+impl Default for Palette {
+    /// White on black, with greys for the second plane.
+    fn default() -> Self {
+        Self {
+            colors: [
+                [0x00, 0x00, 0x00],
+                [0xFF, 0xFF, 0xFF],
+                [0xAA, 0xAA, 0xAA],
+                [0x55, 0x55, 0x55],
+            ],
+        }
+    }
+}
+
+impl Palette {
+    /// A palette with the colors of unlit and lit pixels of monochrome
+    /// programs, keeping the default colors of the second plane.
+    pub fn monochrome(off: Rgb, on: Rgb) -> Self {
+        let mut palette = Self::default();
+        palette.colors[0] = off;
+        palette.colors[1] = on;
+        palette
+    }
+
+    pub fn color(&self, index: u8) -> Rgb {
+        self.colors[index as usize % NUM_COLORS]
+    }
+}
+
+/// Set of display rows changed since the last [`CPU::take_dirty`], for
+/// frontends redrawing only what changed.
+///
+/// [`CPU::take_dirty`]: crate::CPU::take_dirty
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirtyRows([u64; DIRTY_WORDS]);
+
+// one bit per row of the tallest display
+const DIRTY_WORDS: usize = MEGACHIP_SCREEN_HEIGHT.div_ceil(u64::BITS as usize);
+
+impl DirtyRows {
+    const ALL: DirtyRows = DirtyRows([u64::MAX; DIRTY_WORDS]);
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|&word| word == 0)
+    }
+
+    pub fn contains(&self, row: usize) -> bool {
+        row < MEGACHIP_SCREEN_HEIGHT && self.0[row / 64] & (1 << (row % 64)) != 0
+    }
+
+    /// The changed rows, top to bottom. After a resolution switch every row
+    /// of the new resolution is included.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..MEGACHIP_SCREEN_HEIGHT).filter(|&row| self.contains(row))
+    }
+}
+
+/// An ARGB color of the MegaChip palette.
+pub type Argb = [u8; 4];
+
+/// The MegaChip display, in full colors. Programs draw on a back buffer
+/// shown by the next 00E0, the frame sync of MegaChip.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ColorDisplay {
+    // palette indices of the back buffer, 0 being transparent
+    pub indices: Vec<u8>,
+    // colors of the back buffer, sprites blended into them
+    pub pixels: Vec<Rgb>,
+    // colors on display
+    pub front: Vec<Rgb>,
+}
+
+impl Default for ColorDisplay {
+    fn default() -> Self {
+        Self {
+            indices: vec![0; DISPLAY_SIZE],
+            pixels: vec![[0; 3]; DISPLAY_SIZE],
+            front: vec![[0; 3]; DISPLAY_SIZE],
+        }
+    }
+}
+
+// a scroll of the display
+#[derive(Clone, Copy)]
+enum Shift {
+    Up(usize),
+    Down(usize),
+    Left(usize),
+    Right(usize),
+}
+
+// scrolls rows of `width` pixels, filling the gap with the default value
+fn shift<T: Copy + Default>(pixels: &mut [T], width: usize, shift: Shift) {
+    let len = pixels.len();
+    match shift {
+        Shift::Up(lines) => {
+            let shift = (lines * width).min(len);
+            pixels.copy_within(shift.., 0);
+            pixels[len - shift..].fill(T::default());
+        }
+        Shift::Down(lines) => {
+            let shift = (lines * width).min(len);
+            pixels.copy_within(..len - shift, shift);
+            pixels[..shift].fill(T::default());
+        }
+        Shift::Left(cols) => {
+            let cols = cols.min(width);
+            for row in pixels.chunks_mut(width) {
+                row.copy_within(cols.., 0);
+                row[width - cols..].fill(T::default());
+            }
+        }
+        Shift::Right(cols) => {
+            let cols = cols.min(width);
+            for row in pixels.chunks_mut(width) {
+                row.copy_within(..width - cols, cols);
+                row[..cols].fill(T::default());
+            }
+        }
+    }
+}
+
+/// The colors of a CHIP-8X display, set apart from its pixels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ColorZones {
+    // index in `CHIP8X_BACKGROUNDS`
+    pub background: u8,
+    // indices in `CHIP8X_COLORS`, 8 pixels wide and a row high
+    pub zones: [u8; CHIP8X_ZONES],
+}
+
+impl Default for ColorZones {
+    fn default() -> Self {
+        Self {
+            background: 0,
+            zones: [CHIP8X_DEFAULT_COLOR; CHIP8X_ZONES],
+        }
+    }
+}
+
+impl ColorZones {
+    // the color of the low resolution pixel at `x`, `y`
+    fn zone(&self, x: usize, y: usize) -> u8 {
+        self.zones[y * CHIP8X_ZONE_COLUMNS + x / 8]
+    }
+}
+
 pub(crate) struct Screen {
-    pub display: [bool; SCREEN_WIDTH * SCREEN_HEIGHT],
+    // sized for the hi-res mode, or the MegaChip one while in it, only the
+    // first width * height pixels are in use. In MegaChip mode the first
+    // plane is lit where the colors on display aren't black
+    pub planes: [Vec<bool>; NUM_PLANES],
+    pub hires: bool,
+    // 64x64 for the hi-res CHIP-8 variant, when not in hi-res
+    pub two_page: bool,
+    // set in MegaChip mode, which takes precedence over the resolution
+    pub megachip: Option<Box<ColorDisplay>>,
+    // the colors of CHIP-8X programs
+    pub chip8x: Option<Box<ColorZones>>,
+    // bit n set when plane n is drawn, cleared and scrolled
+    pub selected: u8,
+    dirty: DirtyRows,
+    // bumped on every change, unlike the dirty rows never taken
+    changes: u64,
 }
 
 impl Screen {
+    /// Clears the selected planes.
     pub(crate) fn clear(&mut self) {
-        self.display = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
+        self.for_each_selected(|pixels, _| pixels.fill(false));
+    }
+
+    /// Shows the MegaChip back buffer at `alpha` brightness and clears it.
+    pub(crate) fn flip(&mut self, alpha: u8) {
+        let Some(display) = &mut self.megachip else {
+            return;
+        };
+        for (shown, drawn) in display.front.iter_mut().zip(&display.pixels) {
+            *shown = drawn.map(|c| (c as u16 * alpha as u16 / 255) as u8);
+        }
+        display.pixels.fill([0; 3]);
+        display.indices.fill(0);
+        self.light_megachip();
+    }
+
+    /// Lights the pixels of the first plane where the MegaChip colors on
+    /// display aren't black.
+    pub(crate) fn light_megachip(&mut self) {
+        self.fit_planes();
+        if let Some(display) = &self.megachip {
+            for (lit, shown) in self.planes[0].iter_mut().zip(&display.front) {
+                *lit = *shown != [0; 3];
+            }
+        }
+        self.mark_all_dirty();
+    }
+
+    /// Selects the planes the next instructions draw on, a bit per plane.
+    pub(crate) fn select(&mut self, planes: u8) {
+        self.selected = planes & (NUM_COLORS as u8 - 1);
+    }
+
+    // calls `f` with the pixels at the current resolution and the width of
+    // every selected plane
+    fn for_each_selected(&mut self, mut f: impl FnMut(&mut [bool], usize)) {
+        self.mark_all_dirty();
+        let width = self.width();
+        let len = width * self.height();
+        for (plane, pixels) in self.planes.iter_mut().enumerate() {
+            if self.selected & (1 << plane) != 0 {
+                f(&mut pixels[..len], width);
+            }
+        }
+    }
+
+    pub(crate) fn mark_dirty(&mut self, row: usize) {
+        self.dirty.0[row / 64] |= 1 << (row % 64);
+        self.changes = self.changes.wrapping_add(1);
+    }
+
+    /// For changes the rows can't be tracked for, e.g. loading a state.
+    pub(crate) fn mark_all_dirty(&mut self) {
+        self.dirty = DirtyRows::ALL;
+        self.changes = self.changes.wrapping_add(1);
+    }
+
+    /// Differs from an earlier call when the display changed since.
+    pub(crate) fn changes(&self) -> u64 {
+        self.changes
+    }
+
+    pub(crate) fn take_dirty(&mut self) -> DirtyRows {
+        std::mem::take(&mut self.dirty)
+    }
+
+    pub(crate) fn width(&self) -> usize {
+        if self.megachip.is_some() {
+            MEGACHIP_SCREEN_WIDTH
+        } else if self.hires {
+            HIRES_SCREEN_WIDTH
+        } else {
+            SCREEN_WIDTH
+        }
+    }
+
+    pub(crate) fn height(&self) -> usize {
+        if self.megachip.is_some() {
+            MEGACHIP_SCREEN_HEIGHT
+        } else if self.hires {
+            HIRES_SCREEN_HEIGHT
+        } else if self.two_page {
+            TWO_PAGE_SCREEN_HEIGHT
+        } else {
+            SCREEN_HEIGHT
+        }
+    }
+
+    /// The pixels of the first plane at the current resolution, row by row.
+    pub(crate) fn pixels(&self) -> &[bool] {
+        &self.planes[0][..self.width() * self.height()]
+    }
+
+    pub(crate) fn frame_buffer(&self) -> FrameBuffer<'_> {
+        let len = self.width() * self.height();
+        FrameBuffer {
+            planes: std::array::from_fn(|plane| &self.planes[plane][..len]),
+            width: self.width(),
+            height: self.height(),
+            megachip: self.megachip.as_ref().map(|display| &display.front[..]),
+            zones: self.chip8x.as_deref(),
+        }
+    }
+
+    /// Switches resolution, leaving MegaChip and two-page modes and
+    /// clearing every plane.
+    pub(crate) fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.two_page = false;
+        self.megachip = None;
+        self.clear_planes();
+    }
+
+    /// Enters or leaves the 64x64 two-page mode, clearing the display.
+    pub(crate) fn set_two_page(&mut self, on: bool) {
+        self.set_hires(false);
+        self.two_page = on;
+    }
+
+    /// Enters or leaves MegaChip mode, clearing the display.
+    pub(crate) fn set_megachip(&mut self, on: bool) {
+        self.megachip = on.then(Box::default);
+        self.clear_planes();
+    }
+
+    fn clear_planes(&mut self) {
+        self.fit_planes();
+        for plane in self.planes.iter_mut() {
+            plane.fill(false);
+        }
+        self.mark_all_dirty();
+    }
+
+    /// Grows the planes for MegaChip mode, or shrinks them back after, so
+    /// the many machines of a sweep only hold what they use.
+    pub(crate) fn fit_planes(&mut self) {
+        let len = if self.megachip.is_some() {
+            DISPLAY_SIZE
+        } else {
+            HIRES_DISPLAY_SIZE
+        };
+        for plane in self.planes.iter_mut() {
+            plane.resize(len, false);
+            plane.shrink_to_fit();
+        }
+    }
+
+    pub(crate) fn scroll_up(&mut self, lines: usize) {
+        self.scroll(Shift::Up(lines));
+    }
+
+    pub(crate) fn scroll_down(&mut self, lines: usize) {
+        self.scroll(Shift::Down(lines));
+    }
+
+    pub(crate) fn scroll_right(&mut self, cols: usize) {
+        self.scroll(Shift::Right(cols));
+    }
+
+    pub(crate) fn scroll_left(&mut self, cols: usize) {
+        self.scroll(Shift::Left(cols));
+    }
+
+    // scrolls the selected planes, or the back buffer in MegaChip mode
+    fn scroll(&mut self, by: Shift) {
+        if let Some(display) = &mut self.megachip {
+            shift(&mut display.indices, MEGACHIP_SCREEN_WIDTH, by);
+            shift(&mut display.pixels, MEGACHIP_SCREEN_WIDTH, by);
+            return;
+        }
+        self.for_each_selected(|pixels, width| shift(pixels, width, by));
     }
 }
 
 impl Default for Screen {
     fn default() -> Self {
         Self {
-            display: [false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            planes: std::array::from_fn(|_| vec![false; HIRES_DISPLAY_SIZE]),
+            hires: false,
+            two_page: false,
+            megachip: None,
+            chip8x: None,
+            selected: 1,
+            // nothing was drawn yet
+            dirty: DirtyRows::ALL,
+            changes: 0,
         }
     }
 }
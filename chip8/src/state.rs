@@ -0,0 +1,729 @@
+use std::fmt;
+
+use crate::megachip::MegaChip;
+use crate::memory::{MEGACHIP_MEMORY_SIZE, RAM_SIZE, STACK_SIZE};
+use crate::romdb::rom_hash;
+use crate::screen::{
+    Argb, ColorDisplay, ColorZones, CHIP8X_BACKGROUNDS, CHIP8X_ZONE_COLUMNS, HIRES_SCREEN_HEIGHT,
+    HIRES_SCREEN_WIDTH, MEGACHIP_COLORS, MEGACHIP_SCREEN_HEIGHT, MEGACHIP_SCREEN_WIDTH, NUM_PLANES,
+    SCREEN_HEIGHT,
+};
+use crate::{CPU, NUM_FLAGS, NUM_KEYS, NUM_REGS};
+
+// starts the header of versioned snapshots, states saved before it have
+// none
+const HEADER_MAGIC: &[u8; 4] = b"C8SV";
+/// The version of the binary form of [`SaveState`], bumped when it changes.
+/// States of later versions are refused, see [`StateError::NewerVersion`].
+pub const STATE_FORMAT_VERSION: u16 = 2;
+// identifies a chip8 machine snapshot
+const MAGIC: &[u8; 4] = b"C8ST";
+// starts the MegaChip data following the snapshot of the original machines
+const MEGACHIP_MAGIC: &[u8; 4] = b"MC8X";
+// starts the CHIP-8X colors and second keypad, after any MegaChip data
+const CHIP8X_MAGIC: &[u8; 4] = b"C8XC";
+// starts the display wait and the user flags, last, since format 2
+const FLAGS_MAGIC: &[u8; 4] = b"C8FL";
+
+const CHIP8X_ZONES: usize = CHIP8X_ZONE_COLUMNS * SCREEN_HEIGHT;
+
+const MEGACHIP_DISPLAY_SIZE: usize = MEGACHIP_SCREEN_WIDTH * MEGACHIP_SCREEN_HEIGHT;
+
+const DISPLAY_SIZE: usize = HIRES_SCREEN_WIDTH * HIRES_SCREEN_HEIGHT;
+
+const HEADER_SIZE: usize = HEADER_MAGIC.len()
+    + 2 // format version
+    + 8; // ROM hash, 0 for none
+
+const STATE_SIZE: usize = MAGIC.len()
+    + 2 // program counter
+    + NUM_REGS
+    + 2 // i register
+    + 2 // stack pointer
+    + STACK_SIZE * 2
+    + RAM_SIZE
+    + DISPLAY_SIZE * NUM_PLANES
+    + 1 // selected planes
+    + 1 // hi-res flag
+    + NUM_KEYS
+    + 1 // key pressed during FX0A
+    + 2 // delay and sound timers
+    + 1; // halted flag
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    /// The data doesn't start with the snapshot header.
+    BadMagic,
+    /// The data is shorter or longer than a snapshot.
+    BadLength(usize),
+    /// The snapshot holds a stack pointer past the end of the stack.
+    BadStackPointer(u16),
+    /// A memory region of the snapshot doesn't have the machine's size.
+    BadRegionSize { region: &'static str, len: usize },
+    /// The snapshot waits for the release of a key past 0xF.
+    BadKey(u8),
+    /// The snapshot was saved in a later format than this version reads.
+    NewerVersion(u16),
+    /// The snapshot is of a machine running another ROM, by their hashes.
+    WrongRom { state: u64, running: u64 },
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::BadMagic => write!(f, "not a chip8 save state"),
+            StateError::BadLength(len) => write!(
+                f,
+                "save state is {len} bytes, expected {} and its extensions",
+                HEADER_SIZE + STATE_SIZE
+            ),
+            StateError::BadStackPointer(sp) => write!(f, "invalid stack pointer {sp}"),
+            StateError::BadRegionSize { region, len } => {
+                write!(f, "save state {region} has an invalid size of {len}")
+            }
+            StateError::BadKey(key) => write!(f, "save state waits for invalid key {key:#X}"),
+            StateError::NewerVersion(version) => write!(
+                f,
+                "save state format {version} is newer than {STATE_FORMAT_VERSION}, update the emulator"
+            ),
+            StateError::WrongRom { state, running } => write!(
+                f,
+                "save state is for ROM {state:016x}, not the running {running:016x}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+/// A full copy of the machine: registers, RAM, stack, timers, keypad and
+/// display. With the `serde` feature it can be serialized with any serde
+/// format, otherwise [`SaveState::to_bytes`] gives a compact binary form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SaveState {
+    pub program_counter: u16,
+    pub v_registers: [u8; NUM_REGS],
+    pub i_register: u16,
+    pub stack_pointer: u16,
+    /// All the stack slots, including the ones past the stack pointer.
+    pub stack: Vec<u16>,
+    pub ram: Vec<u8>,
+    /// The first plane, sized for the hi-res mode, see [`CPU::get_display`].
+    pub display: Vec<bool>,
+    /// The second plane of XO-CHIP programs.
+    pub display2: Vec<bool>,
+    /// A bit per plane drawn on.
+    pub selected_planes: u8,
+    pub hires: bool,
+    /// The 64x64 display of the two-page hi-res variant.
+    pub two_page: bool,
+    pub keys: [bool; NUM_KEYS],
+    /// The key pressed while FX0A waits for its release.
+    pub key_wait: Option<u8>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub halted: bool,
+    /// DXYN waits for the next frame, see [`Quirks::display_wait`].
+    ///
+    /// [`Quirks::display_wait`]: crate::Quirks::display_wait
+    pub waiting_vblank: bool,
+    /// The user flags of FX75 and FX85. States of format 1 left them out
+    /// and restore without changing them.
+    pub flags: Option<[u8; NUM_FLAGS]>,
+    /// The hash of the ROM the machine runs, see [`romdb::rom_hash`]. A
+    /// state without one restores whatever runs.
+    ///
+    /// [`romdb::rom_hash`]: crate::romdb::rom_hash
+    pub rom_hash: Option<u64>,
+    /// The MegaChip registers and display, once a program used them.
+    pub megachip: Option<MegaChipState>,
+    /// The colors and second keypad of CHIP-8X programs.
+    pub chip8x: Option<Chip8XState>,
+}
+
+/// The CHIP-8X part of a [`SaveState`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Chip8XState {
+    /// Index in [`CHIP8X_BACKGROUNDS`].
+    ///
+    /// [`CHIP8X_BACKGROUNDS`]: crate::screen::CHIP8X_BACKGROUNDS
+    pub background: u8,
+    /// Indices in [`CHIP8X_COLORS`] of the zones 8 pixels wide and a row
+    /// high, row by row.
+    ///
+    /// [`CHIP8X_COLORS`]: crate::screen::CHIP8X_COLORS
+    pub zones: Vec<u8>,
+    pub keys2: [bool; NUM_KEYS],
+}
+
+/// The MegaChip part of a [`SaveState`]. The digitized sound playing isn't
+/// saved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MegaChipState {
+    /// Bits 16 to 23 of I.
+    pub i_high: u8,
+    /// The ARGB colors of the palette.
+    pub palette: Vec<[u8; 4]>,
+    pub sprite_width: u8,
+    pub sprite_height: u8,
+    pub blend_mode: u8,
+    pub collision_color: u8,
+    pub alpha: u8,
+    /// The 256x192 display, in MegaChip mode.
+    pub display: Option<MegaChipDisplay>,
+}
+
+/// The MegaChip display of a [`MegaChipState`], row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MegaChipDisplay {
+    /// The palette indices of the frame being drawn.
+    pub indices: Vec<u8>,
+    /// The colors of the frame being drawn.
+    pub pixels: Vec<[u8; 3]>,
+    /// The colors on display.
+    pub front: Vec<[u8; 3]>,
+}
+
+impl SaveState {
+    /// The machine in a compact binary form, after a header with the
+    /// [`STATE_FORMAT_VERSION`] and the ROM hash. MegaChip programs add
+    /// their registers, display and larger RAM at the end, then CHIP-8X
+    /// programs their colors, then come the display wait and user flags.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (ram, extension) = self.ram.split_at(self.ram.len().min(RAM_SIZE));
+        let mut out = Vec::with_capacity(HEADER_SIZE + STATE_SIZE);
+        out.extend_from_slice(HEADER_MAGIC);
+        out.extend_from_slice(&STATE_FORMAT_VERSION.to_be_bytes());
+        out.extend_from_slice(&self.rom_hash.unwrap_or(0).to_be_bytes());
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&self.program_counter.to_be_bytes());
+        out.extend_from_slice(&self.v_registers);
+        out.extend_from_slice(&self.i_register.to_be_bytes());
+        out.extend_from_slice(&self.stack_pointer.to_be_bytes());
+        for addr in &self.stack {
+            out.extend_from_slice(&addr.to_be_bytes());
+        }
+        out.extend_from_slice(ram);
+        out.extend(self.display.iter().map(|&px| px as u8));
+        out.extend(self.display2.iter().map(|&px| px as u8));
+        out.push(self.selected_planes);
+        // the two-page display is a third resolution
+        out.push(if self.two_page { 2 } else { self.hires as u8 });
+        out.extend(self.keys.iter().map(|&key| key as u8));
+        // no key is stored as 0xFF
+        out.push(self.key_wait.unwrap_or(0xFF));
+        out.push(self.delay_timer);
+        out.push(self.sound_timer);
+        out.push(self.halted as u8);
+
+        if !extension.is_empty() || self.megachip.is_some() {
+            self.write_megachip(extension, &mut out);
+        }
+        if let Some(chip8x) = &self.chip8x {
+            out.extend_from_slice(CHIP8X_MAGIC);
+            out.push(chip8x.background);
+            out.extend_from_slice(&chip8x.zones);
+            out.extend(chip8x.keys2.iter().map(|&key| key as u8));
+        }
+        out.extend_from_slice(FLAGS_MAGIC);
+        out.push(self.waiting_vblank as u8);
+        out.push(self.flags.is_some() as u8);
+        if let Some(flags) = &self.flags {
+            out.extend_from_slice(flags);
+        }
+        out
+    }
+
+    // the RAM past its first 4K then the MegaChip registers and display
+    fn write_megachip(&self, extension: &[u8], out: &mut Vec<u8>) {
+        out.extend_from_slice(MEGACHIP_MAGIC);
+        out.extend_from_slice(&(extension.len() as u32).to_be_bytes());
+        out.extend_from_slice(extension);
+        out.push(self.megachip.is_some() as u8);
+        let Some(megachip) = &self.megachip else {
+            return;
+        };
+        out.extend_from_slice(&[
+            megachip.i_high,
+            megachip.sprite_width,
+            megachip.sprite_height,
+            megachip.blend_mode,
+            megachip.collision_color,
+            megachip.alpha,
+        ]);
+        out.extend(megachip.palette.iter().flatten());
+        out.push(megachip.display.is_some() as u8);
+        if let Some(display) = &megachip.display {
+            out.extend_from_slice(&display.indices);
+            out.extend(display.pixels.iter().flatten());
+            out.extend(display.front.iter().flatten());
+        }
+    }
+
+    /// Reads a state in the binary form of [`SaveState::to_bytes`], of an
+    /// earlier format, or of the versions before the header.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, StateError> {
+        let total = data.len();
+        let (rom_hash, data) = match data.strip_prefix(HEADER_MAGIC) {
+            Some(_) if total < HEADER_SIZE => return Err(StateError::BadLength(total)),
+            Some(header) => {
+                let version = u16::from_be_bytes([header[0], header[1]]);
+                if version > STATE_FORMAT_VERSION {
+                    return Err(StateError::NewerVersion(version));
+                }
+                let mut hash = [0; 8];
+                hash.copy_from_slice(&header[2..10]);
+                let hash = u64::from_be_bytes(hash);
+                (Some(hash).filter(|&hash| hash != 0), &header[10..])
+            }
+            None => (None, data),
+        };
+        if data.len() < STATE_SIZE {
+            return Err(StateError::BadLength(total));
+        }
+        let (data, extension) = data.split_at(STATE_SIZE);
+        let (magic, mut data) = data.split_at(MAGIC.len());
+        if magic != MAGIC {
+            return Err(StateError::BadMagic);
+        }
+
+        let mut take = |len: usize| {
+            let (head, tail) = data.split_at(len);
+            data = tail;
+            head
+        };
+        let u16_at = |bytes: &[u8]| u16::from_be_bytes([bytes[0], bytes[1]]);
+
+        let program_counter = u16_at(take(2));
+        let mut v_registers = [0; NUM_REGS];
+        v_registers.copy_from_slice(take(NUM_REGS));
+        let i_register = u16_at(take(2));
+        let stack_pointer = u16_at(take(2));
+        let stack = take(STACK_SIZE * 2).chunks(2).map(u16_at).collect();
+        let mut ram = take(RAM_SIZE).to_vec();
+        let display = take(DISPLAY_SIZE).iter().map(|&px| px != 0).collect();
+        let display2 = take(DISPLAY_SIZE).iter().map(|&px| px != 0).collect();
+        let selected_planes = take(1)[0];
+        let resolution = take(1)[0];
+        let (hires, two_page) = (resolution == 1, resolution == 2);
+        let mut keys = [false; NUM_KEYS];
+        for (key, &byte) in keys.iter_mut().zip(take(NUM_KEYS)) {
+            *key = byte != 0;
+        }
+        let key_wait = Some(take(1)[0]).filter(|&key| key != 0xFF);
+        let timers = take(2);
+        let halted = take(1)[0] != 0;
+
+        let mut extension = extension;
+        let bad_length = StateError::BadLength(total);
+        let megachip = if extension.starts_with(MEGACHIP_MAGIC) {
+            read_megachip(&mut extension, &mut ram, &bad_length)?
+        } else {
+            None
+        };
+        let chip8x = if extension.starts_with(CHIP8X_MAGIC) {
+            Some(read_chip8x(&mut extension, &bad_length)?)
+        } else {
+            None
+        };
+        let (waiting_vblank, flags) = if extension.starts_with(FLAGS_MAGIC) {
+            read_flags(&mut extension, &bad_length)?
+        } else {
+            (false, None)
+        };
+        if !extension.is_empty() {
+            return Err(if extension.len() < MAGIC.len() {
+                bad_length
+            } else {
+                StateError::BadMagic
+            });
+        }
+
+        Ok(Self {
+            program_counter,
+            v_registers,
+            i_register,
+            stack_pointer,
+            stack,
+            ram,
+            display,
+            display2,
+            selected_planes,
+            hires,
+            two_page,
+            keys,
+            key_wait,
+            delay_timer: timers[0],
+            sound_timer: timers[1],
+            halted,
+            waiting_vblank,
+            flags,
+            rom_hash,
+            megachip,
+            chip8x,
+        })
+    }
+}
+
+// reads the MegaChip data following the snapshot, appending the RAM past
+// its first 4K to `ram`
+fn read_megachip(
+    data: &mut &[u8],
+    ram: &mut Vec<u8>,
+    bad_length: &StateError,
+) -> Result<Option<MegaChipState>, StateError> {
+    let mut take = |len: usize| {
+        let (head, tail) = data.split_at_checked(len).ok_or(bad_length.clone())?;
+        *data = tail;
+        Ok(head)
+    };
+    take(MEGACHIP_MAGIC.len())?;
+    let len = take(4)?;
+    let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+    ram.extend_from_slice(take(len)?);
+    if take(1)?[0] == 0 {
+        return Ok(None);
+    }
+
+    let registers = take(6)?;
+    let palette = take(MEGACHIP_COLORS * 4)?
+        .chunks(4)
+        .map(|argb| [argb[0], argb[1], argb[2], argb[3]])
+        .collect();
+    let display = if take(1)?[0] != 0 {
+        let indices = take(MEGACHIP_DISPLAY_SIZE)?.to_vec();
+        let to_rgb = |bytes: &[u8]| bytes.chunks(3).map(|c| [c[0], c[1], c[2]]).collect();
+        let pixels = to_rgb(take(MEGACHIP_DISPLAY_SIZE * 3)?);
+        let front = to_rgb(take(MEGACHIP_DISPLAY_SIZE * 3)?);
+        Some(MegaChipDisplay {
+            indices,
+            pixels,
+            front,
+        })
+    } else {
+        None
+    };
+    Ok(Some(MegaChipState {
+        i_high: registers[0],
+        palette,
+        sprite_width: registers[1],
+        sprite_height: registers[2],
+        blend_mode: registers[3],
+        collision_color: registers[4],
+        alpha: registers[5],
+        display,
+    }))
+}
+
+// reads the CHIP-8X colors and second keypad
+fn read_chip8x(data: &mut &[u8], bad_length: &StateError) -> Result<Chip8XState, StateError> {
+    let mut take = |len: usize| {
+        let (head, tail) = data.split_at_checked(len).ok_or(bad_length.clone())?;
+        *data = tail;
+        Ok(head)
+    };
+    take(CHIP8X_MAGIC.len())?;
+    let background = take(1)?[0];
+    let zones = take(CHIP8X_ZONES)?.to_vec();
+    let mut keys2 = [false; NUM_KEYS];
+    for (key, &byte) in keys2.iter_mut().zip(take(NUM_KEYS)?) {
+        *key = byte != 0;
+    }
+    Ok(Chip8XState {
+        background,
+        zones,
+        keys2,
+    })
+}
+
+// reads the display wait and the user flags
+fn read_flags(
+    data: &mut &[u8],
+    bad_length: &StateError,
+) -> Result<(bool, Option<[u8; NUM_FLAGS]>), StateError> {
+    let mut take = |len: usize| {
+        let (head, tail) = data.split_at_checked(len).ok_or(bad_length.clone())?;
+        *data = tail;
+        Ok(head)
+    };
+    take(FLAGS_MAGIC.len())?;
+    let waiting_vblank = take(1)?[0] != 0;
+    if take(1)?[0] == 0 {
+        return Ok((waiting_vblank, None));
+    }
+    let mut flags = [0; NUM_FLAGS];
+    flags.copy_from_slice(take(NUM_FLAGS)?);
+    Ok((waiting_vblank, Some(flags)))
+}
+
+/// The registers of the machine at one point, see [`CPU::state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuState {
+    pub program_counter: u16,
+    pub i_register: u16,
+    /// V0 to VF.
+    pub v_registers: [u8; NUM_REGS],
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    /// All the stack slots, including the ones past the stack pointer.
+    pub stack: [u16; STACK_SIZE],
+    pub stack_pointer: u16,
+}
+
+impl CpuState {
+    /// The return addresses of the active subroutine calls, oldest first.
+    pub fn call_stack(&self) -> &[u16] {
+        &self.stack[..(self.stack_pointer as usize).min(STACK_SIZE)]
+    }
+}
+
+impl CPU {
+    /// The registers, stack and timers, for debuggers and tools showing
+    /// what the machine is doing.
+    pub fn state(&self) -> CpuState {
+        CpuState {
+            program_counter: self.program_counter,
+            i_register: self.i_register,
+            v_registers: self.v_registers,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            stack: self.stack.stack,
+            stack_pointer: self.stack.stack_point,
+        }
+    }
+
+    /// Copies the whole machine into a [`SaveState`].
+    pub fn snapshot(&self) -> SaveState {
+        SaveState {
+            program_counter: self.program_counter,
+            v_registers: self.v_registers,
+            i_register: self.i_register,
+            stack_pointer: self.stack.stack_point,
+            stack: self.stack.stack.to_vec(),
+            ram: self.ram.data.to_vec(),
+            display: self.screen.planes[0][..DISPLAY_SIZE].to_vec(),
+            display2: self.screen.planes[1][..DISPLAY_SIZE].to_vec(),
+            selected_planes: self.screen.selected,
+            hires: self.screen.hires,
+            two_page: self.screen.two_page,
+            keys: self.keys,
+            key_wait: self.key_wait,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            halted: self.halted,
+            waiting_vblank: self.waiting_vblank,
+            flags: Some(self.flags),
+            rom_hash: self.rom_hash,
+            megachip: self.megachip_state(),
+            chip8x: self.screen.chip8x.as_ref().map(|colors| Chip8XState {
+                background: colors.background,
+                zones: colors.zones.to_vec(),
+                keys2: self.keys2,
+            }),
+        }
+    }
+
+    fn megachip_state(&self) -> Option<MegaChipState> {
+        let registers = MegaChip {
+            sound: None,
+            sounds_started: 0,
+            ..self.megachip.clone()
+        };
+        if !self.is_megachip() && registers == MegaChip::default() {
+            return None;
+        }
+        Some(MegaChipState {
+            i_high: registers.i_high,
+            palette: registers.palette.to_vec(),
+            sprite_width: registers.sprite_width,
+            sprite_height: registers.sprite_height,
+            blend_mode: registers.blend_mode,
+            collision_color: registers.collision_color,
+            alpha: registers.alpha,
+            display: self
+                .screen
+                .megachip
+                .as_ref()
+                .map(|display| MegaChipDisplay {
+                    indices: display.indices.clone(),
+                    pixels: display.pixels.clone(),
+                    front: display.front.clone(),
+                }),
+        })
+    }
+
+    /// Restores a machine previously captured with [`CPU::snapshot`].
+    /// The CPU is left untouched if the state is invalid or of another ROM
+    /// than the one loaded, clear [`SaveState::rom_hash`] to restore it
+    /// anyway.
+    pub fn restore(&mut self, state: &SaveState) -> Result<(), StateError> {
+        if let (Some(state), Some(running)) = (state.rom_hash, self.rom_hash) {
+            if state != running {
+                return Err(StateError::WrongRom { state, running });
+            }
+        }
+        if state.stack_pointer as usize > STACK_SIZE {
+            return Err(StateError::BadStackPointer(state.stack_pointer));
+        }
+        let check = |region, len, expected| {
+            if len == expected {
+                Ok(())
+            } else {
+                Err(StateError::BadRegionSize { region, len })
+            }
+        };
+        check("stack", state.stack.len(), STACK_SIZE)?;
+        if !(RAM_SIZE..=MEGACHIP_MEMORY_SIZE).contains(&state.ram.len()) {
+            return Err(StateError::BadRegionSize {
+                region: "ram",
+                len: state.ram.len(),
+            });
+        }
+        check("display", state.display.len(), DISPLAY_SIZE)?;
+        check("display2", state.display2.len(), DISPLAY_SIZE)?;
+        if let Some(megachip) = &state.megachip {
+            check("palette", megachip.palette.len(), MEGACHIP_COLORS)?;
+            if let Some(display) = &megachip.display {
+                check("indices", display.indices.len(), MEGACHIP_DISPLAY_SIZE)?;
+                check("pixels", display.pixels.len(), MEGACHIP_DISPLAY_SIZE)?;
+                check("front", display.front.len(), MEGACHIP_DISPLAY_SIZE)?;
+            }
+        }
+        if let Some(chip8x) = &state.chip8x {
+            check("zones", chip8x.zones.len(), CHIP8X_ZONES)?;
+            if chip8x.background as usize >= CHIP8X_BACKGROUNDS.len() {
+                return Err(StateError::BadRegionSize {
+                    region: "background",
+                    len: chip8x.background as usize,
+                });
+            }
+        }
+        if let Some(key) = state.key_wait.filter(|&key| key as usize >= NUM_KEYS) {
+            return Err(StateError::BadKey(key));
+        }
+
+        self.program_counter = state.program_counter;
+        self.v_registers = state.v_registers;
+        self.i_register = state.i_register;
+        self.stack.stack_point = state.stack_pointer;
+        self.stack.stack.copy_from_slice(&state.stack);
+        self.ram.data.clone_from(&state.ram);
+        self.screen.planes[0][..DISPLAY_SIZE].copy_from_slice(&state.display);
+        self.screen.planes[1][..DISPLAY_SIZE].copy_from_slice(&state.display2);
+        self.screen.select(state.selected_planes);
+        self.screen.hires = state.hires;
+        self.screen.two_page = state.two_page && !state.hires;
+        self.restore_megachip(state.megachip.as_ref());
+        self.screen.chip8x = state.chip8x.as_ref().map(|chip8x| {
+            let mut colors = ColorZones {
+                background: chip8x.background,
+                ..ColorZones::default()
+            };
+            colors.zones.copy_from_slice(&chip8x.zones);
+            Box::new(colors)
+        });
+        self.keys2 = state
+            .chip8x
+            .as_ref()
+            .map_or([false; NUM_KEYS], |chip8x| chip8x.keys2);
+        self.screen.mark_all_dirty();
+        self.keys = state.keys;
+        self.key_wait = state.key_wait;
+        self.waiting_key = false;
+        self.delay_timer = state.delay_timer;
+        self.sound_timer = state.sound_timer;
+        self.halted = state.halted;
+        self.waiting_vblank = state.waiting_vblank;
+        if let Some(flags) = state.flags {
+            self.flags = flags;
+        }
+        self.rom_hash = state.rom_hash.or(self.rom_hash);
+        Ok(())
+    }
+
+    // the digitized sound stops, it isn't saved
+    fn restore_megachip(&mut self, state: Option<&MegaChipState>) {
+        let sounds_started = self.megachip.sounds_started;
+        self.megachip = MegaChip {
+            sounds_started,
+            ..MegaChip::default()
+        };
+        self.screen.megachip = None;
+        let Some(state) = state else {
+            return;
+        };
+        let mut palette = [Argb::default(); MEGACHIP_COLORS];
+        palette.copy_from_slice(&state.palette);
+        self.megachip = MegaChip {
+            i_high: state.i_high,
+            palette,
+            sprite_width: state.sprite_width,
+            sprite_height: state.sprite_height,
+            blend_mode: state.blend_mode,
+            collision_color: state.collision_color,
+            alpha: state.alpha,
+            sound: None,
+            sounds_started,
+        };
+        if let Some(display) = &state.display {
+            self.screen.megachip = Some(Box::new(ColorDisplay {
+                indices: display.indices.clone(),
+                pixels: display.pixels.clone(),
+                front: display.front.clone(),
+            }));
+            self.screen.light_megachip();
+        }
+    }
+
+    /// Serializes the whole machine (registers, RAM, stack, timers,
+    /// keypad and display) into a byte buffer.
+    pub fn save_state(&self) -> Vec<u8> {
+        self.snapshot().to_bytes()
+    }
+
+    /// A 64-bit hash of the machine, equal for machines in the same state
+    /// on any platform, to check replays and spot netplay desyncs without
+    /// sending the whole state around. It covers everything a save state
+    /// holds, plus what changes how the machine goes on but isn't saved:
+    /// the random source, see [`RandomSource::fingerprint`], the key events
+    /// queued and latched, the time towards the next timer tick and the
+    /// quirks. The speed, timing, bounds policy, hooks, and the profile,
+    /// coverage and rewind history are left out.
+    ///
+    /// [`RandomSource::fingerprint`]: crate::RandomSource::fingerprint
+    pub fn state_hash(&self) -> u64 {
+        let mut data = self.save_state();
+        data.extend_from_slice(&self.rng.fingerprint().to_be_bytes());
+        data.extend_from_slice(&(self.key_events.len() as u32).to_be_bytes());
+        for &(key, pressed) in &self.key_events {
+            data.extend_from_slice(&[key, pressed as u8]);
+        }
+        data.push(self.latched_keys.is_some() as u8);
+        if let Some(keys) = &self.latched_keys {
+            data.extend(keys.iter().map(|&key| key as u8));
+        }
+        data.extend_from_slice(&(self.timer_elapsed.as_nanos() as u64).to_be_bytes());
+        let quirks = self.quirks;
+        data.extend(
+            [
+                quirks.clip_sprites,
+                quirks.increment_i,
+                quirks.shift_vy,
+                quirks.jump_vx,
+                quirks.display_wait,
+            ]
+            .map(u8::from),
+        );
+        rom_hash(&data)
+    }
+
+    /// Restores a machine previously serialized with [`CPU::save_state`].
+    /// The CPU is left untouched if the data is invalid.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        self.restore(&SaveState::from_bytes(data)?)
+    }
+}
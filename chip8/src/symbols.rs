@@ -0,0 +1,160 @@
+//! Names from the source of a ROM built with Octo, read from a symbol file
+//! next to it, see [`Symbols`].
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::asm::parse_number;
+
+/// Why a symbol file couldn't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolError {
+    /// 1-based line number of the offending line.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SymbolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for SymbolError {}
+
+/// The labels, constants, register aliases and source lines of a program,
+/// for listings and the debugger to show names instead of numbers.
+///
+/// A symbol file has one symbol per line, `label name 0x22A`,
+/// `const name 12`, `alias name v3`, or `line 0x22A 40` for the source
+/// line the code at an address was built from. Numbers are as in
+/// [`crate::asm`] and `#` starts a comment. Lines of other kinds are
+/// skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Symbols {
+    labels: BTreeMap<u16, String>,
+    constants: BTreeMap<String, u16>,
+    aliases: BTreeMap<u8, String>,
+    lines: BTreeMap<u16, usize>,
+}
+
+impl Symbols {
+    /// The name of the label at `addr`.
+    pub fn label(&self, addr: u16) -> Option<&str> {
+        self.labels.get(&addr).map(String::as_str)
+    }
+
+    /// The labels in ascending address order.
+    pub fn labels(&self) -> impl Iterator<Item = (u16, &str)> + '_ {
+        self.labels
+            .iter()
+            .map(|(&addr, name)| (addr, name.as_str()))
+    }
+
+    pub fn constant(&self, name: &str) -> Option<u16> {
+        self.constants.get(name).copied()
+    }
+
+    /// The constants in name order.
+    pub fn constants(&self) -> impl Iterator<Item = (&str, u16)> + '_ {
+        self.constants
+            .iter()
+            .map(|(name, &value)| (name.as_str(), value))
+    }
+
+    /// The value of the label or constant called `name`.
+    pub fn resolve(&self, name: &str) -> Option<u16> {
+        self.labels
+            .iter()
+            .find(|(_, label)| *label == name)
+            .map(|(&addr, _)| addr)
+            .or_else(|| self.constant(name))
+    }
+
+    /// The closest label at or before `addr` and how far past it `addr` is,
+    /// e.g. to show the program counter as `draw+0x4`.
+    pub fn locate(&self, addr: u16) -> Option<(&str, u16)> {
+        self.labels
+            .range(..=addr)
+            .next_back()
+            .map(|(&label, name)| (name.as_str(), addr - label))
+    }
+
+    /// The alias of register Vx in the source.
+    pub fn alias(&self, x: u8) -> Option<&str> {
+        self.aliases.get(&x).map(String::as_str)
+    }
+
+    /// The source line the code at `addr` was built from, the closest one
+    /// mapped at or before it.
+    pub fn source_line(&self, addr: u16) -> Option<usize> {
+        self.lines.range(..=addr).next_back().map(|(_, &line)| line)
+    }
+
+    /// The first address built from source line `line`.
+    pub fn line_address(&self, line: usize) -> Option<u16> {
+        self.lines
+            .iter()
+            .filter(|(_, &mapped)| mapped == line)
+            .map(|(&addr, _)| addr)
+            .next()
+    }
+}
+
+impl FromStr for Symbols {
+    type Err = SymbolError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut symbols = Symbols::default();
+        for (idx, raw) in text.lines().enumerate() {
+            let line = idx + 1;
+            let fields: Vec<_> = raw
+                .split('#')
+                .next()
+                .unwrap_or("")
+                .split_whitespace()
+                .collect();
+            let (kind, name, value) = match *fields.as_slice() {
+                [] => continue,
+                [kind @ ("label" | "const" | "alias" | "line"), name, value] => (kind, name, value),
+                ["label" | "const" | "alias" | "line", ..] => {
+                    return Err(error(line, "expected a kind, a name and a value"))
+                }
+                _ => continue,
+            };
+            let invalid = |text: &str| error(line, format!("invalid value `{text}`"));
+            match kind {
+                "alias" => {
+                    let x = value
+                        .strip_prefix(['v', 'V'])
+                        .and_then(|x| u8::from_str_radix(x, 16).ok())
+                        .filter(|&x| x < 16)
+                        .ok_or_else(|| invalid(value))?;
+                    symbols.aliases.insert(x, name.to_string());
+                }
+                "line" => {
+                    let addr = parse_number(name).ok_or_else(|| invalid(name))?;
+                    let source = value.parse().map_err(|_| invalid(value))?;
+                    symbols.lines.insert(addr, source);
+                }
+                _ => {
+                    let value = parse_number(value).ok_or_else(|| invalid(value))?;
+                    if kind == "label" {
+                        symbols.labels.insert(value, name.to_string());
+                    } else {
+                        symbols.constants.insert(name.to_string(), value);
+                    }
+                }
+            }
+        }
+        Ok(symbols)
+    }
+}
+
+fn error(line: usize, message: impl Into<String>) -> SymbolError {
+    SymbolError {
+        line,
+        message: message.into(),
+    }
+}
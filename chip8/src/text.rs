@@ -0,0 +1,61 @@
+//! Minimal text rendering with the built-in hex font, for frontends that
+//! want an on-screen display without a font renderer of their own.
+
+use crate::font::FONTSET;
+
+/// Width of a glyph in pixels.
+pub const GLYPH_WIDTH: usize = 4;
+/// Height of a glyph in pixels.
+pub const GLYPH_HEIGHT: usize = 5;
+/// Horizontal distance between the start of two consecutive glyphs.
+pub const GLYPH_ADVANCE: usize = GLYPH_WIDTH + 1;
+
+/// Calls `plot(x, y)` for every lit pixel of `text`, relative to its top-left
+/// corner. Only hex digits can be drawn, any other character is a blank
+/// space. Returns the width of the rendered text.
+pub fn for_each_pixel(text: &str, mut plot: impl FnMut(usize, usize)) -> usize {
+    let mut cursor = 0;
+    for c in text.chars() {
+        if let Some(digit) = c.to_digit(16) {
+            let glyph = &FONTSET[digit as usize * GLYPH_HEIGHT..][..GLYPH_HEIGHT];
+            for (row, bits) in glyph.iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (0x80 >> col) != 0 {
+                        plot(cursor + col, row);
+                    }
+                }
+            }
+        }
+        cursor += GLYPH_ADVANCE;
+    }
+    cursor.saturating_sub(1)
+}
+
+/// Draws `text` at (`x`, `y`) into a row-major `framebuffer` that is `width`
+/// pixels wide. Pixels falling outside the framebuffer are skipped. Returns
+/// the width of the rendered text, or 0 without drawing when `width` is 0.
+pub fn draw_text(framebuffer: &mut [bool], width: usize, x: usize, y: usize, text: &str) -> usize {
+    if width == 0 {
+        return 0;
+    }
+    let height = framebuffer.len() / width;
+    for_each_pixel(text, |px, py| {
+        let (Some(px), Some(py)) = (x.checked_add(px), y.checked_add(py)) else {
+            return;
+        };
+        if px < width && py < height {
+            framebuffer[py * width + px] = true;
+        }
+    })
+}
+
+/// Formats a byte as two hex digits and draws it, see [`draw_text`].
+pub fn draw_hex_byte(
+    framebuffer: &mut [bool],
+    width: usize,
+    x: usize,
+    y: usize,
+    byte: u8,
+) -> usize {
+    draw_text(framebuffer, width, x, y, &format!("{byte:02X}"))
+}
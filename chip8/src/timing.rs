@@ -0,0 +1,116 @@
+//! How long instructions take, see [`Timing`].
+
+use crate::{RunSummary, CPU};
+
+/// Machine cycles the COSMAC VIP runs per 60 Hz frame: a 1.76 MHz clock, 8
+/// clock pulses per machine cycle.
+pub const VIP_CYCLES_PER_FRAME: u64 = 1_760_900 / 8 / 60;
+
+/// How the CPU decides how many instructions make a frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Timing {
+    /// Every instruction takes one cycle and a frame runs
+    /// [`CPU::ticks_per_frame`] of them.
+    #[default]
+    Fixed,
+    /// Every instruction takes the machine cycles the original COSMAC VIP
+    /// interpreter spent on it, and a frame runs [`VIP_CYCLES_PER_FRAME`]
+    /// of them. Drawing is slow and arithmetic fast, like on the real thing.
+    CosmacVip,
+}
+
+impl Timing {
+    /// Machine cycles `op` takes.
+    pub fn cycles(&self, op: u16) -> u64 {
+        match self {
+            Timing::Fixed => 1,
+            Timing::CosmacVip => vip_cycles(op),
+        }
+    }
+}
+
+// averages measured on the VIP interpreter, fetching and decoding included
+fn vip_cycles(op: u16) -> u64 {
+    let x = (op >> 8 & 0xF) as u64;
+    let n = (op & 0xF) as u64;
+    match op >> 12 {
+        0x0 => match op {
+            0x00E0 => 24,
+            _ => 23,
+        },
+        0x1 | 0x2 | 0xB => 23,
+        0x3 | 0x4 | 0xA => 12,
+        0x5 | 0x9 | 0xE => 16,
+        0x6 => 6,
+        0x7 => 10,
+        0x8 => 44,
+        0xC => 36,
+        // a row of the sprite is shifted into place and XORed byte by byte
+        0xD => 68 + 46 * n.max(1),
+        _ => match op & 0xFF {
+            0x1E => 19,
+            0x29 => 20,
+            0x33 => 204,
+            0x55 | 0x65 => 16 + 14 * (x + 1),
+            _ => 10,
+        },
+    }
+}
+
+impl CPU {
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.timing = timing;
+    }
+
+    /// Machine cycles spent executing instructions since the CPU was
+    /// created, see [`Timing::cycles`].
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Executes one 60 Hz frame worth of instructions at `ips`
+    /// instructions per second, `ips / 60` of them but at least one, e.g.
+    /// [`CPU::clock_hz`] for the speed set with [`CPU::set_clock_hz`].
+    /// Doesn't tick the timers.
+    ///
+    /// [`Timing::CosmacVip`] ignores `ips` and runs as many instructions as
+    /// fit in the frame's machine cycles, an instruction running past the
+    /// end of the frame shortening the next one.
+    pub fn run_frame(&mut self, ips: u32) -> Result<RunSummary, crate::Chip8Error> {
+        let instructions = (ips as usize / 60).max(1);
+        self.summarize(|cpu| cpu.run_frame_instructions(instructions))
+    }
+
+    fn run_frame_instructions(&mut self, instructions: usize) -> Result<(), crate::Chip8Error> {
+        match self.timing {
+            Timing::Fixed => {
+                let mut left = instructions;
+                while left > 0 {
+                    left -= self.step(left)?;
+                }
+            }
+            Timing::CosmacVip => {
+                // start over when cycles ran outside of frames, e.g. with
+                // the fixed timing
+                if self.cycles > self.frame_end + VIP_CYCLES_PER_FRAME {
+                    self.frame_end = self.cycles;
+                }
+                self.frame_end += VIP_CYCLES_PER_FRAME;
+                while self.cycles < self.frame_end && !self.halted {
+                    if self.waiting_vblank {
+                        // the wait takes the rest of the frame
+                        self.cycles = self.frame_end;
+                        break;
+                    }
+                    self.tick()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+//! Opt-in logging of every executed instruction, see [`CPU::set_tracer`].
+
+use std::fmt;
+
+use crate::{Instruction, CPU, NUM_REGS};
+
+/// A register an instruction can change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    V(u8),
+    I,
+    DelayTimer,
+    SoundTimer,
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Register::V(x) => write!(f, "V{x:X}"),
+            Register::I => write!(f, "I"),
+            Register::DelayTimer => write!(f, "DT"),
+            Register::SoundTimer => write!(f, "ST"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterChange {
+    pub register: Register,
+    pub old: u16,
+    pub new: u16,
+}
+
+/// One executed instruction and the registers it changed, printed as e.g.
+/// `0x200: 6A02  LD VA, 0x02  VA 0x0->0x2`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub address: u16,
+    pub opcode: u16,
+    pub instruction: Instruction,
+    pub changes: Vec<RegisterChange>,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "0x{:03X}: {:04X}  {}",
+            self.address, self.opcode, self.instruction
+        )?;
+        for (idx, change) in self.changes.iter().enumerate() {
+            let sep = if idx == 0 { "  " } else { ", " };
+            write!(
+                f,
+                "{sep}{} {:#X}->{:#X}",
+                change.register, change.old, change.new
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Receives the trace of every instruction the CPU executes.
+///
+/// Implemented for closures, e.g. `|entry| eprintln!("{entry}")`.
+pub trait TraceSink: Send + Sync {
+    fn trace(&mut self, entry: &TraceEntry);
+}
+
+impl<F: FnMut(&TraceEntry) + Send + Sync> TraceSink for F {
+    fn trace(&mut self, entry: &TraceEntry) {
+        self(entry)
+    }
+}
+
+/// Sends the trace to the `log` crate at the trace level.
+#[cfg(feature = "log")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogSink;
+
+#[cfg(feature = "log")]
+impl TraceSink for LogSink {
+    fn trace(&mut self, entry: &TraceEntry) {
+        log::trace!(target: "chip8", "{entry}");
+    }
+}
+
+// the registers before an instruction runs
+pub(crate) struct Registers {
+    v_registers: [u8; NUM_REGS],
+    i_register: u16,
+    delay_timer: u8,
+    sound_timer: u8,
+}
+
+impl Registers {
+    pub(crate) fn capture(cpu: &CPU) -> Self {
+        Self {
+            v_registers: cpu.v_registers,
+            i_register: cpu.i_register,
+            delay_timer: cpu.delay_timer,
+            sound_timer: cpu.sound_timer,
+        }
+    }
+
+    fn changes(&self, cpu: &CPU) -> Vec<RegisterChange> {
+        let v = (0..NUM_REGS).map(|x| {
+            (
+                Register::V(x as u8),
+                self.v_registers[x] as u16,
+                cpu.v_registers[x] as u16,
+            )
+        });
+        let others = [
+            (Register::I, self.i_register, cpu.i_register),
+            (
+                Register::DelayTimer,
+                self.delay_timer as u16,
+                cpu.delay_timer as u16,
+            ),
+            (
+                Register::SoundTimer,
+                self.sound_timer as u16,
+                cpu.sound_timer as u16,
+            ),
+        ];
+        v.chain(others)
+            .filter(|(_, old, new)| old != new)
+            .map(|(register, old, new)| RegisterChange { register, old, new })
+            .collect()
+    }
+}
+
+impl CPU {
+    /// Sends every executed instruction to `tracer`, or stops tracing with
+    /// `None`. Tracing slows the CPU down a lot, keep it for debugging.
+    pub fn set_tracer(&mut self, tracer: Option<Box<dyn TraceSink>>) {
+        self.tracer = tracer;
+    }
+
+    pub(crate) fn trace(
+        &mut self,
+        address: u16,
+        opcode: u16,
+        instruction: Instruction,
+        before: Registers,
+    ) {
+        let entry = TraceEntry {
+            address,
+            opcode,
+            instruction,
+            changes: before.changes(self),
+        };
+        if let Some(tracer) = &mut self.tracer {
+            tracer.trace(&entry);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+//! The two-page hi-res variant of CHIP-8: a 64x64 display for ROMs like
+//! Hires Astrowars, see [`CPU::set_two_page`].
+
+use crate::{CPU, START_ADDR};
+
+// where the first instruction of two-page ROMs jumps, machine code
+// switching the VIP to 64x64
+pub(crate) const ENTRY_JUMP: u16 = 0x260;
+// 1260, that first instruction
+const ENTRY_OPCODE: [u8; 2] = [0x12, 0x60];
+
+/// Where the CHIP-8 part of two-page ROMs starts, past the machine code
+/// their first instruction jumps to.
+pub const TWO_PAGE_START_ADDR: u16 = 0x2C0;
+
+/// Whether `rom` is for the two-page hi-res variant, which starts with 1260.
+pub fn is_two_page_rom(rom: &[u8]) -> bool {
+    rom.starts_with(&ENTRY_OPCODE)
+}
+
+impl CPU {
+    /// Shows the display 64x64, for ROMs of the two-page hi-res variant.
+    /// Their first instruction, 1260, jumps to [`TWO_PAGE_START_ADDR`]
+    /// instead of into machine code, and 0230 clears the display. Loading a
+    /// ROM starting with 1260 at 0x200 turns it on, see
+    /// [`is_two_page_rom`].
+    pub fn set_two_page(&mut self, on: bool) {
+        self.screen.set_two_page(on);
+    }
+
+    pub fn is_two_page(&self) -> bool {
+        self.screen.two_page
+    }
+
+    /// Whether jumping to `addr` is the first instruction of a two-page
+    /// ROM, with the program counter already past it.
+    pub(crate) fn is_two_page_entry(&self, addr: u16) -> bool {
+        self.screen.two_page && addr == ENTRY_JUMP && self.program_counter == START_ADDR + 2
+    }
+}
@@ -0,0 +1,39 @@
+//! Reads and writes counted by address.
+
+use chip8::asm::assemble;
+use chip8::{BoundsPolicy, Chip8Builder, CPU};
+
+fn tracked(source: &str) -> CPU {
+    let rom = assemble(source).unwrap();
+    let mut cpu = Chip8Builder::new()
+        .rom(&rom)
+        .bounds_policy(BoundsPolicy::Wrap)
+        .build()
+        .unwrap();
+    cpu.set_access_tracking(true);
+    cpu.run(source.lines().count()).unwrap();
+    cpu
+}
+
+#[test]
+fn stores_and_loads_are_counted() {
+    let cpu = tracked("LD I, 0x300\nLD [I], V1\nLD V1, [I]\nLD V0, [I]");
+    let access = cpu.memory_access().unwrap();
+    assert_eq!(access.writes()[0x300..=0x301], [1, 1]);
+    assert_eq!(access.reads()[0x300..=0x301], [2, 1]);
+    assert_eq!(access.reads().len(), 0x302);
+}
+
+#[test]
+fn wrapped_addresses_count_where_they_land() {
+    let cpu = tracked("LD I, 0xFFF\nLD [I], V1");
+    let writes = cpu.memory_access().unwrap().writes();
+    assert_eq!((writes[0xFFF], writes[0]), (1, 1));
+}
+
+#[test]
+fn fetches_are_not_counted() {
+    let cpu = tracked("LD V0, 1\nLD V1, 2");
+    let access = cpu.memory_access().unwrap();
+    assert!(access.reads().is_empty() && access.writes().is_empty());
+}
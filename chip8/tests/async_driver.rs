@@ -0,0 +1,73 @@
+//! The async driver awaits the input and sleeps between frames.
+
+use std::future::{self, Future};
+use std::ops::ControlFlow;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use chip8::asm::assemble;
+use chip8::frontend::{AsyncInput, Display, Driver};
+use chip8::{Chip8Builder, NUM_KEYS};
+
+// stops after three frames, holding key 5 in the second
+struct Scripted(usize);
+
+impl AsyncInput for Scripted {
+    fn poll(
+        &mut self,
+        keys: &mut [bool; NUM_KEYS],
+    ) -> impl Future<Output = ControlFlow<()>> + Send {
+        self.0 += 1;
+        keys[5] = self.0 == 2;
+        future::ready(if self.0 > 3 {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        })
+    }
+}
+
+#[derive(Default)]
+struct Frames(usize);
+
+impl Display for Frames {
+    fn draw(&mut self, _pixels: &[bool], _width: usize, _height: usize) {
+        self.0 += 1;
+    }
+}
+
+// every future here is ready at once
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[test]
+fn frames_run_until_the_input_breaks() {
+    // an instruction per frame, key 5 is held when SKNP runs
+    let rom = assemble("LD V0, 5\nSKNP V0\nADD V1, 1\nJP 0x202").unwrap();
+    let cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    let mut driver = Driver::new(cpu, Frames::default(), Scripted(0), ()).with_ticks_per_frame(1);
+    let mut sleeps = Vec::new();
+
+    block_on(driver.run_async(|duration: Duration| {
+        sleeps.push(duration);
+        future::ready(())
+    }))
+    .unwrap();
+
+    assert_eq!(driver.display_mut().0, 3);
+    assert_eq!(sleeps.len(), 3);
+    // the sleeps return at once, so the next frames are further away
+    for (frame, &sleep) in sleeps.iter().enumerate() {
+        assert!(sleep <= Duration::from_millis(17) * (frame as u32 + 1));
+    }
+    assert!(sleeps[2] > sleeps[0]);
+    assert_eq!(driver.cpu().state().v_registers[1], 1);
+}
@@ -0,0 +1,26 @@
+//! Buzzer samples follow the sound timer.
+
+use chip8::asm::assemble;
+use chip8::audio::AudioSource;
+use chip8::Chip8Builder;
+
+#[test]
+fn square_wave_while_the_sound_timer_counts_down() {
+    let rom = assemble("LD V0, 2\nLD ST, V0\nJP 0x204").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    // 4 samples per period
+    let mut source = AudioSource::new(1760).with_pitch(440.0).with_volume(0.5);
+    let mut samples = [1.0; 8];
+
+    source.fill(&cpu, &mut samples);
+    assert_eq!(samples, [0.0; 8]);
+
+    cpu.run(2).unwrap();
+    source.fill(&cpu, &mut samples);
+    assert_eq!(samples, [0.5, 0.5, -0.5, -0.5, 0.5, 0.5, -0.5, -0.5]);
+
+    cpu.tick_timers();
+    cpu.tick_timers();
+    source.fill(&cpu, &mut samples);
+    assert_eq!(samples, [0.0; 8]);
+}
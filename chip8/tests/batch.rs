@@ -0,0 +1,38 @@
+//! Summaries of instruction batches.
+
+use chip8::asm::assemble;
+use chip8::Chip8Builder;
+
+#[test]
+fn run_reports_drawing_and_waiting_for_a_key() {
+    let rom = assemble("LD F, V0\nDRW V0, V0, 5\nLD V1, K\nJP 0x206").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+
+    let summary = cpu.run(2).unwrap();
+    assert_eq!(summary.instructions, 2);
+    assert!(summary.display_changed);
+    assert!(!summary.waiting_for_key);
+
+    let summary = cpu.run(1).unwrap();
+    assert!(!summary.display_changed);
+    assert!(summary.waiting_for_key);
+
+    let summary = cpu.run(10).unwrap();
+    assert_eq!(summary.instructions, 10);
+    assert!(!summary.display_changed);
+    assert!(summary.waiting_for_key);
+
+    cpu.keypress(5, true);
+    cpu.run(1).unwrap();
+    cpu.keypress(5, false);
+    let summary = cpu.run(1).unwrap();
+    assert!(!summary.waiting_for_key);
+}
+
+#[test]
+fn run_frame_runs_a_sixtieth_of_the_speed_given() {
+    let rom = assemble("ADD V0, 1\nJP 0x200").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    assert_eq!(cpu.run_frame(600).unwrap().instructions, 10);
+    assert_eq!(cpu.run_frame(30).unwrap().instructions, 1);
+}
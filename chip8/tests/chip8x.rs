@@ -0,0 +1,66 @@
+//! CHIP-8X programs: color zones, the background and the second keypad.
+
+use chip8::asm::assemble;
+use chip8::screen::{Palette, CHIP8X_BACKGROUNDS, CHIP8X_COLORS};
+use chip8::{Chip8Builder, SaveState, CHIP8X_START_ADDR};
+
+// colors the first two zones of the top 4 rows green, switches to a black
+// background and draws a 0 at (0, 0) and (16, 0)
+const COLORS: &str = "
+    LD V0, 0x10
+    LD V1, 0x00
+    LD V2, 4
+    COL V0, V2
+    BGCOL
+    LD V3, 0
+    LD F, V3
+    DRW V3, V3, 5
+    LD V4, 16
+    DRW V4, V3, 5
+";
+
+#[test]
+fn lit_pixels_take_the_color_of_their_zone() {
+    let rom = assemble(COLORS).unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).chip8x().build().unwrap();
+    assert_eq!(cpu.state().program_counter, CHIP8X_START_ADDR);
+    cpu.run(10).unwrap();
+
+    let frame = cpu.frame_buffer();
+    let palette = Palette::default();
+    let (red, green, black) = (CHIP8X_COLORS[1], CHIP8X_COLORS[4], CHIP8X_BACKGROUNDS[1]);
+    assert_eq!(frame.rgb(0, 0, &palette), green);
+    assert_eq!(frame.rgb(0, 4, &palette), red);
+    assert_eq!(frame.rgb(5, 0, &palette), black);
+    assert_eq!(frame.rgb(16, 0, &palette), red);
+}
+
+#[test]
+fn nibbles_add_apart_and_the_second_keypad_skips() {
+    let rom =
+        assemble("LD V0, 0x37\nLD V1, 0x25\nADDN V0, V1\nLD V2, 3\nSKP2 V2\nLD V5, 1").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).chip8x().build().unwrap();
+    cpu.keypress2(3, true);
+    cpu.run(6).unwrap();
+    let registers = cpu.state().v_registers;
+    assert_eq!(registers[0], 0x54);
+    assert_eq!(registers[5], 0);
+}
+
+#[test]
+fn save_states_keep_the_colors() {
+    let rom = assemble(COLORS).unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).chip8x().build().unwrap();
+    cpu.run(10).unwrap();
+    let state = SaveState::from_bytes(&cpu.save_state()).unwrap();
+    assert_eq!(state.chip8x.as_ref().unwrap().background, 1);
+
+    let mut restored = Chip8Builder::new().rom(&rom).build().unwrap();
+    restored.restore(&state).unwrap();
+    assert!(restored.is_chip8x());
+    assert_eq!(
+        restored.frame_buffer().rgb(0, 0, &Palette::default()),
+        CHIP8X_COLORS[4]
+    );
+    assert_eq!(restored.snapshot(), state);
+}
@@ -0,0 +1,42 @@
+//! The bytes of a ROM executed, and the ones never reached.
+
+use chip8::asm::assemble;
+use chip8::{Chip8Builder, CPU, START_ADDR};
+
+// skips the instruction at 0x204, then loops over the data at 0x208
+fn covered() -> (CPU, usize) {
+    let rom = assemble("LD V0, 1\nSE V0, 1\nLD V1, 2\nJP 0x206\nDB 0xF0, 0x90").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    cpu.set_coverage(true);
+    cpu.run(10).unwrap();
+    (cpu, rom.len())
+}
+
+#[test]
+fn executed_bytes_are_recorded() {
+    let (cpu, _) = covered();
+    let coverage = cpu.coverage().unwrap();
+    assert!(coverage.is_executed(0x200) && coverage.is_executed(0x201));
+    assert!(!coverage.is_executed(0x204));
+    assert!(coverage.is_executed(0x207));
+    assert_eq!(coverage.bitmap().len(), 0x208);
+}
+
+#[test]
+fn gaps_are_the_bytes_never_executed() {
+    let (cpu, len) = covered();
+    let start = START_ADDR as usize;
+    let coverage = cpu.coverage().unwrap();
+    assert_eq!(
+        coverage.gaps(start..start + len),
+        [0x204..0x206, 0x208..0x20A]
+    );
+    assert_eq!(coverage.share(start..start + len), 0.6);
+}
+
+#[test]
+fn turning_coverage_off_forgets_it() {
+    let (mut cpu, _) = covered();
+    cpu.set_coverage(false);
+    assert!(cpu.coverage().is_none());
+}
@@ -0,0 +1,38 @@
+//! Breakpoints and single stepping.
+
+use chip8::asm::assemble;
+use chip8::debugger::{Debugger, TickResult};
+use chip8::Chip8Builder;
+
+fn debugger() -> Debugger {
+    let rom = assemble("LD V0, 1\nLD V1, 2\nLD V2, 3\nJP 0x206").unwrap();
+    Debugger::new(Chip8Builder::new().rom(&rom).build().unwrap())
+}
+
+#[test]
+fn breakpoints_stop_before_the_instruction() {
+    let mut debugger = debugger();
+    debugger.add_breakpoint(0x204);
+    assert_eq!(debugger.tick(), Ok(TickResult::Executed));
+    assert_eq!(debugger.tick(), Ok(TickResult::Executed));
+    assert_eq!(debugger.tick(), Ok(TickResult::Breakpoint(0x204)));
+    assert!(debugger.is_paused());
+    assert_eq!(debugger.v_registers()[2], 0);
+    assert_eq!(debugger.tick(), Ok(TickResult::Paused));
+
+    debugger.resume();
+    assert_eq!(debugger.tick(), Ok(TickResult::Executed));
+    assert_eq!(debugger.v_registers()[2], 3);
+}
+
+#[test]
+fn stepping_runs_one_instruction_and_pauses() {
+    let mut debugger = debugger();
+    debugger.add_breakpoint(0x200);
+    debugger.step().unwrap();
+    assert!(debugger.is_paused());
+    assert_eq!(debugger.program_counter(), 0x202);
+    assert_eq!(debugger.v_registers()[..2], [1, 0]);
+    debugger.step().unwrap();
+    assert_eq!(debugger.v_registers()[..2], [1, 2]);
+}
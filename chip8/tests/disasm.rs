@@ -0,0 +1,47 @@
+//! Listings following the program, with labels and data apart from code.
+
+use chip8::asm::assemble;
+use chip8::disasm::{annotate_rom, Entry};
+use chip8::START_ADDR;
+
+const PROGRAM: &str = "
+    LD I, sprite
+    CALL draw
+    JP 0x204
+draw:
+    DRW V0, V0, 3
+    RET
+sprite:
+    DB 0xF0, 0x90, 0xF0
+";
+
+#[test]
+fn labels_code_and_data() {
+    let rom = assemble(PROGRAM).unwrap();
+    let listing = annotate_rom(&rom, START_ADDR);
+    let labels: Vec<_> = listing
+        .entries
+        .iter()
+        .filter_map(|entry| match entry {
+            Entry::Label { address, .. } => Some(*address),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(labels, [0x204, 0x206, 0x20A]);
+    assert!(matches!(
+        listing.entries.last(),
+        Some(Entry::Data { address: 0x20A, bytes }) if bytes == &[0xF0, 0x90, 0xF0]
+    ));
+
+    let text = listing.to_string();
+    assert!(
+        text.contains("    CALL L_0206             ; 0x202: 2206\n"),
+        "{text}"
+    );
+    assert!(text.contains("L_0204:\n    JP L_0204"), "{text}");
+    assert!(
+        text.contains("    DB 0xF0, 0x90, 0xF0     ; 0x20A\n"),
+        "{text}"
+    );
+    assert_eq!(assemble(&text).unwrap(), rom);
+}
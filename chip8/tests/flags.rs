@@ -0,0 +1,34 @@
+//! SUPER-CHIP user flags.
+
+use std::sync::{Arc, Mutex};
+
+use chip8::asm::assemble;
+use chip8::debugger::Debugger;
+use chip8::{Chip8Builder, NUM_FLAGS};
+
+#[test]
+fn stored_flags_are_loaded_back_and_reach_the_sink() {
+    let rom = assemble("LD V0, 7\nLD V1, 9\nLD R, V1\nLD V0, 0\nLD V1, 0\nLD V1, R").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    let stored = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&stored);
+    cpu.set_flags_sink(Some(Box::new(move |flags: &[u8; NUM_FLAGS]| {
+        sink.lock().unwrap().push(*flags)
+    })));
+
+    cpu.run(6).unwrap();
+    assert_eq!(cpu.flags()[..3], [7, 9, 0]);
+    assert_eq!(Debugger::new(cpu).v_registers()[..2], [7, 9]);
+    let stored = stored.lock().unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0][..3], [7, 9, 0]);
+}
+
+#[test]
+fn flags_from_the_last_session_are_loaded() {
+    let rom = assemble("LD V2, R").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    cpu.set_flags(&[3, 4, 5, 6]);
+    cpu.run(1).unwrap();
+    assert_eq!(Debugger::new(cpu).v_registers()[..4], [3, 4, 5, 0]);
+}
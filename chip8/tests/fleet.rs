@@ -0,0 +1,28 @@
+//! Machines of a fleet run apart, on several threads.
+
+use chip8::asm::assemble;
+use chip8::fleet::Fleet;
+use chip8::Quirks;
+
+#[test]
+fn machines_run_with_their_own_settings() {
+    // V0 counts instructions until the shift quirk sets V1
+    let rom = assemble("LD V2, 4\nSHR V1, V2\nADD V0, 1\nJP 0x204").unwrap();
+    let mut fleet = Fleet::new(rom).with_threads(3);
+    for n in 0..8 {
+        let quirks = if n % 2 == 0 {
+            Quirks::VIP
+        } else {
+            Quirks::SUPER_CHIP
+        };
+        assert_eq!(fleet.spawn(|builder| builder.quirks(quirks)).unwrap(), n);
+    }
+
+    let results = fleet.run_frames(2);
+    assert_eq!(results.len(), 8);
+    assert!(results.iter().all(Result::is_ok));
+    let shifted = fleet.for_each(|n, cpu| (n, cpu.state().v_registers[1]));
+    for (n, v1) in shifted {
+        assert_eq!(v1, if n % 2 == 0 { 2 } else { 0 }, "machine {n}");
+    }
+}
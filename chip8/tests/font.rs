@@ -0,0 +1,63 @@
+//! Fonts replacing the built-in hex digit sprites.
+
+use chip8::asm::assemble;
+use chip8::{Chip8Builder, Font, CPU, FONTSET_SIZE};
+
+// every digit a full 4x5 block but 1, a single column
+fn font() -> [u8; FONTSET_SIZE] {
+    let mut font = [0xF0; FONTSET_SIZE];
+    font[5..10].fill(0x80);
+    font
+}
+
+// draws digit `digit` at the top left corner
+fn draw_digit(cpu: &mut CPU, digit: u8) {
+    let source = format!("LD V0, {digit}\nLD V1, 0\nLD F, V0\nDRW V1, V1, 5");
+    cpu.load(&assemble(&source).unwrap()).unwrap();
+    cpu.run(4).unwrap();
+}
+
+#[test]
+fn builder_font_is_drawn_by_fx29() {
+    let mut cpu = Chip8Builder::new().font(&font()).build().unwrap();
+    draw_digit(&mut cpu, 1);
+    let frame = cpu.frame_buffer();
+    assert!((0..5).all(|y| frame.pixel(0, y)));
+    assert!((0..5).all(|y| !frame.pixel(1, y)));
+}
+
+#[test]
+fn set_font_replaces_the_default_one() {
+    let mut cpu = CPU::default();
+    cpu.set_font(&font());
+    draw_digit(&mut cpu, 0);
+    // the default 0 is hollow
+    assert!(cpu.frame_buffer().pixel(1, 2));
+}
+
+#[test]
+fn fonts_are_found_by_name() {
+    for font in Font::ALL {
+        assert_eq!(Font::from_name(font.name()), Some(font));
+    }
+    assert_eq!(Font::from_name("VIP"), Some(Font::Vip));
+    assert_eq!(Font::from_name("cosmac"), None);
+}
+
+#[test]
+fn modern_font_is_the_default_one() {
+    let cpu = CPU::default();
+    assert_eq!(cpu.read_memory(0..FONTSET_SIZE), Font::Modern.glyphs());
+}
+
+#[test]
+fn dream_6800_digits_are_3_pixels_wide() {
+    let mut cpu = Chip8Builder::new()
+        .font(Font::Dream6800.glyphs())
+        .build()
+        .unwrap();
+    draw_digit(&mut cpu, 0);
+    let frame = cpu.frame_buffer();
+    assert!(frame.pixel(2, 0));
+    assert!(!frame.pixel(3, 0));
+}
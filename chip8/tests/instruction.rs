@@ -0,0 +1,15 @@
+//! Every opcode decodes to an instruction that prints as the mnemonic the
+//! assembler turns back into the same opcode.
+
+use chip8::asm::assemble;
+use chip8::Instruction;
+
+#[test]
+fn decode_round_trips_through_the_assembler() {
+    for op in 0..=u16::MAX {
+        let instruction = Instruction::decode(op);
+        let rom = assemble(&instruction.to_string())
+            .unwrap_or_else(|err| panic!("{op:04X} printed as `{instruction}`: {err}"));
+        assert_eq!(rom, op.to_be_bytes(), "{op:04X} printed as `{instruction}`");
+    }
+}
@@ -0,0 +1,96 @@
+//! Running translated blocks, against the interpreter.
+#![cfg(feature = "jit")]
+
+use chip8::asm::assemble;
+use chip8::{Chip8Builder, Quirks, Timing, CPU};
+
+const ALU_LOOP: &str = "
+LD V0, 0
+LD V1, 0xF0
+LD I, 0x300
+loop:
+ADD V0, 7
+LD V2, V0
+OR V2, V1
+AND V2, V0
+XOR V3, V2
+ADD V3, V1
+SUB V4, V0
+SUBN V5, V1
+SHR V6, V3
+SHL V7, V0
+ADD I, V0
+SE V0, 0x23
+SNE V1, V2
+SE V3, V4
+SNE V5, V6
+JP loop
+JP loop
+";
+
+fn run(source: &str, quirks: Quirks, timing: Timing, jit: bool, instructions: usize) -> CPU {
+    let rom = assemble(source).unwrap();
+    let mut cpu = Chip8Builder::new()
+        .rom(&rom)
+        .quirks(quirks)
+        .seed(0)
+        .build()
+        .unwrap();
+    cpu.set_timing(timing);
+    cpu.set_jit(jit);
+    assert_eq!(cpu.is_jit(), jit);
+    cpu.run(instructions).unwrap();
+    cpu
+}
+
+#[test]
+fn blocks_compute_what_the_interpreter_does() {
+    for quirks in [Quirks::VIP, Quirks::SUPER_CHIP] {
+        for timing in [Timing::Fixed, Timing::CosmacVip] {
+            let interpreted = run(ALU_LOOP, quirks, timing, false, 10_000);
+            let translated = run(ALU_LOOP, quirks, timing, true, 10_000);
+            assert_eq!(translated.state(), interpreted.state());
+            assert_eq!(translated.state_hash(), interpreted.state_hash());
+            assert_eq!(translated.cycles(), interpreted.cycles());
+        }
+    }
+}
+
+#[test]
+fn a_batch_runs_exactly_the_instructions_asked_for() {
+    let rom = assemble(ALU_LOOP).unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    cpu.set_jit(true);
+    for instructions in [1, 7, 16, 100] {
+        assert_eq!(cpu.run(instructions).unwrap().instructions, instructions);
+    }
+}
+
+#[test]
+fn code_the_program_writes_over_is_translated_again() {
+    // the third time round 0x200 becomes ADD V0, 0x20
+    let source = "
+ADD V0, 1
+SE V0, 3
+JP 0x200
+LD V0, 0x20
+LD I, 0x201
+LD [I], V0
+JP 0x200
+";
+    let interpreted = run(source, Quirks::default(), Timing::Fixed, false, 15);
+    let translated = run(source, Quirks::default(), Timing::Fixed, true, 15);
+    assert_eq!(interpreted.state().v_registers[0], 0x40);
+    assert_eq!(translated.state(), interpreted.state());
+}
+
+#[test]
+fn code_poked_while_paused_is_translated_again() {
+    let rom = assemble("ADD V0, 1\nJP 0x200").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    cpu.set_jit(true);
+    cpu.run(10).unwrap();
+    cpu.write_memory(0x201, &[0x10]).unwrap();
+    cpu.run(2).unwrap();
+    assert_eq!(cpu.state().v_registers[0], 5 + 0x10);
+}
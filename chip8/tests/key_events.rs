@@ -0,0 +1,98 @@
+//! Key events pushed in one frame reach the program in order.
+
+use chip8::asm::assemble;
+use chip8::{Chip8Builder, CPU};
+
+#[test]
+fn a_tap_within_a_frame_ends_the_key_wait() {
+    let rom = assemble("LD V0, K\nLD V1, 1").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    cpu.push_key_event(7, true);
+    cpu.push_key_event(7, false);
+    cpu.run(3).unwrap();
+    let registers = cpu.state().v_registers;
+    assert_eq!((registers[0], registers[1]), (7, 1));
+}
+
+#[test]
+fn a_polling_loop_sees_a_tap() {
+    let rom = assemble("LD V0, 5\nSKP V0\nJP 0x202\nLD V1, 1").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    cpu.run(1).unwrap();
+    cpu.push_key_event(5, true);
+    cpu.push_key_event(5, false);
+    cpu.run(2).unwrap();
+    assert_eq!(cpu.state().v_registers[1], 1);
+    assert!(!cpu.snapshot().keys[5]);
+}
+
+#[test]
+fn repeated_events_dont_delay_the_next_change() {
+    let rom = assemble("LD V0, 5\nSKP V0\nJP 0x202\nLD V1, 1").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    cpu.keypress(5, true);
+    cpu.push_key_event(5, true);
+    cpu.push_key_event(5, false);
+    cpu.push_key_event(16, true);
+    cpu.run(2).unwrap();
+    assert!(!cpu.snapshot().keys[5]);
+    assert_eq!(cpu.state().v_registers[1], 0);
+}
+
+#[test]
+fn keys_show_what_is_held() {
+    let mut cpu = Chip8Builder::new().build().unwrap();
+    cpu.keypress(0xA, true);
+    assert!(cpu.is_key_pressed(0xA));
+    assert!(!cpu.is_key_pressed(0xB));
+    assert!(!cpu.is_key_pressed(16));
+    assert_eq!(cpu.keys().iter().filter(|&&held| held).count(), 1);
+}
+
+#[test]
+fn queued_events_show_once_seen() {
+    let rom = assemble("LD V0, 1\nLD V0, 2").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    cpu.push_key_event(3, true);
+    assert!(!cpu.is_key_pressed(3));
+    cpu.run(1).unwrap();
+    assert!(cpu.is_key_pressed(3));
+}
+
+#[test]
+fn latched_keys_change_at_the_next_frame() {
+    let rom = assemble("LD V0, 5\nSKP V0\nJP 0x202\nLD V1, 1").unwrap();
+    let mut cpu = Chip8Builder::new()
+        .rom(&rom)
+        .latched_input()
+        .build()
+        .unwrap();
+    cpu.keypress(5, true);
+    cpu.run(10).unwrap();
+    assert_eq!(cpu.state().v_registers[1], 0);
+    cpu.tick_timers();
+    assert!(cpu.is_key_pressed(5));
+    cpu.run(3).unwrap();
+    assert_eq!(cpu.state().v_registers[1], 1);
+}
+
+#[test]
+fn latched_events_change_a_key_per_frame() {
+    let mut cpu = Chip8Builder::new().latched_input().build().unwrap();
+    cpu.push_key_event(2, true);
+    cpu.push_key_event(2, false);
+    cpu.tick_timers();
+    assert!(cpu.is_key_pressed(2));
+    cpu.tick_timers();
+    assert!(!cpu.is_key_pressed(2));
+}
+
+#[test]
+fn turning_latching_off_applies_the_latched_keys() {
+    let mut cpu = CPU::default();
+    cpu.set_latched_input(true);
+    cpu.keypress(9, true);
+    assert!(!cpu.is_key_pressed(9));
+    cpu.set_latched_input(false);
+    assert!(cpu.is_key_pressed(9));
+}
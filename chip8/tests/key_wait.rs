@@ -0,0 +1,61 @@
+//! Frontends see and answer FX0A waiting for a key.
+
+use chip8::asm::assemble;
+use chip8::{Chip8Builder, Chip8Error, KeyWait, CPU};
+
+fn waiting_cpu() -> CPU {
+    let rom = assemble("LD V0, K\nLD V1, 1").unwrap();
+    Chip8Builder::new().rom(&rom).build().unwrap()
+}
+
+#[test]
+fn the_wait_is_seen_until_the_key_is_released() {
+    let mut cpu = waiting_cpu();
+    assert_eq!(cpu.waiting_for_key(), None);
+    cpu.run(2).unwrap();
+    assert_eq!(cpu.waiting_for_key(), Some(KeyWait::Press));
+    cpu.keypress(4, true);
+    cpu.run(1).unwrap();
+    assert_eq!(cpu.waiting_for_key(), Some(KeyWait::Release(4)));
+    cpu.keypress(4, false);
+    cpu.run(1).unwrap();
+    assert_eq!(cpu.waiting_for_key(), None);
+    assert_eq!(cpu.state().v_registers[0], 4);
+}
+
+#[test]
+fn a_hook_answers_the_wait() {
+    let mut cpu = waiting_cpu();
+    let mut asked = 0;
+    cpu.set_key_wait_hook(Some(Box::new(move |wait| {
+        assert_eq!(wait, KeyWait::Press);
+        asked += 1;
+        (asked == 3).then_some(0xC)
+    })));
+    cpu.run(4).unwrap();
+    let registers = cpu.state().v_registers;
+    assert_eq!((registers[0], registers[1]), (0xC, 1));
+}
+
+#[test]
+fn a_hook_answering_no_key_is_an_error() {
+    let mut cpu = waiting_cpu();
+    cpu.set_key_wait_hook(Some(Box::new(|_| Some(16))));
+    assert!(matches!(cpu.tick(), Err(Chip8Error::InvalidKey(16))));
+}
+
+#[test]
+fn fx0a_finishes_when_the_first_key_is_released() {
+    let mut cpu = waiting_cpu();
+    cpu.keypress(4, true);
+    cpu.run(10).unwrap();
+    assert_eq!(cpu.state().program_counter, 0x200);
+    cpu.keypress(6, true);
+    cpu.keypress(6, false);
+    cpu.run(10).unwrap();
+    assert_eq!(cpu.state().program_counter, 0x200);
+    cpu.keypress(4, false);
+    cpu.run(1).unwrap();
+    assert_eq!(cpu.state().program_counter, 0x202);
+    assert_eq!(cpu.state().v_registers[0], 4);
+}
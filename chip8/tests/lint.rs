@@ -0,0 +1,39 @@
+//! ROM mistakes found without running them.
+
+use chip8::asm::assemble;
+use chip8::lint::{lint, Warning};
+use chip8::START_ADDR;
+
+#[test]
+fn a_clean_rom_has_no_warnings() {
+    let rom = assemble("CALL draw\nloop:\nJP loop\ndraw:\nSE V0, 1\nCLS\nRET").unwrap();
+    assert_eq!(lint(&rom, START_ADDR), []);
+}
+
+#[test]
+fn reachable_mistakes_are_reported_in_order() {
+    // 0x202 jumps into the middle of 0x208, whose path reaches a bad
+    // opcode, and the other path runs off the end
+    let mut rom = assemble("SE V0, 1\nJP 0x209\nCALL 0x800\nLD V0, 0\nLD V0, 0").unwrap();
+    rom.extend([0xE0, 0x00]);
+    let warnings = lint(&rom, START_ADDR);
+    assert_eq!(
+        warnings,
+        [
+            Warning::OutOfRom {
+                address: 0x204,
+                target: 0x800
+            },
+            Warning::Overlap { address: 0x209 },
+            Warning::InvalidOpcode {
+                address: 0x20A,
+                opcode: 0xE000
+            },
+            Warning::RunsOffEnd { address: 0x20B },
+        ]
+    );
+    assert_eq!(
+        warnings[0].to_string(),
+        "0x204: jumps to 0x800, outside of the ROM"
+    );
+}
@@ -0,0 +1,43 @@
+//! Loading a ROM, and the ROMs refused before touching the memory.
+
+use chip8::{Chip8Builder, RomError, CPU, MAX_ROM_SIZE, START_ADDR};
+
+#[test]
+fn an_empty_rom_is_refused() {
+    let mut cpu = CPU::default();
+    assert_eq!(cpu.load(&[]), Err(RomError::Empty));
+    assert_eq!(cpu.read_memory(0x200..0x202), [0, 0]);
+}
+
+#[test]
+fn a_rom_past_the_end_of_the_ram_is_refused() {
+    let rom = vec![0x12; MAX_ROM_SIZE + 1];
+    let mut cpu = CPU::default();
+    assert_eq!(
+        cpu.load(&rom),
+        Err(RomError::TooLarge {
+            len: MAX_ROM_SIZE + 1,
+            max: MAX_ROM_SIZE
+        })
+    );
+    assert_eq!(cpu.read_memory(0x200..0x202), [0, 0]);
+    assert_eq!(
+        cpu.read_memory(..).len(),
+        START_ADDR as usize + MAX_ROM_SIZE
+    );
+
+    // the largest that fits loads
+    cpu.load(&rom[..MAX_ROM_SIZE]).unwrap();
+    assert_eq!(cpu.read_memory(0xFFF..), [0x12]);
+}
+
+#[test]
+fn a_blank_rom_is_refused() {
+    let mut cpu = CPU::default();
+    assert_eq!(cpu.load(&[0x00; 64]), Err(RomError::Blank));
+    assert_eq!(
+        Chip8Builder::new().rom(&[0xFF; 64]).build().unwrap_err(),
+        RomError::Blank
+    );
+    assert_eq!(cpu.read_memory(0x200..0x202), [0, 0]);
+}
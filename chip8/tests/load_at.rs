@@ -0,0 +1,24 @@
+//! Copying data into the RAM with load_at.
+
+use chip8::{Chip8Builder, RomError, MEMORY_SIZE};
+
+#[test]
+fn data_is_copied_to_the_address() {
+    let mut cpu = Chip8Builder::new().build().unwrap();
+    cpu.load_at(0x300, &[1, 2, 3]).unwrap();
+    assert_eq!(cpu.read_memory(0x300..0x303), [1, 2, 3]);
+    cpu.load_at(MEMORY_SIZE as u16, &[]).unwrap();
+}
+
+#[test]
+fn nothing_fits_past_the_end() {
+    let mut cpu = Chip8Builder::new().build().unwrap();
+    assert_eq!(
+        cpu.load_at(0xFFE, &[1, 2, 3]),
+        Err(RomError::TooLarge { len: 3, max: 2 })
+    );
+    assert_eq!(
+        cpu.load_at(0x2000, &[]),
+        Err(RomError::TooLarge { len: 0, max: 0 })
+    );
+}
@@ -0,0 +1,140 @@
+//! MegaChip programs: the color display, the 24 bit I and save states.
+
+use chip8::asm::assemble;
+use chip8::audio::AudioSource;
+use chip8::screen::Palette;
+use chip8::{Chip8Builder, Chip8Error, RomError, SaveState, CPU, MAX_ROM_SIZE, MEMORY_SIZE};
+
+// turns MegaChip on, loads color 1 from 0x21A and draws the 2x1 sprite at
+// 0x21E at (3, 4), then shows it with CLS
+const DRAW: &str = "
+    MEGAON
+    LDHI 0
+    DW 0x021A
+    LDPAL 1
+    LDHI 0
+    DW 0x021E
+    SPRW 2
+    SPRH 1
+    LD V0, 3
+    LD V1, 4
+    DRW V0, V1, 0
+    CLS
+    JP 0x218
+    DB 0xFF, 0x10, 0x20, 0x30
+    DB 1, 1
+";
+
+#[test]
+fn sprites_show_on_the_next_clear() {
+    let rom = assemble(DRAW).unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    let palette = Palette::default();
+
+    // LDHI and the address after it are one instruction
+    cpu.run(9).unwrap();
+    assert!(cpu.is_megachip());
+    assert_eq!(cpu.display_size(), (256, 192));
+    // drawn on the back buffer, not shown yet
+    assert_eq!(cpu.frame_buffer().rgb(3, 4, &palette), [0, 0, 0]);
+
+    cpu.run(1).unwrap();
+    let frame = cpu.frame_buffer();
+    assert_eq!(frame.rgb(3, 4, &palette), [0x10, 0x20, 0x30]);
+    assert_eq!(frame.rgb(4, 4, &palette), [0x10, 0x20, 0x30]);
+    assert_eq!(frame.rgb(5, 4, &palette), [0, 0, 0]);
+    assert!(cpu.get_display()[4 * 256 + 3]);
+}
+
+#[test]
+fn drawing_over_the_collision_color_sets_vf() {
+    // in place of CLS and the loop, so the data stays where it was
+    let again = "CCOL 1\n    DRW V0, V1, 0";
+    let rom = assemble(&DRAW.replace("CLS\n    JP 0x218", again)).unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    cpu.run(9).unwrap();
+    assert_eq!(cpu.state().v_registers[0xF], 0);
+    cpu.run(2).unwrap();
+    assert_eq!(cpu.state().v_registers[0xF], 1);
+}
+
+#[test]
+fn ldhi_reaches_past_64k() {
+    let mut rom = assemble("MEGAON\nLDHI 1\nDW 0x0000\nLD V0, [I]").unwrap();
+    rom.resize(0x10000 - 0x200, 0);
+    rom.push(0x42);
+    let mut cpu = Chip8Builder::new().rom(&rom).megachip().build().unwrap();
+    cpu.run(3).unwrap();
+    assert_eq!(cpu.state().v_registers[0], 0x42);
+    assert_eq!(cpu.read_memory(0x10000..), [0x42]);
+}
+
+#[test]
+fn only_megachip_roms_grow_the_ram() {
+    let mut rom = assemble("LD V0, 1").unwrap();
+    rom.resize(MAX_ROM_SIZE + 1, 0);
+    let err = Chip8Builder::new().rom(&rom).build().unwrap_err();
+    assert_eq!(
+        err,
+        RomError::TooLarge {
+            len: MAX_ROM_SIZE + 1,
+            max: MAX_ROM_SIZE
+        }
+    );
+
+    let mut cpu = CPU::default();
+    assert_eq!(cpu.load(&rom), Err(err));
+    assert_eq!(cpu.read_memory(..).len(), MEMORY_SIZE);
+    cpu.load_megachip(&rom).unwrap();
+    assert_eq!(cpu.read_memory(..).len(), MEMORY_SIZE + 1);
+}
+
+#[test]
+fn digitized_sound_is_mixed_in() {
+    let mut rom =
+        assemble("MEGAON\nLDHI 0\nDW 0x0208\nDIGISND 1\nDB 0x00, 0x04, 0x00, 0x00, 0x02").unwrap();
+    rom.extend([255, 128]);
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    let mut source = AudioSource::new(4).with_volume(0.5);
+    let mut samples = [1.0; 3];
+
+    cpu.run(3).unwrap();
+    let sound = cpu.digitized_sound().unwrap();
+    assert_eq!((sound.sample_rate, sound.samples), (4, &[255, 128][..]));
+    source.fill(&cpu, &mut samples);
+    assert_eq!(samples, [127.0 / 256.0, 0.0, 0.0]);
+}
+
+#[test]
+fn save_states_keep_the_color_display() {
+    let rom = assemble(DRAW).unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    cpu.run(10).unwrap();
+    let state = SaveState::from_bytes(&cpu.save_state()).unwrap();
+    assert!(state.megachip.is_some());
+
+    let mut restored = Chip8Builder::new().rom(&rom).build().unwrap();
+    restored.restore(&state).unwrap();
+    assert!(restored.is_megachip());
+    let palette = Palette::default();
+    assert_eq!(
+        restored.frame_buffer().rgb(3, 4, &palette),
+        [0x10, 0x20, 0x30]
+    );
+    assert_eq!(restored.snapshot(), state);
+}
+
+#[test]
+fn megachip_opcodes_are_machine_code_calls_outside_its_mode() {
+    let rom = assemble("LD I, 0x300\nDW 0x0123\nDW 0x0000").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    cpu.run(1).unwrap();
+    assert_eq!(
+        cpu.tick(),
+        Err(Chip8Error::UnknownOpcode {
+            opcode: 0x0123,
+            address: 0x202
+        })
+    );
+    assert_eq!(cpu.state().i_register, 0x300);
+}
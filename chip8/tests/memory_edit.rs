@@ -0,0 +1,27 @@
+//! Poking the RAM of a paused machine.
+
+use chip8::asm::assemble;
+use chip8::debugger::Debugger;
+use chip8::{Chip8Builder, Chip8Error, MEMORY_SIZE};
+
+#[test]
+fn poked_code_runs() {
+    let rom = assemble("LD V0, 1\nLD V1, 2").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    cpu.run(1).unwrap();
+    // LD V1, 2 becomes LD V1, 9
+    cpu.write_memory(0x203, &[9]).unwrap();
+    cpu.run(1).unwrap();
+    assert_eq!(cpu.state().v_registers[1], 9);
+}
+
+#[test]
+fn writes_past_the_end_change_nothing() {
+    let rom = assemble("LD V0, 1").unwrap();
+    let mut debugger = Debugger::new(Chip8Builder::new().rom(&rom).build().unwrap());
+    let err = debugger.write_memory(MEMORY_SIZE - 1, &[1, 2]).unwrap_err();
+    assert_eq!(err, Chip8Error::AddressOutOfBounds(MEMORY_SIZE));
+    assert_eq!(debugger.memory(MEMORY_SIZE - 1..MEMORY_SIZE), [0]);
+    debugger.write_memory(MEMORY_SIZE - 1, &[1]).unwrap();
+    assert_eq!(debugger.memory(MEMORY_SIZE - 1..MEMORY_SIZE), [1]);
+}
@@ -0,0 +1,69 @@
+//! Embedders run the opcodes the interpreter doesn't know.
+
+use chip8::asm::assemble;
+use chip8::{Chip8Builder, Chip8Error, CPU};
+
+// FX99, not a known instruction, doubles Vx
+fn cpu() -> CPU {
+    let rom = assemble("LD V3, 21\nDW 0xF399\nLD V4, 1").unwrap();
+    Chip8Builder::new().rom(&rom).build().unwrap()
+}
+
+fn double(cpu: &mut CPU, opcode: u16) -> Result<(), Chip8Error> {
+    if opcode & 0xF0FF != 0xF099 {
+        let address = cpu.state().program_counter - 2;
+        return Err(Chip8Error::UnknownOpcode { opcode, address });
+    }
+    let x = (opcode >> 8 & 0xF) as usize;
+    let value = cpu.state().v_registers[x];
+    cpu.set_v_register(x, value * 2);
+    Ok(())
+}
+
+#[test]
+fn unknown_opcodes_stop_the_program_without_a_handler() {
+    let mut cpu = cpu();
+    cpu.tick().unwrap();
+    let err = cpu.tick().unwrap_err();
+    assert_eq!(
+        err,
+        Chip8Error::UnknownOpcode {
+            opcode: 0xF399,
+            address: 0x202
+        }
+    );
+    assert_eq!(cpu.state().program_counter, 0x202);
+}
+
+#[test]
+fn a_handler_runs_unknown_opcodes() {
+    let mut cpu = cpu();
+    cpu.set_opcode_handler(Some(Box::new(double)));
+    cpu.run(3).unwrap();
+    let registers = cpu.state().v_registers;
+    assert_eq!((registers[3], registers[4]), (42, 1));
+}
+
+#[test]
+fn a_handler_can_refuse_opcodes() {
+    let rom = assemble("DW 0xF398").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    cpu.set_opcode_handler(Some(Box::new(double)));
+    assert!(matches!(
+        cpu.tick(),
+        Err(Chip8Error::UnknownOpcode { opcode: 0xF398, .. })
+    ));
+}
+
+#[test]
+fn a_handler_runs_megachip_opcodes_outside_its_mode() {
+    // 0x03NN sets the sprite width in MegaChip mode, here it sets V0
+    let rom = assemble("DW 0x0305").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    cpu.set_opcode_handler(Some(Box::new(|cpu: &mut CPU, opcode: u16| {
+        cpu.set_v_register(0, opcode as u8);
+        Ok(())
+    })));
+    cpu.run(1).unwrap();
+    assert_eq!(cpu.state().v_registers[0], 5);
+}
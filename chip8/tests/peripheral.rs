@@ -0,0 +1,56 @@
+//! Devices mapped into memory take the program's reads and writes.
+
+use std::sync::{Arc, Mutex};
+
+use chip8::asm::assemble;
+use chip8::{Chip8Builder, Peripheral, CPU};
+
+// a serial port: bytes written are sent to the host, reads count up
+#[derive(Default)]
+struct Serial {
+    sent: Arc<Mutex<Vec<u8>>>,
+    reads: u8,
+}
+
+impl Peripheral for Serial {
+    fn read(&mut self, _offset: usize) -> u8 {
+        self.reads += 1;
+        self.reads
+    }
+
+    fn write(&mut self, offset: usize, value: u8) {
+        assert_eq!(offset, 0);
+        self.sent.lock().unwrap().push(value);
+    }
+}
+
+fn run(source: &str, at: usize) -> (CPU, Arc<Mutex<Vec<u8>>>) {
+    let rom = assemble(source).unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    let serial = Serial::default();
+    let sent = serial.sent.clone();
+    cpu.map_peripheral(at..at + 1, Box::new(serial));
+    let lines = source.lines().count();
+    cpu.run(lines).unwrap();
+    (cpu, sent)
+}
+
+#[test]
+fn writes_go_to_the_device() {
+    let (cpu, sent) = run("LD V0, 0x41\nLD I, 0xF00\nLD [I], V0", 0xF00);
+    assert_eq!(*sent.lock().unwrap(), [0x41]);
+    assert_eq!(cpu.read_memory(0xF00..=0xF00), [0]);
+}
+
+#[test]
+fn reads_come_from_the_device() {
+    let (cpu, _) = run("LD I, 0xF00\nLD V0, [I]\nLD V0, [I]", 0xF00);
+    assert_eq!(cpu.state().v_registers[0], 2);
+}
+
+#[test]
+fn devices_can_live_past_the_ram() {
+    let source = "LD V0, 7\nLD I, 0xFFF\nLD V1, V0\nLD [I], V1";
+    let (_, sent) = run(source, 0x1000);
+    assert_eq!(*sent.lock().unwrap(), [7]);
+}
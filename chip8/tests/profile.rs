@@ -0,0 +1,53 @@
+//! Execution counts by address and by opcode family.
+
+use chip8::asm::assemble;
+use chip8::{Chip8Builder, CPU};
+
+fn profiled() -> CPU {
+    let rom = assemble("LD V0, 1\nADD V0, V0\nADD V0, V0\nJP 0x202").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    cpu.set_profiling(true);
+    cpu
+}
+
+#[test]
+fn hot_spots_are_the_most_executed_addresses() {
+    let mut cpu = profiled();
+    cpu.run(7).unwrap();
+    let spots = cpu.hot_spots(1);
+    assert_eq!((spots[0].address, spots[0].count), (0x202, 2));
+}
+
+#[test]
+fn opcodes_are_counted_by_family() {
+    let mut cpu = profiled();
+    cpu.run(7).unwrap();
+    let stats = cpu.execution_stats().unwrap();
+    let counts: Vec<_> = stats
+        .opcodes
+        .iter()
+        .map(|opcode| (opcode.pattern, opcode.count))
+        .collect();
+    assert_eq!(counts, [("8XY4", 4), ("1NNN", 2), ("6XNN", 1)]);
+    assert_eq!(stats.instructions, 7);
+}
+
+#[test]
+fn instructions_per_second_count_emulated_frames() {
+    let mut cpu = profiled();
+    assert_eq!(
+        cpu.execution_stats().unwrap().instructions_per_second(),
+        None
+    );
+    for _ in 0..2 {
+        cpu.run(10).unwrap();
+        cpu.tick_timers();
+    }
+    let stats = cpu.execution_stats().unwrap();
+    assert_eq!(stats.instructions_per_second(), Some(600.0));
+}
+
+#[test]
+fn no_stats_without_profiling() {
+    assert!(CPU::default().execution_stats().is_none());
+}
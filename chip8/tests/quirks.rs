@@ -0,0 +1,77 @@
+//! The behaviours the quirk flags switch between.
+
+use chip8::asm::assemble;
+use chip8::romdb::Platform;
+use chip8::{Chip8Builder, Quirks, CPU};
+
+// runs every line of `source` once, without quirks and with the one `set`
+// turns on
+fn run(set: fn(&mut Quirks), source: &str) -> (CPU, CPU) {
+    let mut quirks = Quirks::default();
+    set(&mut quirks);
+    let rom = assemble(source).unwrap();
+    let run = |quirks| {
+        let mut cpu = Chip8Builder::new()
+            .rom(&rom)
+            .quirks(quirks)
+            .build()
+            .unwrap();
+        cpu.run(source.lines().count()).unwrap();
+        cpu
+    };
+    (run(Quirks::default()), run(quirks))
+}
+
+#[test]
+fn sprites_wrap_or_clip_at_the_edge() {
+    // the 4 pixel wide top of the 0 digit, 2 pixels from the right edge
+    let source = "LD V0, 62\nLD V1, 0\nLD F, V1\nDRW V0, V1, 1";
+    let (wrapped, clipped) = run(|quirks| quirks.clip_sprites = true, source);
+    let top = |cpu: &CPU| [0, 1, 62, 63].map(|x| cpu.frame_buffer().pixel(x, 0));
+    assert_eq!(top(&wrapped), [true, true, true, true]);
+    assert_eq!(top(&clipped), [false, false, true, true]);
+}
+
+#[test]
+fn stores_and_loads_leave_i_or_move_it_past() {
+    let source = "LD I, 0x300\nLD [I], V2\nLD V2, [I]";
+    let (left, moved) = run(|quirks| quirks.increment_i = true, source);
+    assert_eq!(left.state().i_register, 0x300);
+    assert_eq!(moved.state().i_register, 0x306);
+}
+
+#[test]
+fn shifts_take_vx_or_vy() {
+    let source = "LD V0, 0x10\nLD V1, 0x03\nSHR V0, V1";
+    let (in_place, from_vy) = run(|quirks| quirks.shift_vy = true, source);
+    assert_eq!(in_place.state().v_registers[..2], [0x08, 0x03]);
+    assert_eq!(in_place.state().v_registers[0xF], 0);
+    assert_eq!(from_vy.state().v_registers[..2], [0x01, 0x03]);
+    assert_eq!(from_vy.state().v_registers[0xF], 1);
+}
+
+#[test]
+fn jumps_add_v0_or_vx() {
+    let source = "LD V0, 4\nLD V3, 8\nJP V0, 0x300";
+    let (v0, vx) = run(|quirks| quirks.jump_vx = true, source);
+    assert_eq!(v0.state().program_counter, 0x304);
+    assert_eq!(vx.state().program_counter, 0x308);
+}
+
+#[test]
+fn drawing_waits_for_the_next_frame() {
+    let source = "LD F, V0\nDRW V0, V0, 5\nADD V1, 1";
+    let (went_on, mut waiting) = run(|quirks| quirks.display_wait = true, source);
+    assert_eq!(went_on.state().v_registers[1], 1);
+    assert_eq!(waiting.state().v_registers[1], 0);
+    assert_eq!(waiting.run(1).unwrap().instructions, 0);
+    waiting.tick_timers();
+    waiting.run(1).unwrap();
+    assert_eq!(waiting.state().v_registers[1], 1);
+}
+
+#[test]
+fn xo_chip_roms_get_the_xo_chip_quirks() {
+    assert_eq!(Platform::XoChip.quirks(), Quirks::XO_CHIP);
+    assert_ne!(Platform::XoChip.quirks(), Platform::SuperChip.quirks());
+}
@@ -0,0 +1,54 @@
+//! Going back frames in the rewind history.
+
+use chip8::asm::assemble;
+use chip8::{Chip8Builder, CPU, REWIND_MEMORY_LIMIT};
+
+// counts the frames in V0, running the loop once a frame
+fn counting(depth: usize) -> CPU {
+    let rom = assemble("ADD V0, 1\nJP 0x200").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    cpu.set_clock_hz(2 * 60);
+    cpu.set_rewind_depth(depth);
+    cpu
+}
+
+fn run_frames(cpu: &mut CPU, frames: usize) {
+    for _ in 0..frames {
+        cpu.run_frame(cpu.clock_hz()).unwrap();
+        cpu.tick_timers();
+    }
+}
+
+#[test]
+fn rewinding_restores_earlier_frames() {
+    let mut cpu = counting(10);
+    run_frames(&mut cpu, 5);
+    assert_eq!(cpu.state().v_registers[0], 5);
+    assert_eq!(cpu.rewind(2), 2);
+    assert_eq!(cpu.state().v_registers[0], 3);
+    run_frames(&mut cpu, 1);
+    assert_eq!(cpu.state().v_registers[0], 4);
+}
+
+#[test]
+fn the_history_keeps_the_last_frames() {
+    let mut cpu = counting(3);
+    run_frames(&mut cpu, 10);
+    assert_eq!(cpu.rewind_frames_available(), 2);
+    assert_eq!(cpu.rewind(5), 2);
+    assert_eq!(cpu.rewind_frames_available(), 0);
+    assert_eq!(counting(0).rewind(1), 0);
+}
+
+#[test]
+fn the_history_fits_in_its_memory_limit() {
+    // every frame copies the 8 MiB of RAM
+    let ram = 8 << 20;
+    let mut rom = assemble("JP 0x200").unwrap();
+    rom.resize(ram - 0x200, 1);
+    let mut cpu = Chip8Builder::new().rom(&rom).megachip().build().unwrap();
+    cpu.set_rewind_depth(600);
+    run_frames(&mut cpu, 20);
+    assert!(cpu.rewind_frames_available() < REWIND_MEMORY_LIMIT / ram);
+    assert!(cpu.rewind_frames_available() > 0);
+}
@@ -0,0 +1,31 @@
+//! The random source of CXNN, with or without the `rand` feature.
+
+use chip8::asm::assemble;
+use chip8::{Chip8Builder, RandomSource, XorShiftRng};
+
+fn random_bytes(seed: u64) -> Vec<u8> {
+    let rom = assemble(&"RND V0, 0xFF\n".repeat(8)).unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).seed(seed).build().unwrap();
+    let mut bytes = Vec::new();
+    for _ in 0..8 {
+        cpu.run(1).unwrap();
+        bytes.push(cpu.state().v_registers[0]);
+    }
+    bytes
+}
+
+#[test]
+fn a_seed_gives_the_same_bytes_every_run() {
+    assert_eq!(random_bytes(7), random_bytes(7));
+    assert_ne!(random_bytes(7), random_bytes(8));
+}
+
+#[test]
+fn xorshift_bytes_are_spread() {
+    let mut rng = XorShiftRng::new(0);
+    let mut seen = [false; 256];
+    for _ in 0..4096 {
+        seen[rng.next_byte() as usize] = true;
+    }
+    assert!(seen.iter().filter(|&&seen| seen).count() > 240);
+}
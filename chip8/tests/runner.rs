@@ -0,0 +1,58 @@
+//! A runner keeps the speed and the 60 Hz timers against the wall clock.
+
+use std::time::{Duration, Instant};
+
+use chip8::asm::assemble;
+use chip8::runner::Runner;
+use chip8::Chip8Builder;
+
+// counts instructions in V1,V0 and sets the delay timer once
+const COUNT: &str = "
+    LD V2, 0xFF
+    LD DT, V2
+loop:
+    ADD V0, 1
+    SE V0, 0
+    JP loop
+    ADD V1, 1
+    JP loop
+";
+
+fn runner(ips: u32) -> Runner {
+    let rom = assemble(COUNT).unwrap();
+    Runner::new(Chip8Builder::new().rom(&rom).build().unwrap()).with_ips(ips)
+}
+
+#[test]
+fn instructions_and_timers_follow_the_clock() {
+    let mut runner = runner(600);
+    let start = Instant::now();
+    assert_eq!(runner.advance(start).unwrap().instructions, 0);
+
+    // a second in steps of 10 ms, shorter than a frame
+    let mut instructions = 0;
+    for ms in 1..=100 {
+        let summary = runner
+            .advance(start + Duration::from_millis(ms * 10))
+            .unwrap();
+        instructions += summary.instructions;
+    }
+    assert_eq!(instructions, 600);
+    assert_eq!(runner.cpu().state().delay_timer, 0xFF - 60);
+}
+
+#[test]
+fn stalls_and_pauses_are_not_caught_up_on() {
+    let mut runner = runner(6000);
+    let start = Instant::now();
+    runner.advance(start).unwrap();
+    let summary = runner.advance(start + Duration::from_secs(5)).unwrap();
+    assert!(summary.instructions <= 400, "{}", summary.instructions);
+
+    runner.pause();
+    let later = start + Duration::from_secs(60);
+    assert_eq!(runner.advance(later).unwrap().instructions, 0);
+    runner.set_ips(60);
+    let summary = runner.advance(later + Duration::from_secs(1) / 60).unwrap();
+    assert_eq!(summary.instructions, 1);
+}
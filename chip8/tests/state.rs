@@ -0,0 +1,124 @@
+//! Save states, and machines in the same state hashing the same.
+
+use chip8::asm::assemble;
+use chip8::{Chip8Builder, Quirks, SaveState, StateError, CPU, NUM_FLAGS, STATE_FORMAT_VERSION};
+
+fn run(rom: &[u8], seed: u64) -> CPU {
+    let mut cpu = Chip8Builder::new().rom(rom).seed(seed).build().unwrap();
+    for _ in 0..10 {
+        cpu.run_frame(cpu.clock_hz()).unwrap();
+        cpu.tick_timers();
+    }
+    cpu
+}
+
+#[test]
+fn state_hash_follows_the_machine_state() {
+    let rom = assemble("RND V0, 0xFF\nLD F, V0\nDRW V1, V1, 5\nADD V1, 5\nJP 0x200").unwrap();
+    let (a, b) = (run(&rom, 1), run(&rom, 1));
+    assert_eq!(a.state_hash(), b.state_hash());
+    assert_ne!(a.state_hash(), run(&rom, 2).state_hash());
+
+    // the random source isn't saved, only one drawing the same bytes
+    // restores the same machine
+    let mut restored = run(&rom, 1);
+    restored.load_state(&a.save_state()).unwrap();
+    assert_eq!(restored.state_hash(), a.state_hash());
+    let mut other_rng = CPU::default();
+    other_rng.load_state(&a.save_state()).unwrap();
+    assert_ne!(other_rng.state_hash(), a.state_hash());
+
+    restored.keypress(3, true);
+    assert_ne!(restored.state_hash(), a.state_hash());
+    restored.keypress(3, false);
+    restored.set_quirks(Quirks::VIP);
+    assert_ne!(restored.state_hash(), a.state_hash());
+}
+
+#[test]
+fn states_carry_their_version_and_rom() {
+    let rom = assemble("LD V0, 1\nJP 0x202").unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    cpu.run(2).unwrap();
+    let data = cpu.save_state();
+    assert_eq!(&data[..6], b"C8SV\0\x02");
+    assert_eq!(STATE_FORMAT_VERSION, 2);
+
+    let mut newer = data.clone();
+    newer[5] = 3;
+    assert_eq!(cpu.load_state(&newer), Err(StateError::NewerVersion(3)));
+
+    let other = assemble("LD V1, 1").unwrap();
+    let mut wrong = Chip8Builder::new().rom(&other).build().unwrap();
+    let err = wrong.load_state(&data).unwrap_err();
+    assert!(matches!(err, StateError::WrongRom { .. }), "{err}");
+    let mut state = SaveState::from_bytes(&data).unwrap();
+    state.rom_hash = None;
+    wrong.restore(&state).unwrap();
+    assert_eq!(wrong.state().v_registers[0], 1);
+
+    // states saved before the header load with any ROM
+    let legacy = &data[14..];
+    assert_eq!(SaveState::from_bytes(legacy).unwrap().rom_hash, None);
+    let mut fresh = CPU::default();
+    fresh.load_state(legacy).unwrap();
+    assert_eq!(fresh.snapshot().rom_hash, None);
+    fresh.load_state(&data).unwrap();
+    assert_eq!(fresh.snapshot().rom_hash, cpu.snapshot().rom_hash);
+}
+
+#[test]
+fn a_loaded_state_continues_like_the_saved_machine() {
+    // no RND, the random source isn't part of the state
+    let source = "ADD V0, 3\nLD F, V0\nDRW V1, V1, 5\nADD V1, 5\nJP 0x200";
+    let rom = assemble(source).unwrap();
+    let mut cpu = run(&rom, 0);
+    cpu.keypress(7, true);
+    let data = cpu.save_state();
+
+    // seeded like the saved machine, the random source isn't saved
+    let mut restored = Chip8Builder::new().rom(&rom).seed(0).build().unwrap();
+    restored.load_state(&data).unwrap();
+    assert_eq!(restored.state(), cpu.state());
+    assert_eq!(restored.get_display(), cpu.get_display());
+    assert!(restored.is_key_pressed(7));
+    for _ in 0..10 {
+        cpu.run_frame(cpu.clock_hz()).unwrap();
+        restored.run_frame(restored.clock_hz()).unwrap();
+    }
+    assert_eq!(restored.state_hash(), cpu.state_hash());
+}
+
+#[test]
+fn states_carry_the_display_wait_and_the_flags() {
+    let rom = assemble("LD V0, 7\nLD R, V0\nDRW V1, V1, 5\nLD V0, 1").unwrap();
+    let vip = || {
+        Chip8Builder::new()
+            .rom(&rom)
+            .quirks(Quirks::VIP)
+            .build()
+            .unwrap()
+    };
+    let mut cpu = vip();
+    cpu.run(3).unwrap();
+    let data = cpu.save_state();
+
+    let mut restored = vip();
+    restored.load_state(&data).unwrap();
+    assert_eq!(restored.flags()[0], 7);
+    // still waiting for the next frame after the sprite
+    restored.run(1).unwrap();
+    assert_eq!(restored.state().v_registers[0], 7);
+    restored.tick_timers();
+    restored.run(1).unwrap();
+    assert_eq!(restored.state().v_registers[0], 1);
+
+    // states of format 1 have neither, and leave the flags alone
+    let old = &data[..data.len() - (4 + 2 + NUM_FLAGS)];
+    let mut old = old.to_vec();
+    old[5] = 1;
+    let mut flagged = Chip8Builder::new().rom(&rom).build().unwrap();
+    flagged.set_flags(&[9]);
+    flagged.load_state(&old).unwrap();
+    assert_eq!(flagged.flags()[0], 9);
+}
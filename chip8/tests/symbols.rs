@@ -0,0 +1,90 @@
+//! Names from symbol files in listings and the debugger.
+
+use chip8::asm::assemble;
+use chip8::debugger::Debugger;
+use chip8::disasm::annotate_rom_with;
+use chip8::symbols::Symbols;
+use chip8::{Chip8Builder, START_ADDR};
+
+const SYMBOLS: &str = "
+# from the Octo source
+label main 0x200
+label draw-box 0x206
+const BOX_SIZE 5
+breakpoint 0x206
+";
+
+#[test]
+fn symbol_files_name_labels_and_constants() {
+    let symbols: Symbols = SYMBOLS.parse().unwrap();
+    assert_eq!(symbols.label(0x206), Some("draw-box"));
+    assert_eq!(symbols.resolve("draw-box"), Some(0x206));
+    assert_eq!(symbols.resolve("BOX_SIZE"), Some(5));
+    assert_eq!(symbols.locate(0x204), Some(("main", 4)));
+    assert_eq!(symbols.locate(0x100), None);
+
+    let err = "label main\n".parse::<Symbols>().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "line 1: expected a kind, a name and a value"
+    );
+    let err = "\nconst SIZE twelve\n".parse::<Symbols>().unwrap_err();
+    assert_eq!(err.line, 2);
+}
+
+#[test]
+fn listings_and_the_debugger_use_the_names() {
+    let rom = assemble("CALL 0x206\nJP 0x202\nDW 0x0000\nDRW V0, V0, 5\nRET").unwrap();
+    let symbols: Symbols = SYMBOLS.parse().unwrap();
+    let listing = annotate_rom_with(&rom, START_ADDR, &symbols).to_string();
+    assert!(
+        listing.starts_with("main:\n    CALL draw-box "),
+        "{listing}"
+    );
+    assert!(listing.contains("\nL_0202:\n    JP L_0202 "), "{listing}");
+
+    let cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    let mut debugger = Debugger::new(cpu);
+    debugger.set_symbols(symbols);
+    debugger.step().unwrap();
+    debugger.step().unwrap();
+    assert_eq!(debugger.location(), Some(("draw-box", 2)));
+}
+
+#[test]
+fn the_debugger_follows_the_source() {
+    let rom = assemble("LD V3, 7\nCALL 0x206\nJP 0x204\nDRW V3, V3, 5\nRET").unwrap();
+    let symbols = "
+        label main 0x200
+        label draw 0x206
+        alias score v3
+        line 0x200 3
+        line 0x202 4
+        line 0x204 5
+        line 0x206 8
+    ";
+    let cpu = Chip8Builder::new().rom(&rom).build().unwrap();
+    let mut debugger = Debugger::new(cpu);
+    debugger.set_symbols(symbols.parse().unwrap());
+    assert_eq!(debugger.source_line(), Some(3));
+    assert_eq!(debugger.register_name(3), "score");
+    assert_eq!(debugger.register_name(0xA), "VA");
+
+    assert_eq!(debugger.add_breakpoint_at("draw"), Some(0x206));
+    assert_eq!(debugger.add_breakpoint_at("nowhere"), None);
+    while !debugger.is_paused() {
+        debugger.tick().unwrap();
+    }
+    assert_eq!(debugger.source_line(), Some(8));
+
+    debugger.clear_breakpoints();
+    assert_eq!(debugger.add_breakpoint_at_line(5), Some(0x204));
+    debugger.resume();
+    while !debugger.is_paused() {
+        debugger.tick().unwrap();
+    }
+    assert_eq!(debugger.location(), Some(("main", 4)));
+
+    let err = "alias score v16".parse::<Symbols>().unwrap_err();
+    assert_eq!(err.to_string(), "line 1: invalid value `v16`");
+}
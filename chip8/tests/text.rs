@@ -0,0 +1,24 @@
+//! Text drawn with the hex font.
+
+use chip8::text::{draw_text, GLYPH_ADVANCE};
+
+#[test]
+fn text_is_drawn_at_its_position() {
+    let mut framebuffer = [false; 16 * 8];
+    let width = draw_text(&mut framebuffer, 16, 1, 2, "10");
+    assert_eq!(width, 2 * GLYPH_ADVANCE - 1);
+    // the top row of 1 is 0x20, of 0 0xF0
+    let row: Vec<_> = framebuffer[2 * 16..3 * 16]
+        .iter()
+        .map(|&px| px as u8)
+        .collect();
+    assert_eq!(row, [0, 0, 0, 1, 0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn text_outside_the_framebuffer_is_skipped() {
+    let mut framebuffer = [false; 16 * 8];
+    draw_text(&mut framebuffer, 16, usize::MAX - 2, usize::MAX, "FF");
+    assert_eq!(draw_text(&mut [], 0, 0, 0, "FF"), 0);
+    assert!(framebuffer.iter().all(|&px| !px));
+}
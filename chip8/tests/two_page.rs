@@ -0,0 +1,45 @@
+//! The 64x64 display of the two-page hi-res variant.
+
+use chip8::asm::assemble;
+use chip8::{Chip8Builder, SaveState, CPU, TWO_PAGE_START_ADDR};
+
+// jumps over the machine code to a 0 drawn at the bottom of the display,
+// then clears it with 0230
+fn rom() -> Vec<u8> {
+    let mut rom = vec![0x12, 0x60];
+    rom.resize((TWO_PAGE_START_ADDR - 0x200) as usize, 0);
+    rom.extend(assemble("LD V0, 0\nLD V1, 60\nLD F, V0\nDRW V0, V1, 5\nSYS 0x230").unwrap());
+    rom
+}
+
+#[test]
+fn roms_starting_with_1260_draw_on_64x64() {
+    let mut cpu = Chip8Builder::new().rom(&rom()).build().unwrap();
+    assert!(cpu.is_two_page());
+    assert_eq!(cpu.display_size(), (64, 64));
+
+    cpu.run(1).unwrap();
+    assert_eq!(cpu.state().program_counter, TWO_PAGE_START_ADDR);
+    cpu.run(4).unwrap();
+    assert!(cpu.frame_buffer().pixel(0, 60));
+
+    let state = SaveState::from_bytes(&cpu.save_state()).unwrap();
+    assert!(state.two_page);
+    let mut restored = CPU::default();
+    restored.restore(&state).unwrap();
+    assert_eq!(restored.display_size(), (64, 64));
+
+    cpu.run(1).unwrap();
+    assert!(!cpu.get_display().contains(&true));
+}
+
+#[test]
+fn loading_detects_two_page_roms() {
+    let mut cpu = CPU::default();
+    cpu.load(&rom()).unwrap();
+    assert!(cpu.is_two_page());
+
+    let mut cpu = CPU::default();
+    cpu.load(&[0x12, 0x00]).unwrap();
+    assert!(!cpu.is_two_page());
+}
@@ -0,0 +1,55 @@
+use chip8::audio::{AudioSource, DEFAULT_SAMPLE_RATE};
+use chip8::frontend::AudioSink;
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::AudioSubsystem;
+
+struct SquareWave(AudioSource);
+
+impl AudioCallback for SquareWave {
+    type Channel = f32;
+
+    // the device is paused while the sound timer isn't counting down
+    fn callback(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = self.0.next_sample(true);
+        }
+    }
+}
+
+/// Plays a tone while the sound timer is counting down.
+pub struct Beeper {
+    device: AudioDevice<SquareWave>,
+    playing: bool,
+}
+
+impl Beeper {
+    pub fn new(audio: &AudioSubsystem) -> Result<Self, String> {
+        let desired = AudioSpecDesired {
+            freq: Some(DEFAULT_SAMPLE_RATE as i32),
+            channels: Some(1),
+            samples: None,
+        };
+        // the device starts paused
+        let device = audio.open_playback(None, &desired, |spec| {
+            SquareWave(AudioSource::new(spec.freq as u32))
+        })?;
+        Ok(Self {
+            device,
+            playing: false,
+        })
+    }
+}
+
+impl AudioSink for Beeper {
+    fn set_playing(&mut self, playing: bool) {
+        if playing == self.playing {
+            return;
+        }
+        self.playing = playing;
+        if playing {
+            self.device.resume();
+        } else {
+            self.device.pause();
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! The ROM browser shown when no ROM is given on the command line: the
+//! recently played ROMs over a listing of a directory to pick one from.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::EventPump;
+
+use crate::menu::Menu;
+
+// where the listing starts without recent ROMs, if it is there
+const ROMS_DIR: &str = "ROMs";
+
+enum Entry {
+    Rom(PathBuf),
+    Dir(PathBuf),
+}
+
+struct Browser {
+    recent: Vec<PathBuf>,
+    // the directory listed
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    menu: Menu,
+}
+
+impl Browser {
+    fn new(recent: &[String]) -> Self {
+        let recent: Vec<PathBuf> = recent
+            .iter()
+            .map(PathBuf::from)
+            .filter(|path| path.is_file())
+            .collect();
+        let dir = recent
+            .first()
+            .and_then(|rom| rom.parent())
+            .map(Path::to_path_buf)
+            .or_else(|| Some(PathBuf::from(ROMS_DIR)).filter(|dir| dir.is_dir()))
+            .unwrap_or_else(|| PathBuf::from("."));
+        let mut browser = Self {
+            recent,
+            dir: PathBuf::new(),
+            entries: Vec::new(),
+            menu: Menu::new("", Vec::new()),
+        };
+        browser.open_dir(&dir);
+        browser
+    }
+
+    /// Lists `dir` under the recent ROMs, keeping the current listing when
+    /// it can't be read.
+    fn open_dir(&mut self, dir: &Path) {
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let listing = match fs::read_dir(&dir) {
+            Ok(listing) => listing,
+            Err(err) => {
+                eprintln!("{}: {err}", dir.display());
+                return;
+            }
+        };
+        let mut dirs = Vec::new();
+        let mut roms = Vec::new();
+        for path in listing.flatten().map(|entry| entry.path()) {
+            let hidden = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            if hidden {
+                continue;
+            }
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                roms.push(path);
+            }
+        }
+        let key = |path: &PathBuf| name(path).to_lowercase();
+        dirs.sort_by_key(key);
+        roms.sort_by_key(key);
+
+        self.entries = self.recent.iter().cloned().map(Entry::Rom).collect();
+        self.entries
+            .extend(dir.parent().map(|up| Entry::Dir(up.to_path_buf())));
+        self.entries.extend(dirs.into_iter().map(Entry::Dir));
+        self.entries.extend(roms.into_iter().map(Entry::Rom));
+        let parent = dir.parent();
+        let items = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(idx, entry)| match entry {
+                Entry::Rom(path) if idx < self.recent.len() => format!("{} (recent)", name(path)),
+                Entry::Dir(path) if Some(path.as_path()) == parent => "../".to_string(),
+                Entry::Dir(path) => format!("{}/", name(path)),
+                Entry::Rom(path) => name(path),
+            })
+            .collect();
+        let title = format!("Open a ROM: {}", dir.display());
+        self.menu = Menu::new(title, items);
+        self.dir = dir;
+    }
+
+    /// Opens the selected directory, or returns the selected ROM.
+    fn choose(&mut self) -> Option<PathBuf> {
+        match self.entries.get(self.menu.selected())? {
+            Entry::Rom(path) => Some(path.clone()),
+            Entry::Dir(path) => {
+                let path = path.clone();
+                self.open_dir(&path);
+                None
+            }
+        }
+    }
+
+    fn open_parent(&mut self) {
+        if let Some(parent) = self.dir.parent().map(Path::to_path_buf) {
+            self.open_dir(&parent);
+        }
+    }
+}
+
+fn name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+/// Shows the browser in the window until a ROM is picked, or dropped onto
+/// the window, and returns its path. None when the user quits or backs out
+/// with Escape instead.
+pub fn pick(
+    event_pump: &mut EventPump,
+    canvas: &mut Canvas<Window>,
+    recent: &[String],
+    frame: Duration,
+) -> Option<String> {
+    let mut browser = Browser::new(recent);
+    loop {
+        for event in event_pump.poll_iter() {
+            let picked = match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => return None,
+                Event::DropFile { filename, .. } => return Some(filename),
+                Event::KeyDown {
+                    keycode: Some(key), ..
+                } => match key {
+                    Keycode::Up => {
+                        browser.menu.up();
+                        None
+                    }
+                    Keycode::Down => {
+                        browser.menu.down();
+                        None
+                    }
+                    Keycode::Backspace | Keycode::Left => {
+                        browser.open_parent();
+                        None
+                    }
+                    Keycode::Return | Keycode::KpEnter | Keycode::Right => browser.choose(),
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some(path) = picked {
+                return Some(path.display().to_string());
+            }
+        }
+        canvas.set_draw_color(Color::BLACK);
+        canvas.clear();
+        browser
+            .menu
+            .draw(canvas)
+            .expect("Error drawing the ROM browser");
+        canvas.present();
+        std::thread::sleep(frame);
+    }
+}
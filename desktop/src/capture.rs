@@ -0,0 +1,137 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chip8::screen::{Palette, HIRES_SCREEN_HEIGHT, HIRES_SCREEN_WIDTH};
+use chip8::CPU;
+use gif::{Encoder, Frame, Repeat};
+
+// every CHIP-8 pixel becomes a GIF_SCALE x GIF_SCALE block of a hi-res frame
+const GIF_SCALE: usize = 4;
+const GIF_WIDTH: usize = HIRES_SCREEN_WIDTH * GIF_SCALE;
+const GIF_HEIGHT: usize = HIRES_SCREEN_HEIGHT * GIF_SCALE;
+
+// 640x320, a low resolution pixel is a 10x10 block
+const PNG_SCALE: usize = 5;
+const PNG_WIDTH: usize = HIRES_SCREEN_WIDTH * PNG_SCALE;
+const PNG_HEIGHT: usize = HIRES_SCREEN_HEIGHT * PNG_SCALE;
+
+// viewers show frames shorter than this for 1/10 s instead
+const MIN_DELAY_CS: u32 = 2;
+
+/// A file name in the working directory unique to the second, e.g.
+/// `chip8-1700000000.gif`.
+pub fn timestamped_path(extension: &str) -> PathBuf {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    PathBuf::from(format!("chip8-{secs}.{extension}"))
+}
+
+/// Records the display, one call to [`GifRecorder::add_frame`] per 60 Hz
+/// frame, into an animated GIF.
+///
+/// Identical frames are merged into a single longer one, and frames shown
+/// for less than 2/100 s are dropped since GIF viewers can't show them.
+pub struct GifRecorder {
+    path: PathBuf,
+    encoder: Encoder<BufWriter<File>>,
+    // the frame shown since `pending_since`, written once it changes
+    pending: Vec<u8>,
+    pending_since: u32,
+    frames: u32,
+}
+
+impl GifRecorder {
+    pub fn create(path: PathBuf, palette: &Palette) -> Result<Self, gif::EncodingError> {
+        let file = BufWriter::new(File::create(&path)?);
+        // indexed by the pixel color
+        let colors = palette.colors.concat();
+        let mut encoder = Encoder::new(file, GIF_WIDTH as u16, GIF_HEIGHT as u16, &colors)?;
+        encoder.set_repeat(Repeat::Infinite)?;
+        Ok(Self {
+            path,
+            encoder,
+            pending: Vec::new(),
+            pending_since: 0,
+            frames: 0,
+        })
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn add_frame(&mut self, chip8: &CPU) -> Result<(), gif::EncodingError> {
+        let pixels = scale_display(chip8, GIF_WIDTH, GIF_HEIGHT).collect();
+        if pixels != self.pending {
+            if self.pending.is_empty() || self.delay_cs() >= MIN_DELAY_CS {
+                self.flush()?;
+                self.pending_since = self.frames;
+            }
+            self.pending = pixels;
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Writes the last frame and closes the file.
+    pub fn finish(mut self) -> Result<PathBuf, gif::EncodingError> {
+        self.flush()?;
+        let file = self.encoder.into_inner()?;
+        file.into_inner().map_err(|err| err.into_error())?;
+        Ok(self.path)
+    }
+
+    // time the pending frame was shown, in centiseconds at 60 fps
+    fn delay_cs(&self) -> u32 {
+        self.frames * 100 / 60 - self.pending_since * 100 / 60
+    }
+
+    fn flush(&mut self) -> Result<(), gif::EncodingError> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let mut frame = Frame::from_indexed_pixels(
+            GIF_WIDTH as u16,
+            GIF_HEIGHT as u16,
+            std::mem::take(&mut self.pending),
+            None,
+        );
+        frame.delay = self.delay_cs().max(MIN_DELAY_CS) as u16;
+        self.encoder.write_frame(&frame)
+    }
+}
+
+/// Writes the display scaled up with `palette` applied to a PNG file.
+pub fn save_png(chip8: &CPU, palette: &Palette, path: &Path) -> Result<(), png::EncodingError> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, PNG_WIDTH as u32, PNG_HEIGHT as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    let frame = chip8.frame_buffer();
+    let rgb: Vec<u8> = (0..PNG_WIDTH * PNG_HEIGHT)
+        .flat_map(|i| {
+            let (x, y) = (i % PNG_WIDTH, i / PNG_WIDTH);
+            let (x, y) = (
+                x * frame.width() / PNG_WIDTH,
+                y * frame.height() / PNG_HEIGHT,
+            );
+            frame.rgb(x, y, palette)
+        })
+        .collect();
+    writer.write_image_data(&rgb)?;
+    writer.finish()
+}
+
+/// Nearest-neighbour scales the colors of the display of either resolution
+/// to `width` x `height`, row by row.
+fn scale_display(chip8: &CPU, width: usize, height: usize) -> impl Iterator<Item = u8> + '_ {
+    let frame = chip8.frame_buffer();
+    (0..width * height).map(move |i| {
+        let (x, y) = (i % width, i / width);
+        frame.color(x * frame.width() / width, y * frame.height() / height)
+    })
+}
@@ -0,0 +1,379 @@
+//! Command line options.
+
+use chip8::screen::{Palette, Rgb, NUM_COLORS};
+use chip8::{Font, BIG_FONTSET_SIZE, FONTSET_SIZE};
+
+use crate::config::{named, Config, GameConfig};
+use crate::filter::Filter;
+use crate::keymap::Layout;
+use clap::builder::{PossibleValuesParser, RangedU64ValueParser, TypedValueParser};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+/// Window pixels per low resolution display pixel.
+const DEFAULT_SCALE: u32 = 15;
+const MAX_SCALE: u32 = 64;
+
+/// Frames run per frame shown while Tab is held.
+const DEFAULT_TURBO: u32 = 4;
+
+const QUIRKS: [&str; 3] = ["default", "vip", "schip"];
+
+pub struct Options {
+    /// None to pick one in the ROM browser.
+    pub rom: Option<String>,
+    /// Window pixels per low resolution display pixel.
+    pub scale: u32,
+    pub filter: Filter,
+    /// Let pixels fade out over a few frames, hiding flicker.
+    pub phosphor: bool,
+    /// Run a CHIP-8X program, loaded at 0x300 with its colors.
+    pub chip8x: bool,
+    /// Let a MegaChip ROM grow the RAM past 4 KiB to fit.
+    pub megachip: bool,
+    /// Print what looks wrong in the ROM before running it.
+    pub lint: bool,
+    pub mute: bool,
+    /// Pump events between every tick instead of once per frame, so input
+    /// lands on the tick it happened instead of the next frame boundary.
+    pub per_tick_input: bool,
+    /// Let the game see key changes at the next frame only.
+    pub latched_input: bool,
+    /// Frames run per frame shown while fast-forwarding.
+    pub turbo: u32,
+    /// Start in slow motion, this many times slower.
+    pub slow_motion: Option<u32>,
+    /// Show the keypad with the keys held.
+    pub show_keys: bool,
+    /// Print every executed instruction to stderr.
+    pub trace: bool,
+    /// Print the most executed addresses on exit.
+    pub profile: bool,
+    /// Print the parts of the ROM never executed on exit.
+    pub coverage: bool,
+    pub play: Option<String>,
+    pub host: Option<String>,
+    pub join: Option<String>,
+    /// The settings to remember, with the options given.
+    pub config: Config,
+    /// Speed, palette, quirks, layout and font given for the ROM, to remember for
+    /// it, see [`Config::game`].
+    pub given: GameConfig,
+}
+
+impl Options {
+    /// Parses the command line, exiting with the usage on errors. The scale
+    /// and filter default to the settings in `config`, which remembers the
+    /// ones given.
+    pub fn parse(config: Config) -> Self {
+        Self::from_matches(command().get_matches(), config)
+    }
+
+    fn from_matches(mut matches: ArgMatches, mut config: Config) -> Self {
+        let flag = |id: &str| matches.get_flag(id);
+        let (chip8x, lint, mute, per_tick_input, trace, profile) = (
+            flag("chip8x"),
+            flag("lint"),
+            flag("mute"),
+            flag("per-tick-input"),
+            flag("trace"),
+            flag("profile"),
+        );
+        let show_keys = flag("show-keys");
+        let coverage = flag("coverage");
+        let latched_input = flag("latched-input");
+        let megachip = flag("megachip");
+
+        // the command line overrides the settings and is remembered in them,
+        // the ones of a single ROM once it is known
+        let raw = |id: &str| {
+            let mut values = matches.get_raw(id)?;
+            values
+                .next()
+                .map(|value| value.to_string_lossy().into_owned())
+        };
+        config.scale = matches.get_one("scale").copied().or(config.scale);
+        config.filter = raw("filter").or(config.filter);
+        if flag("phosphor") {
+            config.phosphor = Some(true);
+        }
+        let given = GameConfig {
+            ips: matches.get_one("ips").copied(),
+            palette: raw("palette"),
+            quirks: raw("quirks"),
+            layout: raw("layout"),
+            font: matches.remove_one("font"),
+        };
+
+        // a filter the config file got wrong is left out
+        let filter = named("filter", config.filter.as_deref(), Filter::from_name);
+        Self {
+            rom: matches.remove_one("rom"),
+            scale: config
+                .scale
+                .filter(|scale| (1..=MAX_SCALE).contains(scale))
+                .unwrap_or(DEFAULT_SCALE),
+            filter: filter.unwrap_or(Filter::None),
+            phosphor: config.phosphor.unwrap_or(false),
+            chip8x,
+            megachip,
+            lint,
+            mute,
+            per_tick_input,
+            latched_input,
+            show_keys,
+            slow_motion: matches.get_one("slow-motion").copied(),
+            turbo: matches.get_one("turbo").copied().unwrap_or(DEFAULT_TURBO),
+            trace,
+            profile,
+            coverage,
+            play: matches.remove_one("play"),
+            host: matches.remove_one("host"),
+            join: matches.remove_one("join"),
+            config,
+            given,
+        }
+    }
+}
+
+fn command() -> Command {
+    Command::new("chip8")
+        .about("Chip-8 CPU Emulator")
+        .arg(
+            Arg::new("rom")
+                .value_name("ROM")
+                .help("ROM to play, picked in a browser when not given"),
+        )
+        .arg(
+            Arg::new("scale")
+                .long("scale")
+                .value_name("N")
+                .value_parser(RangedU64ValueParser::<u32>::new().range(1..=MAX_SCALE as u64))
+                .help(format!(
+                    "Window pixels per display pixel [default: {DEFAULT_SCALE}]"
+                )),
+        )
+        .arg(
+            Arg::new("ips")
+                .long("ips")
+                .visible_alias("speed")
+                .value_name("HZ")
+                .value_parser(RangedU64ValueParser::<u32>::new().range(1..))
+                .help("Instructions per second [default: 600 or the ROM's]"),
+        )
+        .arg(
+            Arg::new("palette")
+                .long("palette")
+                .value_name("COLORS")
+                .value_parser(parse_palette)
+                .help(
+                    "Comma separated RRGGBB colors, unlit and lit pixels then the XO-CHIP planes",
+                ),
+        )
+        .arg(
+            Arg::new("filter")
+                .long("filter")
+                .value_name("NAME")
+                .value_parser(
+                    PossibleValuesParser::new(Filter::ALL.map(Filter::name)).map(|name: String| {
+                        Filter::from_name(&name).expect("only filter names are accepted")
+                    }),
+                )
+                .help("Display filter, F6 switches while playing [default: none]"),
+        )
+        .arg(
+            Arg::new("phosphor")
+                .long("phosphor")
+                .action(ArgAction::SetTrue)
+                .help("Fade pixels out over a few frames, hiding flicker"),
+        )
+        .arg(
+            Arg::new("quirks")
+                .long("quirks")
+                .value_name("NAME")
+                .value_parser(PossibleValuesParser::new(QUIRKS))
+                .help("Interpreter to behave like, instead of the ROM's"),
+        )
+        .arg(
+            Arg::new("chip8x")
+                .long("chip8x")
+                .action(ArgAction::SetTrue)
+                .help("Run a CHIP-8X program, loaded at 0x300 with its colors"),
+        )
+        .arg(
+            Arg::new("megachip")
+                .long("megachip")
+                .action(ArgAction::SetTrue)
+                .help("Load a MegaChip ROM, growing the RAM past 4 KiB to fit it"),
+        )
+        .arg(
+            Arg::new("font")
+                .long("font")
+                .value_name("NAME|FILE")
+                .value_parser(parse_font)
+                .help(format!(
+                    "Digit sprites to use instead of the built-in ones: {}, or a file of \
+                     {FONTSET_SIZE} bytes or {} with the big font",
+                    Font::ALL.map(Font::name).join(", "),
+                    FONTSET_SIZE + BIG_FONTSET_SIZE
+                )),
+        )
+        .arg(
+            Arg::new("lint")
+                .long("lint")
+                .action(ArgAction::SetTrue)
+                .help("Print what looks wrong in the ROM before running it"),
+        )
+        .arg(
+            Arg::new("layout")
+                .long("layout")
+                .value_name("NAME")
+                .value_parser(
+                    PossibleValuesParser::new(Layout::ALL.map(Layout::name)).map(|name: String| {
+                        Layout::from_name(&name).expect("only layout names are accepted")
+                    }),
+                )
+                .help("Keyboard layout the keypad is laid over [default: qwerty]"),
+        )
+        .arg(
+            Arg::new("mute")
+                .long("mute")
+                .action(ArgAction::SetTrue)
+                .help("Don't play sound"),
+        )
+        .arg(
+            Arg::new("per-tick-input")
+                .long("per-tick-input")
+                .action(ArgAction::SetTrue)
+                .help("Poll input between every instruction, for lower latency"),
+        )
+        .arg(
+            Arg::new("latched-input")
+                .long("latched-input")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("per-tick-input")
+                .help(
+                    "Apply key changes at the next frame, like interpreters sampling once a frame",
+                ),
+        )
+        .arg(
+            Arg::new("turbo")
+                .long("turbo")
+                .value_name("N")
+                .value_parser(RangedU64ValueParser::<u32>::new().range(2..=100))
+                .help(format!(
+                    "Speed multiplier while Tab is held [default: {DEFAULT_TURBO}]"
+                )),
+        )
+        .arg(
+            Arg::new("slow-motion")
+                .long("slow-motion")
+                .value_name("N")
+                .value_parser(RangedU64ValueParser::<u32>::new().range(2..=60))
+                .conflicts_with_all(["host", "join"])
+                .help("Start N times slower, timers included, F11 switches it on and off"),
+        )
+        .arg(
+            Arg::new("show-keys")
+                .long("show-keys")
+                .action(ArgAction::SetTrue)
+                .help("Show the keypad with the keys held, F8 switches it on and off"),
+        )
+        .arg(
+            Arg::new("trace")
+                .long("trace")
+                .action(ArgAction::SetTrue)
+                .help("Print every executed instruction to stderr"),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .action(ArgAction::SetTrue)
+                .help("List the most executed instructions and opcodes on exit"),
+        )
+        .arg(
+            Arg::new("coverage")
+                .long("coverage")
+                .action(ArgAction::SetTrue)
+                .help("List the parts of the ROM never executed on exit"),
+        )
+        .arg(
+            Arg::new("play")
+                .long("play")
+                .value_name("MOVIE")
+                .help("Replay an input movie recorded with F7"),
+        )
+        .arg(
+            Arg::new("host")
+                .long("host")
+                .value_name("ADDR:PORT")
+                .conflicts_with_all(["join", "play"])
+                .help("Wait for a second player to join"),
+        )
+        .arg(
+            Arg::new("join")
+                .long("join")
+                .value_name("ADDR:PORT")
+                .conflicts_with("play")
+                .help("Join a game hosted with --host"),
+        )
+}
+
+// a font name, or the absolute path of a font file to remember
+fn parse_font(value: &str) -> Result<String, String> {
+    if let Some(font) = Font::from_name(value) {
+        return Ok(font.name().to_string());
+    }
+    read_font(value)?;
+    Ok(std::fs::canonicalize(value)
+        .map_or_else(|_| value.to_string(), |path| path.display().to_string()))
+}
+
+/// The sprites of the built-in font `value` names, or of the font file at
+/// `value`: the 4x5 font optionally followed by the SUPER-CHIP 8x10 one.
+pub fn load_font(value: &str) -> Result<Vec<u8>, String> {
+    match Font::from_name(value) {
+        Some(font) => Ok(font.glyphs().to_vec()),
+        None => read_font(value),
+    }
+}
+
+fn read_font(path: &str) -> Result<Vec<u8>, String> {
+    let font = std::fs::read(path).map_err(|err| err.to_string())?;
+    let sizes = [FONTSET_SIZE, FONTSET_SIZE + BIG_FONTSET_SIZE];
+    if !sizes.contains(&font.len()) {
+        return Err(format!(
+            "expected {} or {} bytes, found {}",
+            sizes[0],
+            sizes[1],
+            font.len()
+        ));
+    }
+    Ok(font)
+}
+
+// two colors for monochrome programs or all four
+pub fn parse_palette(colors: &str) -> Result<Palette, String> {
+    let colors = colors
+        .split(',')
+        .map(parse_color)
+        .collect::<Result<Vec<_>, _>>()?;
+    match colors[..] {
+        [off, on] => Ok(Palette::monochrome(off, on)),
+        _ if colors.len() == NUM_COLORS => {
+            let mut palette = Palette::default();
+            palette.colors.copy_from_slice(&colors);
+            Ok(palette)
+        }
+        _ => Err(format!("expected 2 or {NUM_COLORS} colors")),
+    }
+}
+
+fn parse_color(color: &str) -> Result<Rgb, String> {
+    let color = color.trim().trim_start_matches('#');
+    let value = u32::from_str_radix(color, 16)
+        .ok()
+        .filter(|_| color.len() == 6)
+        .ok_or_else(|| format!("{color:?} isn't a RRGGBB color"))?;
+    let [_, r, g, b] = value.to_be_bytes();
+    Ok([r, g, b])
+}
@@ -0,0 +1,261 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use chip8::romdb::rom_hash;
+use chip8::screen::Palette;
+use chip8::Quirks;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{load_font, parse_palette};
+use crate::keymap::Layout;
+
+/// Settings file in [`config_dir`], e.g.
+///
+/// ```toml
+/// scale = 10
+/// ips = 700
+/// palette = "000000,33ff66"
+/// filter = "crt"
+/// phosphor = true
+/// quirks = "vip"
+/// layout = "azerty"
+/// font = "vip"
+/// recent = ["/home/me/ROMs/INVADERS"]
+///
+/// [games.8c0b5e6ee1e3c5d6]
+/// ips = 1000
+/// quirks = "schip"
+/// ```
+///
+/// Every setting but `recent` is the default of the command line option of
+/// the same name, and those under `games` apply to the ROM of that hash
+/// only, see [`GameConfig`]. The display filter, phosphor persistence and
+/// scale given on the command line or picked while playing and the ROMs
+/// played are written back on exit.
+pub const CONFIG_FILE: &str = "config.toml";
+
+// ROMs remembered for the browser, the latest first
+const MAX_RECENT: usize = 10;
+
+/// Keymap file in [`config_dir`], used unless `CHIP8_KEYMAP` names another.
+pub const KEYMAP_FILE: &str = "keymap.toml";
+
+/// The settings kept between runs, as written in [`CONFIG_FILE`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ips: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub palette: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quirks: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+    /// A built-in font by name, see [`chip8::Font`], or a font file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phosphor: Option<bool>,
+    /// Paths of the ROMs played last, the latest first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub recent: Vec<String>,
+    /// Settings of single ROMs, by the hex [`rom_hash`] of the ROM.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub games: BTreeMap<String, GameConfig>,
+}
+
+/// The settings remembered for one ROM, over the defaults of [`Config`]:
+/// the ones given on the command line when playing it, the speed set with
+/// the - and = keys and the keyboard layout picked with F4.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ips: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub palette: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quirks: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font: Option<String>,
+}
+
+impl GameConfig {
+    /// These settings, the ones of `defaults` where these have none.
+    fn or(self, defaults: GameConfig) -> GameConfig {
+        GameConfig {
+            ips: self.ips.or(defaults.ips),
+            palette: self.palette.or(defaults.palette),
+            quirks: self.quirks.or(defaults.quirks),
+            layout: self.layout.or(defaults.layout),
+            font: self.font.or(defaults.font),
+        }
+    }
+}
+
+/// The settings to play a ROM with, see [`Config::game`].
+pub struct GameSettings {
+    /// Instructions per second, the ROM database's or the default otherwise.
+    pub ips: Option<u32>,
+    pub palette: Palette,
+    /// Overrides the ROM database's quirks.
+    pub quirks: Option<Quirks>,
+    /// Overrides the keymap's keyboard layout.
+    pub layout: Option<Layout>,
+    /// Digit sprites replacing the built-in ones, the 4x5 font optionally
+    /// followed by the SUPER-CHIP 8x10 one.
+    pub font: Option<Vec<u8>>,
+}
+
+impl Config {
+    /// Reads [`CONFIG_FILE`], the defaults when there is none or it can't
+    /// be read.
+    pub fn load() -> Self {
+        let Some(path) = config_dir().map(|dir| dir.join(CONFIG_FILE)) else {
+            return Self::default();
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => {
+                eprintln!("{}: {err}", path.display());
+                return Self::default();
+            }
+        };
+        toml::from_str(&text).unwrap_or_else(|err| {
+            eprintln!("{}: {err}", path.display());
+            Self::default()
+        })
+    }
+
+    /// Puts `rom` first in the recently played ROMs.
+    pub fn add_recent(&mut self, rom: &str) {
+        let path = fs::canonicalize(rom)
+            .map_or_else(|_| rom.to_string(), |path| path.display().to_string());
+        self.recent.retain(|recent| *recent != path);
+        self.recent.insert(0, path);
+        self.recent.truncate(MAX_RECENT);
+    }
+
+    /// Remembers the settings `given` for `rom`, over the ones it had.
+    pub fn remember(&mut self, rom: &[u8], given: &GameConfig) {
+        let game = self.game_mut(rom);
+        *game = given.clone().or(game.clone());
+    }
+
+    /// The settings remembered for `rom`, to change them.
+    pub fn game_mut(&mut self, rom: &[u8]) -> &mut GameConfig {
+        self.games
+            .entry(format!("{:016x}", rom_hash(rom)))
+            .or_default()
+    }
+
+    /// The settings to play `rom` with, the ones remembered for it over the
+    /// defaults. Settings the file got wrong are left out with a warning.
+    pub fn game(&self, rom: &[u8]) -> GameSettings {
+        let defaults = GameConfig {
+            ips: self.ips,
+            palette: self.palette.clone(),
+            quirks: self.quirks.clone(),
+            layout: self.layout.clone(),
+            font: self.font.clone(),
+        };
+        let game = self
+            .games
+            .get(&format!("{:016x}", rom_hash(rom)))
+            .cloned()
+            .unwrap_or_default()
+            .or(defaults);
+        let palette = game.palette.as_deref().and_then(|colors| {
+            parse_palette(colors)
+                .map_err(|err| eprintln!("{CONFIG_FILE}: palette: {err}"))
+                .ok()
+        });
+        let font = game.font.as_deref().and_then(|font| {
+            load_font(font)
+                .map_err(|err| eprintln!("{CONFIG_FILE}: font {font:?}: {err}"))
+                .ok()
+        });
+        GameSettings {
+            ips: game.ips.filter(|&ips| ips > 0),
+            palette: palette.unwrap_or_default(),
+            quirks: named("quirks", game.quirks.as_deref(), quirks_from_name),
+            layout: named("layout", game.layout.as_deref(), Layout::from_name),
+            font,
+        }
+    }
+
+    /// Writes [`CONFIG_FILE`], creating the config directory.
+    pub fn save(&self) -> io::Result<()> {
+        let dir = config_dir().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "no config directory on this system",
+            )
+        })?;
+        fs::create_dir_all(&dir)?;
+        let text = toml::to_string(self).map_err(io::Error::other)?;
+        fs::write(dir.join(CONFIG_FILE), text)
+    }
+}
+
+/// Where the settings live: `$XDG_CONFIG_HOME/chip8` or `~/.config/chip8`
+/// on Linux, `~/Library/Application Support/chip8` on macOS and
+/// `%APPDATA%\chip8` on Windows.
+pub fn config_dir() -> Option<PathBuf> {
+    let home = || env::var_os("HOME").map(PathBuf::from);
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            // the spec says to ignore relative paths
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| home().map(|home| home.join(".config")))
+    };
+    base.map(|dir| dir.join("chip8"))
+}
+
+/// Where saves live: `$XDG_DATA_HOME/chip8` or `~/.local/share/chip8` on
+/// Linux, the [`config_dir`] on macOS and Windows.
+pub fn data_dir() -> Option<PathBuf> {
+    if cfg!(windows) || cfg!(target_os = "macos") {
+        return config_dir();
+    }
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .map(|dir| dir.join("chip8"))
+}
+
+/// A setting by name, warning about the names the config file got wrong.
+pub fn named<T>(setting: &str, name: Option<&str>, from_name: fn(&str) -> Option<T>) -> Option<T> {
+    let name = name?;
+    let value = from_name(name);
+    if value.is_none() {
+        eprintln!("{CONFIG_FILE}: unknown {setting} {name:?}");
+    }
+    value
+}
+
+fn quirks_from_name(name: &str) -> Option<Quirks> {
+    match name {
+        "default" => Some(Quirks::default()),
+        "vip" => Some(Quirks::VIP),
+        "schip" => Some(Quirks::SUPER_CHIP),
+        _ => None,
+    }
+}
@@ -0,0 +1,229 @@
+//! Software post-processing of the display for the look of a CRT, see
+//! [`Filter`], and the phosphor persistence hiding flicker.
+
+use std::fmt;
+
+use chip8::screen::{FrameBuffer, Palette};
+
+/// Size the display is filtered at, a multiple of both resolutions so
+/// display pixels stay square blocks.
+pub const FILTERED_WIDTH: usize = 512;
+pub const FILTERED_HEIGHT: usize = 256;
+
+// brightness of the gap at the bottom of every display row
+const SCANLINE_BRIGHTNESS: f32 = 0.45;
+// blur radius of the glow in filtered pixels, and how much of it is added
+const GLOW_RADIUS: usize = 5;
+const GLOW_STRENGTH: f32 = 0.8;
+// how far the edges bend away, as a share of the distance to the center
+const CURVATURE: f32 = 0.06;
+// share of its light a pixel keeps from one frame to the next with phosphor
+// persistence, a sprite erased and drawn again every frame barely dims
+const PHOSPHOR_DECAY: f32 = 0.6;
+
+/// How the display is drawn when scaled up to the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    /// Sharp pixels, stretched by SDL without post-processing.
+    None,
+    /// A dark gap between the rows of display pixels.
+    Scanlines,
+    /// Lit pixels bleed light onto their neighbours.
+    Glow,
+    /// Scanlines, glow and a screen bulging like a tube.
+    Crt,
+}
+
+impl Filter {
+    pub const ALL: [Filter; 4] = [Filter::None, Filter::Scanlines, Filter::Glow, Filter::Crt];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Filter::None => "none",
+            Filter::Scanlines => "scanlines",
+            Filter::Glow => "glow",
+            Filter::Crt => "crt",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Filter> {
+        Filter::ALL
+            .into_iter()
+            .find(|filter| filter.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The filter after this one, wrapping around.
+    pub fn next(self) -> Filter {
+        let idx = Filter::ALL.iter().position(|&filter| filter == self);
+        Filter::ALL[idx.map_or(0, |idx| (idx + 1) % Filter::ALL.len())]
+    }
+
+    fn scanlines(self) -> bool {
+        matches!(self, Filter::Scanlines | Filter::Crt)
+    }
+
+    fn glow(self) -> bool {
+        matches!(self, Filter::Glow | Filter::Crt)
+    }
+
+    fn curvature(self) -> bool {
+        self == Filter::Crt
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Renders the display through a [`Filter`] into an RGB24 image of
+/// [`FILTERED_WIDTH`] by [`FILTERED_HEIGHT`], reusing its buffers between
+/// frames.
+#[derive(Default)]
+pub struct Renderer {
+    light: Vec<[f32; 3]>,
+    // the light of the last frame, fading with phosphor persistence
+    afterglow: Vec<[f32; 3]>,
+    blur: Vec<[f32; 3]>,
+    rows: Vec<[f32; 3]>,
+    rgb: Vec<u8>,
+}
+
+impl Renderer {
+    /// With `phosphor` pixels turned off fade out over a few frames instead
+    /// of disappearing at once, like on the phosphor of a tube, so sprites
+    /// erased and drawn again every frame don't flicker.
+    pub fn render(
+        &mut self,
+        frame: FrameBuffer<'_>,
+        palette: &Palette,
+        filter: Filter,
+        phosphor: bool,
+    ) -> &[u8] {
+        let (width, height) = (frame.width(), frame.height());
+        self.light.clear();
+        for y in 0..FILTERED_HEIGHT {
+            // the last quarter of every display row, wherever it falls
+            let gap =
+                filter.scanlines() && (y * height % FILTERED_HEIGHT) * 4 >= FILTERED_HEIGHT * 3;
+            let brightness = if gap { SCANLINE_BRIGHTNESS } else { 1.0 };
+            self.light.extend((0..FILTERED_WIDTH).map(|x| {
+                frame
+                    .rgb(
+                        x * width / FILTERED_WIDTH,
+                        y * height / FILTERED_HEIGHT,
+                        palette,
+                    )
+                    .map(|c| c as f32 * brightness)
+            }));
+        }
+
+        if phosphor {
+            // fading towards the background, whatever its color
+            let background = palette.color(0).map(f32::from);
+            self.afterglow.resize(self.light.len(), background);
+            for (pixel, afterglow) in self.light.iter_mut().zip(&mut self.afterglow) {
+                for c in 0..3 {
+                    let faded = background[c] + (afterglow[c] - background[c]) * PHOSPHOR_DECAY;
+                    pixel[c] = if (pixel[c] - background[c]).abs() >= (faded - background[c]).abs()
+                    {
+                        pixel[c]
+                    } else {
+                        faded
+                    };
+                }
+                *afterglow = *pixel;
+            }
+        } else {
+            self.afterglow.clear();
+        }
+
+        if filter.glow() {
+            // only what's brighter than the background glows
+            let background = palette.color(0).map(f32::from);
+            self.rows.resize(self.light.len(), [0.0; 3]);
+            self.blur.resize(self.light.len(), [0.0; 3]);
+            blur_lines(
+                &self.light,
+                &mut self.rows,
+                FILTERED_HEIGHT,
+                FILTERED_WIDTH,
+                1,
+            );
+            blur_lines(
+                &self.rows,
+                &mut self.blur,
+                FILTERED_WIDTH,
+                1,
+                FILTERED_WIDTH,
+            );
+            for (pixel, glow) in self.light.iter_mut().zip(&self.blur) {
+                for c in 0..3 {
+                    pixel[c] += (glow[c] - background[c]).max(0.0) * GLOW_STRENGTH;
+                }
+            }
+        }
+
+        self.rgb.clear();
+        for y in 0..FILTERED_HEIGHT {
+            for x in 0..FILTERED_WIDTH {
+                let source = if filter.curvature() {
+                    bend(x, y)
+                } else {
+                    Some((x, y))
+                };
+                let pixel = source.map_or([0.0; 3], |(x, y)| self.light[y * FILTERED_WIDTH + x]);
+                self.rgb.extend(pixel.map(|c| c.min(255.0) as u8));
+            }
+        }
+        &self.rgb
+    }
+}
+
+// averages every pixel with the GLOW_RADIUS ones on each side, along
+// `lines` lines of pixels `step` apart, starting `line_step` apart
+fn blur_lines(src: &[[f32; 3]], dst: &mut [[f32; 3]], lines: usize, line_step: usize, step: usize) {
+    let len = src.len() / lines;
+    let window = (2 * GLOW_RADIUS + 1) as f32;
+    for line in 0..lines {
+        let at = |i: usize| line * line_step + i * step;
+        // a running sum of the window, pixels past the edges are black
+        let mut sum = [0.0; 3];
+        for i in 0..GLOW_RADIUS.min(len) {
+            add(&mut sum, src[at(i)], 1.0);
+        }
+        for i in 0..len {
+            if i + GLOW_RADIUS < len {
+                add(&mut sum, src[at(i + GLOW_RADIUS)], 1.0);
+            }
+            dst[at(i)] = sum.map(|c| c / window);
+            if i >= GLOW_RADIUS {
+                add(&mut sum, src[at(i - GLOW_RADIUS)], -1.0);
+            }
+        }
+    }
+}
+
+fn add(sum: &mut [f32; 3], pixel: [f32; 3], sign: f32) {
+    for c in 0..3 {
+        sum[c] += pixel[c] * sign;
+    }
+}
+
+// the pixel shown at (x, y) of a screen bulging towards the viewer, None
+// past its edges
+fn bend(x: usize, y: usize) -> Option<(usize, usize)> {
+    let (width, height) = (FILTERED_WIDTH as f32, FILTERED_HEIGHT as f32);
+    let u = 2.0 * (x as f32 + 0.5) / width - 1.0;
+    let v = 2.0 * (y as f32 + 0.5) / height - 1.0;
+    let bend = 1.0 + CURVATURE * (u * u + v * v);
+    let (u, v) = (u * bend, v * bend);
+    if u.abs() >= 1.0 || v.abs() >= 1.0 {
+        return None;
+    }
+    Some((
+        ((u + 1.0) / 2.0 * width) as usize,
+        ((v + 1.0) / 2.0 * height) as usize,
+    ))
+}
@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+
+use sdl2::controller::GameController;
+use sdl2::event::Event;
+use sdl2::GameControllerSubsystem;
+
+/// Keeps the connected game controllers open, SDL only reports the buttons
+/// of open ones.
+pub struct Gamepads {
+    // None when SDL has no controller support, only the keyboard plays
+    subsystem: Option<GameControllerSubsystem>,
+    // by instance id, the id button events carry
+    open: HashMap<u32, GameController>,
+}
+
+impl Gamepads {
+    /// SDL sends an added event for the controllers already connected, they
+    /// are opened by [`Gamepads::handle`] like the ones plugged in later.
+    pub fn new(subsystem: Option<GameControllerSubsystem>) -> Self {
+        Self {
+            subsystem,
+            open: HashMap::new(),
+        }
+    }
+
+    /// Opens and closes controllers as they are plugged in and out.
+    pub fn handle(&mut self, event: &Event) {
+        let Some(subsystem) = &self.subsystem else {
+            return;
+        };
+        match *event {
+            Event::ControllerDeviceAdded { which, .. } => match subsystem.open(which) {
+                Ok(controller) => {
+                    println!("Controller connected: {}", controller.name());
+                    self.open.insert(controller.instance_id(), controller);
+                }
+                Err(err) => eprintln!("Can't open controller {which}: {err}"),
+            },
+            Event::ControllerDeviceRemoved { which, .. } => {
+                if let Some(controller) = self.open.remove(&which) {
+                    println!("Controller disconnected: {}", controller.name());
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! The memory editor M opens while paused, see [`HexEditor`].
+
+use chip8::CPU;
+use sdl2::keyboard::Keycode;
+
+/// Bytes per row of the editor.
+pub const COLUMNS: usize = 4;
+/// Rows shown at a time.
+pub const ROWS: usize = 8;
+
+/// A cursor over the RAM and the digits typed at it. The arrows move it,
+/// Page Up and Page Down a screen at a time, and the hex digits overwrite
+/// the byte under it, high digit first, moving on after the low one.
+pub struct HexEditor {
+    cursor: usize,
+    // the high digit of the byte at the cursor was typed
+    low: bool,
+    // address of the first row shown
+    top: usize,
+}
+
+impl HexEditor {
+    /// An editor with its cursor at I, or the last byte of the RAM when I
+    /// points past it.
+    pub fn new(cpu: &CPU) -> Self {
+        let size = cpu.read_memory(..).len();
+        let mut editor = Self {
+            cursor: (cpu.state().i_register as usize).min(size - 1),
+            low: false,
+            top: 0,
+        };
+        editor.scroll();
+        editor
+    }
+
+    /// Address of the byte under the cursor.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Address of the first byte shown.
+    pub fn top(&self) -> usize {
+        self.top
+    }
+
+    /// Handles a key, returns whether it changed the RAM of `cpu`.
+    pub fn key(&mut self, key: Keycode, cpu: &mut CPU) -> bool {
+        let size = cpu.read_memory(..).len();
+        // the RAM shrinks when a ROM without MegaChip code is loaded
+        if self.cursor >= size {
+            self.cursor = size - 1;
+            self.low = false;
+            self.scroll();
+        }
+        let moved = match key {
+            Keycode::Left => self.cursor.checked_sub(1),
+            Keycode::Right => Some(self.cursor + 1),
+            Keycode::Up => self.cursor.checked_sub(COLUMNS),
+            Keycode::Down => Some(self.cursor + COLUMNS),
+            Keycode::PageUp => Some(self.cursor.saturating_sub(COLUMNS * ROWS)),
+            Keycode::PageDown => Some(self.cursor + COLUMNS * ROWS),
+            _ => None,
+        };
+        if let Some(cursor) = moved {
+            self.cursor = cursor.min(size - 1);
+            self.low = false;
+            self.scroll();
+            return false;
+        }
+
+        let Some(digit) = hex_digit(key) else {
+            return false;
+        };
+        let byte = cpu.read_memory(self.cursor..=self.cursor)[0];
+        let byte = if self.low {
+            byte & 0xF0 | digit
+        } else {
+            byte & 0x0F | digit << 4
+        };
+        if cpu.write_memory(self.cursor, &[byte]).is_err() {
+            return false;
+        }
+        if self.low && self.cursor + 1 < size {
+            self.cursor += 1;
+            self.scroll();
+        }
+        self.low = !self.low;
+        true
+    }
+
+    // keeps the cursor's row in view
+    fn scroll(&mut self) {
+        let row = self.cursor - self.cursor % COLUMNS;
+        if row < self.top {
+            self.top = row;
+        } else if row >= self.top + COLUMNS * ROWS {
+            self.top = row + COLUMNS - COLUMNS * ROWS;
+        }
+    }
+}
+
+fn hex_digit(key: Keycode) -> Option<u8> {
+    let digit = match key {
+        Keycode::Num0 | Keycode::Kp0 => 0x0,
+        Keycode::Num1 | Keycode::Kp1 => 0x1,
+        Keycode::Num2 | Keycode::Kp2 => 0x2,
+        Keycode::Num3 | Keycode::Kp3 => 0x3,
+        Keycode::Num4 | Keycode::Kp4 => 0x4,
+        Keycode::Num5 | Keycode::Kp5 => 0x5,
+        Keycode::Num6 | Keycode::Kp6 => 0x6,
+        Keycode::Num7 | Keycode::Kp7 => 0x7,
+        Keycode::Num8 | Keycode::Kp8 => 0x8,
+        Keycode::Num9 | Keycode::Kp9 => 0x9,
+        Keycode::A => 0xA,
+        Keycode::B => 0xB,
+        Keycode::C => 0xC,
+        Keycode::D => 0xD,
+        Keycode::E => 0xE,
+        Keycode::F => 0xF,
+        _ => return None,
+    };
+    Some(digit)
+}
@@ -0,0 +1,272 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use chip8::NUM_KEYS;
+use sdl2::controller::Button;
+use sdl2::keyboard::Keycode;
+use serde::Deserialize;
+
+use crate::config::{config_dir, KEYMAP_FILE};
+
+/// Environment variable holding the path of a TOML keymap, e.g.
+///
+/// ```toml
+/// [keys]
+/// 1 = 0x1
+/// Q = 0x4
+/// Space = 0x5
+///
+/// [buttons]
+/// dpup = 0x2
+/// a = 0x5
+/// ```
+///
+/// Keys are SDL key names, buttons SDL game controller button names, values
+/// the keypad key 0x0 to 0xF. A keymap replaces the default keyboard layout
+/// entirely, and the default controller layout when it has buttons. It can
+/// start from one of the [`Layout`] presets with e.g. `layout = "azerty"`.
+pub const KEYMAP_ENV: &str = "CHIP8_KEYMAP";
+
+// the COSMAC VIP keypad, row by row
+const KEYPAD: [usize; NUM_KEYS] = [
+    0x1, 0x2, 0x3, 0xC, //
+    0x4, 0x5, 0x6, 0xD, //
+    0x7, 0x8, 0x9, 0xE, //
+    0xA, 0x0, 0xB, 0xF,
+];
+
+/// Built-in keyboard layouts, the keypad laid over the keys at the left of
+/// the keyboard whatever letters they carry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    Qwerty,
+    Azerty,
+    Dvorak,
+    /// QWERTY with the arrow keys on the 2/4/6/8 keys most games move with,
+    /// and space on 5.
+    Natural,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 4] = [
+        Layout::Qwerty,
+        Layout::Azerty,
+        Layout::Dvorak,
+        Layout::Natural,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Layout::Qwerty => "qwerty",
+            Layout::Azerty => "azerty",
+            Layout::Dvorak => "dvorak",
+            Layout::Natural => "natural",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Layout> {
+        Layout::ALL
+            .into_iter()
+            .find(|layout| layout.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The layout after this one, wrapping around.
+    pub fn next(self) -> Layout {
+        let idx = Layout::ALL.iter().position(|&layout| layout == self);
+        Layout::ALL[idx.map_or(0, |idx| (idx + 1) % Layout::ALL.len())]
+    }
+
+    fn keys(self) -> Vec<(Keycode, usize)> {
+        let grid = match self {
+            Layout::Qwerty | Layout::Natural => "1234qwerasdfzxcv",
+            // the unshifted number row of a French keyboard
+            Layout::Azerty => "&\u{e9}\"'azerqsdfwxcv",
+            Layout::Dvorak => "1234',.paoeu;qjk",
+        };
+        let mut keys: Vec<_> = grid
+            .chars()
+            .map(|c| Keycode::from_i32(c as i32).expect("the layout keys are valid"))
+            .zip(KEYPAD)
+            .collect();
+        if self == Layout::Natural {
+            keys.extend([
+                (Keycode::Up, 0x2),
+                (Keycode::Left, 0x4),
+                (Keycode::Right, 0x6),
+                (Keycode::Down, 0x8),
+                (Keycode::Space, 0x5),
+            ]);
+        }
+        keys
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// the d-pad on the 2/4/6/8 keys most games move with, A and B on the 5 and
+// 0 keys next to them
+const DEFAULT_BUTTONS: [(Button, usize); 6] = [
+    (Button::DPadUp, 0x2),
+    (Button::DPadLeft, 0x4),
+    (Button::DPadRight, 0x6),
+    (Button::DPadDown, 0x8),
+    (Button::A, 0x5),
+    (Button::B, 0x0),
+];
+
+#[derive(Debug)]
+pub enum KeymapError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    UnknownKey(String),
+    UnknownButton(String),
+    UnknownLayout(String),
+    BadButton { key: String, button: u8 },
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeymapError::Io(err) => write!(f, "can't read keymap: {err}"),
+            KeymapError::Parse(err) => write!(f, "invalid keymap: {err}"),
+            KeymapError::UnknownKey(key) => write!(f, "unknown key name {key:?}"),
+            KeymapError::UnknownLayout(name) => {
+                let names: Vec<_> = Layout::ALL.iter().map(|layout| layout.name()).collect();
+                write!(
+                    f,
+                    "unknown layout {name:?}, expected one of {}",
+                    names.join(", ")
+                )
+            }
+            KeymapError::UnknownButton(button) => {
+                write!(f, "unknown controller button name {button:?}")
+            }
+            KeymapError::BadButton { key, button } => {
+                write!(
+                    f,
+                    "{key} is mapped to {button:#X}, keypad keys go from 0x0 to 0xF"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for KeymapError {}
+
+#[derive(Deserialize)]
+struct KeymapFile {
+    /// A preset to start from, `keys` then overrides some of its keys.
+    layout: Option<String>,
+    #[serde(default)]
+    keys: BTreeMap<String, u8>,
+    #[serde(default)]
+    buttons: BTreeMap<String, u8>,
+}
+
+/// Maps keyboard keys and game controller buttons to keypad keys.
+pub struct Keymap {
+    keys: HashMap<Keycode, usize>,
+    buttons: HashMap<Button, usize>,
+    // None for the keys of a keymap file
+    layout: Option<Layout>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            keys: Layout::Qwerty.keys().into_iter().collect(),
+            buttons: DEFAULT_BUTTONS.into_iter().collect(),
+            layout: Some(Layout::Qwerty),
+        }
+    }
+}
+
+impl Keymap {
+    /// Loads the keymap named by [`KEYMAP_ENV`], otherwise the one in the
+    /// config directory if there is one, or the default layout.
+    pub fn from_env() -> Result<Self, KeymapError> {
+        if let Some(path) = std::env::var_os(KEYMAP_ENV) {
+            return Self::load(Path::new(&path));
+        }
+        match config_dir().map(|dir| dir.join(KEYMAP_FILE)) {
+            Some(path) if path.exists() => Self::load(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, KeymapError> {
+        let text = fs::read_to_string(path).map_err(KeymapError::Io)?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, KeymapError> {
+        let file: KeymapFile = toml::from_str(text).map_err(KeymapError::Parse)?;
+        let mut layout = match file.layout {
+            Some(name) => Some(Layout::from_name(&name).ok_or(KeymapError::UnknownLayout(name))?),
+            None => None,
+        };
+        let mut keys: HashMap<_, _> = layout
+            .map(|layout| layout.keys().into_iter().collect())
+            .unwrap_or_default();
+        if !file.keys.is_empty() {
+            layout = None;
+        }
+        for (name, button) in file.keys {
+            check_button(&name, button)?;
+            let key = Keycode::from_name(&name).ok_or(KeymapError::UnknownKey(name))?;
+            keys.insert(key, button as usize);
+        }
+        let mut buttons = HashMap::with_capacity(file.buttons.len());
+        for (name, button) in file.buttons {
+            check_button(&name, button)?;
+            let pad = Button::from_string(&name).ok_or(KeymapError::UnknownButton(name))?;
+            buttons.insert(pad, button as usize);
+        }
+        if buttons.is_empty() {
+            buttons = DEFAULT_BUTTONS.into_iter().collect();
+        }
+        Ok(Self {
+            keys,
+            buttons,
+            layout,
+        })
+    }
+
+    /// The preset the keyboard is mapped with, if the keys weren't
+    /// customized.
+    pub fn layout(&self) -> Option<Layout> {
+        self.layout
+    }
+
+    /// Maps the keyboard with a preset, keeping the controller buttons.
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.keys = layout.keys().into_iter().collect();
+        self.layout = Some(layout);
+    }
+
+    pub fn button(&self, key: Keycode) -> Option<usize> {
+        self.keys.get(&key).copied()
+    }
+
+    /// The keypad key a game controller button presses.
+    pub fn controller_button(&self, button: Button) -> Option<usize> {
+        self.buttons.get(&button).copied()
+    }
+}
+
+fn check_button(key: &str, button: u8) -> Result<(), KeymapError> {
+    if button as usize >= NUM_KEYS {
+        return Err(KeymapError::BadButton {
+            key: key.to_string(),
+            button,
+        });
+    }
+    Ok(())
+}
@@ -1,36 +1,93 @@
 use chip8::{
-    screen::{SCREEN_HEIGHT, SCREEN_WIDTH},
-    CPU,
+    frontend::AudioSink,
+    movie::{Movie, MoviePlayer, MovieRecorder},
+    screen::{Palette, MEGACHIP_SCREEN_HEIGHT, MEGACHIP_SCREEN_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH},
+    trace::TraceEntry,
+    Chip8Builder, Quirks, RomError, CHIP8X_START_ADDR, CPU, DEFAULT_REWIND_FRAMES, FONTSET_SIZE,
+    NUM_FLAGS, START_ADDR,
 };
-use sdl2::event::Event;
+use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
-use sdl2::pixels::Color;
+use sdl2::messagebox::{self, ButtonData, ClickedButton, MessageBoxButtonFlag, MessageBoxFlag};
+use sdl2::pixels::{Color, PixelFormatEnum};
 use sdl2::rect::Rect;
-use sdl2::render::Canvas;
+use sdl2::render::{Canvas, Texture};
 use sdl2::video::Window;
-use std::{env, fs::File, io::Read};
+use sdl2::EventPump;
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{env, fs, fs::File, io::Read};
 
-const TICKS_PER_FRAME: usize = 10;
+mod audio;
+mod browser;
+mod capture;
+mod cli;
+mod config;
+mod filter;
+mod gamepad;
+mod hex_editor;
+mod keymap;
+mod menu;
+mod metrics;
+mod netplay;
+mod overlay;
+mod pause_menu;
+mod saves;
 
-const SCALE: u32 = 15;
-const WINDOW_WIDTH: u32 = (SCREEN_WIDTH as u32) * SCALE;
-const WINDOW_HEIGHT: u32 = (SCREEN_HEIGHT as u32) * SCALE;
+use audio::Beeper;
+use capture::GifRecorder;
+use cli::Options;
+use config::Config;
+use filter::{Filter, Renderer, FILTERED_HEIGHT, FILTERED_WIDTH};
+use gamepad::Gamepads;
+use hex_editor::HexEditor;
+use keymap::{Keymap, Layout};
+use metrics::{FrameMeter, Metrics};
+use netplay::Netplay;
+use overlay::DebugPanel;
+use pause_menu::{PauseMenu, Settings};
+use saves::SaveFile;
+
+// addresses listed by --profile
+const PROFILE_REPORT_LEN: usize = 20;
+
+// F11 runs the game this many times slower, unless --slow-motion says
+const DEFAULT_SLOW_MOTION: u32 = 4;
+
+// the - and = hotkeys change the speed by this many instructions per second
+const SPEED_STEP_HZ: u32 = 60;
+
+const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+const WINDOW_TITLE: &str = "Chip-8 CPU Emulator";
 
 fn main() {
-    let args: Vec<_> = env::args().collect();
-    if args.len() != 2 {
-        println!("Usage: cargo run path-to-game");
+    let mut options = Options::parse(Config::load());
+
+    let mut keymap = Keymap::from_env().unwrap_or_else(|err| {
+        eprintln!("{err}");
         std::process::exit(1);
-    }
+    });
 
     let sdl_context = sdl2::init().expect("Failed to init SDL2 lib");
     let video_subsystem = sdl_context.video().unwrap();
-    let window = video_subsystem
-        .window("Chip-8 CPU Emulator", WINDOW_WIDTH, WINDOW_HEIGHT)
+    let mut window = video_subsystem
+        .window(
+            WINDOW_TITLE,
+            SCREEN_WIDTH as u32 * options.scale,
+            SCREEN_HEIGHT as u32 * options.scale,
+        )
         .position_centered()
         .opengl()
+        .resizable()
         .build()
         .expect("Failed to create window");
+    window
+        .set_minimum_size(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
+        .expect("Failed to set the minimum window size");
+    let (window_width, window_height) = window.size();
 
     let mut canvas = window
         .into_canvas()
@@ -40,94 +97,1204 @@ fn main() {
     canvas.clear();
     canvas.present();
 
+    let texture_creator = canvas.texture_creator();
+    let mut texture = texture_creator
+        .create_texture_streaming(
+            PixelFormatEnum::RGB24,
+            MEGACHIP_SCREEN_WIDTH as u32,
+            MEGACHIP_SCREEN_HEIGHT as u32,
+        )
+        .expect("Failed to create screen texture");
+    let mut filtered = texture_creator
+        .create_texture_streaming(
+            PixelFormatEnum::RGB24,
+            FILTERED_WIDTH as u32,
+            FILTERED_HEIGHT as u32,
+        )
+        .expect("Failed to create filtered screen texture");
+
     let mut event_pump = sdl_context.event_pump().expect("Failed to get event pump");
+    // the keyboard still plays without controller support
+    let mut gamepads = Gamepads::new(
+        sdl_context
+            .game_controller()
+            .map_err(|err| eprintln!("Game controllers disabled: {err}"))
+            .ok(),
+    );
+
+    // games still run without a sound card
+    let mut beeper = if options.mute {
+        None
+    } else {
+        sdl_context
+            .audio()
+            .and_then(|audio| Beeper::new(&audio))
+            .map_err(|err| eprintln!("Sound disabled: {err}"))
+            .ok()
+    };
+
+    // double-clicking the program leads to the ROM browser
+    let Some(path) = options.rom.take().or_else(|| {
+        browser::pick(
+            &mut event_pump,
+            &mut canvas,
+            &options.config.recent,
+            FRAME_DURATION,
+        )
+    }) else {
+        return;
+    };
+    let mut config = std::mem::take(&mut options.config);
+    config.add_recent(&path);
+    let rom = Game::read(&path).expect("Error reading game ROM data");
+    config.remember(&rom, &options.given);
+    let settings = config.game(&rom);
+    if let Some(layout) = settings.layout {
+        keymap.set_layout(layout);
+    }
+    let game = Game {
+        rom,
+        path,
+        ips: settings.ips,
+        quirks: settings.quirks,
+        chip8x: options.chip8x,
+        megachip: options.megachip,
+        latched_input: options.latched_input,
+        font: settings.font,
+        profile: options.profile,
+        coverage: options.coverage,
+        trace: options.trace,
+    };
+    if options.lint {
+        game.lint();
+    }
+    let mut chip8 = game.start().unwrap_or_else(|err| {
+        eprintln!("{}: {err}", game.path);
+        std::process::exit(1);
+    });
 
-    let mut chip8 = CPU::default();
+    let autosave = SaveFile::autosave(&game.rom);
+    let playback = match &options.play {
+        Some(path) => {
+            let movie = fs::read(path)
+                .map_err(|err| err.to_string())
+                .and_then(|data| Movie::from_bytes(&data).map_err(|err| err.to_string()))
+                .and_then(|movie| {
+                    MoviePlayer::start(movie, &mut chip8).map_err(|err| err.to_string())
+                });
+            match movie {
+                Ok(player) => Some(player),
+                Err(err) => {
+                    eprintln!("Can't play {path}: {err}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+    // a joining peer starts from the host's machine
+    if let (None, None, Some(state)) = (&playback, &options.join, autosave.load()) {
+        if ask_resume(canvas.window()) {
+            if let Err(err) = chip8.load_state(&state) {
+                eprintln!("Ignoring autosave: {err}");
+            }
+        }
+    }
+    let mut frames_since_save = 0;
 
-    let mut rom = File::open(&args[1]).expect("Unable to open file");
-    let mut buffer = Vec::new();
+    let netplay = match (&options.host, &options.join) {
+        (Some(addr), _) => Some(Netplay::host(addr, &game.rom, &mut chip8)),
+        (_, Some(addr)) => Some(Netplay::join(addr, &game.rom, &mut chip8)),
+        _ => None,
+    };
+    let netplay = netplay.map(|netplay| {
+        netplay.unwrap_or_else(|err| {
+            eprintln!("Netplay failed: {err}");
+            std::process::exit(1);
+        })
+    });
 
-    rom.read_to_end(&mut buffer)
-        .expect("Error reading game ROM data");
-    chip8.load(&buffer);
+    let metrics = Arc::new(Metrics::default());
+    if let Ok(addr) = env::var(metrics::METRICS_ADDR_ENV) {
+        metrics::serve(&addr, metrics.clone()).expect("Failed to start metrics server");
+    }
+    let mut frame_meter = FrameMeter::new(metrics, FRAME_DURATION);
+    let mut session = Session {
+        quicksave: SaveFile::quicksave(&game.rom),
+        game,
+        autosave,
+        keymap,
+        pause: Pause::default(),
+        recording: None,
+        palette: settings.palette,
+        movie: None,
+        playback,
+        netplay,
+        rewinding: false,
+        turbo: false,
+        slow_motion: SlowMotion {
+            on: options.slow_motion.is_some(),
+            divisor: options.slow_motion.unwrap_or(DEFAULT_SLOW_MOTION),
+            phase: 0,
+        },
+        overlay: false,
+        debug_panel: DebugPanel::new(&canvas),
+        show_keys: options.show_keys,
+        menu: None,
+        editor: None,
+        browse: false,
+        window_size: (window_width, window_height),
+        display_size: chip8.display_size(),
+        viewport: letterbox((window_width, window_height), chip8.display_size()),
+        filter: options.filter,
+        phosphor: options.phosphor,
+        renderer: Renderer::default(),
+        config,
+    };
 
+    // a state of the machine stopped by an error would stop again on resume
+    let mut crashed = false;
     'gameloop: loop {
-        for evt in event_pump.poll_iter() {
-            match evt {
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                } => {
+        let commands = pump_events(&mut event_pump, &mut gamepads, &session);
+        if !run_commands(commands, &mut chip8, &mut session, 0) {
+            break 'gameloop;
+        }
+        if std::mem::take(&mut session.browse) {
+            let picked = browser::pick(
+                &mut event_pump,
+                &mut canvas,
+                &session.config.recent,
+                FRAME_DURATION,
+            );
+            if let Some(path) = picked {
+                if let Err(err) = session.autosave.save(&chip8) {
+                    eprintln!("Autosave failed: {err}");
+                }
+                session.config.add_recent(&path);
+                open_rom(&mut chip8, &mut session, path);
+            }
+        }
+        // the game goes on behind the menu during netplay, the peer can't wait
+        let menu_pause = session.menu.is_some() && session.netplay.is_none();
+        let rewind = session.rewinding && !menu_pause;
+        let title = if rewind {
+            format!("{WINDOW_TITLE} (rewinding)")
+        } else if session.pause.paused || menu_pause {
+            format!("{WINDOW_TITLE} (paused)")
+        } else if session.turbo {
+            format!("{WINDOW_TITLE} (fast-forward)")
+        } else if session.slow_motion.on {
+            format!("{WINDOW_TITLE} (slow motion)")
+        } else if chip8.is_waiting_for_key() {
+            format!("{WINDOW_TITLE} (press a key)")
+        } else {
+            WINDOW_TITLE.to_string()
+        };
+        if canvas.window().title() != title {
+            let _ = canvas.window_mut().set_title(&title);
+        }
+        // one frame back for every frame the key is held
+        if rewind {
+            chip8.rewind(1);
+        }
+        if rewind || menu_pause || !session.pause.take_frame() {
+            if let Some(beeper) = &mut beeper {
+                beeper.set_playing(false);
+            }
+            // keep the overlay in sync with toggles while paused
+            draw_screen(
+                &mut chip8,
+                &mut session,
+                &mut canvas,
+                &mut texture,
+                &mut filtered,
+            );
+            std::thread::sleep(FRAME_DURATION);
+            continue;
+        }
+        if let Some(netplay) = &mut session.netplay {
+            if let Err(err) = netplay.sync_frame(&mut chip8) {
+                eprintln!("Netplay ended: {err}");
+                session.netplay = None;
+            }
+        }
+        // turbo runs several frames for every one shown, steps stay single
+        let frames = if session.turbo && !session.pause.paused {
+            options.turbo
+        } else {
+            1
+        };
+        let ticks_per_frame = chip8.ticks_per_frame();
+        let mut ticks_run = 0;
+        for _ in 0..frames {
+            let frame_start = Instant::now();
+            // slow motion spreads a frame over several, steps stay whole
+            let (ticks, frame_ends) = session
+                .slow_motion
+                .slice(ticks_per_frame, session.pause.paused);
+            ticks_run += ticks.len();
+            let first = ticks.start;
+            let slice_len = ticks.len().max(1) as u32;
+            for tick in ticks {
+                if options.per_tick_input && frames == 1 {
+                    // spread the ticks over the frame so each one sees fresh input
+                    let slot = frame_start + FRAME_DURATION * (tick - first) as u32 / slice_len;
+                    std::thread::sleep(slot.saturating_duration_since(Instant::now()));
+                    let commands = pump_events(&mut event_pump, &mut gamepads, &session);
+                    if !run_commands(commands, &mut chip8, &mut session, tick as u16) {
+                        break 'gameloop;
+                    }
+                }
+                if let Some(player) = &mut session.playback {
+                    player.apply(&mut chip8, tick as u16);
+                }
+                if let Err(err) = chip8.tick() {
+                    eprintln!("Emulation stopped: {err}");
+                    crashed = true;
                     break 'gameloop;
                 }
-                Event::KeyDown {
-                    keycode: Some(key), ..
-                } => {
-                    if let Some(k) = key2btn(key) {
-                        chip8.keypress(k, true);
+            }
+            if !frame_ends {
+                continue;
+            }
+            chip8.tick_timers();
+            if let Some(beeper) = &mut beeper {
+                beeper.set_playing(chip8.is_sound_playing());
+            }
+            if let Some(movie) = &mut session.movie {
+                movie.end_frame();
+            }
+            if session.pause.paused {
+                release_latched(&mut chip8, &mut session);
+            }
+            if let Some(player) = &mut session.playback {
+                player.end_frame();
+                if player.is_finished() {
+                    println!("Movie finished");
+                    session.playback = None;
+                }
+            }
+        }
+        draw_screen(
+            &mut chip8,
+            &mut session,
+            &mut canvas,
+            &mut texture,
+            &mut filtered,
+        );
+        if let Some(recorder) = &mut session.recording {
+            if let Err(err) = recorder.add_frame(&chip8) {
+                eprintln!("Recording failed: {err}");
+                session.recording = None;
+            }
+        }
+        frame_meter.end_frame(ticks_run as u64);
+
+        frames_since_save += 1;
+        if frames_since_save >= saves::AUTOSAVE_INTERVAL {
+            frames_since_save = 0;
+            if let Err(err) = session.autosave.save(&chip8) {
+                eprintln!("Autosave failed: {err}");
+            }
+        }
+    }
+
+    if !crashed {
+        if let Err(err) = session.autosave.save(&chip8) {
+            eprintln!("Autosave failed: {err}");
+        }
+    }
+    session.config.filter = Some(session.filter.name().into());
+    session.config.phosphor = Some(session.phosphor);
+    if let Err(err) = session.config.save() {
+        eprintln!("Saving settings failed: {err}");
+    }
+    if options.profile {
+        println!("Most executed instructions:");
+        for spot in chip8.hot_spots(PROFILE_REPORT_LEN) {
+            println!("{spot}");
+        }
+        if let Some(stats) = chip8.execution_stats() {
+            println!("By opcode: {stats}");
+        }
+    }
+    if let Some(coverage) = chip8.coverage() {
+        let start = session.game.start_addr() as usize;
+        let rom = start..start + session.game.rom.len();
+        println!(
+            "Executed {:.1}% of the ROM, never:",
+            coverage.share(rom.clone()) * 100.0
+        );
+        for gap in coverage.gaps(rom) {
+            println!("0x{:03X}-0x{:03X}", gap.start, gap.end - 1);
+        }
+    }
+    if let Some(recorder) = session.recording {
+        finish_recording(recorder);
+    }
+    if let Some(movie) = session.movie {
+        finish_movie(movie);
+    }
+}
+
+/// Frontend actions bound to hotkeys.
+enum Command {
+    Quit,
+    /// Open the pause menu, or go back out of it.
+    Menu,
+    /// A key pressed while the pause menu is open.
+    MenuKey(Keycode),
+    /// Open or close the memory editor.
+    ToggleEditor,
+    /// A key pressed while the memory editor is open.
+    EditorKey(Keycode),
+    /// Pick another ROM in the browser and play it.
+    OpenRom,
+    SaveState,
+    LoadState,
+    TogglePause,
+    Step,
+    ToggleRecording,
+    ToggleMovie,
+    Screenshot,
+    SpeedUp,
+    SlowDown,
+    ToggleOverlay,
+    /// Show or hide the keypad display.
+    ToggleKeys,
+    /// Switch to the next keyboard layout preset.
+    NextLayout,
+    /// Switch to the next display filter.
+    NextFilter,
+    /// Turn phosphor persistence on or off.
+    TogglePhosphor,
+    /// The window was resized to this width and height.
+    Resize(u32, u32),
+    /// Start the ROM over.
+    Reset,
+    /// Read the ROM from disk again and start it over.
+    Reload,
+    /// Turn slow motion on or off.
+    ToggleSlowMotion,
+    /// Start or stop running several frames per frame, while the key is
+    /// held.
+    Turbo(bool),
+    /// Start or stop rewinding, while the key is held.
+    Rewind(bool),
+    /// A keypad key pressed or released.
+    Key(usize, bool),
+}
+
+/// The ROM being played and how it was started, to start it over.
+struct Game {
+    path: String,
+    rom: Vec<u8>,
+    ips: Option<u32>,
+    quirks: Option<Quirks>,
+    chip8x: bool,
+    megachip: bool,
+    latched_input: bool,
+    // the small font, then the big one if it has both
+    font: Option<Vec<u8>>,
+    profile: bool,
+    coverage: bool,
+    trace: bool,
+}
+
+impl Game {
+    fn read(path: &str) -> std::io::Result<Vec<u8>> {
+        let mut rom = Vec::new();
+        File::open(path)?.read_to_end(&mut rom)?;
+        Ok(rom)
+    }
+
+    // where the ROM is loaded
+    fn start_addr(&self) -> u16 {
+        if self.chip8x {
+            CHIP8X_START_ADDR
+        } else {
+            START_ADDR
+        }
+    }
+
+    /// Prints what looks wrong in the ROM, see [`chip8::lint`].
+    fn lint(&self) {
+        for warning in chip8::lint::lint(&self.rom, self.start_addr()) {
+            eprintln!("{}:{warning}", self.path);
+        }
+    }
+
+    /// A CPU running the ROM from the start with its settings.
+    fn start(&self) -> Result<CPU, RomError> {
+        let mut builder = Chip8Builder::new().rom(&self.rom);
+        if self.chip8x {
+            builder = builder.chip8x().quirks(Quirks::VIP);
+        }
+        if self.megachip {
+            builder = builder.megachip();
+        }
+        if self.latched_input {
+            builder = builder.latched_input();
+        }
+        if let Some(font) = &self.font {
+            let (small, big) = font.split_at(FONTSET_SIZE);
+            builder = builder.font(small.try_into().expect("the font size is checked"));
+            if let Ok(big) = big.try_into() {
+                builder = builder.big_font(big);
+            }
+        }
+        let mut chip8 = builder.build()?;
+        if let Some(info) = chip8.configure_for_rom(&self.rom) {
+            println!("Playing {}", info.title);
+        }
+        if let Some(hz) = self.ips {
+            chip8.set_clock_hz(hz);
+        }
+        if let Some(quirks) = self.quirks {
+            chip8.set_quirks(quirks);
+        }
+        let flags = SaveFile::flags(&self.rom);
+        if let Some(saved) = flags.load() {
+            chip8.set_flags(&saved);
+        }
+        chip8.set_flags_sink(Some(Box::new(move |stored: &[u8; NUM_FLAGS]| {
+            if let Err(err) = flags.write(stored) {
+                eprintln!("Failed to save the user flags: {err}");
+            }
+        })));
+        chip8.set_profiling(self.profile);
+        chip8.set_coverage(self.coverage);
+        chip8.set_rewind_depth(DEFAULT_REWIND_FRAMES);
+        if self.trace {
+            chip8.set_tracer(Some(Box::new(|entry: &TraceEntry| eprintln!("{entry}"))));
+        }
+        Ok(chip8)
+    }
+}
+
+/// Frontend state the hotkey commands act on.
+struct Session {
+    game: Game,
+    autosave: SaveFile,
+    keymap: Keymap,
+    quicksave: SaveFile,
+    pause: Pause,
+    recording: Option<GifRecorder>,
+    palette: Palette,
+    movie: Option<MovieRecorder>,
+    playback: Option<MoviePlayer>,
+    netplay: Option<Netplay>,
+    // the rewind key is held
+    rewinding: bool,
+    // the fast-forward key is held
+    turbo: bool,
+    slow_motion: SlowMotion,
+    // the debug overlay is shown
+    overlay: bool,
+    debug_panel: DebugPanel,
+    // the keypad display is shown, with the keys the game sees held
+    show_keys: bool,
+    menu: Option<PauseMenu>,
+    // the memory editor, open while paused
+    editor: Option<HexEditor>,
+    // the ROM browser is to be shown, it needs the window
+    browse: bool,
+    // the window, and the display resolution the viewport was fit to
+    window_size: (u32, u32),
+    display_size: (usize, usize),
+    // where the display is drawn in the window
+    viewport: Rect,
+    filter: Filter,
+    // pixels fade out instead of going off at once
+    phosphor: bool,
+    renderer: Renderer,
+    // saved on exit, with the settings changed while playing
+    config: Config,
+}
+
+/// Pause state, while paused frames only run one at a time on request.
+#[derive(Default)]
+struct Pause {
+    paused: bool,
+    steps: u32,
+    // keys released while paused, held through the next frame so a tap
+    // between two frame advances isn't lost
+    released: Vec<usize>,
+}
+
+impl Pause {
+    /// Whether the next frame should run.
+    fn take_frame(&mut self) -> bool {
+        if !self.paused {
+            return true;
+        }
+        if self.steps > 0 {
+            self.steps -= 1;
+            return true;
+        }
+        false
+    }
+}
+
+/// Slow motion state, every frame run over `divisor` frames shown, its
+/// timers ticking once at the end.
+struct SlowMotion {
+    on: bool,
+    divisor: u32,
+    // frames shown of the current frame
+    phase: u32,
+}
+
+impl SlowMotion {
+    /// The ticks of a frame of `ticks_per_frame` to run next and whether
+    /// they end the frame. With `whole` the rest of the frame runs at once.
+    fn slice(&mut self, ticks_per_frame: usize, whole: bool) -> (Range<usize>, bool) {
+        let (phase, divisor) = (self.phase as usize, self.divisor as usize);
+        let start = ticks_per_frame * phase / divisor;
+        if !self.on || whole {
+            self.phase = 0;
+            return (start..ticks_per_frame, true);
+        }
+        self.phase = (self.phase + 1) % self.divisor;
+        (
+            start..ticks_per_frame * (phase + 1) / divisor,
+            self.phase == 0,
+        )
+    }
+}
+
+/// Turns pending SDL events into the commands to run.
+fn pump_events(
+    event_pump: &mut EventPump,
+    gamepads: &mut Gamepads,
+    session: &Session,
+) -> Vec<Command> {
+    let keymap = &session.keymap;
+    let mut commands = Vec::new();
+    for evt in event_pump.poll_iter() {
+        gamepads.handle(&evt);
+        match evt {
+            Event::Quit { .. } => {
+                commands.push(Command::Quit);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Escape | Keycode::M),
+                repeat: false,
+                ..
+            } if session.editor.is_some() && session.menu.is_none() => {
+                commands.push(Command::ToggleEditor);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                repeat: false,
+                ..
+            } => {
+                commands.push(Command::Menu);
+            }
+            // the open menu takes the keys, the keypad still sees releases
+            Event::KeyDown {
+                keycode: Some(key), ..
+            } if session.menu.is_some() => {
+                commands.push(Command::MenuKey(key));
+            }
+            // and so does the memory editor, typing hex digits
+            Event::KeyDown {
+                keycode: Some(key), ..
+            } if session.editor.is_some() => {
+                commands.push(Command::EditorKey(key));
+            }
+            // keypad keys win over the hotkeys, e.g. P is keypad D with the
+            // Dvorak layout
+            Event::KeyDown {
+                keycode: Some(key),
+                repeat,
+                ..
+            } if keymap.button(key).is_some() => {
+                if let (Some(k), false) = (keymap.button(key), repeat) {
+                    commands.push(Command::Key(k, true));
+                }
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::M),
+                repeat: false,
+                ..
+            } => {
+                commands.push(Command::ToggleEditor);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F5),
+                ..
+            } => {
+                commands.push(Command::SaveState);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F9),
+                ..
+            } => {
+                commands.push(Command::LoadState);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::P),
+                repeat: false,
+                ..
+            } => {
+                commands.push(Command::TogglePause);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F10),
+                repeat: false,
+                ..
+            } => {
+                commands.push(Command::ToggleRecording);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F7),
+                repeat: false,
+                ..
+            } => {
+                commands.push(Command::ToggleMovie);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Equals),
+                ..
+            } => {
+                commands.push(Command::SpeedUp);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Minus),
+                ..
+            } => {
+                commands.push(Command::SlowDown);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F12),
+                repeat: false,
+                ..
+            } => {
+                commands.push(Command::Screenshot);
+            }
+            Event::Window {
+                win_event: WindowEvent::SizeChanged(width, height),
+                ..
+            } => {
+                commands.push(Command::Resize(width as u32, height as u32));
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F2),
+                repeat: false,
+                ..
+            } => {
+                commands.push(Command::Reset);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F3),
+                repeat: false,
+                ..
+            } => {
+                commands.push(Command::Reload);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F4),
+                repeat: false,
+                ..
+            } => {
+                commands.push(Command::NextLayout);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F6),
+                repeat: false,
+                ..
+            } => {
+                commands.push(Command::NextFilter);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F1),
+                repeat: false,
+                ..
+            } => {
+                commands.push(Command::ToggleOverlay);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F8),
+                repeat: false,
+                ..
+            } => {
+                commands.push(Command::ToggleKeys);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F11),
+                repeat: false,
+                ..
+            } => {
+                commands.push(Command::ToggleSlowMotion);
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Tab),
+                repeat: false,
+                ..
+            } => {
+                commands.push(Command::Turbo(true));
+            }
+            Event::KeyUp {
+                keycode: Some(Keycode::Tab),
+                ..
+            } => {
+                commands.push(Command::Turbo(false));
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Backspace),
+                repeat: false,
+                ..
+            } => {
+                commands.push(Command::Rewind(true));
+            }
+            Event::KeyUp {
+                keycode: Some(Keycode::Backspace),
+                ..
+            } => {
+                commands.push(Command::Rewind(false));
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::N),
+                ..
+            } => {
+                commands.push(Command::Step);
+            }
+            Event::KeyUp {
+                keycode: Some(key), ..
+            } => {
+                if let Some(k) = keymap.button(key) {
+                    commands.push(Command::Key(k, false));
+                }
+            }
+            Event::ControllerButtonDown { button, .. } => {
+                if let Some(k) = keymap.controller_button(button) {
+                    commands.push(Command::Key(k, true));
+                }
+            }
+            Event::ControllerButtonUp { button, .. } => {
+                if let Some(k) = keymap.controller_button(button) {
+                    commands.push(Command::Key(k, false));
+                }
+            }
+            _ => (),
+        }
+    }
+    commands
+}
+
+/// Runs the commands before instruction `tick` of the frame, returns false
+/// when the user quits.
+fn run_commands(commands: Vec<Command>, chip8: &mut CPU, session: &mut Session, tick: u16) -> bool {
+    let mut commands = VecDeque::from(commands);
+    while let Some(command) = commands.pop_front() {
+        match command {
+            Command::Quit => return false,
+            // both sides must run the same frames from the same state
+            Command::LoadState
+            | Command::TogglePause
+            | Command::Step
+            | Command::ToggleMovie
+            | Command::SpeedUp
+            | Command::SlowDown
+            | Command::Reset
+            | Command::Reload
+            | Command::OpenRom
+            | Command::Rewind(true)
+            | Command::Turbo(true)
+            | Command::ToggleSlowMotion
+            | Command::ToggleEditor
+                if session.netplay.is_some() =>
+            {
+                eprintln!("Not available during netplay");
+            }
+            Command::SaveState => {
+                if let Err(err) = session.quicksave.save(chip8) {
+                    eprintln!("Saving state failed: {err}");
+                }
+            }
+            Command::LoadState => match session.quicksave.load() {
+                Some(state) => {
+                    // a movie can't replay a jump to another state
+                    if let Some(movie) = session.movie.take() {
+                        finish_movie(movie);
+                    }
+                    if let Err(err) = chip8.load_state(&state) {
+                        eprintln!("Loading state failed: {err}");
                     }
                 }
-                Event::KeyUp {
-                    keycode: Some(key), ..
-                } => {
-                    if let Some(k) = key2btn(key) {
-                        chip8.keypress(k, false);
+                None => eprintln!("No saved state to load"),
+            },
+            Command::TogglePause => {
+                session.pause.paused = !session.pause.paused;
+                session.pause.steps = 0;
+                session.editor = None;
+                release_latched(chip8, session);
+            }
+            // holding the key steps repeatedly
+            Command::Step if session.pause.paused => session.pause.steps += 1,
+            Command::Step => {}
+            Command::SpeedUp | Command::SlowDown => {
+                let hz = match command {
+                    Command::SpeedUp => chip8.clock_hz() + SPEED_STEP_HZ,
+                    _ => chip8.clock_hz().saturating_sub(SPEED_STEP_HZ),
+                };
+                chip8.set_clock_hz(hz);
+                // remembered for the game, and kept when it starts over
+                session.game.ips = Some(hz);
+                session.config.game_mut(&session.game.rom).ips = Some(hz);
+                println!("Speed: {hz} Hz");
+            }
+            Command::ToggleOverlay => session.overlay = !session.overlay,
+            Command::ToggleKeys => session.show_keys = !session.show_keys,
+            Command::NextLayout => {
+                let layout = session.keymap.layout().map_or(Layout::Qwerty, Layout::next);
+                session.keymap.set_layout(layout);
+                session.config.game_mut(&session.game.rom).layout = Some(layout.name().into());
+                println!("Keyboard layout: {layout}");
+            }
+            Command::NextFilter => {
+                session.filter = session.filter.next();
+                println!("Display filter: {}", session.filter);
+            }
+            Command::TogglePhosphor => session.phosphor = !session.phosphor,
+            Command::Resize(width, height) => {
+                session.window_size = (width, height);
+                session.viewport = letterbox(session.window_size, session.display_size);
+            }
+            Command::Menu => match &mut session.menu {
+                Some(menu) => {
+                    if !menu.back() {
+                        session.menu = None;
+                    }
+                }
+                None => session.menu = Some(PauseMenu::new()),
+            },
+            Command::MenuKey(key) => {
+                let Some(menu) = &mut session.menu else {
+                    continue;
+                };
+                let chosen = match key {
+                    Keycode::Up => {
+                        menu.up();
+                        None
+                    }
+                    Keycode::Down => {
+                        menu.down();
+                        None
+                    }
+                    Keycode::Left => menu.adjust(false),
+                    Keycode::Right => menu.adjust(true),
+                    Keycode::Return | Keycode::KpEnter => menu.choose(),
+                    _ => None,
+                };
+                commands.extend(chosen);
+            }
+            Command::ToggleEditor => match session.editor {
+                Some(_) => session.editor = None,
+                None if !session.pause.paused => eprintln!("Pause to edit the memory"),
+                None => session.editor = Some(HexEditor::new(chip8)),
+            },
+            Command::EditorKey(key) => {
+                let Some(editor) = &mut session.editor else {
+                    continue;
+                };
+                if editor.key(key, chip8) {
+                    // neither can replay the edit
+                    if let Some(movie) = session.movie.take() {
+                        finish_movie(movie);
+                    }
+                    if session.playback.take().is_some() {
+                        println!("Movie stopped");
                     }
                 }
-                _ => (),
             }
+            Command::OpenRom => {
+                session.menu = None;
+                session.browse = true;
+            }
+            Command::Reset => {
+                session.menu = None;
+                restart(chip8, session);
+            }
+            Command::Reload => open_rom(chip8, session, session.game.path.clone()),
+            Command::Turbo(held) => session.turbo = held,
+            Command::ToggleSlowMotion => {
+                session.slow_motion.on = !session.slow_motion.on;
+                println!(
+                    "Slow motion: {}",
+                    if session.slow_motion.on { "on" } else { "off" }
+                );
+            }
+            Command::Rewind(held) => {
+                session.rewinding = held;
+                // neither can follow the game going back
+                if let (true, Some(movie)) = (held, session.movie.take()) {
+                    finish_movie(movie);
+                }
+                if held && session.playback.take().is_some() {
+                    println!("Movie stopped");
+                }
+            }
+            Command::Screenshot => {
+                let path = capture::timestamped_path("png");
+                match capture::save_png(chip8, &session.palette, &path) {
+                    Ok(()) => println!("Saved screenshot to {}", path.display()),
+                    Err(err) => eprintln!("Screenshot failed: {err}"),
+                }
+            }
+            Command::ToggleMovie => match session.movie.take() {
+                Some(movie) => finish_movie(movie),
+                None if session.playback.is_some() => eprintln!("Can't record during playback"),
+                None => {
+                    let seed = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |time| time.as_nanos() as u64);
+                    session.movie = Some(MovieRecorder::start(chip8, seed));
+                    println!("Recording input");
+                }
+            },
+            // the movie drives the keypad during playback
+            Command::Key(_, _) if session.playback.is_some() => {}
+            Command::Key(key, pressed) if session.netplay.is_some() => {
+                if let Some(netplay) = &mut session.netplay {
+                    netplay.set_key(key, pressed);
+                }
+            }
+            Command::Key(key, false) if session.pause.paused => {
+                if !session.pause.released.contains(&key) {
+                    session.pause.released.push(key);
+                }
+            }
+            Command::Key(key, pressed) => {
+                session.pause.released.retain(|&released| released != key);
+                if let Some(movie) = &mut session.movie {
+                    movie.key(tick, key as u8, pressed);
+                }
+                chip8.keypress(key, pressed);
+            }
+            Command::ToggleRecording => match session.recording.take() {
+                Some(recorder) => finish_recording(recorder),
+                None => {
+                    match GifRecorder::create(capture::timestamped_path("gif"), &session.palette) {
+                        Ok(recorder) => {
+                            println!("Recording to {}", recorder.path().display());
+                            session.recording = Some(recorder);
+                        }
+                        Err(err) => eprintln!("Recording failed: {err}"),
+                    }
+                }
+            },
         }
-        for _ in 0..TICKS_PER_FRAME {
-            chip8.tick();
+    }
+    true
+}
+
+/// Releases the keys let go of while paused, before the next frame.
+fn release_latched(chip8: &mut CPU, session: &mut Session) {
+    for key in session.pause.released.drain(..) {
+        if let Some(movie) = &mut session.movie {
+            movie.key(0, key as u8, false);
         }
-        chip8.tick_timers();
-        draw_screen(&chip8, &mut canvas);
+        chip8.keypress(key, false);
     }
 }
 
-fn draw_screen(emu: &CPU, canvas: &mut Canvas<Window>) {
-    canvas.set_draw_color(Color::RGB(0, 0, 0));
-    canvas.clear();
+/// Reads the ROM at `path` and starts it, keeping the current game and its
+/// settings when it can't be read or is rejected.
+fn open_rom(chip8: &mut CPU, session: &mut Session, path: String) {
+    let rom = match Game::read(&path) {
+        Ok(rom) => rom,
+        Err(err) => {
+            eprintln!("Reading {path} failed: {err}");
+            return;
+        }
+    };
+    let settings = session.config.game(&rom);
+    let game = Game {
+        path,
+        rom,
+        ips: settings.ips,
+        quirks: settings.quirks,
+        chip8x: session.game.chip8x,
+        megachip: session.game.megachip,
+        latched_input: session.game.latched_input,
+        font: settings.font,
+        profile: session.game.profile,
+        coverage: session.game.coverage,
+        trace: session.game.trace,
+    };
+    match game.start() {
+        Ok(cpu) => *chip8 = cpu,
+        Err(err) => {
+            eprintln!("{}: {err}", game.path);
+            return;
+        }
+    }
+    session.palette = settings.palette;
+    if let Some(layout) = settings.layout {
+        session.keymap.set_layout(layout);
+    }
+    session.autosave = SaveFile::autosave(&game.rom);
+    session.quicksave = SaveFile::quicksave(&game.rom);
+    session.game = game;
+    stop_movies(session);
+}
 
-    let screen_buf = emu.get_display();
+/// Starts the game over, keeping the current machine when the ROM is rejected.
+fn restart(chip8: &mut CPU, session: &mut Session) {
+    match session.game.start() {
+        Ok(cpu) => *chip8 = cpu,
+        Err(err) => {
+            eprintln!("{}: {err}", session.game.path);
+            return;
+        }
+    }
+    stop_movies(session);
+}
 
-    canvas.set_draw_color(Color::RGB(255, 255, 255));
-    for (i, pixel) in screen_buf.iter().enumerate() {
-        // draw white pixels
-        if *pixel {
-            // convert our 1d array's index into a 2d (x,y) position
-            let x = (i % chip8::screen::SCREEN_WIDTH) as u32;
-            let y = (i / chip8::screen::SCREEN_WIDTH) as u32;
+// neither can follow the machine starting over
+fn stop_movies(session: &mut Session) {
+    if let Some(movie) = session.movie.take() {
+        finish_movie(movie);
+    }
+    if session.playback.take().is_some() {
+        println!("Movie stopped");
+    }
+}
 
-            // draw a rectangle at (x,y), scaled up by our scale value
-            let rect = Rect::new((x * SCALE) as i32, (y * SCALE) as i32, SCALE, SCALE);
-            canvas
-                .fill_rect(rect)
-                .expect("Error drawing rectangle for animation");
-        }
+fn finish_recording(recorder: GifRecorder) {
+    match recorder.finish() {
+        Ok(path) => println!("Saved recording to {}", path.display()),
+        Err(err) => eprintln!("Recording failed: {err}"),
     }
-    canvas.present();
 }
 
-fn key2btn(key: Keycode) -> Option<usize> {
-    match key {
-        Keycode::Num1 => Some(0x1),
-        Keycode::Num2 => Some(0x2),
-        Keycode::Num3 => Some(0x3),
-        Keycode::Num4 => Some(0xC),
-        Keycode::Q => Some(0x4),
-        Keycode::W => Some(0x5),
-        Keycode::E => Some(0x6),
-        Keycode::R => Some(0xD),
-        Keycode::A => Some(0x7),
-        Keycode::S => Some(0x8),
-        Keycode::D => Some(0x9),
-        Keycode::F => Some(0xE),
-        Keycode::Z => Some(0xA),
-        Keycode::X => Some(0x0),
-        Keycode::C => Some(0xB),
-        Keycode::V => Some(0xF),
-        _ => None,
+fn finish_movie(movie: MovieRecorder) {
+    let path = capture::timestamped_path("c8m");
+    match fs::write(&path, movie.finish().to_bytes()) {
+        Ok(()) => println!("Saved movie to {}", path.display()),
+        Err(err) => eprintln!("Saving movie failed: {err}"),
+    }
+}
+
+fn ask_resume(window: &Window) -> bool {
+    let buttons = [
+        ButtonData {
+            flags: MessageBoxButtonFlag::RETURNKEY_DEFAULT,
+            button_id: 1,
+            text: "Resume",
+        },
+        ButtonData {
+            flags: MessageBoxButtonFlag::ESCAPEKEY_DEFAULT,
+            button_id: 0,
+            text: "Start over",
+        },
+    ];
+    let clicked = messagebox::show_message_box(
+        MessageBoxFlag::INFORMATION,
+        &buttons,
+        "Resume game",
+        "An autosave was found for this ROM. Resume where you left off?",
+        window,
+        None,
+    );
+    matches!(clicked, Ok(ClickedButton::CustomButton(button)) if button.button_id == 1)
+}
+
+/// The largest area of a `width` by `height` window with the aspect ratio of
+/// a display of `display_size`, centered with black bars around it.
+fn letterbox((width, height): (u32, u32), display_size: (usize, usize)) -> Rect {
+    let (display_width, display_height) = (display_size.0 as u32, display_size.1 as u32);
+    let scale = (width as f32 / display_width as f32).min(height as f32 / display_height as f32);
+    let (w, h) = (
+        (display_width as f32 * scale) as u32,
+        (display_height as f32 * scale) as u32,
+    );
+    Rect::new(
+        (width.saturating_sub(w) / 2) as i32,
+        (height.saturating_sub(h) / 2) as i32,
+        w.max(1),
+        h.max(1),
+    )
+}
+
+/// Uploads the display rows changed since the last frame to `texture`, a
+/// MegaChip sized copy of the display, and stretches it over the viewport of
+/// the window, with the debug overlay on top when enabled. With a filter
+/// the whole display goes through it into `filtered` instead.
+fn draw_screen(
+    emu: &mut CPU,
+    session: &mut Session,
+    canvas: &mut Canvas<Window>,
+    texture: &mut Texture,
+    filtered: &mut Texture,
+) {
+    // the two-page and MegaChip displays aren't 2:1
+    if emu.display_size() != session.display_size {
+        session.display_size = emu.display_size();
+        session.viewport = letterbox(session.window_size, session.display_size);
+    }
+    let palette = &session.palette;
+    let dirty = emu.take_dirty();
+    let frame = emu.frame_buffer();
+    let (width, height) = (frame.width(), frame.height());
+    let mut rgb = Vec::with_capacity(width * 3);
+    for row in dirty.iter().filter(|&row| row < height) {
+        rgb.clear();
+        rgb.extend((0..width).flat_map(|x| frame.rgb(x, row, palette)));
+        texture
+            .update(Rect::new(0, row as i32, width as u32, 1), &rgb, width * 3)
+            .expect("Error updating the screen texture");
+    }
+    canvas.set_draw_color(Color::BLACK);
+    canvas.clear();
+    if session.filter == Filter::None && !session.phosphor {
+        canvas
+            .copy(
+                texture,
+                Rect::new(0, 0, width as u32, height as u32),
+                session.viewport,
+            )
+            .expect("Error drawing the screen");
+    } else {
+        let rgb = session
+            .renderer
+            .render(frame, palette, session.filter, session.phosphor);
+        filtered
+            .update(None, rgb, FILTERED_WIDTH * 3)
+            .expect("Error updating the filtered screen texture");
+        canvas
+            .copy(filtered, None, session.viewport)
+            .expect("Error drawing the screen");
+    }
+    // accesses are counted while the overlay shows them, from when it opens
+    if session.overlay != emu.memory_access().is_some() {
+        emu.set_access_tracking(session.overlay);
     }
+    if session.overlay {
+        session.debug_panel.draw(emu, canvas);
+        if let Some(access) = emu.memory_access() {
+            overlay::draw_heatmap(access, canvas).expect("Error drawing the memory heatmap");
+        }
+    }
+    if let Some(editor) = &session.editor {
+        overlay::draw_editor(editor, emu, canvas).expect("Error drawing the memory editor");
+    }
+    if session.show_keys {
+        overlay::draw_keypad(emu.keys(), canvas).expect("Error drawing the keypad");
+    }
+    if let Some(menu) = &mut session.menu {
+        let settings = Settings {
+            layout: session.keymap.layout(),
+            filter: session.filter,
+            phosphor: session.phosphor,
+            overlay: session.overlay,
+            show_keys: session.show_keys,
+            ips: emu.clock_hz(),
+        };
+        menu.draw(canvas, &settings)
+            .expect("Error drawing the pause menu");
+    }
+    canvas.present();
 }
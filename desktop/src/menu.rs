@@ -0,0 +1,195 @@
+//! Menus drawn over the window with a small built-in font of capitals,
+//! digits and punctuation, lower case drawn as capitals.
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+
+const GLYPH_WIDTH: u32 = 3;
+const GLYPH_HEIGHT: u32 = 5;
+// window pixels per font pixel
+const SCALE: u32 = 3;
+/// Window pixels from one letter to the next.
+pub const ADVANCE: i32 = ((GLYPH_WIDTH + 1) * SCALE) as i32;
+const LINE_HEIGHT: i32 = ((GLYPH_HEIGHT + 2) * SCALE) as i32;
+const MARGIN: i32 = 12;
+
+const BACKGROUND: Color = Color::RGBA(0, 0, 0, 224);
+const TEXT: Color = Color::RGB(0xFF, 0xFF, 0xFF);
+const HIGHLIGHT: Color = Color::RGB(0xFF, 0xD0, 0x00);
+
+/// A title over a list of items, one of them selected.
+pub struct Menu {
+    title: String,
+    items: Vec<String>,
+    selected: usize,
+}
+
+impl Menu {
+    pub fn new(title: impl Into<String>, items: Vec<String>) -> Self {
+        Self {
+            title: title.into(),
+            items,
+            selected: 0,
+        }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn select(&mut self, idx: usize) {
+        self.selected = idx.min(self.items.len().saturating_sub(1));
+    }
+
+    /// Replaces the items, e.g. to show changed values, keeping the
+    /// selection where it still fits.
+    pub fn set_items(&mut self, items: Vec<String>) {
+        self.selected = self.selected.min(items.len().saturating_sub(1));
+        self.items = items;
+    }
+
+    /// Selects the previous item, wrapping around to the last.
+    pub fn up(&mut self) {
+        self.selected = self
+            .selected
+            .checked_sub(1)
+            .unwrap_or(self.items.len().saturating_sub(1));
+    }
+
+    /// Selects the next item, wrapping around to the first.
+    pub fn down(&mut self) {
+        self.selected = (self.selected + 1) % self.items.len().max(1);
+    }
+
+    /// Draws the menu in a box in the middle of `canvas`. Items that don't
+    /// fit scroll with the selection and long lines are cut.
+    pub fn draw(&self, canvas: &mut Canvas<Window>) -> Result<(), String> {
+        let (width, height) = canvas.output_size()?;
+        let columns = ((width as i32 - 2 * MARGIN) / ADVANCE).max(1) as usize;
+        // the title and a blank line above the items
+        let rows = ((height as i32 - 2 * MARGIN) / LINE_HEIGHT - 2).max(1) as usize;
+        let first = self
+            .selected
+            .saturating_sub(rows / 2)
+            .min(self.items.len().saturating_sub(rows));
+        let visible = &self.items[first..self.items.len().min(first + rows)];
+
+        let longest = visible
+            .iter()
+            .chain([&self.title])
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(columns);
+        let box_width = longest as u32 * ADVANCE as u32 + 2 * MARGIN as u32;
+        let box_height = (visible.len() + 2) as u32 * LINE_HEIGHT as u32 + 2 * MARGIN as u32;
+        let left = (width as i32 - box_width as i32) / 2;
+        let top = (height as i32 - box_height as i32).max(0) / 2;
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(BACKGROUND);
+        canvas.fill_rect(Rect::new(left, top, box_width, box_height))?;
+
+        let (x, y) = (left + MARGIN, top + MARGIN);
+        draw_text(canvas, &self.title, columns, x, y, TEXT)?;
+        for (row, item) in visible.iter().enumerate() {
+            let color = if first + row == self.selected {
+                HIGHLIGHT
+            } else {
+                TEXT
+            };
+            let y = y + (row as i32 + 2) * LINE_HEIGHT;
+            draw_text(canvas, item, columns, x, y, color)?;
+        }
+        Ok(())
+    }
+}
+
+/// Draws at most `columns` characters of `text` at (`x`, `y`).
+pub fn draw_text(
+    canvas: &mut Canvas<Window>,
+    text: &str,
+    columns: usize,
+    x: i32,
+    y: i32,
+    color: Color,
+) -> Result<(), String> {
+    let mut pixels = Vec::new();
+    for (column, c) in text.chars().take(columns).enumerate() {
+        let left = x + column as i32 * ADVANCE;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (0b100 >> col) != 0 {
+                    pixels.push(Rect::new(
+                        left + (col * SCALE) as i32,
+                        y + row as i32 * SCALE as i32,
+                        SCALE,
+                        SCALE,
+                    ));
+                }
+            }
+        }
+    }
+    canvas.set_draw_color(color);
+    canvas.fill_rects(&pixels)
+}
+
+// rows of 3 pixels, the most significant bit on the left
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT as usize] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '\\' => [0b100, 0b100, 0b010, 0b001, 0b001],
+        '(' | '[' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' | ']' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        // a question mark for the rest
+        _ => [0b110, 0b001, 0b010, 0b000, 0b010],
+    }
+}
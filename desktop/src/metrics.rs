@@ -0,0 +1,138 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Environment variable holding the address to serve metrics on, e.g. `127.0.0.1:9898`.
+pub const METRICS_ADDR_ENV: &str = "CHIP8_METRICS_ADDR";
+
+/// Runtime counters shared between the emulation loop and the metrics server.
+#[derive(Default)]
+pub struct Metrics {
+    frames: AtomicU64,
+    instructions: AtomicU64,
+    dropped_frames: AtomicU64,
+    instructions_per_second: AtomicU64,
+}
+
+impl Metrics {
+    pub fn record_frame(&self, instructions: u64) {
+        self.frames.fetch_add(1, Ordering::Relaxed);
+        self.instructions.fetch_add(instructions, Ordering::Relaxed);
+    }
+
+    pub fn record_dropped_frame(&self) {
+        self.dropped_frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_instructions_per_second(&self, ips: u64) {
+        self.instructions_per_second.store(ips, Ordering::Relaxed);
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: u64| {
+            out.push_str(&format!("# HELP {name} {help}\n"));
+            out.push_str(&format!("# TYPE {name} {kind}\n"));
+            out.push_str(&format!("{name} {value}\n"));
+        };
+        metric(
+            "chip8_frames_total",
+            "counter",
+            "Frames emulated since start.",
+            self.frames.load(Ordering::Relaxed),
+        );
+        metric(
+            "chip8_instructions_total",
+            "counter",
+            "Instructions executed since start.",
+            self.instructions.load(Ordering::Relaxed),
+        );
+        metric(
+            "chip8_dropped_frames_total",
+            "counter",
+            "Frames that took longer than the frame budget.",
+            self.dropped_frames.load(Ordering::Relaxed),
+        );
+        metric(
+            "chip8_instructions_per_second",
+            "gauge",
+            "Instructions executed during the last second.",
+            self.instructions_per_second.load(Ordering::Relaxed),
+        );
+        out
+    }
+}
+
+/// Keeps the per-second and per-frame bookkeeping out of the game loop.
+pub struct FrameMeter {
+    metrics: Arc<Metrics>,
+    budget: Duration,
+    frame_start: Instant,
+    second_start: Instant,
+    second_instructions: u64,
+}
+
+impl FrameMeter {
+    pub fn new(metrics: Arc<Metrics>, budget: Duration) -> Self {
+        let now = Instant::now();
+        Self {
+            metrics,
+            budget,
+            frame_start: now,
+            second_start: now,
+            second_instructions: 0,
+        }
+    }
+
+    /// Called once at the end of every frame with the number of ticks that ran.
+    pub fn end_frame(&mut self, instructions: u64) {
+        let now = Instant::now();
+        self.metrics.record_frame(instructions);
+        // allow some slack so vsync jitter doesn't count as a drop
+        if now.duration_since(self.frame_start) > self.budget * 3 / 2 {
+            self.metrics.record_dropped_frame();
+        }
+        self.frame_start = now;
+
+        self.second_instructions += instructions;
+        if now.duration_since(self.second_start) >= Duration::from_secs(1) {
+            self.metrics
+                .set_instructions_per_second(self.second_instructions);
+            self.second_instructions = 0;
+            self.second_start = now;
+        }
+    }
+}
+
+/// Serves `metrics` over HTTP on `addr` from a background thread.
+pub fn serve(addr: &str, metrics: Arc<Metrics>) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // a misbehaving scraper must never take the emulator down
+            let _ = respond(stream, &metrics);
+        }
+    });
+    Ok(())
+}
+
+fn respond(mut stream: TcpStream, metrics: &Metrics) -> std::io::Result<()> {
+    // drain the request headers, every path answers with the metrics
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let body = metrics.render();
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
@@ -0,0 +1,214 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chip8::romdb::rom_hash;
+use chip8::{Quirks, CPU, NUM_KEYS};
+
+// identifies the netplay protocol, bumped with the version on every change
+const MAGIC: &[u8; 4] = b"C8NP";
+const VERSION: u8 = 2;
+
+// frames between two comparisons of the machine state of both sides
+const CHECK_INTERVAL: u32 = 60;
+
+// a peer silent for this long is considered gone
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Lockstep netplay with one peer over TCP.
+///
+/// Both sides start from the host's machine, random seed and run settings,
+/// then exchange their keypad every frame before running it. The keypad of the machine is
+/// the union of both players' keys, so each side runs the exact same frames.
+pub struct Netplay {
+    stream: TcpStream,
+    frame: u32,
+    local_keys: [bool; NUM_KEYS],
+}
+
+impl Netplay {
+    /// Waits for a peer on `addr`, then sends it the machine to start from.
+    pub fn host(addr: impl ToSocketAddrs, rom: &[u8], chip8: &mut CPU) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        println!("Waiting for a peer on {}", listener.local_addr()?);
+        let (mut stream, peer) = listener.accept()?;
+        configure(&stream)?;
+
+        let peer_hash = read_hello(&mut stream)?;
+        write_hello(&mut stream, rom)?;
+        if peer_hash != rom_hash(rom) {
+            return Err(invalid_data("the peer is running a different ROM"));
+        }
+
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        chip8.seed_rng(seed);
+        let state = chip8.save_state();
+        stream.write_all(&seed.to_be_bytes())?;
+        write_settings(&mut stream, chip8)?;
+        stream.write_all(&(state.len() as u32).to_be_bytes())?;
+        stream.write_all(&state)?;
+        println!("Playing with {peer}");
+        Ok(Self::new(stream))
+    }
+
+    /// Connects to a host on `addr` and takes over its machine and the
+    /// settings it runs with, whatever the local ones are.
+    pub fn join(addr: impl ToSocketAddrs, rom: &[u8], chip8: &mut CPU) -> io::Result<Self> {
+        let mut stream = TcpStream::connect(addr)?;
+        configure(&stream)?;
+
+        write_hello(&mut stream, rom)?;
+        if read_hello(&mut stream)? != rom_hash(rom) {
+            return Err(invalid_data("the host is running a different ROM"));
+        }
+
+        let seed = u64::from_be_bytes(read_array(&mut stream)?);
+        let settings: [u8; SETTINGS_SIZE] = read_array(&mut stream)?;
+        let len = u32::from_be_bytes(read_array(&mut stream)?) as usize;
+        let mut state = vec![0; len];
+        stream.read_exact(&mut state)?;
+        chip8
+            .load_state(&state)
+            .map_err(|err| invalid_data(&err.to_string()))?;
+        chip8.seed_rng(seed);
+        apply_settings(&settings, chip8);
+        println!("Playing with {}", stream.peer_addr()?);
+        Ok(Self::new(stream))
+    }
+
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            frame: 0,
+            local_keys: [false; NUM_KEYS],
+        }
+    }
+
+    /// Updates the local keypad, sent to the peer on the next frame.
+    pub fn set_key(&mut self, key: usize, pressed: bool) {
+        self.local_keys[key] = pressed;
+    }
+
+    /// Exchanges keypads with the peer and presses the keys of both on
+    /// `chip8`. Call it once before running every frame.
+    pub fn sync_frame(&mut self, chip8: &mut CPU) -> io::Result<()> {
+        let local = keys_to_mask(&self.local_keys);
+        let checksum = if self.frame.is_multiple_of(CHECK_INTERVAL) {
+            chip8.state_hash()
+        } else {
+            0
+        };
+
+        let mut message = [0; 14];
+        message[..4].copy_from_slice(&self.frame.to_be_bytes());
+        message[4..6].copy_from_slice(&local.to_be_bytes());
+        message[6..].copy_from_slice(&checksum.to_be_bytes());
+        self.stream.write_all(&message)?;
+
+        let reply: [u8; 14] = read_array(&mut self.stream)?;
+        let frame = u32::from_be_bytes([reply[0], reply[1], reply[2], reply[3]]);
+        let remote = u16::from_be_bytes([reply[4], reply[5]]);
+        let mut remote_checksum = [0; 8];
+        remote_checksum.copy_from_slice(&reply[6..]);
+        if frame != self.frame {
+            return Err(invalid_data("the peer is on another frame"));
+        }
+        if u64::from_be_bytes(remote_checksum) != checksum {
+            return Err(invalid_data("the machines went out of sync"));
+        }
+
+        let keys = local | remote;
+        for key in 0..NUM_KEYS {
+            chip8.keypress(key, keys & (1 << key) != 0);
+        }
+        self.frame += 1;
+        Ok(())
+    }
+}
+
+fn configure(stream: &TcpStream) -> io::Result<()> {
+    // a frame of input is a few bytes, don't wait to fill a packet
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(TIMEOUT))
+}
+
+fn write_hello(stream: &mut TcpStream, rom: &[u8]) -> io::Result<()> {
+    stream.write_all(MAGIC)?;
+    stream.write_all(&[VERSION])?;
+    stream.write_all(&rom_hash(rom).to_be_bytes())
+}
+
+// the quirks, ticks per frame and latched input flag. The font is in the
+// RAM of the save state.
+const SETTINGS_SIZE: usize = 1 + 4 + 1;
+
+fn write_settings(stream: &mut TcpStream, chip8: &CPU) -> io::Result<()> {
+    let mut settings = [0; SETTINGS_SIZE];
+    settings[0] = quirks_to_bits(chip8.quirks());
+    settings[1..5].copy_from_slice(&(chip8.ticks_per_frame() as u32).to_be_bytes());
+    settings[5] = chip8.is_latched_input() as u8;
+    stream.write_all(&settings)
+}
+
+fn apply_settings(settings: &[u8; SETTINGS_SIZE], chip8: &mut CPU) {
+    chip8.set_quirks(quirks_from_bits(settings[0]));
+    let ticks = u32::from_be_bytes([settings[1], settings[2], settings[3], settings[4]]);
+    chip8.set_ticks_per_frame(ticks as usize);
+    chip8.set_latched_input(settings[5] != 0);
+}
+
+fn quirks_to_bits(quirks: Quirks) -> u8 {
+    [
+        quirks.clip_sprites,
+        quirks.increment_i,
+        quirks.shift_vy,
+        quirks.jump_vx,
+        quirks.display_wait,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0, |bits, (bit, &on)| bits | (on as u8) << bit)
+}
+
+fn quirks_from_bits(bits: u8) -> Quirks {
+    let mut quirks = Quirks::default();
+    quirks.clip_sprites = bits & 1 != 0;
+    quirks.increment_i = bits & 2 != 0;
+    quirks.shift_vy = bits & 4 != 0;
+    quirks.jump_vx = bits & 8 != 0;
+    quirks.display_wait = bits & 16 != 0;
+    quirks
+}
+
+// returns the peer's ROM hash
+fn read_hello(stream: &mut TcpStream) -> io::Result<u64> {
+    let magic: [u8; 4] = read_array(stream)?;
+    let [version]: [u8; 1] = read_array(stream)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("the peer isn't a chip8 netplay instance"));
+    }
+    if version != VERSION {
+        return Err(invalid_data(&format!(
+            "the peer speaks netplay version {version}, expected {VERSION}"
+        )));
+    }
+    Ok(u64::from_be_bytes(read_array(stream)?))
+}
+
+fn read_array<const N: usize>(stream: &mut TcpStream) -> io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    stream.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn keys_to_mask(keys: &[bool; NUM_KEYS]) -> u16 {
+    keys.iter()
+        .enumerate()
+        .fold(0, |mask, (key, &pressed)| mask | (pressed as u16) << key)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
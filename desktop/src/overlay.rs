@@ -0,0 +1,269 @@
+//! Debug overlay drawn over the game. The registers, timers, stack and the
+//! code around PC are shown in an egui window, the [`DebugPanel`].
+//!
+//! The memory heatmap, [`draw_heatmap`], shows in the bottom right corner
+//! how often every byte of the 4 KiB was read and written since the overlay
+//! opened, a row of 128 bytes per line.
+//!
+//! The memory editor, [`draw_editor`], shows in the top left corner the
+//! bytes around its cursor, a row of 4 per line after the row's address.
+//!
+//! The keypad display, [`draw_keypad`], shows the 4x4 keypad in the bottom
+//! left corner with the keys held lit.
+
+use crate::hex_editor::{self, HexEditor};
+use chip8::text::{self, GLYPH_ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH};
+use chip8::{Instruction, MemoryAccess, CPU, MEMORY_SIZE, NUM_KEYS};
+use egui_sdl2::egui::{self, Align2, Color32, Grid, RichText, TextStyle, Ui};
+use egui_sdl2::EguiCanvas;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+
+// window pixels per font pixel
+const SCALE: u32 = 3;
+const MARGIN: i32 = 8;
+const LINE_HEIGHT: i32 = (GLYPH_HEIGHT as i32 + 2) * SCALE as i32;
+
+// instructions listed before and after the one at PC
+const CONTEXT: u16 = 4;
+
+const BACKGROUND: Color = Color::RGBA(0, 0, 0, 192);
+const TEXT: Color = Color::RGB(0xFF, 0xFF, 0xFF);
+const HIGHLIGHT: Color = Color::RGB(0xFF, 0xD0, 0x00);
+
+// bytes per row of the heatmap, and window pixels per side of a byte
+const HEATMAP_COLUMNS: usize = 128;
+const HEATMAP_CELL: u32 = 2;
+
+// the keys as laid out on the COSMAC VIP keypad
+const KEYPAD: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+// window pixels per side of a key
+const KEY_SIZE: u32 = (GLYPH_HEIGHT as u32 + 4) * SCALE;
+const KEY_GAP: u32 = SCALE;
+
+/// The registers, timers, stack and the code around PC, drawn with egui
+/// in the top right corner of the window.
+pub struct DebugPanel {
+    egui: EguiCanvas,
+}
+
+impl DebugPanel {
+    pub fn new(canvas: &Canvas<Window>) -> Self {
+        Self {
+            egui: EguiCanvas::new(canvas),
+        }
+    }
+
+    /// Draws the panel for `cpu` over what `canvas` holds.
+    pub fn draw(&mut self, cpu: &CPU, canvas: &mut Canvas<Window>) {
+        // the panel takes no input, the window size is all egui needs
+        self.egui.state.sync_window_size(canvas.window());
+        self.egui.run(|ctx| {
+            egui::Window::new("Debugger")
+                .anchor(Align2::RIGHT_TOP, [-MARGIN as f32, MARGIN as f32])
+                .title_bar(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.style_mut().override_text_style = Some(TextStyle::Monospace);
+                    registers(cpu, ui);
+                    ui.separator();
+                    code(cpu, ui);
+                });
+        });
+        self.egui.paint(canvas);
+    }
+}
+
+// PC, I, the timers, V0 to VF and the stack
+fn registers(cpu: &CPU, ui: &mut Ui) {
+    let state = cpu.state();
+    ui.label(format!(
+        "PC {:03X}  I {:03X}",
+        state.program_counter, state.i_register
+    ));
+    ui.label(format!(
+        "DT {:02X}  ST {:02X}",
+        state.delay_timer, state.sound_timer
+    ));
+    Grid::new("registers").show(ui, |ui| {
+        for (n, value) in state.v_registers.iter().enumerate() {
+            ui.label(format!("V{n:X} {value:02X}"));
+            if n % 4 == 3 {
+                ui.end_row();
+            }
+        }
+    });
+    // innermost call last
+    let stack: Vec<_> = state
+        .call_stack()
+        .iter()
+        .map(|addr| format!("{addr:03X}"))
+        .collect();
+    ui.label(format!("Stack {}", stack.join(" ")));
+}
+
+// the instructions around PC, the current one highlighted
+fn code(cpu: &CPU, ui: &mut Ui) {
+    let pc = cpu.state().program_counter;
+    let first = pc.saturating_sub(CONTEXT * 2);
+    for addr in (first..=pc.saturating_add(CONTEXT * 2)).step_by(2) {
+        let bytes = cpu.read_memory(addr as usize..addr as usize + 2);
+        if let &[hi, lo] = bytes {
+            let opcode = u16::from_be_bytes([hi, lo]);
+            let instruction = Instruction::decode(opcode);
+            let text = RichText::new(format!("{addr:03X} {opcode:04X} {instruction}"));
+            ui.label(if addr == pc {
+                text.color(Color32::from_rgb(HIGHLIGHT.r, HIGHLIGHT.g, HIGHLIGHT.b))
+            } else {
+                text
+            });
+        }
+    }
+}
+
+/// Draws the bytes shown by `editor` in the top left corner of `canvas`,
+/// the one under its cursor highlighted.
+pub fn draw_editor(
+    editor: &HexEditor,
+    cpu: &CPU,
+    canvas: &mut Canvas<Window>,
+) -> Result<(), String> {
+    let top = editor.top();
+    let bytes = cpu.read_memory(top..top + hex_editor::COLUMNS * hex_editor::ROWS);
+    // address, wider for MegaChip RAM, a space, then the bytes and the
+    // spaces between them
+    let digits = format!("{:X}", cpu.read_memory(..).len() - 1).len().max(3);
+    let columns = digits + 1 + hex_editor::COLUMNS * 3 - 1;
+    let width = (columns * GLYPH_ADVANCE) as u32 * SCALE + 2 * MARGIN as u32;
+    let height = hex_editor::ROWS as u32 * LINE_HEIGHT as u32 + 2 * MARGIN as u32;
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(BACKGROUND);
+    canvas.fill_rect(Rect::new(0, 0, width, height))?;
+
+    for (row, chunk) in bytes.chunks(hex_editor::COLUMNS).enumerate() {
+        let addr = top + row * hex_editor::COLUMNS;
+        let y = MARGIN + row as i32 * LINE_HEIGHT;
+        let (mut pixels, mut highlighted) = (Vec::new(), Vec::new());
+        // the text starting `column` glyphs in
+        let draw = |text: &str, column: usize, pixels: &mut Vec<Rect>| {
+            let x = MARGIN + (column * GLYPH_ADVANCE) as i32 * SCALE as i32;
+            draw_text(text, x, y, pixels);
+        };
+        draw(&format!("{addr:0digits$X}"), 0, &mut pixels);
+        for (col, byte) in chunk.iter().enumerate() {
+            let target = if addr + col == editor.cursor() {
+                &mut highlighted
+            } else {
+                &mut pixels
+            };
+            draw(&format!("{byte:02X}"), digits + 1 + col * 3, target);
+        }
+        canvas.set_draw_color(TEXT);
+        canvas.fill_rects(&pixels)?;
+        canvas.set_draw_color(HIGHLIGHT);
+        canvas.fill_rects(&highlighted)?;
+    }
+    Ok(())
+}
+
+/// Draws the reads and writes of every address in the bottom right corner
+/// of `canvas`: reads in blue, writes in red, brighter the more often,
+/// on a log scale. Variables light up both, sprite data blue and code the
+/// program rewrites red.
+pub fn draw_heatmap(access: &MemoryAccess, canvas: &mut Canvas<Window>) -> Result<(), String> {
+    let rows = MEMORY_SIZE / HEATMAP_COLUMNS;
+    let width = HEATMAP_COLUMNS as u32 * HEATMAP_CELL + 2 * MARGIN as u32;
+    let height = rows as u32 * HEATMAP_CELL + 2 * MARGIN as u32;
+    let (window_width, window_height) = canvas.output_size()?;
+    let (left, top) = (
+        window_width as i32 - width as i32,
+        window_height as i32 - height as i32,
+    );
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(BACKGROUND);
+    canvas.fill_rect(Rect::new(left, top, width, height))?;
+
+    let (reads, writes) = (access.reads(), access.writes());
+    let count = |counts: &[u32], addr: usize| counts.get(addr).copied().unwrap_or(0);
+    let max = |counts: &[u32]| counts.iter().take(MEMORY_SIZE).copied().max().unwrap_or(0);
+    let (max_reads, max_writes) = (max(reads), max(writes));
+    for addr in 0..MEMORY_SIZE {
+        let (red, blue) = (
+            heat(count(writes, addr), max_writes),
+            heat(count(reads, addr), max_reads),
+        );
+        if red == 0 && blue == 0 {
+            continue;
+        }
+        let (col, row) = (addr % HEATMAP_COLUMNS, addr / HEATMAP_COLUMNS);
+        canvas.set_draw_color(Color::RGB(red, blue / 2, blue));
+        canvas.fill_rect(Rect::new(
+            left + MARGIN + (col as u32 * HEATMAP_CELL) as i32,
+            top + MARGIN + (row as u32 * HEATMAP_CELL) as i32,
+            HEATMAP_CELL,
+            HEATMAP_CELL,
+        ))?;
+    }
+    Ok(())
+}
+
+// brightness of `count` accesses, from a dim 64 for one to 255 for `max`
+fn heat(count: u32, max: u32) -> u8 {
+    if count == 0 {
+        return 0;
+    }
+    let scale = (count as f32).ln_1p() / (max as f32).ln_1p();
+    (64.0 + 191.0 * scale) as u8
+}
+
+/// Draws the keypad in the bottom left corner of `canvas`, the keys set in
+/// `keys` highlighted.
+pub fn draw_keypad(keys: &[bool; NUM_KEYS], canvas: &mut Canvas<Window>) -> Result<(), String> {
+    let side = 4 * KEY_SIZE + 3 * KEY_GAP + 2 * MARGIN as u32;
+    let top = canvas.output_size()?.1 as i32 - side as i32;
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(BACKGROUND);
+    canvas.fill_rect(Rect::new(0, top, side, side))?;
+
+    for (row, keys_in_row) in KEYPAD.iter().enumerate() {
+        for (col, &key) in keys_in_row.iter().enumerate() {
+            let step = (KEY_SIZE + KEY_GAP) as i32;
+            let (x, y) = (MARGIN + col as i32 * step, top + MARGIN + row as i32 * step);
+            let (fill, text) = if keys[key as usize] {
+                (HIGHLIGHT, Color::BLACK)
+            } else {
+                (Color::RGB(0x40, 0x40, 0x40), TEXT)
+            };
+            canvas.set_draw_color(fill);
+            canvas.fill_rect(Rect::new(x, y, KEY_SIZE, KEY_SIZE))?;
+
+            // the digit in the middle of the key
+            let offset = |glyph: usize| (KEY_SIZE as i32 - (glyph as u32 * SCALE) as i32) / 2;
+            let (x, y) = (x + offset(GLYPH_WIDTH), y + offset(GLYPH_HEIGHT));
+            let mut pixels = Vec::new();
+            draw_text(&format!("{key:X}"), x, y, &mut pixels);
+            canvas.set_draw_color(text);
+            canvas.fill_rects(&pixels)?;
+        }
+    }
+    Ok(())
+}
+
+// adds the pixels of `text` in the hex font at (`x`, `y`) to `pixels`
+fn draw_text(text: &str, x: i32, y: i32, pixels: &mut Vec<Rect>) {
+    text::for_each_pixel(text, |px, py| {
+        pixels.push(Rect::new(
+            x + (px as u32 * SCALE) as i32,
+            y + (py as u32 * SCALE) as i32,
+            SCALE,
+            SCALE,
+        ));
+    });
+}
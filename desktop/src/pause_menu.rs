@@ -0,0 +1,120 @@
+//! The menu Escape opens over the game, see [`PauseMenu`].
+
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use crate::filter::Filter;
+use crate::keymap::Layout;
+use crate::menu::Menu;
+use crate::Command;
+
+const MAIN: [&str; 5] = ["Resume", "Reset", "Load ROM", "Options", "Quit"];
+const OPTIONS: usize = 3;
+const SPEED: usize = 5;
+
+/// What the options page shows the values of.
+pub struct Settings {
+    pub layout: Option<Layout>,
+    pub filter: Filter,
+    pub phosphor: bool,
+    pub overlay: bool,
+    pub show_keys: bool,
+    pub ips: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Page {
+    Main,
+    Options,
+}
+
+/// The pause menu, its choices turned into the commands of the hotkeys
+/// doing the same.
+pub struct PauseMenu {
+    page: Page,
+    menu: Menu,
+}
+
+impl PauseMenu {
+    pub fn new() -> Self {
+        Self {
+            page: Page::Main,
+            menu: main_menu(),
+        }
+    }
+
+    pub fn up(&mut self) {
+        self.menu.up();
+    }
+
+    pub fn down(&mut self) {
+        self.menu.down();
+    }
+
+    /// The command of the selected item, or None when it only moves
+    /// between pages. [`Command::Menu`] closes the menu.
+    pub fn choose(&mut self) -> Option<Command> {
+        match (self.page, self.menu.selected()) {
+            (Page::Main, 0) => Some(Command::Menu),
+            (Page::Main, 1) => Some(Command::Reset),
+            (Page::Main, 2) => Some(Command::OpenRom),
+            (Page::Main, OPTIONS) => {
+                self.page = Page::Options;
+                self.menu = Menu::new("Options", Vec::new());
+                None
+            }
+            (Page::Main, _) => Some(Command::Quit),
+            (Page::Options, 0) => Some(Command::NextLayout),
+            (Page::Options, 1) => Some(Command::NextFilter),
+            (Page::Options, 2) => Some(Command::TogglePhosphor),
+            (Page::Options, 3) => Some(Command::ToggleOverlay),
+            (Page::Options, 4) => Some(Command::ToggleKeys),
+            (Page::Options, SPEED) => Some(Command::SpeedUp),
+            (Page::Options, _) => {
+                self.back();
+                None
+            }
+        }
+    }
+
+    /// Left and right change the speed on the options page, and otherwise
+    /// pick what Enter does.
+    pub fn adjust(&mut self, faster: bool) -> Option<Command> {
+        match (self.page, self.menu.selected()) {
+            (Page::Options, SPEED) if !faster => Some(Command::SlowDown),
+            (Page::Options, 0..=SPEED) => self.choose(),
+            _ => None,
+        }
+    }
+
+    /// Goes back to the main page, false when already there.
+    pub fn back(&mut self) -> bool {
+        if self.page == Page::Main {
+            return false;
+        }
+        self.page = Page::Main;
+        self.menu = main_menu();
+        self.menu.select(OPTIONS);
+        true
+    }
+
+    pub fn draw(&mut self, canvas: &mut Canvas<Window>, settings: &Settings) -> Result<(), String> {
+        if self.page == Page::Options {
+            let on_off = |on| if on { "on" } else { "off" };
+            self.menu.set_items(vec![
+                format!("Layout: {}", settings.layout.map_or("custom", Layout::name)),
+                format!("Filter: {}", settings.filter.name()),
+                format!("Phosphor: {}", on_off(settings.phosphor)),
+                format!("Debug overlay: {}", on_off(settings.overlay)),
+                format!("Keypad display: {}", on_off(settings.show_keys)),
+                format!("Speed: < {} >", settings.ips),
+                "Back".to_string(),
+            ]);
+        }
+        self.menu.draw(canvas)
+    }
+}
+
+fn main_menu() -> Menu {
+    Menu::new("Paused", MAIN.map(String::from).to_vec())
+}
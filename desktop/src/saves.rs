@@ -0,0 +1,98 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use chip8::romdb::rom_hash;
+use chip8::CPU;
+
+use crate::config::data_dir;
+
+/// Frames between two autosaves, 5 seconds at 60 fps.
+pub const AUTOSAVE_INTERVAL: u32 = 60 * 5;
+
+/// A save file for a single ROM, keyed by the hash of its contents so
+/// renamed or moved ROMs still find their saves. They live in the `saves`
+/// directory of the [`data_dir`], saves older versions left in the
+/// temporary directory are moved there the first time they are opened.
+pub struct SaveFile {
+    path: PathBuf,
+}
+
+impl SaveFile {
+    /// The slot written periodically and on exit.
+    pub fn autosave(rom: &[u8]) -> Self {
+        Self::for_rom(rom, "state")
+    }
+
+    /// The slot bound to the save/load state hotkeys.
+    pub fn quicksave(rom: &[u8]) -> Self {
+        Self::for_rom(rom, "quick.state")
+    }
+
+    /// The SUPER-CHIP user flags, written every time the game stores them.
+    pub fn flags(rom: &[u8]) -> Self {
+        Self::for_rom(rom, "flags")
+    }
+
+    fn for_rom(rom: &[u8], extension: &str) -> Self {
+        // without a home directory the saves stay where they used to be
+        let dir = data_dir().map_or_else(temp_dir, |dir| dir.join("saves"));
+        let save = Self::in_dir(dir, rom, extension);
+        save.migrate(&Self::in_dir(temp_dir(), rom, extension));
+        save
+    }
+
+    fn in_dir(dir: PathBuf, rom: &[u8], extension: &str) -> Self {
+        Self {
+            path: dir.join(format!("{:016x}.{extension}", rom_hash(rom))),
+        }
+    }
+
+    // moves the save `old` older versions kept elsewhere here, unless
+    // there is one here already
+    fn migrate(&self, old: &SaveFile) {
+        if old.path == self.path || self.path.exists() || !old.path.exists() {
+            return;
+        }
+        // copied, a rename can't cross into another file system
+        let moved = self
+            .path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::copy(&old.path, &self.path))
+            .and_then(|_| fs::remove_file(&old.path));
+        if let Err(err) = moved {
+            eprintln!(
+                "Moving {} to {} failed: {err}",
+                old.path.display(),
+                self.path.display()
+            );
+        }
+    }
+
+    /// Returns the saved state, if any was left by a previous save.
+    pub fn load(&self) -> Option<Vec<u8>> {
+        fs::read(&self.path).ok()
+    }
+
+    pub fn save(&self, cpu: &CPU) -> io::Result<()> {
+        self.write(&cpu.save_state())
+    }
+
+    /// Writes to a temporary file first and renames it over the previous
+    /// save, so a crash mid-write never leaves a truncated save.
+    pub fn write(&self, data: &[u8]) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, &self.path)
+    }
+}
+
+// where saves were kept before they moved to the data directory
+fn temp_dir() -> PathBuf {
+    std::env::temp_dir().join("chip8-autosave")
+}
@@ -0,0 +1,165 @@
+#ifndef CHIP8_H
+#define CHIP8_H
+
+/* Generated by cbindgen from ffi/src/lib.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Result of the fallible calls, `CHIP8_STATUS_OK` on success.
+ */
+typedef enum Chip8Status {
+  CHIP8_STATUS_OK = 0,
+  CHIP8_STATUS_NULL_POINTER,
+  CHIP8_STATUS_ROM_TOO_LARGE,
+  CHIP8_STATUS_UNKNOWN_OPCODE,
+  CHIP8_STATUS_STACK_OVERFLOW,
+  CHIP8_STATUS_STACK_UNDERFLOW,
+  CHIP8_STATUS_ADDRESS_OUT_OF_BOUNDS,
+  CHIP8_STATUS_INVALID_KEY,
+  CHIP8_STATUS_BAD_STATE,
+  CHIP8_STATUS_BUFFER_TOO_SMALL,
+  CHIP8_STATUS_ROM_EMPTY,
+  CHIP8_STATUS_ROM_BLANK,
+} Chip8Status;
+
+/**
+ * An emulator instance.
+ */
+typedef struct Chip8 Chip8;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates an emulator with nothing loaded. Release it with `chip8_free`.
+ */
+struct Chip8 *chip8_new(void);
+
+/**
+ * # Safety
+ *
+ * `chip8` must be null or come from `chip8_new`, and not be used again.
+ */
+void chip8_free(struct Chip8 *chip8);
+
+/**
+ * Copies a ROM of `len` bytes into memory at 0x200. Empty, too large and
+ * blank ROMs are rejected.
+ *
+ * # Safety
+ *
+ * `chip8` must come from `chip8_new` and `rom` point to `len` readable bytes.
+ */
+enum Chip8Status chip8_load(struct Chip8 *chip8, const uint8_t *rom, size_t len);
+
+/**
+ * Executes one instruction.
+ *
+ * # Safety
+ *
+ * `chip8` must come from `chip8_new`.
+ */
+enum Chip8Status chip8_tick(struct Chip8 *chip8);
+
+/**
+ * Counts the delay and sound timers down, call it at 60 Hz.
+ *
+ * # Safety
+ *
+ * `chip8` must come from `chip8_new`.
+ */
+void chip8_tick_timers(struct Chip8 *chip8);
+
+/**
+ * Returns the pixels of the current resolution row by row, one `bool` per
+ * pixel, and writes the resolution to `width` and `height` when they are not
+ * null. The pointer is valid until the next call on `chip8`.
+ *
+ * # Safety
+ *
+ * `chip8` must come from `chip8_new`, `width` and `height` must be null or
+ * writable.
+ */
+const bool *chip8_get_display(const struct Chip8 *chip8, size_t *width, size_t *height);
+
+/**
+ * Presses or releases key 0x0 to 0xF.
+ *
+ * # Safety
+ *
+ * `chip8` must come from `chip8_new`.
+ */
+enum Chip8Status chip8_keypress(struct Chip8 *chip8, uint8_t key, bool pressed);
+
+/**
+ * Whether the buzzer should currently sound.
+ *
+ * # Safety
+ *
+ * `chip8` must come from `chip8_new`.
+ */
+bool chip8_is_sound_playing(const struct Chip8 *chip8);
+
+/**
+ * Whether the program stopped itself with the SUPER-CHIP 00FD instruction.
+ *
+ * # Safety
+ *
+ * `chip8` must come from `chip8_new`.
+ */
+bool chip8_is_halted(const struct Chip8 *chip8);
+
+/**
+ * Makes the random instruction reproducible.
+ *
+ * # Safety
+ *
+ * `chip8` must come from `chip8_new`.
+ */
+void chip8_seed_rng(struct Chip8 *chip8, uint64_t seed);
+
+/**
+ * Size in bytes of a save state.
+ *
+ * # Safety
+ *
+ * `chip8` must come from `chip8_new`.
+ */
+size_t chip8_save_state_size(const struct Chip8 *chip8);
+
+/**
+ * Writes a save state to `buf`, which must hold `chip8_save_state_size`
+ * bytes.
+ *
+ * # Safety
+ *
+ * `chip8` must come from `chip8_new` and `buf` point to `len` writable bytes.
+ */
+enum Chip8Status chip8_save_state(const struct Chip8 *chip8, uint8_t *buf, size_t len);
+
+/**
+ * Restores a save state written by `chip8_save_state`. A rejected state
+ * leaves the emulator untouched.
+ *
+ * # Safety
+ *
+ * `chip8` must come from `chip8_new` and `buf` point to `len` readable bytes.
+ */
+enum Chip8Status chip8_load_state(struct Chip8 *chip8, const uint8_t *buf, size_t len);
+
+/**
+ * A static, NUL-terminated description of `status`.
+ */
+const char *chip8_status_message(enum Chip8Status status);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CHIP8_H */
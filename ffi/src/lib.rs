@@ -0,0 +1,277 @@
+//! C bindings for the chip8 core, the header is generated into
+//! `include/chip8.h` by the build script.
+//!
+//! The emulator is an opaque `Chip8` handle created with `chip8_new` and
+//! released with `chip8_free`. The caller owns the loop: call `chip8_tick`
+//! for every instruction and `chip8_tick_timers` 60 times a second.
+
+use std::ffi::c_char;
+use std::ptr;
+use std::slice;
+
+use chip8::{Chip8Error, RomError, CPU, NUM_KEYS};
+
+/// An emulator instance.
+pub struct Chip8(CPU);
+
+/// Result of the fallible calls, `CHIP8_STATUS_OK` on success.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Status {
+    Ok = 0,
+    NullPointer,
+    RomTooLarge,
+    UnknownOpcode,
+    StackOverflow,
+    StackUnderflow,
+    AddressOutOfBounds,
+    InvalidKey,
+    BadState,
+    BufferTooSmall,
+    RomEmpty,
+    RomBlank,
+}
+
+impl From<Chip8Error> for Chip8Status {
+    fn from(err: Chip8Error) -> Self {
+        match err {
+            Chip8Error::UnknownOpcode { .. } => Chip8Status::UnknownOpcode,
+            Chip8Error::StackOverflow => Chip8Status::StackOverflow,
+            Chip8Error::StackUnderflow => Chip8Status::StackUnderflow,
+            Chip8Error::AddressOutOfBounds(_) => Chip8Status::AddressOutOfBounds,
+            Chip8Error::InvalidKey(_) => Chip8Status::InvalidKey,
+        }
+    }
+}
+
+impl From<RomError> for Chip8Status {
+    fn from(err: RomError) -> Self {
+        match err {
+            RomError::Empty => Chip8Status::RomEmpty,
+            RomError::TooLarge { .. } => Chip8Status::RomTooLarge,
+            RomError::Blank => Chip8Status::RomBlank,
+        }
+    }
+}
+
+/// Creates an emulator with nothing loaded. Release it with `chip8_free`.
+#[no_mangle]
+pub extern "C" fn chip8_new() -> *mut Chip8 {
+    Box::into_raw(Box::new(Chip8(CPU::default())))
+}
+
+/// # Safety
+///
+/// `chip8` must be null or come from `chip8_new`, and not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_free(chip8: *mut Chip8) {
+    if !chip8.is_null() {
+        drop(Box::from_raw(chip8));
+    }
+}
+
+/// Copies a ROM of `len` bytes into memory at 0x200. Empty, too large and
+/// blank ROMs are rejected.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_new` and `rom` point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load(chip8: *mut Chip8, rom: *const u8, len: usize) -> Chip8Status {
+    let Some(chip8) = chip8.as_mut() else {
+        return Chip8Status::NullPointer;
+    };
+    if rom.is_null() {
+        return Chip8Status::NullPointer;
+    }
+    match chip8.0.load(slice::from_raw_parts(rom, len)) {
+        Ok(()) => Chip8Status::Ok,
+        Err(err) => err.into(),
+    }
+}
+
+/// Executes one instruction.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_new`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_tick(chip8: *mut Chip8) -> Chip8Status {
+    match chip8.as_mut() {
+        Some(chip8) => chip8
+            .0
+            .tick()
+            .map_or_else(Chip8Status::from, |()| Chip8Status::Ok),
+        None => Chip8Status::NullPointer,
+    }
+}
+
+/// Counts the delay and sound timers down, call it at 60 Hz.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_new`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_tick_timers(chip8: *mut Chip8) {
+    if let Some(chip8) = chip8.as_mut() {
+        chip8.0.tick_timers();
+    }
+}
+
+/// Returns the pixels of the current resolution row by row, one `bool` per
+/// pixel, and writes the resolution to `width` and `height` when they are not
+/// null. The pointer is valid until the next call on `chip8`.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_new`, `width` and `height` must be null or
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_get_display(
+    chip8: *const Chip8,
+    width: *mut usize,
+    height: *mut usize,
+) -> *const bool {
+    let Some(chip8) = chip8.as_ref() else {
+        return ptr::null();
+    };
+    let (w, h) = chip8.0.display_size();
+    if let Some(width) = width.as_mut() {
+        *width = w;
+    }
+    if let Some(height) = height.as_mut() {
+        *height = h;
+    }
+    chip8.0.get_display().as_ptr()
+}
+
+/// Presses or releases key 0x0 to 0xF.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_new`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_keypress(chip8: *mut Chip8, key: u8, pressed: bool) -> Chip8Status {
+    let Some(chip8) = chip8.as_mut() else {
+        return Chip8Status::NullPointer;
+    };
+    if key as usize >= NUM_KEYS {
+        return Chip8Status::InvalidKey;
+    }
+    chip8.0.keypress(key as usize, pressed);
+    Chip8Status::Ok
+}
+
+/// Whether the buzzer should currently sound.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_new`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_is_sound_playing(chip8: *const Chip8) -> bool {
+    chip8
+        .as_ref()
+        .is_some_and(|chip8| chip8.0.is_sound_playing())
+}
+
+/// Whether the program stopped itself with the SUPER-CHIP 00FD instruction.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_new`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_is_halted(chip8: *const Chip8) -> bool {
+    chip8.as_ref().is_some_and(|chip8| chip8.0.is_halted())
+}
+
+/// Makes the random instruction reproducible.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_new`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_seed_rng(chip8: *mut Chip8, seed: u64) {
+    if let Some(chip8) = chip8.as_mut() {
+        chip8.0.seed_rng(seed);
+    }
+}
+
+/// Size in bytes of a save state.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_new`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_save_state_size(chip8: *const Chip8) -> usize {
+    chip8.as_ref().map_or(0, |chip8| chip8.0.save_state().len())
+}
+
+/// Writes a save state to `buf`, which must hold `chip8_save_state_size`
+/// bytes.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_new` and `buf` point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_save_state(
+    chip8: *const Chip8,
+    buf: *mut u8,
+    len: usize,
+) -> Chip8Status {
+    let Some(chip8) = chip8.as_ref() else {
+        return Chip8Status::NullPointer;
+    };
+    if buf.is_null() {
+        return Chip8Status::NullPointer;
+    }
+    let state = chip8.0.save_state();
+    if state.len() > len {
+        return Chip8Status::BufferTooSmall;
+    }
+    ptr::copy_nonoverlapping(state.as_ptr(), buf, state.len());
+    Chip8Status::Ok
+}
+
+/// Restores a save state written by `chip8_save_state`. A rejected state
+/// leaves the emulator untouched.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_new` and `buf` point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_state(
+    chip8: *mut Chip8,
+    buf: *const u8,
+    len: usize,
+) -> Chip8Status {
+    let Some(chip8) = chip8.as_mut() else {
+        return Chip8Status::NullPointer;
+    };
+    if buf.is_null() {
+        return Chip8Status::NullPointer;
+    }
+    match chip8.0.load_state(slice::from_raw_parts(buf, len)) {
+        Ok(()) => Chip8Status::Ok,
+        Err(_) => Chip8Status::BadState,
+    }
+}
+
+/// A static, NUL-terminated description of `status`.
+#[no_mangle]
+pub extern "C" fn chip8_status_message(status: Chip8Status) -> *const c_char {
+    let message = match status {
+        Chip8Status::Ok => c"ok",
+        Chip8Status::NullPointer => c"null pointer",
+        Chip8Status::RomTooLarge => c"ROM doesn't fit in memory",
+        Chip8Status::UnknownOpcode => c"unknown opcode",
+        Chip8Status::StackOverflow => c"stack overflow",
+        Chip8Status::StackUnderflow => c"stack underflow",
+        Chip8Status::AddressOutOfBounds => c"memory access out of bounds",
+        Chip8Status::InvalidKey => c"invalid key",
+        Chip8Status::BadState => c"invalid save state",
+        Chip8Status::BufferTooSmall => c"buffer too small",
+        Chip8Status::RomEmpty => c"ROM is empty",
+        Chip8Status::RomBlank => c"ROM is blank",
+    };
+    message.as_ptr()
+}
@@ -0,0 +1,189 @@
+//! Runs ROMs headlessly with scripted input, to regression-test the
+//! interpreter against the displays test ROMs are known to draw.
+//!
+//! ```no_run
+//! use chip8::Chip8Builder;
+//! use chip8_test::{run, Script};
+//!
+//! let rom = std::fs::read("ROMs/INVADERS").unwrap();
+//! let cpu = Chip8Builder::new().rom(&rom).seed(0).build().unwrap();
+//! let script: Script = "30:+5 31:-5".parse().unwrap();
+//! let outcome = run(cpu, &script, 120).unwrap();
+//! println!("{:016x}\n{}", outcome.display_hash, outcome.snapshot);
+//! ```
+
+mod trace;
+
+use std::fmt;
+use std::mem;
+use std::ops::ControlFlow;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use chip8::frontend::{Display, Driver, Input};
+use chip8::romdb::rom_hash;
+use chip8::screen::FrameBuffer;
+use chip8::trace::TraceEntry;
+use chip8::{Chip8Error, CPU, NUM_KEYS};
+
+pub use trace::{Step, Trace, TraceMismatch, TraceParseError};
+
+/// A key pressed or released at the start of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub frame: u32,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+/// Key presses and releases by frame, written `FRAME:+KEY` and `FRAME:-KEY`
+/// separated by spaces or commas, e.g. `30:+5 32:-5` holds key 5 during
+/// frames 30 and 31.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    // sorted by frame
+    events: Vec<KeyEvent>,
+}
+
+impl Script {
+    pub fn new(mut events: Vec<KeyEvent>) -> Self {
+        events.sort_by_key(|event| event.frame);
+        Self { events }
+    }
+
+    pub fn events(&self) -> &[KeyEvent] {
+        &self.events
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptError(String);
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid key event {:?}, expected FRAME:+KEY or FRAME:-KEY",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+impl FromStr for Script {
+    type Err = ScriptError;
+
+    fn from_str(script: &str) -> Result<Self, Self::Err> {
+        let events = script
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|event| !event.is_empty())
+            .map(|event| parse_event(event).ok_or_else(|| ScriptError(event.to_string())))
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(events))
+    }
+}
+
+fn parse_event(event: &str) -> Option<KeyEvent> {
+    let (frame, key) = event.split_once(':')?;
+    let pressed = match key.as_bytes().first()? {
+        b'+' => true,
+        b'-' => false,
+        _ => return None,
+    };
+    let key = u8::from_str_radix(&key[1..], 16).ok()?;
+    if key as usize >= NUM_KEYS {
+        return None;
+    }
+    Some(KeyEvent {
+        frame: frame.parse().ok()?,
+        key,
+        pressed,
+    })
+}
+
+/// Feeds the script to the driver, one poll per frame.
+struct ScriptInput<'a> {
+    events: &'a [KeyEvent],
+    frame: u32,
+}
+
+impl Input for ScriptInput<'_> {
+    fn poll(&mut self, keys: &mut [bool; NUM_KEYS]) -> ControlFlow<()> {
+        while let Some((event, rest)) = self.events.split_first() {
+            if event.frame > self.frame {
+                break;
+            }
+            keys[event.key as usize] = event.pressed;
+            self.events = rest;
+        }
+        self.frame += 1;
+        ControlFlow::Continue(())
+    }
+}
+
+/// The display is only looked at once the run is over.
+struct Headless;
+
+impl Display for Headless {
+    fn draw(&mut self, _pixels: &[bool], _width: usize, _height: usize) {}
+}
+
+/// The machine after a run, see [`run`].
+pub struct Outcome {
+    pub cpu: CPU,
+    /// See [`display_hash`].
+    pub display_hash: u64,
+    /// The display in ASCII, see the `Display` of [`FrameBuffer`].
+    pub snapshot: String,
+}
+
+/// Runs `frames` frames of `cpu` at its own speed, pressing and releasing
+/// keys as the script says.
+pub fn run(cpu: CPU, script: &Script, frames: u32) -> Result<Outcome, Chip8Error> {
+    let input = ScriptInput {
+        events: &script.events,
+        frame: 0,
+    };
+    let mut driver = Driver::new(cpu, Headless, input, ());
+    for _ in 0..frames {
+        // the script never stops the driver
+        let _ = driver.run_frame()?;
+    }
+    let (cpu, ..) = driver.into_parts();
+    let frame = cpu.frame_buffer();
+    Ok(Outcome {
+        display_hash: display_hash(&frame),
+        snapshot: frame.to_string(),
+        cpu,
+    })
+}
+
+/// Like [`run`], also recording every executed instruction. Replaces the
+/// tracer of `cpu`.
+pub fn run_traced(
+    mut cpu: CPU,
+    script: &Script,
+    frames: u32,
+) -> Result<(Outcome, Trace), Chip8Error> {
+    let steps = Arc::new(Mutex::new(Vec::new()));
+    let sink = steps.clone();
+    cpu.set_tracer(Some(Box::new(move |entry: &TraceEntry| {
+        sink.lock().expect("the trace isn't poisoned").push(Step {
+            address: entry.address,
+            opcode: entry.opcode,
+        });
+    })));
+    let mut outcome = run(cpu, script, frames)?;
+    outcome.cpu.set_tracer(None);
+    let steps = mem::take(&mut *steps.lock().expect("the trace isn't poisoned"));
+    Ok((outcome, Trace::new(steps)))
+}
+
+/// FNV-1a of the size and the color of every pixel, stable across runs and
+/// platforms.
+pub fn display_hash(frame: &FrameBuffer) -> u64 {
+    let mut bytes = vec![frame.width() as u8, frame.height() as u8];
+    bytes.extend(frame.colors());
+    rom_hash(&bytes)
+}
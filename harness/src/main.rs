@@ -0,0 +1,338 @@
+//! Runs a ROM headlessly and checks the display it ends up with.
+//!
+//! ```shell
+//! chip8-test ROMs/INVADERS --frames=120 --keys="30:+5 31:-5" --snapshot=invaders.txt
+//! ```
+//!
+//! `--trace=FILE` also compares every executed instruction with a golden
+//! trace. Exits with 1 when the display or the trace doesn't match, 2 when
+//! the ROM can't run.
+//!
+//! `chip8-test diff A.state B.state` prints what differs between two save
+//! states, as written by `--save-state=FILE` or the desktop frontend, and
+//! exits with 1 when they differ.
+//!
+//! `chip8-test lint ROM` prints what looks wrong in a ROM without running
+//! it, see [`chip8::lint`], and exits with 1 when anything does.
+//! `chip8-test disasm ROM` prints an annotated listing of it, see
+//! [`chip8::disasm::annotate_rom`], with the names of `ROM.sym` or
+//! `--symbols=FILE` when there is one.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::ExitCode;
+
+use chip8::disasm::annotate_rom_with;
+use chip8::symbols::{SymbolError, Symbols};
+use chip8::{Chip8Builder, Quirks, SaveState, START_ADDR};
+use chip8_test::{run, run_traced, Script, Trace};
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{value_parser, Arg, ArgAction, ArgGroup, ArgMatches, Command};
+
+fn command() -> Command {
+    Command::new("chip8-test")
+        .about("Runs a ROM headlessly and checks the display it ends up with")
+        .args_conflicts_with_subcommands(true)
+        .subcommand_negates_reqs(true)
+        .subcommand(
+            Command::new("diff")
+                .about("Prints what differs between two save states")
+                .arg(Arg::new("old").required(true).value_name("OLD"))
+                .arg(Arg::new("new").required(true).value_name("NEW")),
+        )
+        .subcommand(
+            Command::new("lint")
+                .about("Prints what looks wrong in a ROM without running it")
+                .arg(Arg::new("rom").required(true).value_name("ROM")),
+        )
+        .subcommand(
+            Command::new("disasm")
+                .about("Prints an annotated listing of a ROM, labels and data apart")
+                .arg(Arg::new("rom").required(true).value_name("ROM"))
+                .arg(
+                    Arg::new("symbols")
+                        .long("symbols")
+                        .value_name("FILE")
+                        .help("Octo symbol file naming the labels [default: ROM.sym]"),
+                ),
+        )
+        .arg(Arg::new("rom").required(true).value_name("ROM"))
+        .arg(
+            Arg::new("frames")
+                .long("frames")
+                .value_name("N")
+                .value_parser(value_parser!(u32))
+                .default_value("60")
+                .help("60 Hz frames to run"),
+        )
+        .arg(
+            Arg::new("keys")
+                .long("keys")
+                .value_name("SCRIPT")
+                .value_parser(|script: &str| script.parse::<Script>())
+                .help("Keys to press and release, e.g. \"30:+5 32:-5\""),
+        )
+        .arg(
+            Arg::new("ips")
+                .long("ips")
+                .value_name("HZ")
+                .value_parser(value_parser!(u32).range(1..))
+                .help("Instructions per second [default: 600 or the ROM's]"),
+        )
+        .arg(
+            Arg::new("quirks")
+                .long("quirks")
+                .value_name("NAME")
+                .value_parser(PossibleValuesParser::new(["default", "vip", "schip"]).map(
+                    |quirks: String| match quirks.as_str() {
+                        "vip" => Quirks::VIP,
+                        "schip" => Quirks::SUPER_CHIP,
+                        _ => Quirks::default(),
+                    },
+                ))
+                .help("Interpreter to behave like, instead of the ROM's"),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_parser(value_parser!(u64))
+                .default_value("0")
+                .help("Seed of the random instruction, runs are reproducible"),
+        )
+        .arg(
+            Arg::new("hash")
+                .long("hash")
+                .value_name("HEX")
+                .value_parser(|hash: &str| u64::from_str_radix(hash, 16))
+                .conflicts_with("snapshot")
+                .help("Expected display hash, as printed without a check"),
+        )
+        .arg(
+            Arg::new("snapshot")
+                .long("snapshot")
+                .value_name("FILE")
+                .help("File holding the expected display in ASCII"),
+        )
+        .arg(
+            Arg::new("trace")
+                .long("trace")
+                .value_name("FILE")
+                .help("File holding the expected executed instructions"),
+        )
+        .group(
+            ArgGroup::new("golden")
+                .args(["snapshot", "trace"])
+                .multiple(true),
+        )
+        .arg(
+            Arg::new("update")
+                .long("update")
+                .action(ArgAction::SetTrue)
+                .requires("golden")
+                .help("Write the snapshot and trace files instead of checking them"),
+        )
+        .arg(
+            Arg::new("save-state")
+                .long("save-state")
+                .value_name("FILE")
+                .help("File to save the state the run ends in to"),
+        )
+}
+
+fn diff(matches: &ArgMatches) -> ExitCode {
+    let load = |name: &str| {
+        let path: &String = matches.get_one(name).expect("both states are required");
+        fs::read(path)
+            .map_err(|err| err.to_string())
+            .and_then(|data| SaveState::from_bytes(&data).map_err(|err| err.to_string()))
+            .map_err(|err| eprintln!("{path}: {err}"))
+    };
+    let (Ok(old), Ok(new)) = (load("old"), load("new")) else {
+        return ExitCode::from(2);
+    };
+    let diff = old.diff(&new);
+    print!("{diff}");
+    if diff.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+// the ROM of a subcommand and its path, printing why it can't be read
+fn read_rom(matches: &ArgMatches) -> Result<(&String, Vec<u8>), ExitCode> {
+    let path: &String = matches.get_one("rom").expect("the ROM is required");
+    match fs::read(path) {
+        Ok(rom) => Ok((path, rom)),
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            Err(ExitCode::from(2))
+        }
+    }
+}
+
+// the symbols named by `--symbols`, or next to the ROM when it has any
+fn read_symbols(matches: &ArgMatches, rom: &str) -> Result<Symbols, ExitCode> {
+    let (file, text) = match matches.get_one::<String>("symbols") {
+        Some(file) => (file.clone(), fs::read_to_string(file)),
+        None => {
+            let file = Path::new(rom).with_extension("sym");
+            match fs::read_to_string(&file) {
+                Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Symbols::default()),
+                text => (file.display().to_string(), text),
+            }
+        }
+    };
+    text.map_err(|err| err.to_string())
+        .and_then(|text| text.parse().map_err(|err: SymbolError| err.to_string()))
+        .map_err(|err| {
+            eprintln!("{file}: {err}");
+            ExitCode::from(2)
+        })
+}
+
+fn disasm(matches: &ArgMatches) -> ExitCode {
+    let (path, rom) = match read_rom(matches) {
+        Ok(read) => read,
+        Err(code) => return code,
+    };
+    match read_symbols(matches, path) {
+        Ok(symbols) => {
+            print!("{}", annotate_rom_with(&rom, START_ADDR, &symbols));
+            ExitCode::SUCCESS
+        }
+        Err(code) => code,
+    }
+}
+
+fn lint(matches: &ArgMatches) -> ExitCode {
+    let (path, rom) = match read_rom(matches) {
+        Ok(read) => read,
+        Err(code) => return code,
+    };
+    let warnings = chip8::lint::lint(&rom, START_ADDR);
+    for warning in &warnings {
+        println!("{path}:{warning}");
+    }
+    if warnings.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn main() -> ExitCode {
+    let matches = command().get_matches();
+    match matches.subcommand() {
+        Some(("diff", matches)) => return diff(matches),
+        Some(("lint", matches)) => return lint(matches),
+        Some(("disasm", matches)) => return disasm(matches),
+        _ => {}
+    }
+    let path: &String = matches.get_one("rom").expect("the ROM is required");
+    let rom = match fs::read(path) {
+        Ok(rom) => rom,
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            return ExitCode::from(2);
+        }
+    };
+    let seed = *matches.get_one("seed").expect("the seed has a default");
+    let mut cpu = match Chip8Builder::new().rom(&rom).seed(seed).build() {
+        Ok(cpu) => cpu,
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            return ExitCode::from(2);
+        }
+    };
+    cpu.configure_for_rom(&rom);
+    if let Some(&hz) = matches.get_one("ips") {
+        cpu.set_clock_hz(hz);
+    }
+    if let Some(&quirks) = matches.get_one("quirks") {
+        cpu.set_quirks(quirks);
+    }
+
+    let script = matches.get_one("keys").cloned().unwrap_or_default();
+    let frames = *matches.get_one("frames").expect("frames has a default");
+    let trace_file = matches.get_one::<String>("trace");
+    let result = match trace_file {
+        Some(_) => run_traced(cpu, &script, frames).map(|(outcome, trace)| (outcome, Some(trace))),
+        None => run(cpu, &script, frames).map(|outcome| (outcome, None)),
+    };
+    let (outcome, trace) = match result {
+        Ok(result) => result,
+        Err(err) => {
+            eprintln!("{path}: emulation stopped: {err}");
+            return ExitCode::from(2);
+        }
+    };
+    let update = matches.get_flag("update");
+
+    if let Some(file) = matches.get_one::<String>("save-state") {
+        if let Err(err) = fs::write(file, outcome.cpu.save_state()) {
+            eprintln!("{file}: {err}");
+            return ExitCode::from(2);
+        }
+    }
+
+    if let (Some(file), Some(trace)) = (trace_file, &trace) {
+        if update {
+            if let Err(err) = fs::write(file, trace.to_string()) {
+                eprintln!("{file}: {err}");
+                return ExitCode::from(2);
+            }
+        } else {
+            let expected = match fs::read_to_string(file)
+                .map_err(|err| err.to_string())
+                .and_then(|text| text.parse::<Trace>().map_err(|err| err.to_string()))
+            {
+                Ok(expected) => expected,
+                Err(err) => {
+                    eprintln!("{file}: {err}");
+                    return ExitCode::from(2);
+                }
+            };
+            if let Err(mismatch) = trace.compare(&expected) {
+                eprintln!("{path}: execution left {file} at {mismatch}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if let Some(&expected) = matches.get_one::<u64>("hash") {
+        if outcome.display_hash != expected {
+            eprintln!(
+                "{path}: display hash {:016x}, expected {expected:016x}\n{}",
+                outcome.display_hash, outcome.snapshot
+            );
+            return ExitCode::FAILURE;
+        }
+    } else if let Some(snapshot) = matches.get_one::<String>("snapshot") {
+        if update {
+            if let Err(err) = fs::write(snapshot, &outcome.snapshot) {
+                eprintln!("{snapshot}: {err}");
+                return ExitCode::from(2);
+            }
+            return ExitCode::SUCCESS;
+        }
+        let expected = match fs::read_to_string(snapshot) {
+            Ok(expected) => expected,
+            Err(err) => {
+                eprintln!("{snapshot}: {err}");
+                return ExitCode::from(2);
+            }
+        };
+        if outcome.snapshot != expected {
+            eprintln!(
+                "{path}: display differs from {snapshot}, got\n{}",
+                outcome.snapshot
+            );
+            return ExitCode::FAILURE;
+        }
+    } else if trace.is_none() {
+        println!("{:016x}\n{}", outcome.display_hash, outcome.snapshot);
+    }
+    ExitCode::SUCCESS
+}
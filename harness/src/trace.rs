@@ -0,0 +1,121 @@
+//! Execution traces in a canonical text format, to compare a run against a
+//! golden one step by step.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// An executed instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    pub address: u16,
+    pub opcode: u16,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:03X} {:04X}", self.address, self.opcode)
+    }
+}
+
+/// The instructions a run executed, printed a `AAA OOOO` line per step with
+/// the address and the opcode in hex.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    steps: Vec<Step>,
+}
+
+impl Trace {
+    pub fn new(steps: Vec<Step>) -> Self {
+        Self { steps }
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// The first step where this trace leaves `expected`.
+    pub fn compare(&self, expected: &Trace) -> Result<(), TraceMismatch> {
+        let len = self.steps.len().max(expected.steps.len());
+        match (0..len).find(|&idx| self.steps.get(idx) != expected.steps.get(idx)) {
+            Some(step) => Err(TraceMismatch {
+                step,
+                got: self.steps.get(step).copied(),
+                expected: expected.steps.get(step).copied(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{step}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A line of a trace file that isn't `AAA OOOO`, counted from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceParseError {
+    pub line: usize,
+}
+
+impl fmt::Display for TraceParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {} of the trace isn't an address and an opcode",
+            self.line
+        )
+    }
+}
+
+impl std::error::Error for TraceParseError {}
+
+impl FromStr for Trace {
+    type Err = TraceParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let steps = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| parse_step(line).ok_or(TraceParseError { line: idx + 1 }))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { steps })
+    }
+}
+
+fn parse_step(line: &str) -> Option<Step> {
+    let (address, opcode) = line.trim().split_once(' ')?;
+    Some(Step {
+        address: u16::from_str_radix(address, 16).ok()?,
+        opcode: u16::from_str_radix(opcode.trim(), 16).ok()?,
+    })
+}
+
+/// Where a trace left the golden one, `None` past the end of a trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceMismatch {
+    /// Counted from 0.
+    pub step: usize,
+    pub got: Option<Step>,
+    pub expected: Option<Step>,
+}
+
+impl fmt::Display for TraceMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |step: Option<Step>| step.map_or("the end".to_string(), |step| step.to_string());
+        write!(
+            f,
+            "step {}: got {}, expected {}",
+            self.step,
+            show(self.got),
+            show(self.expected)
+        )
+    }
+}
+
+impl std::error::Error for TraceMismatch {}
@@ -0,0 +1,31 @@
+//! Save state diffs of a run against itself a few frames later.
+
+use std::fs;
+use std::path::Path;
+
+use chip8::Chip8Builder;
+use chip8_test::run;
+
+#[test]
+fn invaders() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let rom = fs::read(root.join("../ROMs/INVADERS")).unwrap();
+    let start = |frames| {
+        let cpu = Chip8Builder::new().rom(&rom).seed(0).build().unwrap();
+        run(cpu, &"30:+5 32:-5".parse().unwrap(), frames)
+            .unwrap()
+            .cpu
+            .snapshot()
+    };
+    let (before, mut after) = (start(30), start(60));
+    assert!(before.diff(&before).is_empty());
+
+    after.ram[0x300] ^= 0xFF;
+    let diff = before.diff(&after);
+    assert!(diff.fields.iter().any(|change| change.name == "PC"));
+    assert!(diff.memory.iter().any(|change| change.address == 0x300));
+    assert!(!diff.pixels.is_empty());
+    let report = diff.to_string();
+    assert!(report.contains("memory bytes differ"), "{report}");
+    assert!(report.contains("pixels differ"), "{report}");
+}
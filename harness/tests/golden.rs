@@ -0,0 +1,38 @@
+//! The instructions the bundled ROMs execute, regenerate a trace with
+//! `cargo run -p chip8-test -- ROMs/NAME ... --trace=... --update`.
+
+use std::fs;
+use std::path::Path;
+
+use chip8::Chip8Builder;
+use chip8_test::{run_traced, Trace};
+
+fn check(name: &str, frames: u32, keys: &str) {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let rom = fs::read(root.join("../ROMs").join(name)).unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).seed(0).build().unwrap();
+    cpu.configure_for_rom(&rom);
+    let (_, trace) = run_traced(cpu, &keys.parse().unwrap(), frames).unwrap();
+    let golden: Trace = fs::read_to_string(root.join("golden").join(format!("{name}.trace")))
+        .unwrap()
+        .parse()
+        .unwrap();
+    if let Err(mismatch) = trace.compare(&golden) {
+        panic!("{name} left its golden trace at {mismatch}");
+    }
+}
+
+#[test]
+fn invaders() {
+    check("INVADERS", 60, "20:+5 22:-5");
+}
+
+#[test]
+fn tetris() {
+    check("TETRIS", 60, "20:+5 22:-5");
+}
+
+#[test]
+fn tictac() {
+    check("TICTAC", 60, "20:+1 22:-1");
+}
@@ -0,0 +1,20 @@
+//! Annotated listings of the ROMs assemble back into the same ROMs.
+
+use std::fs;
+use std::path::Path;
+
+use chip8::asm::assemble;
+use chip8::disasm::annotate_rom;
+use chip8::START_ADDR;
+
+#[test]
+fn listings_assemble_back() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    for entry in fs::read_dir(root.join("../ROMs")).unwrap() {
+        let path = entry.unwrap().path();
+        let rom = fs::read(&path).unwrap();
+        let listing = annotate_rom(&rom, START_ADDR).to_string();
+        let rebuilt = assemble(&listing).unwrap_or_else(|err| panic!("{path:?}: {err}"));
+        assert_eq!(rebuilt, rom, "{path:?}");
+    }
+}
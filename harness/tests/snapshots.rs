@@ -0,0 +1,37 @@
+//! The displays the bundled ROMs draw after some scripted input, regenerate
+//! one with `cargo run -p chip8-test -- ROMs/NAME ... --snapshot=... --update`.
+
+use std::fs;
+use std::path::Path;
+
+use chip8::Chip8Builder;
+use chip8_test::run;
+
+fn check(name: &str, frames: u32, keys: &str) {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let rom = fs::read(root.join("../ROMs").join(name)).unwrap();
+    let mut cpu = Chip8Builder::new().rom(&rom).seed(0).build().unwrap();
+    cpu.configure_for_rom(&rom);
+    let outcome = run(cpu, &keys.parse().unwrap(), frames).unwrap();
+    let expected = fs::read_to_string(root.join("snapshots").join(format!("{name}.txt"))).unwrap();
+    assert!(
+        outcome.snapshot == expected,
+        "{name} drew\n{}expected\n{expected}",
+        outcome.snapshot
+    );
+}
+
+#[test]
+fn invaders() {
+    check("INVADERS", 240, "120:+5 122:-5");
+}
+
+#[test]
+fn tetris() {
+    check("TETRIS", 180, "60:+5 62:-5");
+}
+
+#[test]
+fn tictac() {
+    check("TICTAC", 180, "60:+1 62:-1 100:+5 102:-5");
+}
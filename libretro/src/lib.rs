@@ -0,0 +1,347 @@
+//! The chip8 core packaged as a libretro core, loadable in RetroArch.
+//!
+//! The keypad follows the desktop layout on the keyboard (1234/QWER/ASDF/ZXCV).
+//! On a RetroPad the d-pad is 2/4/6/8, A is 5, B is 0 and Y, X, L, R, Select
+//! and Start are A to F.
+
+use std::ffi::{c_uint, c_void};
+use std::sync::{Mutex, MutexGuard};
+
+use chip8::audio::{AudioSource, DEFAULT_SAMPLE_RATE};
+use chip8::screen::{MEGACHIP_SCREEN_HEIGHT, MEGACHIP_SCREEN_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8::{Chip8Builder, RomError, CPU};
+
+mod ffi;
+
+use ffi::*;
+
+const FPS: f64 = 60.0;
+const SAMPLES_PER_FRAME: usize = DEFAULT_SAMPLE_RATE as usize / 60;
+
+const ON_COLOR: u32 = 0x00FF_FFFF;
+const OFF_COLOR: u32 = 0x0000_0000;
+
+// keyboard keys in keypad order 0x0 to 0xF, libretro key codes are ASCII
+const KEYBOARD: [u8; 16] = *b"x123qweasdzc4rfv";
+
+const JOYPAD: [(c_uint, usize); 12] = [
+    (RETRO_DEVICE_ID_JOYPAD_UP, 0x2),
+    (RETRO_DEVICE_ID_JOYPAD_LEFT, 0x4),
+    (RETRO_DEVICE_ID_JOYPAD_RIGHT, 0x6),
+    (RETRO_DEVICE_ID_JOYPAD_DOWN, 0x8),
+    (RETRO_DEVICE_ID_JOYPAD_A, 0x5),
+    (RETRO_DEVICE_ID_JOYPAD_B, 0x0),
+    (RETRO_DEVICE_ID_JOYPAD_Y, 0xA),
+    (RETRO_DEVICE_ID_JOYPAD_X, 0xB),
+    (RETRO_DEVICE_ID_JOYPAD_L, 0xC),
+    (RETRO_DEVICE_ID_JOYPAD_R, 0xD),
+    (RETRO_DEVICE_ID_JOYPAD_SELECT, 0xE),
+    (RETRO_DEVICE_ID_JOYPAD_START, 0xF),
+];
+
+struct Callbacks {
+    environment: Option<RetroEnvironment>,
+    video_refresh: Option<RetroVideoRefresh>,
+    audio_sample_batch: Option<RetroAudioSampleBatch>,
+    input_poll: Option<RetroInputPoll>,
+    input_state: Option<RetroInputState>,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+});
+
+struct Core {
+    cpu: CPU,
+    rom: Vec<u8>,
+    // set when the program fails, the last frame stays on screen
+    crashed: bool,
+    framebuffer: Vec<u32>,
+    beeper: AudioSource,
+    samples: Vec<i16>,
+    // interleaved stereo
+    audio: Vec<i16>,
+}
+
+impl Core {
+    fn new(rom: Vec<u8>) -> Result<Self, RomError> {
+        let cpu = Chip8Builder::new().rom(&rom).build()?;
+        Ok(Self {
+            cpu,
+            rom,
+            crashed: false,
+            framebuffer: Vec::with_capacity(MEGACHIP_SCREEN_WIDTH * MEGACHIP_SCREEN_HEIGHT),
+            beeper: AudioSource::new(DEFAULT_SAMPLE_RATE),
+            samples: vec![0; SAMPLES_PER_FRAME],
+            audio: Vec::with_capacity(SAMPLES_PER_FRAME * 2),
+        })
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new(std::mem::take(&mut self.rom)).expect("the ROM loaded before");
+    }
+
+    fn run_frame(&mut self, callbacks: &Callbacks) {
+        if let (Some(poll), Some(state)) = (callbacks.input_poll, callbacks.input_state) {
+            // SAFETY: the frontend hands out valid callbacks for the core's lifetime
+            unsafe { poll() };
+            let mut keys = [false; 16];
+            for (key, &code) in keys.iter_mut().zip(KEYBOARD.iter()) {
+                *key = unsafe { state(0, RETRO_DEVICE_KEYBOARD, 0, code as c_uint) } != 0;
+            }
+            for &(id, key) in JOYPAD.iter() {
+                keys[key] |= unsafe { state(0, RETRO_DEVICE_JOYPAD, 0, id) } != 0;
+            }
+            for (idx, &pressed) in keys.iter().enumerate() {
+                self.cpu.keypress(idx, pressed);
+            }
+        }
+
+        if !self.crashed {
+            if let Err(err) = self.cpu.run_frame(self.cpu.clock_hz()) {
+                eprintln!("chip8: emulation stopped: {err}");
+                self.crashed = true;
+            }
+            self.cpu.tick_timers();
+        }
+
+        let (width, height) = self.cpu.display_size();
+        self.framebuffer.clear();
+        self.framebuffer
+            .extend(
+                self.cpu
+                    .get_display()
+                    .iter()
+                    .map(|&px| if px { ON_COLOR } else { OFF_COLOR }),
+            );
+        if let Some(video_refresh) = callbacks.video_refresh {
+            unsafe {
+                video_refresh(
+                    self.framebuffer.as_ptr() as *const c_void,
+                    width as c_uint,
+                    height as c_uint,
+                    width * 4,
+                )
+            };
+        }
+
+        if self.crashed {
+            self.samples.fill(0);
+        } else {
+            self.beeper.fill_i16(&self.cpu, &mut self.samples);
+        }
+        self.audio.clear();
+        self.audio
+            .extend(self.samples.iter().flat_map(|&sample| [sample, sample]));
+        if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+            unsafe { audio_sample_batch(self.audio.as_ptr(), SAMPLES_PER_FRAME) };
+        }
+    }
+}
+
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+// a panic while holding a lock must not poison the whole core
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+/// # Safety
+///
+/// `info` must point to a writable `retro_system_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut RetroSystemInfo) {
+    *info = RetroSystemInfo {
+        library_name: c"chip8".as_ptr(),
+        library_version: c"0.1.0".as_ptr(),
+        valid_extensions: c"ch8|c8|sc8|rom".as_ptr(),
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+/// # Safety
+///
+/// `info` must point to a writable `retro_system_av_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut RetroSystemAvInfo) {
+    *info = RetroSystemAvInfo {
+        geometry: RetroGameGeometry {
+            base_width: SCREEN_WIDTH as c_uint,
+            base_height: SCREEN_HEIGHT as c_uint,
+            max_width: MEGACHIP_SCREEN_WIDTH as c_uint,
+            max_height: MEGACHIP_SCREEN_HEIGHT as c_uint,
+            aspect_ratio: 2.0,
+        },
+        timing: RetroSystemTiming {
+            fps: FPS,
+            sample_rate: DEFAULT_SAMPLE_RATE as f64,
+        },
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: RetroEnvironment) {
+    lock(&CALLBACKS).environment = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: RetroVideoRefresh) {
+    lock(&CALLBACKS).video_refresh = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_callback: RetroAudioSample) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: RetroAudioSampleBatch) {
+    lock(&CALLBACKS).audio_sample_batch = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: RetroInputPoll) {
+    lock(&CALLBACKS).input_poll = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: RetroInputState) {
+    lock(&CALLBACKS).input_state = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    *lock(&CORE) = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    if let Some(core) = lock(&CORE).as_mut() {
+        core.reset();
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let callbacks = lock(&CALLBACKS);
+    if let Some(core) = lock(&CORE).as_mut() {
+        core.run_frame(&callbacks);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    lock(&CORE)
+        .as_ref()
+        .map_or(0, |core| core.cpu.save_state().len())
+}
+
+/// # Safety
+///
+/// `data` must point to `size` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let Some(state) = lock(&CORE).as_ref().map(|core| core.cpu.save_state()) else {
+        return false;
+    };
+    if state.len() > size {
+        return false;
+    }
+    std::ptr::copy_nonoverlapping(state.as_ptr(), data as *mut u8, state.len());
+    true
+}
+
+/// # Safety
+///
+/// `data` must point to `size` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    let state = std::slice::from_raw_parts(data as *const u8, size);
+    match lock(&CORE).as_mut() {
+        Some(core) => core.cpu.load_state(state).is_ok(),
+        None => false,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_void) {}
+
+/// # Safety
+///
+/// `game` must be null or point to a valid `retro_game_info` whose data
+/// holds `size` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const RetroGameInfo) -> bool {
+    if game.is_null() || (*game).data.is_null() {
+        return false;
+    }
+    let rom = std::slice::from_raw_parts((*game).data as *const u8, (*game).size).to_vec();
+
+    let environment = lock(&CALLBACKS).environment;
+    if let Some(environment) = environment {
+        let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+        if !environment(
+            RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+            &mut format as *mut c_uint as *mut c_void,
+        ) {
+            return false;
+        }
+    }
+
+    match Core::new(rom) {
+        Ok(core) => {
+            *lock(&CORE) = Some(core);
+            true
+        }
+        Err(err) => {
+            eprintln!("chip8: {err}");
+            false
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const RetroGameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    *lock(&CORE) = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+    0
+}
@@ -0,0 +1,134 @@
+//! Python bindings for the chip8 core, built with maturin:
+//!
+//! ```python
+//! import chip8, numpy
+//!
+//! cpu = chip8.Chip8(open("ROMs/INVADERS", "rb").read())
+//! cpu.run_frame()
+//! w, h = cpu.display_size
+//! pixels = numpy.frombuffer(cpu.display(), dtype=numpy.uint8).reshape(h, w)
+//! ```
+
+use chip8::frontend::DEFAULT_TICKS_PER_FRAME;
+use chip8::{CPU, NUM_KEYS};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+create_exception!(chip8, Chip8Error, PyException, "The program failed.");
+
+/// A CHIP-8 machine.
+// the random source isn't Sync, so an instance stays on the thread creating it
+#[pyclass(name = "Chip8", module = "chip8", unsendable)]
+struct Chip8 {
+    cpu: CPU,
+}
+
+#[pymethods]
+impl Chip8 {
+    /// Creates a machine, loading `rom` when given. `seed` makes the random
+    /// instruction reproducible.
+    #[new]
+    #[pyo3(signature = (rom=None, seed=None))]
+    fn new(rom: Option<&[u8]>, seed: Option<u64>) -> PyResult<Self> {
+        let mut chip8 = Self {
+            cpu: CPU::default(),
+        };
+        if let Some(rom) = rom {
+            chip8.load(rom)?;
+        }
+        if let Some(seed) = seed {
+            chip8.cpu.seed_rng(seed);
+        }
+        Ok(chip8)
+    }
+
+    /// Copies a ROM into memory at 0x200. Raises `ValueError` for an empty,
+    /// too large or blank ROM.
+    fn load(&mut self, rom: &[u8]) -> PyResult<()> {
+        self.cpu
+            .load(rom)
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    /// Executes one instruction.
+    fn tick(&mut self) -> PyResult<()> {
+        self.cpu
+            .tick()
+            .map_err(|err| Chip8Error::new_err(err.to_string()))
+    }
+
+    /// Counts the delay and sound timers down, call it at 60 Hz.
+    fn tick_timers(&mut self) {
+        self.cpu.tick_timers();
+    }
+
+    /// Runs `ticks` instructions then ticks the timers, one 60 Hz frame.
+    #[pyo3(signature = (ticks=DEFAULT_TICKS_PER_FRAME))]
+    fn run_frame(&mut self, ticks: usize) -> PyResult<()> {
+        self.cpu
+            .run(ticks)
+            .map_err(|err| Chip8Error::new_err(err.to_string()))?;
+        self.tick_timers();
+        Ok(())
+    }
+
+    /// The pixels of the current resolution row by row, one byte per pixel
+    /// set to 0 or 1. Reshape it to `display_size` with numpy.
+    fn display<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        let pixels = self.cpu.get_display();
+        PyBytes::new_with(py, pixels.len(), |buf| {
+            for (byte, &px) in buf.iter_mut().zip(pixels) {
+                *byte = px as u8;
+            }
+            Ok(())
+        })
+        .expect("filling the buffer can't fail")
+    }
+
+    /// `(width, height)` of the current resolution.
+    #[getter]
+    fn display_size(&self) -> (usize, usize) {
+        self.cpu.display_size()
+    }
+
+    /// Presses or releases key 0x0 to 0xF.
+    fn set_key(&mut self, key: usize, pressed: bool) -> PyResult<()> {
+        if key >= NUM_KEYS {
+            return Err(PyValueError::new_err(format!("no key {key:#X}")));
+        }
+        self.cpu.keypress(key, pressed);
+        Ok(())
+    }
+
+    /// Whether the buzzer should currently sound.
+    #[getter]
+    fn is_sound_playing(&self) -> bool {
+        self.cpu.is_sound_playing()
+    }
+
+    /// Whether the program stopped itself with the SUPER-CHIP 00FD instruction.
+    #[getter]
+    fn is_halted(&self) -> bool {
+        self.cpu.is_halted()
+    }
+
+    fn save_state<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.cpu.save_state())
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> PyResult<()> {
+        self.cpu
+            .load_state(state)
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "chip8")]
+fn chip8_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Chip8>()?;
+    m.add("Chip8Error", m.py().get_type::<Chip8Error>())?;
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use std::ops::ControlFlow;
+use std::time::Duration;
+
+use chip8::frontend::{Display, Driver, Input};
+use chip8::{Chip8Builder, NUM_KEYS};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::Clamped;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
+
+const ON_COLOR: [u8; 4] = [255, 255, 255, 255];
+const OFF_COLOR: [u8; 4] = [0, 0, 0, 255];
+
+/// Draws the display 1:1 into the canvas, scaling is left to CSS.
+struct CanvasDisplay {
+    canvas: HtmlCanvasElement,
+    ctx: CanvasRenderingContext2d,
+    rgba: Vec<u8>,
+}
+
+impl Display for CanvasDisplay {
+    fn draw(&mut self, pixels: &[bool], width: usize, height: usize) {
+        self.rgba.clear();
+        for &px in pixels {
+            self.rgba
+                .extend_from_slice(if px { &ON_COLOR } else { &OFF_COLOR });
+        }
+        // the canvas follows the SUPER-CHIP resolution switches
+        if self.canvas.width() != width as u32 {
+            self.canvas.set_width(width as u32);
+            self.canvas.set_height(height as u32);
+        }
+        if let Ok(image) = ImageData::new_with_u8_clamped_array_and_sh(
+            Clamped(&self.rgba),
+            width as u32,
+            height as u32,
+        ) {
+            let _ = self.ctx.put_image_data(&image, 0.0, 0.0);
+        }
+    }
+}
+
+/// Key state fed by the page's keyboard event listeners.
+#[derive(Default)]
+struct Keys {
+    held: [bool; NUM_KEYS],
+}
+
+impl Input for Keys {
+    fn poll(&mut self, keys: &mut [bool; NUM_KEYS]) -> ControlFlow<()> {
+        *keys = self.held;
+        ControlFlow::Continue(())
+    }
+}
+
+/// The emulator as seen from JavaScript. The page calls `run_frame` from
+/// `requestAnimationFrame` and forwards `KeyboardEvent.code` values.
+#[wasm_bindgen]
+pub struct Emulator {
+    driver: Driver<CanvasDisplay, Keys, ()>,
+}
+
+#[wasm_bindgen]
+impl Emulator {
+    #[wasm_bindgen(constructor)]
+    pub fn new(canvas: HtmlCanvasElement, rom: &[u8]) -> Result<Emulator, JsValue> {
+        let ctx = canvas
+            .get_context("2d")?
+            .ok_or("canvas has no 2d context")?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+
+        let cpu = Chip8Builder::new()
+            .rom(rom)
+            .build()
+            .map_err(|err| err.to_string())?;
+        let display = CanvasDisplay {
+            canvas,
+            ctx,
+            rgba: Vec::new(),
+        };
+        Ok(Emulator {
+            driver: Driver::new(cpu, display, Keys::default(), ()),
+        })
+    }
+
+    /// Runs one 60 Hz frame and draws it.
+    pub fn run_frame(&mut self) -> Result<(), JsError> {
+        // the page owns the loop, the keyboard input never breaks it
+        let _ = self.driver.run_frame()?;
+        Ok(())
+    }
+
+    /// Runs the frames fitting in `elapsed_ms` milliseconds, so the game
+    /// keeps its speed whatever the refresh rate of the display.
+    pub fn run_for(&mut self, elapsed_ms: f64) -> Result<(), JsError> {
+        let elapsed = Duration::from_secs_f64(elapsed_ms.max(0.0) / 1000.0);
+        let _ = self.driver.run_for(elapsed)?;
+        Ok(())
+    }
+
+    /// Whether the buzzer should currently sound.
+    pub fn is_sound_playing(&self) -> bool {
+        self.driver.cpu().is_sound_playing()
+    }
+
+    pub fn key_down(&mut self, code: &str) {
+        self.set_key(code, true);
+    }
+
+    pub fn key_up(&mut self, code: &str) {
+        self.set_key(code, false);
+    }
+
+    fn set_key(&mut self, code: &str, pressed: bool) {
+        if let Some(k) = code2btn(code) {
+            self.driver.input_mut().held[k] = pressed;
+        }
+    }
+}
+
+// same layout as the desktop frontend, by physical key position
+fn code2btn(code: &str) -> Option<usize> {
+    match code {
+        "Digit1" => Some(0x1),
+        "Digit2" => Some(0x2),
+        "Digit3" => Some(0x3),
+        "Digit4" => Some(0xC),
+        "KeyQ" => Some(0x4),
+        "KeyW" => Some(0x5),
+        "KeyE" => Some(0x6),
+        "KeyR" => Some(0xD),
+        "KeyA" => Some(0x7),
+        "KeyS" => Some(0x8),
+        "KeyD" => Some(0x9),
+        "KeyF" => Some(0xE),
+        "KeyZ" => Some(0xA),
+        "KeyX" => Some(0x0),
+        "KeyC" => Some(0xB),
+        "KeyV" => Some(0xF),
+        _ => None,
+    }
+}
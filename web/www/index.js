@@ -0,0 +1,38 @@
+import init, { Emulator } from "./pkg/web.js";
+
+await init();
+
+const canvas = document.getElementById("screen");
+let emulator = null;
+let frame = null;
+let lastTime = null;
+
+document.getElementById("rom").addEventListener("change", async (evt) => {
+  const file = evt.target.files[0];
+  if (!file) {
+    return;
+  }
+  const rom = new Uint8Array(await file.arrayBuffer());
+  if (frame !== null) {
+    cancelAnimationFrame(frame);
+  }
+  emulator = new Emulator(canvas, rom);
+  lastTime = null;
+  frame = requestAnimationFrame(loop);
+});
+
+function loop(time) {
+  // the display may refresh at any rate, the emulator runs at 60 Hz
+  const elapsed = lastTime === null ? 1000 / 60 : time - lastTime;
+  lastTime = time;
+  try {
+    emulator.run_for(elapsed);
+  } catch (err) {
+    console.error("Emulation stopped:", err);
+    return;
+  }
+  frame = requestAnimationFrame(loop);
+}
+
+document.addEventListener("keydown", (evt) => emulator?.key_down(evt.code));
+document.addEventListener("keyup", (evt) => emulator?.key_up(evt.code));